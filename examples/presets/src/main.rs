@@ -0,0 +1,43 @@
+//! The library presets, `cargo run -- <voronoi|heightmap|biomes|tilemap>`
+
+use bevy::prelude::*;
+use bevy_pancam::{PanCam, PanCamPlugin};
+use bevy_generative_chunks::camera_loader::{CameraChunkLoader, CameraChunkLoaderPlugin};
+use bevy_generative_chunks::prelude::*;
+use bevy_generative_chunks::presets::*;
+
+/// Pixels per world unit
+const WORLD_SCALE: WorldScale = WorldScale::new(10.0);
+
+fn main() {
+    let preset = std::env::args().nth(1).unwrap_or_else(|| "voronoi".to_string());
+    let mut app = App::new();
+    app.add_plugins((DefaultPlugins, PanCamPlugin, CameraChunkLoaderPlugin));
+    let chunks = GenerativeChunksPlugin::new().with_world_scale(WORLD_SCALE);
+    let loader = CameraChunkLoader::new(WORLD_SCALE.get_scale()).with_margin(Vec2::new(5.0, 5.0));
+    let loader = match preset.as_str() {
+        "voronoi" => {
+            app.add_plugins(chunks.with_bundle(VoronoiPreset::default()))
+                .add_plugins(VoronoiPreset::draw(WORLD_SCALE));
+            loader.with_layer::<VoronoiLayer>()
+        }
+        "heightmap" => {
+            app.add_plugins(chunks.with_bundle(HeightmapPreset::default()))
+                .add_plugins(HeightmapPreset::draw(WORLD_SCALE));
+            loader.with_layer::<HeightmapLayer>()
+        }
+        "biomes" => {
+            app.add_plugins(chunks.with_bundle(BiomePreset::default()))
+                .add_plugins(BiomePreset::draw(WORLD_SCALE));
+            loader.with_layer::<BiomeLayer>()
+        }
+        "tilemap" => {
+            app.add_plugins(chunks.with_bundle(TilemapPreset::default()))
+                .add_plugins(TilemapPreset::draw(WORLD_SCALE));
+            loader.with_layer::<TilemapLayer>()
+        }
+        other => panic!("Unknown preset {:?}, try voronoi, heightmap, biomes or tilemap", other),
+    };
+    app.world_mut().spawn((Camera2d, PanCam::default(), loader));
+    app.run();
+}
@@ -1,14 +1,11 @@
 use bevy::math::NormedVectorSpace;
-use bevy_generative_chunks::generative_chunks::bounds::Point;
-use bevy_generative_chunks::generative_chunks::usage::UsageStrategy;
 use bevy::prelude::*;
-use bevy::utils::HashMap;
 use bevy_pancam::{PanCam, PanCamPlugin};
-use bevy_generative_chunks::generative_chunks::bounds::{Bounds, ChunkIdx};
-use bevy_generative_chunks::generative_chunks::layer::{Chunk, Dependency, Layer};
-use bevy_generative_chunks::generative_chunks::layer_client::LayerClient;
-use bevy_generative_chunks::generative_chunks::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+use bevy_generative_chunks::bounds::ChunkAnchor;
+use bevy_generative_chunks::camera_loader::{CameraChunkLoader, CameraChunkLoaderPlugin};
+use bevy_generative_chunks::prelude::*;
 use rand::{Rng, SeedableRng};
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 #[derive(Debug, Clone)]
@@ -20,6 +17,8 @@ struct PointChunk {
 }
 
 const POINT_CHUNK_SIZE: Vec2 = Vec2::new(25., 25.);
+/// Pixels per world unit
+const WORLD_SCALE: WorldScale = WorldScale::new(10.0);
 struct PointsLayer;
 impl Chunk for PointChunk {
     fn get_size() -> Vec2 {
@@ -30,10 +29,10 @@ impl Chunk for PointChunk {
 impl Layer for PointsLayer {
     type Chunk = PointChunk;
 
-    fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
-        // Get thread random with the chunk_idx as seed
-        let seed = chunk_idx.x + chunk_idx.y * 512;
-        let mut random = rand::prelude::SmallRng::seed_from_u64(seed as u64);
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        // Seeded from the world seed, the layer and the chunk
+        let seed = lookup.context().get_seed();
+        let mut random = rand::prelude::SmallRng::seed_from_u64(seed);
         // info!("Generating points chunk with idx: {:?}", chunk_idx);
 
         PointChunk {
@@ -104,110 +103,47 @@ impl Layer for VoronoiLayer {
 }
 
 
-#[derive(Resource, Deref, DerefMut )]
-struct LayerManagerRes(LayersManager);
-
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins,PanCamPlugin))
-        .add_plugins(WorldInspectorPlugin::new())
-        .insert_resource(ChunkIndex {
-            index: HashMap::new(),
+        .add_plugins((DefaultPlugins, PanCamPlugin, CameraChunkLoaderPlugin))
+        .add_plugins(
+            GenerativeChunksPlugin::new()
+                .with_layer(PointsLayer)
+                .with_layer(VoronoiLayer)
+                .with_world_scale(WORLD_SCALE),
+        )
+        // Spawns a square for each voronoi chunk, despawned with the chunk
+        .add_plugins(ChunkSpawnerPlugin::<VoronoiLayer, _>::new(spawn_voronoi_chunk))
+        .add_plugins(EguiPlugin {
+            enable_multipass_for_primary_context: true,
         })
-        .add_systems(Startup, (setup, setup_layers_manager))
-        .add_systems(Update, (regenerate, draw).chain())
+        .add_plugins(WorldInspectorPlugin::new())
+        .add_systems(Startup, setup)
         .run();
 }
 
-pub fn setup_layers_manager(mut commands: Commands) {
-    let manager = LayersManagerBuilder::new()
-        .add_layer(PointsLayer)
-        .add_layer(VoronoiLayer)
-        .build();
-    manager.print_dot();
-    commands.insert_resource(LayerManagerRes(manager));
-}
-
-#[derive(Resource)]
-pub struct RectShape(Handle<Mesh>);
-
-pub fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
-    commands.spawn((Camera2d, PanCam::default()));
-    let rect = meshes.add(Rectangle::new(10.0, 10.0));
-    commands.insert_resource(RectShape(rect));
-
-}
-
-
-fn regenerate(
-    // mut commands: Commands,
-    mut layer_manager: ResMut<LayerManagerRes>,
-    query: Query<&Transform, With<Camera2d>>,
-) {
-    let camera_transform = query.single();
-    let camera_position = camera_transform.translation.xy();
-
-    // let bounds = Bounds::new(
-    //     Vec2::new(camera_position.x - 100.0, camera_position.y - 100.0),
-    //     Vec2::new(camera_position.x + 100.0, camera_position.y + 100.0),
-    // );
-    layer_manager.clear_layer_clients();
-    layer_manager.add_layer_client(LayerClient::new(
-        camera_position/10.,
-        vec![Dependency::new::<VoronoiLayer>(Vec2::new(40.0, 40.0))],
-        UsageStrategy::Fast,
+pub fn setup(mut commands: Commands) {
+    // The camera client is kept up to date by the CameraChunkLoaderPlugin
+    commands.spawn((
+        Camera2d,
+        PanCam::default(),
+        CameraChunkLoader::new(WORLD_SCALE.get_scale())
+            .with_layer::<VoronoiLayer>()
+            .with_margin(Vec2::new(5.0, 5.0)),
     ));
-
-    layer_manager.regenerate();
 }
 
-// #[derive(Component)]
-// struct VornoiChunkVisual(
-//     // The index of the chunk
-//     ChunkIdx,
-// );
-
-#[derive(Resource)]
-struct ChunkIndex {
-    index: HashMap<ChunkIdx, Entity>,
-}
-
-fn draw(
-    mut commands: Commands,
-    layer_manager: Res<LayerManagerRes>,
-    rect_shape: Res<RectShape>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut chunk_index: ResMut<ChunkIndex>,
-) {
-    for (idx, chunk) in layer_manager.get_all_chunks_in::<VoronoiLayer>() {
-        let color = Color::srgb(
-            chunk.color.0 as f32 / 255.0,
-            chunk.color.1 as f32 / 255.0,
-            chunk.color.2 as f32 / 255.0,
-        );
-
-        // TODO: Check if the chunk needs to be recreated
-        if chunk_index.index.contains_key(&idx) {
-            continue;
-        } else {
-            let entity = commands.spawn((
-                Transform::from_translation(Vec3::new(
-                    idx.center(VoronoiChunk::get_size()).x * 10.0,
-                    idx.center(VoronoiChunk::get_size()).y * 10.0,
-                    0.0,
-                )),
-                Mesh2d(rect_shape.0.clone()),
-                MeshMaterial2d(materials.add(color)),
-                // VornoiChunkVisual(idx),
-            ));
-            chunk_index.index.insert(idx, entity.id());
-            // println!("Drawing chunk at {:?}", idx);
-        }
-    }
-    for idx in layer_manager.get_deleted_chunks::<VoronoiLayer>() {
-        if let Some(entity) = chunk_index.index.remove(idx) {
-            commands.entity(entity).despawn();
-            // println!("Despawning chunk at {:?}", idx);
-        } 
-    }
+fn spawn_voronoi_chunk(commands: &mut Commands, idx: ChunkIdx, chunk: &VoronoiChunk) -> Entity {
+    let color = Color::srgb(
+        chunk.color.0 as f32 / 255.0,
+        chunk.color.1 as f32 / 255.0,
+        chunk.color.2 as f32 / 255.0,
+    );
+    let size = VoronoiChunk::get_size();
+    commands
+        .spawn((
+            Sprite::from_color(color, WORLD_SCALE.chunk_render_size(size)),
+            WORLD_SCALE.chunk_transform(idx, size, ChunkAnchor::Center),
+        ))
+        .id()
 }
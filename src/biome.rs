@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use bevy::math::Vec2;
+use crate::bounds::ChunkIdx;
+use crate::checksum::fnv1a;
+use crate::layer::{Chunk, Dependency, Layer};
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayerLookupChunk;
+
+/// Generates the chunks of one biome of a [`BiomeDispatch`] layer
+pub trait BiomeGenerator<C>: Send + Sync {
+    /// `seed` comes from [`biome_seed`], each biome draws its own random numbers
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx, seed: u64) -> C;
+
+    /// Layers the generator reads besides the biome layer, the dispatch layer declares them
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![]
+    }
+}
+
+impl<C, F> BiomeGenerator<C> for F
+where
+    F: Fn(&LayerLookupChunk, &ChunkIdx, u64) -> C + Send + Sync,
+{
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx, seed: u64) -> C {
+        self(lookup, chunk_idx, seed)
+    }
+}
+
+/// Seed of a chunk of a biome, stable across platforms and runs
+pub fn biome_seed(world_seed: u64, biome: &str, chunk_idx: &ChunkIdx) -> u64 {
+    let hash = fnv1a(world_seed, biome.as_bytes());
+    let hash = fnv1a(hash, &chunk_idx.x.to_le_bytes());
+    fnv1a(hash, &chunk_idx.y.to_le_bytes())
+}
+
+/// Layer generating each chunk with the sub-generator of its biome, the biome being read from
+/// the chunk of the layer `B` at the center of the chunk. The dependencies of all the
+/// sub-generators are declared on this layer, each with the union of their paddings
+pub struct BiomeDispatch<B: Layer, C> {
+    select: Box<dyn Fn(&B::Chunk) -> &'static str + Send + Sync>,
+    generators: HashMap<&'static str, Box<dyn BiomeGenerator<C>>>,
+    /// Biome used for the biomes without a generator
+    fallback: Option<&'static str>,
+    _biomes: PhantomData<fn() -> B>,
+}
+
+impl<B: Layer, C> BiomeDispatch<B, C> {
+    /// `select` names the biome of a chunk of the biome layer
+    pub fn new(select: impl Fn(&B::Chunk) -> &'static str + Send + Sync + 'static) -> Self {
+        BiomeDispatch {
+            select: Box::new(select),
+            generators: HashMap::new(),
+            fallback: None,
+            _biomes: PhantomData,
+        }
+    }
+
+    pub fn with_biome(
+        mut self,
+        biome: &'static str,
+        generator: impl BiomeGenerator<C> + 'static,
+    ) -> Self {
+        let previous = self.generators.insert(biome, Box::new(generator));
+        assert!(previous.is_none(), "Biome {:?} is registered twice", biome);
+        self
+    }
+
+    /// Generate the biomes without a generator as `biome`, they panic otherwise
+    pub fn with_fallback(mut self, biome: &'static str) -> Self {
+        self.fallback = Some(biome);
+        self
+    }
+
+    /// The biome whose generator builds a chunk with this biome chunk
+    pub fn biome_of(&self, biome_chunk: &B::Chunk) -> &'static str {
+        let biome = (self.select)(biome_chunk);
+        match self.fallback {
+            Some(fallback) if !self.generators.contains_key(biome) => fallback,
+            _ => biome,
+        }
+    }
+}
+
+impl<B, C> Layer for BiomeDispatch<B, C>
+where
+    B: Layer + 'static,
+    B::Chunk: Clone,
+    C: Chunk,
+{
+    type Chunk = C;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let center = chunk_idx.center(C::get_size());
+        let biome_chunk = lookup
+            .get_chunk::<B>(LayerId::from_type::<B>(), center)
+            .expect("The biome chunk is generated before its dependents");
+        let biome = self.biome_of(&biome_chunk);
+        let generator = self
+            .generators
+            .get(biome)
+            .unwrap_or_else(|| panic!("No generator for biome {:?}", biome));
+        generator.generate(
+            lookup,
+            chunk_idx,
+            biome_seed(lookup.get_seed(), biome, chunk_idx),
+        )
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        let mut dependencies = vec![Dependency::new::<B>(Vec2::ZERO)];
+        // Sorted, so the declared dependencies don't depend on the map order
+        let mut biomes: Vec<&&'static str> = self.generators.keys().collect();
+        biomes.sort();
+        for biome in biomes {
+            for dependency in self.generators[*biome].get_dependencies() {
+                match dependencies
+                    .iter_mut()
+                    .find(|declared| declared.get_layer_id() == dependency.get_layer_id())
+                {
+                    Some(declared) => declared.merge(&dependency),
+                    None => dependencies.push(dependency),
+                }
+            }
+        }
+        dependencies
+    }
+}
@@ -0,0 +1,562 @@
+use bevy::prelude::{IVec2, Vec2};
+use bevy::transform::components::Transform;
+use std::cmp::Ordering;
+
+// Bounds are always in real coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Bounds {
+    pub fn expand(&self, x: f32, y: f32) -> Bounds {
+        Bounds {
+            min: Vec2::new(self.min.x - x, self.min.y - y),
+            max: Vec2::new(self.max.x + x, self.max.y + y),
+        }
+    }
+}
+
+/// Padding around bounds, each side can be different
+/// Left and bottom extend towards negative x and y, right and top towards positive x and y
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Padding {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl Padding {
+    pub fn new(left: f32, right: f32, bottom: f32, top: f32) -> Padding {
+        Padding {
+            left,
+            right,
+            bottom,
+            top,
+        }
+    }
+
+    pub fn symmetric(padding: Vec2) -> Padding {
+        Padding::new(padding.x, padding.x, padding.y, padding.y)
+    }
+
+    /// Padding covering both, the largest of each side
+    pub fn union(self, other: Padding) -> Padding {
+        Padding::new(
+            self.left.max(other.left),
+            self.right.max(other.right),
+            self.bottom.max(other.bottom),
+            self.top.max(other.top),
+        )
+    }
+
+    /// Chunks of the given size the padding reaches past the border, on its longest side
+    pub fn reach(&self, chunk_size: Point) -> usize {
+        // Tolerates the rounding of reads landing on a chunk border
+        let chunks = |side: f32, size: f32| (side / size - 1e-4).ceil().max(0.0) as usize;
+        chunks(self.left, chunk_size.x)
+            .max(chunks(self.right, chunk_size.x))
+            .max(chunks(self.bottom, chunk_size.y))
+            .max(chunks(self.top, chunk_size.y))
+    }
+}
+
+impl From<Vec2> for Padding {
+    fn from(padding: Vec2) -> Self {
+        Padding::symmetric(padding)
+    }
+}
+
+impl Bounds {
+    pub fn add_padding(&self, padding: impl Into<Padding>) -> Bounds {
+        let padding = padding.into();
+        Bounds::new(
+            Vec2::new(self.min.x - padding.left, self.min.y - padding.bottom),
+            Vec2::new(self.max.x + padding.right, self.max.y + padding.top),
+        )
+    }
+
+    pub fn new(min: Point, max: Point) -> Bounds {
+        Bounds { min, max }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+
+    /// Smallest padding of these bounds covering the other bounds
+    pub fn padding_to(&self, other: &Bounds) -> Padding {
+        Padding::new(
+            (self.min.x - other.min.x).max(0.0),
+            (other.max.x - self.max.x).max(0.0),
+            (self.min.y - other.min.y).max(0.0),
+            (other.max.y - self.max.y).max(0.0),
+        )
+    }
+
+    /// The other bounds are fully inside these, edges included
+    pub fn contains_bounds(&self, other: &Bounds) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.contains(other.min)
+            || self.contains(other.max)
+            || other.contains(self.min)
+            || other.contains(self.max)
+    }
+
+    pub fn get_center(&self) -> Point {
+        Vec2::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+        )
+    }
+
+    pub fn center_and_padding(center: (f32, f32), padding: (f32, f32)) -> (f32, f32) {
+        (center.0 - padding.0, center.1 - padding.1)
+    }
+
+    pub fn from_point(point: Vec2) -> Bounds {
+        Bounds::new(point, point)
+    }
+
+    pub fn add_point(self, point: (f32, f32)) -> Bounds {
+        Bounds::new(
+            Vec2::new(self.min.x.min(point.0), self.min.y.min(point.1)),
+            Vec2::new(self.max.x.max(point.0), self.max.y.max(point.1)),
+        )
+    }
+
+    /// Check if the areas overlap, touching edges count as overlapping
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// Fewer bounds covering exactly the same area, and so the same chunks. Bounds spanning
+    /// the same rows are joined where they overlap or touch, then the same for the columns,
+    /// so the bounds of a block of neighbouring chunks become a single one
+    pub fn coalesce(bounds: Vec<Bounds>) -> Vec<Bounds> {
+        let rows = Self::coalesce_axis(bounds, |point| point.y, |point| point.x);
+        Self::coalesce_axis(rows, |point| point.x, |point| point.y)
+    }
+
+    /// Join the bounds with the same extent on the `across` axis that overlap or touch along
+    /// the other axis
+    fn coalesce_axis(
+        mut bounds: Vec<Bounds>,
+        across: impl Fn(Point) -> f32,
+        along: impl Fn(Point) -> f32,
+    ) -> Vec<Bounds> {
+        let key = |bounds: &Bounds| (across(bounds.min), across(bounds.max), along(bounds.min));
+        bounds.sort_by(|a, b| {
+            let (a, b) = (key(a), key(b));
+            a.0.total_cmp(&b.0)
+                .then(a.1.total_cmp(&b.1))
+                .then(a.2.total_cmp(&b.2))
+        });
+        let mut merged: Vec<Bounds> = Vec::with_capacity(bounds.len());
+        for next in bounds {
+            match merged.last_mut() {
+                Some(last)
+                    if across(last.min) == across(next.min)
+                        && across(last.max) == across(next.max)
+                        && along(next.min) <= along(last.max) =>
+                {
+                    last.min = last.min.min(next.min);
+                    last.max = last.max.max(next.max);
+                }
+                _ => merged.push(next),
+            }
+        }
+        merged
+    }
+
+    pub fn get_min(&self) -> Point {
+        self.min
+    }
+
+    pub fn get_max(&self) -> Point {
+        self.max
+    }
+
+    /// The lowest and highest chunk indices visited by [`Bounds::chunks`]
+    pub fn chunk_range(&self, chunk_size: Point) -> (ChunkIdx, ChunkIdx) {
+        CoordinateMath::Float.chunk_range(self, chunk_size)
+    }
+
+    pub fn chunks(&self, chunk_size: Point) -> impl Iterator<Item = ChunkIdx> {
+        CoordinateMath::Float.chunks(self, chunk_size)
+    }
+}
+
+/// How the chunks containing real coordinates are found
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateMath {
+    /// Float division, chunk edges may round differently on other platforms
+    #[default]
+    Float,
+    /// The coordinates are snapped to steps of `2^-fraction_bits` units and divided as
+    /// integers, so every platform puts a point in the same chunk. Needed by cross-platform
+    /// lockstep games. Chunk sizes should be whole steps
+    FixedPoint { fraction_bits: u32 },
+}
+
+impl CoordinateMath {
+    /// Most fraction bits of [`CoordinateMath::FixedPoint`], leaves 31 bits for the integer part
+    pub const MAX_FRACTION_BITS: u32 = 32;
+
+    /// The value in steps of the fixed-point grid. Scaling by a power of two and rounding are
+    /// exact, so the result is the same everywhere
+    fn to_fixed(value: f32, fraction_bits: u32) -> i64 {
+        (value as f64 * (1u64 << fraction_bits) as f64).round() as i64
+    }
+
+    /// The chunk containing the point
+    pub fn chunk_idx(self, pos: Point, chunk_size: Point) -> ChunkIdx {
+        if !chunk_size.is_finite() {
+            return ChunkIdx::GLOBAL;
+        }
+        match self {
+            CoordinateMath::Float => ChunkIdx {
+                x: (pos.x / chunk_size.x).floor() as i32,
+                y: (pos.y / chunk_size.y).floor() as i32,
+            },
+            CoordinateMath::FixedPoint { fraction_bits } => {
+                let axis = |value: f32, size: f32| {
+                    let size = Self::to_fixed(size, fraction_bits).max(1);
+                    Self::to_fixed(value, fraction_bits).div_euclid(size) as i32
+                };
+                ChunkIdx {
+                    x: axis(pos.x, chunk_size.x),
+                    y: axis(pos.y, chunk_size.y),
+                }
+            }
+        }
+    }
+
+    /// The lowest and highest chunk indices visited by [`CoordinateMath::chunks`], the
+    /// highest is rounded up so chunks touching the bounds are included
+    pub fn chunk_range(self, bounds: &Bounds, chunk_size: Point) -> (ChunkIdx, ChunkIdx) {
+        if !chunk_size.is_finite() {
+            return (ChunkIdx::GLOBAL, ChunkIdx::GLOBAL);
+        }
+        match self {
+            CoordinateMath::Float => (
+                self.chunk_idx(bounds.min, chunk_size),
+                ChunkIdx {
+                    x: (bounds.max.x / chunk_size.x).ceil() as i32,
+                    y: (bounds.max.y / chunk_size.y).ceil() as i32,
+                },
+            ),
+            CoordinateMath::FixedPoint { fraction_bits } => {
+                let ceil = |value: f32, size: f32| {
+                    let size = Self::to_fixed(size, fraction_bits).max(1);
+                    // Ceiling division, floor of the negated value
+                    let steps = (-Self::to_fixed(value, fraction_bits)).div_euclid(size);
+                    -steps as i32
+                };
+                (
+                    self.chunk_idx(bounds.min, chunk_size),
+                    ChunkIdx {
+                        x: ceil(bounds.max.x, chunk_size.x),
+                        y: ceil(bounds.max.y, chunk_size.y),
+                    },
+                )
+            }
+        }
+    }
+
+    /// Chunks overlapping the bounds
+    pub fn chunks(self, bounds: &Bounds, chunk_size: Point) -> impl Iterator<Item = ChunkIdx> {
+        let (min_chunk, max_chunk) = self.chunk_range(bounds, chunk_size);
+
+        (min_chunk.x..=max_chunk.x)
+            .flat_map(move |x| (min_chunk.y..=max_chunk.y).map(move |y| ChunkIdx { x, y }))
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub struct ChunkIdx {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Chunk size of a global layer, e.g. a world map summary or the faction list, whose single
+/// chunk [`ChunkIdx::GLOBAL`] covers the whole world. Every point maps to it and the layers
+/// depending on a global layer all read that chunk
+pub const GLOBAL_CHUNK_SIZE: Vec2 = Vec2::INFINITY;
+
+impl ChunkIdx {
+    /// The only chunk of a layer with [`GLOBAL_CHUNK_SIZE`]
+    pub const GLOBAL: ChunkIdx = ChunkIdx { x: 0, y: 0 };
+
+    // pub(crate) fn to_point(&self, chunk_width: f32, chunk_height: f32) -> Point {
+    //     (self.x as f32 * chunk_width, self.y as f32 * chunk_height)
+    // }
+    pub fn to_point(self, chunk_size: Point) -> Point {
+        // The global chunk starts at minus infinity, not at 0 * infinity
+        if !chunk_size.is_finite() {
+            return Vec2::NEG_INFINITY;
+        }
+        Vec2::new(self.x as f32 * chunk_size.x, self.y as f32 * chunk_size.y)
+    }
+
+    pub fn center(&self, chunk_size: Point) -> Point {
+        if !chunk_size.is_finite() {
+            return Vec2::ZERO;
+        }
+        self.to_point(chunk_size) + chunk_size / 2.0
+    }
+
+    /// Area covered by the chunk, in real coordinates
+    pub fn bounds(self, chunk_size: Point) -> Bounds {
+        self.to_bounds(chunk_size.x, chunk_size.y)
+    }
+
+    /// Transform placing a sprite or mesh at the anchor of the chunk, with the real
+    /// coordinates multiplied by `scale`
+    pub fn transform(self, chunk_size: Point, anchor: ChunkAnchor, scale: f32) -> Transform {
+        let point = match anchor {
+            ChunkAnchor::Center => self.center(chunk_size),
+            ChunkAnchor::Origin => self.to_point(chunk_size),
+        };
+        Transform::from_translation((point * scale).extend(0.0))
+    }
+}
+
+/// Chunks crossed by a ray, in order from its origin, walked with a 2D DDA so only the chunks
+/// along the path are visited. A ray passing exactly through a corner visits the chunk beside
+/// it on the x axis before the diagonal one
+#[derive(Debug, Clone)]
+pub struct RayChunks {
+    current: ChunkIdx,
+    /// Direction of the next chunk on each axis, 0 when the ray is parallel to the axis
+    step: IVec2,
+    /// Distance along the ray to the next chunk edge on each axis
+    next_edge: Vec2,
+    /// Distance along the ray between two chunk edges on each axis
+    edge_spacing: Vec2,
+    length: f32,
+    done: bool,
+}
+
+impl RayChunks {
+    /// The chunks within `length` of the origin along the direction
+    pub fn new(origin: Point, direction: Vec2, length: f32, chunk_size: Point) -> Self {
+        let current = ChunkIdx::from_point(origin, chunk_size.x, chunk_size.y);
+        let direction = direction.normalize_or_zero();
+        if !chunk_size.is_finite() || direction == Vec2::ZERO {
+            // A single chunk, the global one or the one of the origin
+            return RayChunks {
+                current,
+                step: IVec2::ZERO,
+                next_edge: Vec2::INFINITY,
+                edge_spacing: Vec2::INFINITY,
+                length,
+                done: false,
+            };
+        }
+        let axis = |origin: f32, direction: f32, index: i32, size: f32| {
+            if direction > 0.0 {
+                (1, ((index + 1) as f32 * size - origin) / direction, size / direction)
+            } else if direction < 0.0 {
+                (-1, (index as f32 * size - origin) / direction, -size / direction)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, next_x, spacing_x) = axis(origin.x, direction.x, current.x, chunk_size.x);
+        let (step_y, next_y, spacing_y) = axis(origin.y, direction.y, current.y, chunk_size.y);
+        RayChunks {
+            current,
+            step: IVec2::new(step_x, step_y),
+            next_edge: Vec2::new(next_x, next_y),
+            edge_spacing: Vec2::new(spacing_x, spacing_y),
+            length,
+            done: false,
+        }
+    }
+
+    /// The chunks crossed by the segment from `a` to `b`, both ends included
+    pub fn segment(a: Point, b: Point, chunk_size: Point) -> Self {
+        RayChunks::new(a, b - a, a.distance(b), chunk_size)
+    }
+}
+
+impl Iterator for RayChunks {
+    type Item = ChunkIdx;
+
+    fn next(&mut self) -> Option<ChunkIdx> {
+        if self.done {
+            return None;
+        }
+        let current = self.current;
+        if self.next_edge.x <= self.next_edge.y {
+            self.current.x += self.step.x;
+            self.done = self.next_edge.x > self.length;
+            self.next_edge.x += self.edge_spacing.x;
+        } else {
+            self.current.y += self.step.y;
+            self.done = self.next_edge.y > self.length;
+            self.next_edge.y += self.edge_spacing.y;
+        }
+        Some(current)
+    }
+}
+
+/// Point of a chunk a [`Transform`] is placed at
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkAnchor {
+    /// Middle of the chunk, for centered sprites
+    #[default]
+    Center,
+    /// Corner with the lowest coordinates, for meshes built from the chunk origin
+    Origin,
+}
+
+impl ChunkIdx {
+    /// Morton (Z-order) key of the index, keeps nearby chunks close together when sorted
+    pub fn morton_key(&self) -> u64 {
+        fn spread(v: u32) -> u64 {
+            let mut v = v as u64;
+            v = (v | (v << 16)) & 0x0000_FFFF_0000_FFFF;
+            v = (v | (v << 8)) & 0x00FF_00FF_00FF_00FF;
+            v = (v | (v << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+            v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+            v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+            v
+        }
+        // Flip the sign bit so negative indices sort before positive ones
+        let x = (self.x as u32) ^ 0x8000_0000;
+        let y = (self.y as u32) ^ 0x8000_0000;
+        spread(x) | (spread(y) << 1)
+    }
+}
+
+impl Ord for ChunkIdx {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.morton_key().cmp(&other.morton_key())
+    }
+}
+
+impl PartialOrd for ChunkIdx {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ChunkIdx {
+    pub(crate) fn from_point(pos: Point, chunk_width: f32, chunk_height: f32) -> ChunkIdx {
+        CoordinateMath::Float.chunk_idx(pos, Vec2::new(chunk_width, chunk_height))
+    }
+
+    /// The chunk containing the point, treating points within `epsilon` of a chunk edge as
+    /// being on it. Points on an edge belong to the chunk above it, on the positive side, so a
+    /// point jittering around an edge after a transform stays in one chunk
+    pub fn from_point_tolerant(pos: Point, chunk_size: Point, epsilon: f32) -> ChunkIdx {
+        if !chunk_size.is_finite() {
+            return ChunkIdx::GLOBAL;
+        }
+        let axis = |value: f32, size: f32| {
+            let edge = (value / size).round();
+            if (value - edge * size).abs() <= epsilon {
+                edge as i32
+            } else {
+                (value / size).floor() as i32
+            }
+        };
+        ChunkIdx {
+            x: axis(pos.x, chunk_size.x),
+            y: axis(pos.y, chunk_size.y),
+        }
+    }
+
+    /// Every chunk within `epsilon` of the point, up to four near a corner. Ordered by x then y
+    pub fn near_point(pos: Point, chunk_size: Point, epsilon: f32) -> Vec<ChunkIdx> {
+        if !chunk_size.is_finite() {
+            return vec![ChunkIdx::GLOBAL];
+        }
+        let area = Bounds::from_point(pos).expand(epsilon, epsilon);
+        let min = ChunkIdx::from_point(area.min, chunk_size.x, chunk_size.y);
+        let max = ChunkIdx::from_point(area.max, chunk_size.x, chunk_size.y);
+        (min.x..=max.x)
+            .flat_map(|x| (min.y..=max.y).map(move |y| ChunkIdx { x, y }))
+            .collect()
+    }
+}
+
+impl ChunkIdx {
+    pub(crate) fn to_bounds(self, width: f32, height: f32) -> Bounds {
+        if !width.is_finite() || !height.is_finite() {
+            return Bounds::new(Vec2::NEG_INFINITY, Vec2::INFINITY);
+        }
+        Bounds::new(
+            Vec2::new(self.x as f32 * width, self.y as f32 * height),
+            Vec2::new((self.x + 1) as f32 * width, (self.y + 1) as f32 * height),
+        )
+    }
+}
+
+pub type Point = Vec2;
+
+/// A position in world coordinates, the space of the bounds, the client centers and the chunk
+/// sizes. Every position argument of the public API takes one, plain [`Vec2`]s convert into it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WorldPos(pub Vec2);
+
+/// A position inside a chunk, relative to its lowest corner, so it goes from zero to the chunk
+/// size. Only meaningful together with the chunk and its layer chunk size
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChunkLocalPos(pub Vec2);
+
+impl WorldPos {
+    pub fn new(x: f32, y: f32) -> Self {
+        WorldPos(Vec2::new(x, y))
+    }
+
+    /// The position relative to the lowest corner of the chunk
+    pub fn to_local(self, chunk_idx: ChunkIdx, chunk_size: Point) -> ChunkLocalPos {
+        if !chunk_size.is_finite() {
+            return ChunkLocalPos(self.0);
+        }
+        ChunkLocalPos(self.0 - chunk_idx.to_point(chunk_size))
+    }
+}
+
+impl ChunkLocalPos {
+    pub fn new(x: f32, y: f32) -> Self {
+        ChunkLocalPos(Vec2::new(x, y))
+    }
+
+    /// The world position of this position inside the chunk
+    pub fn to_world(self, chunk_idx: ChunkIdx, chunk_size: Point) -> WorldPos {
+        if !chunk_size.is_finite() {
+            return WorldPos(self.0);
+        }
+        WorldPos(chunk_idx.to_point(chunk_size) + self.0)
+    }
+}
+
+impl From<Vec2> for WorldPos {
+    fn from(pos: Vec2) -> Self {
+        WorldPos(pos)
+    }
+}
+
+impl From<WorldPos> for Vec2 {
+    fn from(pos: WorldPos) -> Self {
+        pos.0
+    }
+}
+
+impl From<ChunkLocalPos> for Vec2 {
+    fn from(pos: ChunkLocalPos) -> Self {
+        pos.0
+    }
+}
@@ -0,0 +1,316 @@
+use std::ops::Range;
+use bevy::app::{App, Plugin, PostUpdate, PreUpdate};
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::event::{Event, EventWriter};
+use bevy::ecs::removal_detection::RemovedComponents;
+use bevy::ecs::query::Has;
+use bevy::ecs::system::{Commands, Query, Res, ResMut};
+use bevy::math::{Rect, Vec2};
+use bevy::render::camera::Projection;
+use bevy::render::view::{RenderLayers, Visibility};
+use bevy::transform::components::GlobalTransform;
+use crate::bounds::Bounds;
+use crate::chunk_entities::ChunkEntity;
+use crate::layer::{Dependency, Layer};
+use crate::layer_client::LayerClient;
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+use crate::usage::UsageStrategy;
+
+/// Requests the chunks visible by the orthographic camera it is added to, plus a margin.
+/// The client is updated each frame by [`CameraChunkLoaderPlugin`]
+#[derive(Component, Debug, Clone)]
+pub struct CameraChunkLoader {
+    layers: Vec<Dependency>,
+    /// World units per layer unit, the same scale the chunks are drawn with
+    render_scale: f32,
+    /// Extra area around the visible rect, in layer units
+    margin: Vec2,
+    strategy: UsageStrategy,
+    /// Layers requested only over a range of zoom
+    lods: Vec<LodLevel>,
+    /// Width of the zoom band where two levels blend
+    lod_fade: f32,
+    /// Blend weight of each level on the last update
+    lod_weights: Vec<f32>,
+    /// Bit of the camera in the [`ChunkViews`] masks
+    view: u32,
+    /// Requested area on the last update, in layer units
+    area: Option<Bounds>,
+    /// Render layer of the camera, given to the chunk entities it sees
+    render_layer: Option<usize>,
+}
+
+/// Mask of the camera views whose loader requests the chunk of a
+/// [`ChunkEntity`](crate::chunk_entities::ChunkEntity), bit `n` is the loader with
+/// [`CameraChunkLoader::with_view`] `n`. Kept up to date by [`CameraChunkLoaderPlugin`]
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkViews(pub u32);
+
+impl ChunkViews {
+    pub fn contains(&self, view: u32) -> bool {
+        self.0 & (1 << view) != 0
+    }
+
+    /// Check if the view is the only one seeing the chunk, e.g. a chunk only on the minimap
+    /// can use a cheaper material
+    pub fn is_only(&self, view: u32) -> bool {
+        self.0 == 1 << view
+    }
+}
+
+/// Let the camera loaders drive the `Visibility` of this chunk entity, hidden when no camera
+/// requests it, and its `RenderLayers`, the layers of the cameras seeing it that have one
+/// (see [`CameraChunkLoader::with_render_layer`])
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SyncChunkVisibility;
+
+/// A layer a camera requests only over a range of orthographic scales
+#[derive(Debug, Clone)]
+struct LodLevel {
+    layer: Dependency,
+    scales: Range<f32>,
+}
+
+impl LodLevel {
+    /// 1 inside the range, fading linearly to 0 over a band of `fade` centered on each end
+    fn weight(&self, scale: f32, fade: f32) -> f32 {
+        if fade <= 0.0 {
+            return if self.scales.contains(&scale) { 1.0 } else { 0.0 };
+        }
+        let fade_in = (scale - self.scales.start) / fade + 0.5;
+        let fade_out = (self.scales.end - scale) / fade + 0.5;
+        fade_in.clamp(0.0, 1.0) * fade_out.clamp(0.0, 1.0)
+    }
+}
+
+/// The blend weight of a LOD layer of a camera changed, renderers fade its chunks in or out
+/// with it
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct LodWeightChanged {
+    pub camera: Entity,
+    pub layer: LayerId,
+    pub weight: f32,
+}
+
+impl CameraChunkLoader {
+    pub fn new(render_scale: f32) -> Self {
+        CameraChunkLoader {
+            layers: Vec::new(),
+            render_scale,
+            margin: Vec2::ZERO,
+            strategy: UsageStrategy::Fast,
+            lods: Vec::new(),
+            lod_fade: 0.0,
+            lod_weights: Vec::new(),
+            view: 0,
+            area: None,
+            render_layer: None,
+        }
+    }
+
+    /// Render layer of the camera, the chunk entities it sees with [`SyncChunkVisibility`] are
+    /// put on it
+    pub fn with_render_layer(mut self, render_layer: usize) -> Self {
+        self.render_layer = Some(render_layer);
+        self
+    }
+
+    /// Bit of this camera in the [`ChunkViews`] of the chunk entities, give each camera of a
+    /// split screen or minimap its own view. Defaults to 0
+    pub fn with_view(mut self, view: u32) -> Self {
+        assert!(view < u32::BITS, "Views go from 0 to 31");
+        self.view = view;
+        self
+    }
+
+    pub fn get_view(&self) -> u32 {
+        self.view
+    }
+
+    /// Request the layer only while the orthographic scale is in `scales`, e.g. a coarse
+    /// summary layer when zoomed out and the detailed layer when zoomed in
+    pub fn with_lod_layer<L: Layer + 'static>(mut self, scales: Range<f32>) -> Self {
+        self.lods.push(LodLevel {
+            layer: Dependency::new::<L>(Vec2::ZERO),
+            scales,
+        });
+        self.lod_weights.push(0.0);
+        self
+    }
+
+    /// Width of the scale band around each LOD boundary where both levels are requested and
+    /// blended, see [`LodWeightChanged`]
+    pub fn with_lod_fade(mut self, fade: f32) -> Self {
+        self.lod_fade = fade;
+        self
+    }
+
+    /// Blend weight of the LOD layer on the last update, 0 when not requested
+    pub fn get_lod_weight(&self, layer: LayerId) -> f32 {
+        self.lods
+            .iter()
+            .zip(self.lod_weights.iter())
+            .filter(|(level, _)| level.layer.get_layer_id() == layer)
+            .map(|(_, weight)| *weight)
+            .fold(0.0, f32::max)
+    }
+
+    /// Request the visible chunks of the layer
+    pub fn with_layer<L: Layer + 'static>(mut self) -> Self {
+        self.layers.push(Dependency::new::<L>(Vec2::ZERO));
+        self
+    }
+
+    /// Also request the chunks within the margin around the visible rect, in layer units
+    pub fn with_margin(mut self, margin: Vec2) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: UsageStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn get_render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// The client requesting the layers over the visible rect, in world units, with the LOD
+    /// layers of the orthographic scale
+    pub fn client_for(&self, visible: Rect, scale: f32) -> LayerClient {
+        let padding = visible.half_size() / self.render_scale + self.margin;
+        let lods = self
+            .lods
+            .iter()
+            .filter(|level| level.weight(scale, self.lod_fade) > 0.0)
+            .map(|level| &level.layer);
+        LayerClient::new(
+            visible.center() / self.render_scale,
+            self.layers
+                .iter()
+                .chain(lods)
+                .map(|layer| layer.clone().with_padding(padding))
+                .collect(),
+            self.strategy,
+        )
+        .with_name("Camera")
+    }
+
+    /// Area requested for the visible rect, in layer units
+    pub fn area_for(&self, visible: Rect) -> Bounds {
+        let padding = visible.half_size() / self.render_scale + self.margin;
+        Bounds::from_point(visible.center() / self.render_scale).expand(padding.x, padding.y)
+    }
+
+    /// Update the LOD weights for the scale, returns the layers whose weight changed
+    pub(crate) fn update_lod_weights(&mut self, scale: f32) -> Vec<(LayerId, f32)> {
+        let mut changed = Vec::new();
+        for (level, weight) in self.lods.iter().zip(self.lod_weights.iter_mut()) {
+            let new_weight = level.weight(scale, self.lod_fade);
+            if new_weight != *weight {
+                *weight = new_weight;
+                changed.push((level.layer.get_layer_id(), new_weight));
+            }
+        }
+        changed
+    }
+}
+
+/// Keeps a client for each [`CameraChunkLoader`], regenerate the [`LayersManager`] after
+/// `PreUpdate` to see the new area
+pub struct CameraChunkLoaderPlugin;
+
+impl Plugin for CameraChunkLoaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LodWeightChanged>()
+            .add_systems(PreUpdate, Self::update_clients)
+            .add_systems(PostUpdate, Self::update_chunk_views);
+    }
+}
+
+impl CameraChunkLoaderPlugin {
+    fn update_clients(
+        manager: Option<ResMut<LayersManager>>,
+        mut loaders: Query<(Entity, &mut CameraChunkLoader, &Projection, &GlobalTransform)>,
+        mut removed: RemovedComponents<CameraChunkLoader>,
+        mut lod_events: EventWriter<LodWeightChanged>,
+    ) {
+        let Some(mut manager) = manager else {
+            return;
+        };
+        for entity in removed.read() {
+            manager.remove_layer_clients_of(entity);
+        }
+        for (entity, mut loader, projection, transform) in loaders.iter_mut() {
+            let Projection::Orthographic(projection) = projection else {
+                continue;
+            };
+            // Rotations are ignored, the area is axis aligned
+            let center = transform.translation().truncate();
+            let visible = Rect::from_corners(
+                projection.area.min + center,
+                projection.area.max + center,
+            );
+            let client = loader.client_for(visible, projection.scale);
+            loader.area = Some(loader.area_for(visible));
+            // Overlapping cameras share the chunks, each chunk is requested once per regenerate
+            manager.set_layer_client_of(entity, client);
+            for (layer, weight) in loader.update_lod_weights(projection.scale) {
+                lod_events.write(LodWeightChanged {
+                    camera: entity,
+                    layer,
+                    weight,
+                });
+            }
+        }
+    }
+
+    fn update_chunk_views(
+        mut commands: Commands,
+        manager: Option<Res<LayersManager>>,
+        loaders: Query<&CameraChunkLoader>,
+        chunks: Query<(
+            Entity,
+            &ChunkEntity,
+            Option<&ChunkViews>,
+            Has<SyncChunkVisibility>,
+        )>,
+    ) {
+        let Some(manager) = manager else {
+            return;
+        };
+        for (entity, chunk, views, sync_visibility) in chunks.iter() {
+            let Some(chunk_size) = manager.get_layer_chunk_size(chunk.layer) else {
+                continue;
+            };
+            let bounds = chunk.chunk_idx.bounds(chunk_size);
+            let seeing = || {
+                loaders
+                    .iter()
+                    .filter(|loader| loader.area.is_some_and(|area| area.overlaps(&bounds)))
+            };
+            let mask = seeing().fold(0, |mask, loader| mask | (1 << loader.view));
+            if views.is_some_and(|views| views.0 == mask) {
+                continue;
+            }
+            let mut entity = commands.entity(entity);
+            entity.insert(ChunkViews(mask));
+            if !sync_visibility {
+                continue;
+            }
+            entity.insert(if mask == 0 {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            });
+            let render_layers: Vec<usize> =
+                seeing().filter_map(|loader| loader.render_layer).collect();
+            if !render_layers.is_empty() {
+                entity.insert(RenderLayers::from_layers(&render_layers));
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt::Write;
+use crate::bounds::ChunkIdx;
+use crate::layer::Chunk;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a, stable across platforms and builds unlike the std hashers
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Checksum of the `Debug` output of the chunk, the default of [`Chunk::checksum`]
+pub fn debug_checksum(chunk: &(impl Chunk + ?Sized)) -> u64 {
+    // Hash while formatting, so the output is never allocated
+    struct Hasher(u64);
+    impl Write for Hasher {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.0 = fnv1a(self.0, s.as_bytes());
+            Ok(())
+        }
+    }
+    let mut hasher = Hasher(FNV_OFFSET);
+    let _ = write!(hasher, "{:?}", chunk);
+    hasher.0
+}
+
+/// Checksums of the chunks a layer generated, compare them between lockstep peers to find
+/// desyncs on the step they happen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerChecksum {
+    /// Chunks generated by the last regenerate, in Morton order
+    pub frame: u64,
+    /// Every chunk generated so far, the frame checksums chained
+    pub rolling: u64,
+}
+
+impl LayerChecksum {
+    /// Chain the chunks generated by a regenerate, they must be in Morton order
+    pub(crate) fn update<'a>(
+        &mut self,
+        chunks: impl IntoIterator<Item = (ChunkIdx, &'a dyn Chunk)>,
+    ) {
+        let mut frame = FNV_OFFSET;
+        for (chunk_idx, chunk) in chunks {
+            frame = fnv1a(frame, &chunk_idx.x.to_le_bytes());
+            frame = fnv1a(frame, &chunk_idx.y.to_le_bytes());
+            frame = fnv1a(frame, &chunk.checksum().to_le_bytes());
+        }
+        self.frame = frame;
+        self.rolling = fnv1a(self.rolling, &frame.to_le_bytes());
+    }
+}
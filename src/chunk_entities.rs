@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::query::Added;
+use bevy::ecs::resource::Resource;
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::system::{Commands, Local, Query, Res, ResMut};
+use crate::bounds::ChunkIdx;
+use crate::layer::Layer;
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+
+/// Marks the entity showing a chunk, it is despawned by [`ChunkEntitiesPlugin`] on the frame
+/// the chunk is deleted
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEntity {
+    pub layer: LayerId,
+    pub chunk_idx: ChunkIdx,
+}
+
+impl ChunkEntity {
+    pub fn new<L: Layer + 'static>(chunk_idx: ChunkIdx) -> Self {
+        ChunkEntity {
+            layer: LayerId::from_type::<L>(),
+            chunk_idx,
+        }
+    }
+}
+
+/// The entity of each chunk, filled from the spawned [`ChunkEntity`] components
+#[derive(Resource, Debug, Default)]
+pub struct ChunkEntities {
+    entities: HashMap<(LayerId, ChunkIdx), Entity>,
+}
+
+impl ChunkEntities {
+    pub fn get(&self, layer: LayerId, chunk_idx: ChunkIdx) -> Option<Entity> {
+        self.entities.get(&(layer, chunk_idx)).copied()
+    }
+
+    pub fn get_for<L: Layer + 'static>(&self, chunk_idx: ChunkIdx) -> Option<Entity> {
+        self.get(LayerId::from_type::<L>(), chunk_idx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// Despawns the [`ChunkEntity`] entities on the frame their chunk is deleted, the entities of
+/// dependent layers before the ones of their dependencies, so no frame shows a dependent chunk
+/// over a hole. Runs in `PostUpdate`, regenerate the [`LayersManager`] before it
+pub struct ChunkEntitiesPlugin;
+
+impl Plugin for ChunkEntitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkEntities>().add_systems(
+            PostUpdate,
+            (Self::register, Self::despawn_deleted).chain(),
+        );
+    }
+}
+
+impl ChunkEntitiesPlugin {
+    fn register(
+        mut entities: ResMut<ChunkEntities>,
+        spawned: Query<(Entity, &ChunkEntity), Added<ChunkEntity>>,
+    ) {
+        for (entity, chunk) in spawned.iter() {
+            entities
+                .entities
+                .insert((chunk.layer, chunk.chunk_idx), entity);
+        }
+    }
+
+    fn despawn_deleted(
+        mut commands: Commands,
+        mut entities: ResMut<ChunkEntities>,
+        manager: Option<Res<LayersManager>>,
+    ) {
+        let Some(manager) = manager else {
+            return;
+        };
+        let deleted = manager.get_all_deleted_chunks();
+        for layer_id in manager.get_layer_ids() {
+            for chunk_idx in deleted.get(&layer_id).into_iter().flatten() {
+                if let Some(entity) = entities.entities.remove(&(layer_id, *chunk_idx)) {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the entity showing each chunk of the layer, see [`ChunkSpawnerPlugin`]
+pub trait ChunkSpawner<L: Layer>: Send + Sync + 'static {
+    fn spawn(&self, commands: &mut Commands, idx: ChunkIdx, chunk: &L::Chunk) -> Entity;
+}
+
+impl<L, F> ChunkSpawner<L> for F
+where
+    L: Layer,
+    F: Fn(&mut Commands, ChunkIdx, &L::Chunk) -> Entity + Send + Sync + 'static,
+{
+    fn spawn(&self, commands: &mut Commands, idx: ChunkIdx, chunk: &L::Chunk) -> Entity {
+        self(commands, idx, chunk)
+    }
+}
+
+/// Spawns an entity with the [`ChunkSpawner`] for each generated chunk of the layer, and
+/// respawns it when the chunk is regenerated. The entities get a [`ChunkEntity`], so
+/// [`ChunkEntitiesPlugin`] despawns them with their chunk; it is added if missing
+///
+/// ```ignore
+/// app.add_plugins(ChunkSpawnerPlugin::<VoronoiLayer, _>::new(
+///     |commands: &mut Commands, idx: ChunkIdx, chunk: &VoronoiChunk| {
+///         commands.spawn(Sprite::from_color(chunk.color, Vec2::ONE)).id()
+///     },
+/// ));
+/// ```
+pub struct ChunkSpawnerPlugin<L, S> {
+    spawner: Arc<S>,
+    layer: PhantomData<fn() -> L>,
+}
+
+impl<L: Layer + 'static, S: ChunkSpawner<L>> ChunkSpawnerPlugin<L, S> {
+    pub fn new(spawner: S) -> Self {
+        ChunkSpawnerPlugin {
+            spawner: Arc::new(spawner),
+            layer: PhantomData,
+        }
+    }
+
+    fn spawn_generated(
+        spawner: &S,
+        mut commands: Commands,
+        mut entities: ResMut<ChunkEntities>,
+        manager: Option<Res<LayersManager>>,
+        mut last_frame: Local<Option<u64>>,
+    ) {
+        let Some(manager) = manager else {
+            return;
+        };
+        // The lists of the manager are the ones of its last regenerate
+        if last_frame.replace(manager.get_frame()) == Some(manager.get_frame()) {
+            return;
+        }
+        let layer = LayerId::from_type::<L>();
+        for chunk_idx in manager.get_generated_chunks::<L>() {
+            manager.with_chunk::<L, _>(*chunk_idx, |chunk| {
+                // Regenerated, the old entity shows the old data
+                if let Some(entity) = entities.entities.remove(&(layer, *chunk_idx)) {
+                    commands.entity(entity).despawn();
+                }
+                let entity = spawner.spawn(&mut commands, *chunk_idx, chunk);
+                commands.entity(entity).insert(ChunkEntity::new::<L>(*chunk_idx));
+                entities.entities.insert((layer, *chunk_idx), entity);
+            });
+        }
+    }
+}
+
+impl<L: Layer + 'static, S: ChunkSpawner<L>> Plugin for ChunkSpawnerPlugin<L, S> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ChunkEntitiesPlugin>() {
+            app.add_plugins(ChunkEntitiesPlugin);
+        }
+        let spawner = self.spawner.clone();
+        let spawn_generated = move |commands: Commands,
+                                    entities: ResMut<ChunkEntities>,
+                                    manager: Option<Res<LayersManager>>,
+                                    last_frame: Local<Option<u64>>| {
+            Self::spawn_generated(&spawner, commands, entities, manager, last_frame)
+        };
+        app.add_systems(
+            PostUpdate,
+            spawn_generated.after(ChunkEntitiesPlugin::despawn_deleted),
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use bevy::ecs::component::Component;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::query::{Changed, Or};
+use bevy::ecs::removal_detection::RemovedComponents;
+use bevy::ecs::system::{Local, Query, Res, ResMut};
+use bevy::math::Vec2;
+use bevy::transform::components::GlobalTransform;
+use crate::bounds::Point;
+use crate::layer::{Dependency, Layer};
+use crate::layer_client::{ClientId, LayerClient};
+use crate::layer_manager::LayersManager;
+use crate::usage::UsageStrategy;
+use crate::world_scale::WorldScale;
+
+/// Makes the entity it is added to, e.g. a player or a camera, request the chunks around its
+/// `GlobalTransform`. The client is kept in sync by
+/// [`GenerativeChunksPlugin`](crate::plugin::GenerativeChunksPlugin), don't combine it with a
+/// [`CameraChunkLoader`](crate::camera_loader::CameraChunkLoader) on the same entity
+#[derive(Component, Debug, Clone)]
+pub struct ChunkLoader {
+    dependencies: Vec<Dependency>,
+    strategy: UsageStrategy,
+    /// Padding of the layers added with [`ChunkLoader::with_layer`]
+    radius: f32,
+    /// World units per layer unit, the [`WorldScale`] when None
+    render_scale: Option<f32>,
+}
+
+impl ChunkLoader {
+    pub fn new(radius: f32) -> Self {
+        ChunkLoader {
+            dependencies: Vec::new(),
+            strategy: UsageStrategy::Fast,
+            radius,
+            render_scale: None,
+        }
+    }
+
+    /// Request the chunks of the layer within the radius
+    pub fn with_layer<L: Layer + 'static>(self) -> Self {
+        let padding = Vec2::splat(self.radius);
+        self.with_dependency(Dependency::new::<L>(padding))
+    }
+
+    /// Request the chunks of the dependency, with its own padding instead of the radius
+    pub fn with_dependency(mut self, dependency: Dependency) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: UsageStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// World units per layer unit, the translation is divided by it. Overrides the
+    /// [`WorldScale`] resource
+    pub fn with_render_scale(mut self, render_scale: f32) -> Self {
+        self.render_scale = Some(render_scale);
+        self
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn get_strategy(&self) -> UsageStrategy {
+        self.strategy
+    }
+
+    /// Position of the entity in layer units, rotations are ignored
+    pub fn center_for(&self, transform: &GlobalTransform) -> Point {
+        self.center_in(transform, &WorldScale::default())
+    }
+
+    /// Like [`ChunkLoader::center_for`], with the world scale used when the loader has no
+    /// render scale
+    pub fn center_in(&self, transform: &GlobalTransform, world_scale: &WorldScale) -> Point {
+        let scale = self.render_scale.unwrap_or(world_scale.get_scale());
+        transform.translation().truncate() / scale
+    }
+
+    pub fn client_for(&self, transform: &GlobalTransform) -> LayerClient {
+        self.client_in(transform, &WorldScale::default())
+    }
+
+    /// Like [`ChunkLoader::client_for`], with the world scale used when the loader has no
+    /// render scale
+    pub fn client_in(&self, transform: &GlobalTransform, world_scale: &WorldScale) -> LayerClient {
+        LayerClient::new(
+            self.center_in(transform, world_scale),
+            self.dependencies.clone(),
+            self.strategy,
+        )
+    }
+}
+
+/// Add, move and remove the clients of the [`ChunkLoader`]s. Only moved loaders are updated,
+/// reading the `GlobalTransform` propagated on the previous frame
+pub(crate) fn sync_chunk_loaders(
+    mut manager: ResMut<LayersManager>,
+    mut clients: Local<HashMap<Entity, ClientId>>,
+    loaders: Query<
+        (Entity, &ChunkLoader, &GlobalTransform),
+        Or<(Changed<ChunkLoader>, Changed<GlobalTransform>)>,
+    >,
+    changed: Query<(), Changed<ChunkLoader>>,
+    mut removed: RemovedComponents<ChunkLoader>,
+    world_scale: Option<Res<WorldScale>>,
+) {
+    let world_scale = world_scale.map(|world_scale| *world_scale).unwrap_or_default();
+    for entity in removed.read() {
+        if let Some(id) = clients.remove(&entity) {
+            manager.remove_client(id);
+        }
+    }
+    for (entity, loader, transform) in loaders.iter() {
+        match clients.get(&entity) {
+            // Only moved, the client keeps its requests
+            Some(id) if !changed.contains(entity) => {
+                manager.update_client_center(*id, loader.center_in(transform, &world_scale));
+            }
+            _ => {
+                let client = loader.client_in(transform, &world_scale);
+                let id = manager.set_layer_client_of(entity, client);
+                clients.insert(entity, id);
+            }
+        }
+    }
+}
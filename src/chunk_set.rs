@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use crate::bounds::{Bounds, ChunkIdx, Point};
+
+/// Side of the square blocks of chunks stored as one bitmask
+const BLOCK_SIZE: i32 = 8;
+
+/// Set of chunk indices, stored as a hash map of 8x8 blocks with one bit per chunk, so dense
+/// areas take 8 bytes per 64 chunks and the set operations work a block at a time.
+/// Iterates in a deterministic order, block by block
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChunkSet {
+    /// Bitmask of each block with at least one chunk, bit `y * 8 + x` of the block
+    blocks: HashMap<(i32, i32), u64>,
+    len: usize,
+}
+
+fn locate(chunk_idx: &ChunkIdx) -> ((i32, i32), u64) {
+    let block = (chunk_idx.x.div_euclid(BLOCK_SIZE), chunk_idx.y.div_euclid(BLOCK_SIZE));
+    let bit = chunk_idx.y.rem_euclid(BLOCK_SIZE) * BLOCK_SIZE + chunk_idx.x.rem_euclid(BLOCK_SIZE);
+    (block, 1 << bit)
+}
+
+impl ChunkSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The chunks overlapping the bounds, see [`Bounds::chunks`]
+    pub fn from_bounds(bounds: &Bounds, chunk_size: Point) -> Self {
+        bounds.chunks(chunk_size).collect()
+    }
+
+    /// Returns false when the chunk was already in the set
+    pub fn insert(&mut self, chunk_idx: ChunkIdx) -> bool {
+        let (block, bit) = locate(&chunk_idx);
+        let mask = self.blocks.entry(block).or_default();
+        let added = *mask & bit == 0;
+        *mask |= bit;
+        self.len += added as usize;
+        added
+    }
+
+    /// Returns false when the chunk was not in the set
+    pub fn remove(&mut self, chunk_idx: &ChunkIdx) -> bool {
+        let (block, bit) = locate(chunk_idx);
+        let Some(mask) = self.blocks.get_mut(&block) else {
+            return false;
+        };
+        let removed = *mask & bit != 0;
+        *mask &= !bit;
+        if *mask == 0 {
+            self.blocks.remove(&block);
+        }
+        self.len -= removed as usize;
+        removed
+    }
+
+    pub fn contains(&self, chunk_idx: &ChunkIdx) -> bool {
+        let (block, bit) = locate(chunk_idx);
+        self.blocks.get(&block).is_some_and(|mask| mask & bit != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.len = 0;
+    }
+
+    /// The chunks block by block, lowest block row first, then by row and column in a block
+    pub fn iter(&self) -> impl Iterator<Item = ChunkIdx> + '_ {
+        let mut blocks: Vec<(i32, i32)> = self.blocks.keys().copied().collect();
+        blocks.sort_unstable_by_key(|(x, y)| (*y, *x));
+        blocks.into_iter().flat_map(move |block| {
+            let mut mask = self.blocks[&block];
+            std::iter::from_fn(move || {
+                if mask == 0 {
+                    return None;
+                }
+                let bit = mask.trailing_zeros() as i32;
+                mask &= mask - 1;
+                Some(ChunkIdx {
+                    x: block.0 * BLOCK_SIZE + bit % BLOCK_SIZE,
+                    y: block.1 * BLOCK_SIZE + bit / BLOCK_SIZE,
+                })
+            })
+        })
+    }
+
+    /// Add the chunks of the other set to this one
+    pub fn union_with(&mut self, other: &ChunkSet) {
+        for (block, bits) in other.blocks.iter() {
+            let mask = self.blocks.entry(*block).or_default();
+            self.len += (bits & !*mask).count_ones() as usize;
+            *mask |= bits;
+        }
+    }
+
+    /// Keep only the chunks also in the other set
+    pub fn intersect_with(&mut self, other: &ChunkSet) {
+        self.retain_blocks(|block, mask| mask & other.blocks.get(block).copied().unwrap_or(0));
+    }
+
+    /// Remove the chunks of the other set
+    pub fn difference_with(&mut self, other: &ChunkSet) {
+        self.retain_blocks(|block, mask| mask & !other.blocks.get(block).copied().unwrap_or(0));
+    }
+
+    pub fn union(&self, other: &ChunkSet) -> ChunkSet {
+        let mut union = self.clone();
+        union.union_with(other);
+        union
+    }
+
+    pub fn intersection(&self, other: &ChunkSet) -> ChunkSet {
+        let mut intersection = self.clone();
+        intersection.intersect_with(other);
+        intersection
+    }
+
+    pub fn difference(&self, other: &ChunkSet) -> ChunkSet {
+        let mut difference = self.clone();
+        difference.difference_with(other);
+        difference
+    }
+
+    /// Replace each block mask, dropping the emptied blocks
+    fn retain_blocks(&mut self, update: impl Fn(&(i32, i32), u64) -> u64) {
+        let mut len = 0;
+        self.blocks.retain(|block, mask| {
+            *mask = update(block, *mask);
+            len += mask.count_ones() as usize;
+            *mask != 0
+        });
+        self.len = len;
+    }
+}
+
+impl FromIterator<ChunkIdx> for ChunkSet {
+    fn from_iter<T: IntoIterator<Item = ChunkIdx>>(iter: T) -> Self {
+        let mut set = ChunkSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<ChunkIdx> for ChunkSet {
+    fn extend<T: IntoIterator<Item = ChunkIdx>>(&mut self, iter: T) {
+        for chunk_idx in iter {
+            self.insert(chunk_idx);
+        }
+    }
+}
+
+impl<'a> Extend<&'a ChunkIdx> for ChunkSet {
+    fn extend<T: IntoIterator<Item = &'a ChunkIdx>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl IntoIterator for ChunkSet {
+    type Item = ChunkIdx;
+    type IntoIter = std::vec::IntoIter<ChunkIdx>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
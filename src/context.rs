@@ -0,0 +1,74 @@
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use crate::bounds::{ChunkIdx, Point};
+use crate::checksum::fnv1a;
+use crate::layer_id::LayerId;
+
+/// Seed of a chunk being generated, derived from the world seed, the layer and the chunk
+/// index, so every run and platform generates the same chunk and no two chunks or layers
+/// share a seed. Get it with
+/// [`LayerLookupChunk::context`](crate::layer_manager::LayerLookupChunk::context)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationContext {
+    layer: LayerId,
+    chunk_idx: ChunkIdx,
+    seed: u64,
+}
+
+impl GenerationContext {
+    pub fn new(world_seed: u64, layer: LayerId, chunk_idx: ChunkIdx) -> Self {
+        // The type name is hashed, type ids change between builds
+        let hash = fnv1a(world_seed, layer.get_name().as_bytes());
+        let hash = fnv1a(hash, &chunk_idx.x.to_le_bytes());
+        let seed = fnv1a(hash, &chunk_idx.y.to_le_bytes());
+        GenerationContext {
+            layer,
+            chunk_idx,
+            seed,
+        }
+    }
+
+    pub fn get_layer(&self) -> LayerId {
+        self.layer
+    }
+
+    pub fn get_chunk_idx(&self) -> ChunkIdx {
+        self.chunk_idx
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Random numbers of the chunk, the same on every generation of the chunk
+    pub fn rng(&self) -> SmallRng {
+        SmallRng::seed_from_u64(self.seed)
+    }
+
+    /// Random numbers of a named part of the generation, e.g. "trees" and "rocks", so drawing
+    /// more numbers for one part doesn't change the others
+    pub fn rng_for(&self, stream: &str) -> SmallRng {
+        SmallRng::seed_from_u64(fnv1a(self.seed, stream.as_bytes()))
+    }
+}
+
+/// What a layer knows while it prepares, see [`Layer::prepare`](crate::layer::Layer::prepare)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrepareContext {
+    layer: LayerId,
+    chunk_size: Point,
+}
+
+impl PrepareContext {
+    pub fn new(layer: LayerId, chunk_size: Point) -> Self {
+        PrepareContext { layer, chunk_size }
+    }
+
+    pub fn get_layer(&self) -> LayerId {
+        self.layer
+    }
+
+    pub fn get_chunk_size(&self) -> Point {
+        self.chunk_size
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::math::Vec2;
+use crate::bounds::ChunkIdx;
+use crate::layer::{Chunk, Layer};
+
+/// The chunk of the layer `To` containing the center of the chunk of the layer `From`
+pub fn convert_idx<From: Layer, To: Layer>(chunk_idx: ChunkIdx) -> ChunkIdx {
+    let Vec2 {
+        x: width,
+        y: height,
+    } = To::Chunk::get_size();
+    ChunkIdx::from_point(chunk_idx.center(From::Chunk::get_size()), width, height)
+}
+
+/// The chunks of the layer `To` overlapping the chunk of the layer `From`, ordered by x then
+/// y. Chunks only touching its edges are left out
+pub fn covering_indices<From: Layer, To: Layer>(chunk_idx: ChunkIdx) -> Vec<ChunkIdx> {
+    let to_size = To::Chunk::get_size();
+    let min = chunk_idx.to_point(From::Chunk::get_size());
+    let max = min + From::Chunk::get_size();
+    let min_x = (min.x / to_size.x).floor() as i32;
+    let min_y = (min.y / to_size.y).floor() as i32;
+    let max_x = ((max.x / to_size.x).ceil() as i32 - 1).max(min_x);
+    let max_y = ((max.y / to_size.y).ceil() as i32 - 1).max(min_y);
+    (min_x..=max_x)
+        .flat_map(|x| (min_y..=max_y).map(move |y| ChunkIdx { x, y }))
+        .collect()
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+use bevy::app::{App, Plugin, Update};
+use bevy::color::Color;
+use bevy::ecs::resource::Resource;
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::system::{Query, Res, ResMut};
+use bevy::gizmos::gizmos::Gizmos;
+use bevy::math::Isometry2d;
+use bevy::render::camera::Camera;
+use bevy::transform::components::GlobalTransform;
+use bevy::window::{PrimaryWindow, Window};
+use bevy::ecs::query::With;
+use bevy::log::info;
+use crate::bounds::{Bounds, ChunkIdx, WorldPos};
+use crate::diagnostics::ProbedChunk;
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+use crate::log_targets;
+use crate::plugin::GenerativeChunksSet;
+use crate::world_scale::WorldScale;
+
+const SELECTED_COLOR: Color = Color::srgb(1.0, 1.0, 1.0);
+const READ_COLOR: Color = Color::srgb(1.0, 0.6, 0.1);
+const READ_CHUNK_COLOR: Color = Color::srgba(0.3, 0.7, 1.0, 0.5);
+
+/// Colors the generated chunks of a layer from blue to red by generation time
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkCostHeatmap {
+    /// Layer shown, the heatmap is hidden when `None`
+    pub layer: Option<LayerId>,
+    /// Generation time drawn fully red
+    pub max_cost: Duration,
+}
+
+impl Default for ChunkCostHeatmap {
+    fn default() -> Self {
+        ChunkCostHeatmap {
+            layer: None,
+            max_cost: Duration::from_millis(5),
+        }
+    }
+}
+
+/// The chunk the overlay shows the dependencies of
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ChunkDebugSelection {
+    /// Layer picked by the cursor, set it to inspect a layer
+    pub layer: Option<LayerId>,
+    /// Selected chunk of the layer, follows the cursor while `follow_cursor` is set
+    pub chunk: Option<ChunkIdx>,
+    pub follow_cursor: bool,
+}
+
+/// The chunks of every layer under the cursor, updated by the [`ChunkProbePlugin`]
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkProbe {
+    /// Follow the cursor, the last probed chunks are kept while false
+    pub enabled: bool,
+    /// Log the chunks each time the cursor moves onto other chunks
+    pub log: bool,
+    /// World position under the cursor on the last probe
+    pub point: Option<WorldPos>,
+    pub chunks: Vec<ProbedChunk>,
+}
+
+impl Default for ChunkProbe {
+    fn default() -> Self {
+        ChunkProbe {
+            enabled: true,
+            log: true,
+            point: None,
+            chunks: Vec::new(),
+        }
+    }
+}
+
+/// Draws the selected chunk and the padded dependency bounds it read on each dependency layer,
+/// with the dependency chunks covering them, so mismatches between the declared padding and
+/// the actual lookups stand out. Also draws the [`ChunkCostHeatmap`] when a layer is set.
+/// Needs a [`LayersManager`] resource and a 2D camera
+pub struct ChunkDebugOverlayPlugin;
+
+impl Plugin for ChunkDebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkDebugSelection>()
+            .init_resource::<ChunkCostHeatmap>()
+            .add_systems(
+                Update,
+                (Self::follow_cursor, Self::draw, Self::draw_heatmap).chain(),
+            );
+    }
+}
+
+/// Probes the chunk of every layer under the cursor, with its status and `Debug` data, into
+/// the [`ChunkProbe`] resource and logs them. The cursor is converted with the [`WorldScale`]
+/// resource when there is one. Needs a [`LayersManager`] resource and a 2D camera
+pub struct ChunkProbePlugin;
+
+impl Plugin for ChunkProbePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkProbe>().add_systems(
+            Update,
+            Self::probe_cursor.after(GenerativeChunksSet::Regenerate),
+        );
+    }
+}
+
+impl ChunkProbePlugin {
+    fn probe_cursor(
+        mut probe: ResMut<ChunkProbe>,
+        manager: Option<Res<LayersManager>>,
+        world_scale: Option<Res<WorldScale>>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        cameras: Query<(&Camera, &GlobalTransform)>,
+    ) {
+        let Some(manager) = manager else {
+            return;
+        };
+        if !probe.enabled {
+            return;
+        }
+        let world_scale = world_scale.map(|world_scale| *world_scale).unwrap_or_default();
+        let Some(point) = windows.iter().find_map(|window| {
+            cameras.iter().find_map(|(camera, transform)| {
+                world_scale.cursor_to_world(window, camera, transform)
+            })
+        }) else {
+            return;
+        };
+        let chunks = manager.probe(point);
+        let moved = chunks
+            .iter()
+            .map(|probed| probed.chunk)
+            .ne(probe.chunks.iter().map(|probed| probed.chunk));
+        if probe.log && moved {
+            for probed in chunks.iter() {
+                info!(target: log_targets::PROBE, "{}", probed);
+            }
+        }
+        probe.point = Some(point);
+        probe.chunks = chunks;
+    }
+}
+
+impl ChunkDebugOverlayPlugin {
+    fn follow_cursor(
+        mut selection: ResMut<ChunkDebugSelection>,
+        manager: Option<Res<LayersManager>>,
+        windows: Query<&Window, With<PrimaryWindow>>,
+        cameras: Query<(&Camera, &GlobalTransform)>,
+    ) {
+        let (Some(manager), Some(layer_id)) = (manager, selection.layer) else {
+            return;
+        };
+        if !selection.follow_cursor {
+            return;
+        }
+        let Some(chunk_size) = manager.get_layer_chunk_size(layer_id) else {
+            return;
+        };
+        let Some(cursor) = windows.iter().find_map(|window| window.cursor_position()) else {
+            return;
+        };
+        let Some(point) = cameras.iter().find_map(|(camera, transform)| {
+            camera.viewport_to_world_2d(transform, cursor).ok()
+        }) else {
+            return;
+        };
+        selection.chunk = Some(ChunkIdx {
+            x: (point.x / chunk_size.x).floor() as i32,
+            y: (point.y / chunk_size.y).floor() as i32,
+        });
+    }
+
+    fn draw(
+        mut gizmos: Gizmos,
+        selection: Res<ChunkDebugSelection>,
+        manager: Option<Res<LayersManager>>,
+    ) {
+        let (Some(manager), Some(layer_id), Some(chunk_idx)) =
+            (manager, selection.layer, selection.chunk)
+        else {
+            return;
+        };
+        let Some(chunk_size) = manager.get_layer_chunk_size(layer_id) else {
+            return;
+        };
+        let mut rect = |bounds: &Bounds, color: Color| {
+            // Global chunks cover the whole world, there is no outline to draw
+            if !bounds.get_min().is_finite() || !bounds.get_max().is_finite() {
+                return;
+            }
+            gizmos.rect_2d(
+                Isometry2d::from_translation(bounds.get_center()),
+                bounds.get_max() - bounds.get_min(),
+                color,
+            );
+        };
+        rect(&chunk_idx.to_bounds(chunk_size.x, chunk_size.y), SELECTED_COLOR);
+        for (dependency, bounds) in manager.get_chunk_reads(layer_id, chunk_idx) {
+            let Some(dependency_size) = manager.get_layer_chunk_size(dependency) else {
+                continue;
+            };
+            for read in bounds.chunks(dependency_size) {
+                rect(&read.to_bounds(dependency_size.x, dependency_size.y), READ_CHUNK_COLOR);
+            }
+            rect(&bounds, READ_COLOR);
+        }
+    }
+
+    fn draw_heatmap(
+        mut gizmos: Gizmos,
+        heatmap: Res<ChunkCostHeatmap>,
+        manager: Option<Res<LayersManager>>,
+    ) {
+        let (Some(manager), Some(layer_id)) = (manager, heatmap.layer) else {
+            return;
+        };
+        // Global chunks cover the whole world, there is no cell to draw
+        let Some(chunk_size) = manager
+            .get_layer_chunk_size(layer_id)
+            .filter(|chunk_size| chunk_size.is_finite())
+        else {
+            return;
+        };
+        let max_cost = heatmap.max_cost.as_secs_f32().max(f32::EPSILON);
+        for (chunk_idx, cost) in manager.get_chunk_costs(layer_id) {
+            let heat = (cost.as_secs_f32() / max_cost).min(1.0);
+            // Slightly inset so the neighbours don't overdraw the shared edges
+            gizmos.rect_2d(
+                Isometry2d::from_translation(chunk_idx.center(chunk_size)),
+                chunk_size * 0.9,
+                Color::srgb(heat, 0.2, 1.0 - heat),
+            );
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::system::Res;
+use crate::bounds::{ChunkIdx, Padding};
+use crate::layer::ChunkStatus;
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+
+/// Numbers of the last regenerate of a [`LayersManager`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RegenerateStats {
+    /// Chunks generated by the regenerate
+    pub generated: usize,
+    /// Used chunks still waiting to be generated
+    pub pending: usize,
+    /// Chunks whose generation was deferred by their layer
+    pub deferred: usize,
+    /// Chunks that missed their deadline on the regenerate
+    pub missed_deadlines: usize,
+    /// Clients whose requests were recomputed, the others kept holding their chunks
+    pub refreshed_clients: usize,
+    /// Chunks in storage, generated or not
+    pub stored: usize,
+    /// Shallow estimate of the memory used by the stored chunks
+    pub storage_bytes: usize,
+    /// Time spent in the regenerate
+    pub duration: Duration,
+}
+
+/// What [`LayersManager::generate_bounds`] did
+#[derive(Debug, Default, Clone)]
+pub struct BoundsGeneration {
+    /// Chunks generated by each layer, with the dependencies of the requested layer
+    pub layers: HashMap<LayerId, LayerTiming>,
+    /// Regenerates it took
+    pub passes: usize,
+    /// Wall time of the whole generation, including the waits
+    pub duration: Duration,
+    /// Time spent waiting on the chunks of the async pool between the passes
+    pub waiting: Duration,
+    /// Every chunk of the bounds was generated, false when the layers stopped making progress,
+    /// e.g. a layer deferring its chunks
+    pub complete: bool,
+}
+
+/// Chunks a layer generated and the time it spent on them
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LayerTiming {
+    pub chunks: usize,
+    /// Sum of the generation times of the chunks, chunks generated in parallel add up
+    pub time: Duration,
+}
+
+/// A chunk under a point, see [`LayersManager::probe`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbedChunk {
+    pub layer: LayerId,
+    pub chunk: ChunkIdx,
+    /// None when no client requested the chunk
+    pub status: Option<ChunkStatus>,
+    /// Pretty printed data of the chunk, None until it is generated
+    pub data: Option<String>,
+}
+
+impl fmt::Display for ProbedChunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}: ", self.layer.get_name(), self.chunk)?;
+        match (&self.status, &self.data) {
+            (_, Some(data)) => write!(f, "{}", data),
+            (Some(status), None) => write!(f, "{:?}", status),
+            (None, None) => write!(f, "not requested"),
+        }
+    }
+}
+
+/// Padding a layer needs on one of its dependencies, see
+/// [`LayersManager::report_required_padding`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequiredPadding {
+    pub layer: LayerId,
+    pub dependency: LayerId,
+    /// The padding of the dependency in
+    /// [`Layer::get_dependencies`](crate::layer::Layer::get_dependencies),
+    /// None when the layer reads it without declaring it
+    pub declared: Option<Padding>,
+    /// Smallest padding covering the recorded reads, None when no chunk read the dependency
+    pub required: Option<Padding>,
+}
+
+impl RequiredPadding {
+    /// The declared padding covers the recorded reads on every side
+    pub fn is_sufficient(&self) -> bool {
+        match (self.declared, self.required) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(declared), Some(required)) => declared.union(required) == declared,
+        }
+    }
+}
+
+impl fmt::Display for RequiredPadding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} reads {}: ", self.layer.get_name(), self.dependency.get_name())?;
+        match self.required {
+            Some(required) => write!(
+                f,
+                "needs left {}, right {}, bottom {}, top {}",
+                required.left, required.right, required.bottom, required.top
+            )?,
+            None => write!(f, "never read")?,
+        }
+        match self.declared {
+            Some(declared) => write!(
+                f,
+                ", declared left {}, right {}, bottom {}, top {}",
+                declared.left, declared.right, declared.bottom, declared.top
+            ),
+            None => write!(f, ", undeclared"),
+        }
+    }
+}
+
+/// Reads of a dependency by how many of its chunks they reach past the border of the chunk
+/// being generated
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReadHistogram {
+    /// Reads by reach, the first bucket counts the reads inside the chunk being generated
+    pub buckets: Vec<usize>,
+    /// Reads without a region, e.g. of a global chunk
+    pub unbounded: usize,
+}
+
+impl ReadHistogram {
+    pub(crate) fn record(&mut self, reach: Option<usize>) {
+        let Some(reach) = reach else {
+            self.unbounded += 1;
+            return;
+        };
+        if self.buckets.len() <= reach {
+            self.buckets.resize(reach + 1, 0);
+        }
+        self.buckets[reach] += 1;
+    }
+
+    /// Reads with a region
+    pub fn total(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+
+    /// Fraction of the reads with a region reaching at most that many chunks past the border
+    pub fn fraction_within(&self, chunks: usize) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 1.0;
+        }
+        let within: usize = self.buckets.iter().take(chunks + 1).sum();
+        within as f32 / total as f32
+    }
+
+    /// Smallest reach covering the fraction of the reads with a region, None without reads
+    pub fn reach_covering(&self, fraction: f32) -> Option<usize> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let mut within = 0;
+        for (reach, count) in self.buckets.iter().enumerate() {
+            within += count;
+            if within as f32 >= fraction * total as f32 {
+                return Some(reach);
+            }
+        }
+        Some(self.buckets.len() - 1)
+    }
+
+    /// Reach of the farthest read, None without reads
+    pub fn max_reach(&self) -> Option<usize> {
+        self.buckets.iter().rposition(|count| *count > 0)
+    }
+}
+
+/// How far a layer reads one of its dependencies, see
+/// [`LayersManager::report_read_histograms`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyReads {
+    pub layer: LayerId,
+    pub dependency: LayerId,
+    /// Chunks of the dependency the declared padding reaches, None when the layer reads it
+    /// without declaring it
+    pub declared_reach: Option<usize>,
+    pub reads: ReadHistogram,
+}
+
+impl fmt::Display for DependencyReads {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} reads {}: ", self.layer.get_name(), self.dependency.get_name())?;
+        match (self.reads.reach_covering(0.95), self.reads.max_reach()) {
+            (Some(reach), Some(max)) => write!(
+                f,
+                "95% of {} reads within {} chunks of the border, all within {}",
+                self.reads.total(),
+                reach,
+                max
+            )?,
+            _ => write!(f, "no reads with a region")?,
+        }
+        if self.reads.unbounded > 0 {
+            write!(f, ", {} without a region", self.reads.unbounded)?;
+        }
+        match self.declared_reach {
+            Some(reach) => write!(f, ", declared {}", reach),
+            None => write!(f, ", undeclared"),
+        }
+    }
+}
+
+/// Registers the world streaming numbers with Bevy's diagnostics, so `LogDiagnosticsPlugin`
+/// and the diagnostic overlays show them. Needs a [`LayersManager`] resource
+pub struct ChunksDiagnosticsPlugin;
+
+impl ChunksDiagnosticsPlugin {
+    pub const GENERATED: DiagnosticPath = DiagnosticPath::const_new("generative_chunks/generated");
+    pub const PENDING: DiagnosticPath = DiagnosticPath::const_new("generative_chunks/pending");
+    pub const STORAGE_BYTES: DiagnosticPath =
+        DiagnosticPath::const_new("generative_chunks/storage_bytes");
+    pub const REGENERATE_DURATION: DiagnosticPath =
+        DiagnosticPath::const_new("generative_chunks/regenerate_duration");
+}
+
+impl Plugin for ChunksDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::GENERATED).with_suffix(" chunks"))
+            .register_diagnostic(Diagnostic::new(Self::PENDING).with_suffix(" chunks"))
+            .register_diagnostic(Diagnostic::new(Self::STORAGE_BYTES).with_suffix(" B"))
+            .register_diagnostic(Diagnostic::new(Self::REGENERATE_DURATION).with_suffix(" ms"))
+            .add_systems(PostUpdate, Self::update);
+    }
+}
+
+impl ChunksDiagnosticsPlugin {
+    fn update(mut diagnostics: Diagnostics, manager: Option<Res<LayersManager>>) {
+        let Some(manager) = manager else {
+            return;
+        };
+        let stats = manager.get_stats();
+        diagnostics.add_measurement(&Self::GENERATED, || stats.generated as f64);
+        diagnostics.add_measurement(&Self::PENDING, || stats.pending as f64);
+        diagnostics.add_measurement(&Self::STORAGE_BYTES, || stats.storage_bytes as f64);
+        diagnostics.add_measurement(&Self::REGENERATE_DURATION, || {
+            stats.duration.as_secs_f64() * 1000.0
+        });
+    }
+}
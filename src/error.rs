@@ -0,0 +1,53 @@
+use std::fmt;
+use crate::bounds::ChunkIdx;
+use crate::layer_id::LayerId;
+
+/// Why a query of the [`LayersManager`](crate::layer_manager::LayersManager) or a build failed,
+/// returned by the `try_` methods instead of panicking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunksError {
+    /// The layer was not added to the manager
+    LayerNotRegistered(LayerId),
+    /// No client requested the chunk, or it is still pending
+    ChunkNotGenerated { layer: LayerId, chunk: ChunkIdx },
+    /// The chunk stored for the layer is not of the requested type
+    TypeMismatch { layer: LayerId, chunk: ChunkIdx },
+    /// A layer declared a [`Dependency`](crate::layer::Dependency) on a layer that was not added
+    DependencyNotRegistered { layer: LayerId, dependency: LayerId },
+    /// The buffer passed to [`LayersManager::try_rasterize`] doesn't have one value per pixel
+    ///
+    /// [`LayersManager::try_rasterize`]: crate::layer_manager::LayersManager::try_rasterize
+    BufferSizeMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ChunksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunksError::LayerNotRegistered(layer) => {
+                write!(f, "layer {:?} is not registered", layer)
+            }
+            ChunksError::ChunkNotGenerated { layer, chunk } => write!(
+                f,
+                "chunk ({}, {}) of layer {:?} is not generated",
+                chunk.x, chunk.y, layer
+            ),
+            ChunksError::TypeMismatch { layer, chunk } => write!(
+                f,
+                "chunk ({}, {}) of layer {:?} is of another type",
+                chunk.x, chunk.y, layer
+            ),
+            ChunksError::DependencyNotRegistered { layer, dependency } => write!(
+                f,
+                "layer {:?} depends on {:?}, which is not registered",
+                layer, dependency
+            ),
+            ChunksError::BufferSizeMismatch { expected, actual } => write!(
+                f,
+                "the buffer has {} values, expected one per pixel, {}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChunksError {}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+use bevy::ecs::event::Event;
+use crate::bounds::{Bounds, ChunkIdx};
+use crate::layer_client::ClientId;
+use crate::layer_id::LayerId;
+
+/// Some chunks of a layer took longer than the per chunk budget of the layer to generate
+#[derive(Event, Debug, Clone)]
+pub struct LayerBudgetExceeded {
+    pub layer: LayerId,
+    pub budget: Duration,
+    /// The offending chunks and how long each one took
+    pub chunks: Vec<(ChunkIdx, Duration)>,
+}
+
+/// A chunk requested with a [`Deadline`](crate::usage::Deadline) was still pending once the
+/// deadline passed, reported once per chunk
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct DeadlineMissed {
+    pub layer: LayerId,
+    pub chunk_idx: ChunkIdx,
+    /// Game time the chunk was due at
+    pub due_at: Duration,
+    /// How late the chunk already is, zero for a chunk due this frame
+    pub late_by: Duration,
+}
+
+/// Every chunk a client requested from a layer is generated. Reported once per area, again
+/// once the client moved and its new area is generated
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct RegionReady {
+    pub client: ClientId,
+    pub layer: LayerId,
+    /// Area around the client, the padding of its dependency on the layer
+    pub bounds: Bounds,
+    /// Number of chunks in the area
+    pub chunks: usize,
+}
+
+/// Chunks of an area reported by [`RegionReady`] are not generated anymore, e.g. invalidated
+/// or evicted. The area is reported ready again once they are regenerated
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct RegionDegraded {
+    pub client: ClientId,
+    pub layer: LayerId,
+    pub bounds: Bounds,
+    pub chunks: usize,
+    /// The chunks of the area that are not generated
+    pub missing: Vec<ChunkIdx>,
+}
+
+/// Send it on a low memory warning, e.g. from the memory pressure signal of the OS, and the
+/// [`GenerativeChunksPlugin`](crate::plugin::GenerativeChunksPlugin) drops the chunks the
+/// clients don't use, see
+/// [`LayersManager::relieve_memory_pressure`](crate::LayersManager::relieve_memory_pressure)
+#[derive(Event, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryPressure;
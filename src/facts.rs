@@ -0,0 +1,65 @@
+use std::any::Any;
+use std::sync::Arc;
+use crate::bounds::{Bounds, Point};
+
+/// Gameplay facts scoped to regions of the world, e.g. "this area is war-torn", published with
+/// [`LayersManager::set_region_fact`](crate::layer_manager::LayersManager::set_region_fact)
+/// and read by the layers through
+/// [`LayerLookupChunk::get_fact`](crate::layer_manager::LayerLookupChunk::get_fact)
+#[derive(Default, Clone)]
+pub struct RegionFacts {
+    /// Facts in publication order, the latest wins where regions of a key overlap
+    facts: Vec<RegionFact>,
+}
+
+#[derive(Clone)]
+struct RegionFact {
+    key: &'static str,
+    bounds: Bounds,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+impl RegionFacts {
+    pub fn insert<T: Send + Sync + 'static>(&mut self, key: &'static str, bounds: Bounds, value: T) {
+        self.facts.push(RegionFact {
+            key,
+            bounds,
+            value: Arc::new(value),
+        });
+    }
+
+    /// Remove the facts of the key overlapping the region, returns their regions
+    pub fn remove(&mut self, key: &str, bounds: &Bounds) -> Vec<Bounds> {
+        let mut removed = Vec::new();
+        self.facts.retain(|fact| {
+            let overlaps = fact.key == key && fact.bounds.overlaps(bounds);
+            if overlaps {
+                removed.push(fact.bounds);
+            }
+            !overlaps
+        });
+        removed
+    }
+
+    /// The latest fact of the key covering the point
+    pub fn get<T: Send + Sync + 'static>(&self, key: &str, point: Point) -> Option<&T> {
+        self.facts
+            .iter()
+            .rev()
+            .filter(|fact| fact.key == key && fact.bounds.contains(point))
+            .find_map(|fact| fact.value.downcast_ref::<T>())
+    }
+
+    /// The facts of the key overlapping the region with their regions, oldest first
+    pub fn get_in<T: Send + Sync + 'static>(&self, key: &str, bounds: &Bounds) -> Vec<(Bounds, &T)> {
+        self.facts
+            .iter()
+            .filter(|fact| fact.key == key && fact.bounds.overlaps(bounds))
+            .filter_map(|fact| Some((fact.bounds, fact.value.downcast_ref::<T>()?)))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.facts.is_empty()
+    }
+}
@@ -0,0 +1,15 @@
+use bevy::math::UVec2;
+use crate::layer::Chunk;
+
+/// A chunk made of a regular grid of cells, e.g. a heightmap or a tilemap
+pub trait GridChunk: Chunk {
+    type Cell;
+
+    /// Number of cells of the chunk on each axis
+    fn resolution() -> UVec2
+    where
+        Self: Sized;
+
+    /// The cell at the column `x` and row `y`, row 0 is the bottom of the chunk
+    fn cell(&self, x: usize, y: usize) -> &Self::Cell;
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+/// Layers sharing a generation budget, e.g. "visual" or "gameplay". Each group spends its own
+/// budget, so decoration layers can't starve the terrain or collision layers of their chunks.
+/// Layers join a group with [`Layer::group`](crate::layer::Layer::group)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerGroup {
+    name: &'static str,
+    priority: i32,
+    max_chunks: Option<usize>,
+    time_budget: Option<Duration>,
+}
+
+impl LayerGroup {
+    pub fn new(name: &'static str) -> Self {
+        LayerGroup {
+            name,
+            priority: 0,
+            max_chunks: None,
+            time_budget: None,
+        }
+    }
+
+    /// Groups with a higher priority generate first, where their dependencies allow it.
+    /// Layers out of any group have a priority of 0
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Maximum number of chunks the layers of the group generate per regenerate, the rest
+    /// stays queued. The chunks with the highest [`Layer::priority`] of any of the layers go
+    /// first
+    ///
+    /// [`Layer::priority`]: crate::layer::Layer::priority
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+        self.max_chunks = Some(max_chunks);
+        self
+    }
+
+    /// Generation time the layers of the group may spend per regenerate. Checked between
+    /// layers, once spent the remaining layers of the group wait for the next regenerate
+    pub fn with_time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn get_max_chunks(&self) -> Option<usize> {
+        self.max_chunks
+    }
+
+    pub fn get_time_budget(&self) -> Option<Duration> {
+        self.time_budget
+    }
+
+    /// Chunks the group may still generate this regenerate, `None` when unlimited
+    pub(crate) fn remaining_chunks(&self, usage: &GroupUsage) -> Option<usize> {
+        if self.time_budget.is_some_and(|budget| usage.time >= budget) {
+            return Some(0);
+        }
+        self.max_chunks
+            .map(|max_chunks| max_chunks.saturating_sub(usage.chunks))
+    }
+}
+
+/// What the layers of a group spent on the last regenerate
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GroupUsage {
+    /// Chunks generated
+    pub chunks: usize,
+    /// Time spent generating
+    pub time: Duration,
+}
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use bevy::log::debug;
+use crate::bounds::{Bounds, ChunkIdx, CoordinateMath, Padding, Point};
+use crate::layer_client::{ClientId, LayerClient};
+use crate::layer_id::LayerId;
+use crate::log_targets;
+use crate::usage::UsageStrategy;
+
+/// Uniform grid over the client centers, so spatial queries don't scan every client. Updated
+/// one client at a time, only the clients that moved touch the grid
+#[derive(Debug)]
+pub struct ClientSpatialIndex {
+    cell_size: Point,
+    cells: HashMap<ChunkIdx, Vec<ClientId>>,
+    /// The center of each active client
+    centers: HashMap<ClientId, Point>,
+    /// Occupied cells of each column and row, the first and last ones bound the rings
+    /// [`ClientSpatialIndex::nearest_distance`] searches
+    columns: BTreeMap<i32, usize>,
+    rows: BTreeMap<i32, usize>,
+}
+
+impl ClientSpatialIndex {
+    pub fn new(cell_size: Point) -> Self {
+        ClientSpatialIndex {
+            cell_size,
+            cells: HashMap::new(),
+            centers: HashMap::new(),
+            columns: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Count the cell in its column and row when it becomes occupied, or uncount it when empty
+    fn count_cell(&mut self, cell: ChunkIdx, occupied: bool) {
+        for (lines, line) in [(&mut self.columns, cell.x), (&mut self.rows, cell.y)] {
+            let count = lines.entry(line).or_default();
+            if occupied {
+                *count += 1;
+            } else {
+                *count -= 1;
+                if *count == 0 {
+                    lines.remove(&line);
+                }
+            }
+        }
+    }
+
+    fn cell(&self, center: Point) -> ChunkIdx {
+        ChunkIdx::from_point(center, self.cell_size.x, self.cell_size.y)
+    }
+
+    /// Move the client to its center, None removes it, e.g. when it is inactive. Does nothing
+    /// when the client didn't move
+    pub fn update(&mut self, id: ClientId, center: Option<Point>) {
+        let previous = self.centers.get(&id).copied();
+        if previous == center {
+            return;
+        }
+        if let Some(previous) = previous {
+            let cell = self.cell(previous);
+            if let Some(clients) = self.cells.get_mut(&cell) {
+                clients.retain(|client| *client != id);
+                if clients.is_empty() {
+                    self.cells.remove(&cell);
+                    self.count_cell(cell, false);
+                }
+            }
+            self.centers.remove(&id);
+        }
+        if let Some(center) = center {
+            let cell = self.cell(center);
+            let clients = self.cells.entry(cell).or_default();
+            clients.push(id);
+            if clients.len() == 1 {
+                self.count_cell(cell, true);
+            }
+            self.centers.insert(id, center);
+        }
+    }
+
+    pub fn remove(&mut self, id: ClientId) {
+        self.update(id, None);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.centers.clear();
+        self.columns.clear();
+        self.rows.clear();
+    }
+
+    /// Handles of the active clients whose center is inside the bounds
+    pub fn query(&self, bounds: &Bounds) -> Vec<ClientId> {
+        bounds
+            .chunks(self.cell_size)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(|id| self.centers.get(id).is_some_and(|center| bounds.contains(*center)))
+            .collect()
+    }
+
+    /// Distance from the point to the closest active client center
+    pub fn nearest_distance(&self, point: Point) -> Option<f32> {
+        let origin = ChunkIdx::from_point(point, self.cell_size.x, self.cell_size.y);
+        // The farthest occupied cell is at most on the ring through the far corner of the
+        // occupied columns and rows
+        let reach = |lines: &BTreeMap<i32, usize>, at: i32| -> Option<i32> {
+            let first = *lines.keys().next()?;
+            let last = *lines.keys().next_back()?;
+            Some((at - first).abs().max((last - at).abs()))
+        };
+        let max_ring = reach(&self.columns, origin.x)?.max(reach(&self.rows, origin.y)?);
+        let mut best: Option<f32> = None;
+        for ring in 0..=max_ring {
+            for x in -ring..=ring {
+                for y in -ring..=ring {
+                    if x.abs() != ring && y.abs() != ring {
+                        continue;
+                    }
+                    let cell = ChunkIdx {
+                        x: origin.x + x,
+                        y: origin.y + y,
+                    };
+                    for id in self.cells.get(&cell).into_iter().flatten() {
+                        let distance = self.centers[id].distance(point);
+                        best = Some(best.map_or(distance, |b| b.min(distance)));
+                    }
+                }
+            }
+            // Anything in the next rings is at least this far away
+            if best.is_some_and(|b| b <= ring as f32 * self.cell_size.min_element()) {
+                break;
+            }
+        }
+        best
+    }
+}
+
+/// Chunks a client requests with its strategy, by layer
+pub(crate) type ClientHold = (UsageStrategy, Vec<(LayerId, Vec<ChunkIdx>)>);
+
+/// Chunks that started and stopped being held by any client
+#[derive(Debug, Default)]
+pub(crate) struct HeldChanges {
+    pub(crate) held: Vec<((LayerId, UsageStrategy), Vec<ChunkIdx>)>,
+    pub(crate) released: Vec<((LayerId, UsageStrategy), Vec<ChunkIdx>)>,
+}
+
+/// Chunks held by the clients, merged by layer and strategy. Overlapping clients (e.g. split
+/// screen cameras) hold each chunk once, the counts say how many clients share it. Only the
+/// clients that changed update it
+#[derive(Debug, Default)]
+pub(crate) struct HeldUsages {
+    counts: HashMap<(LayerId, UsageStrategy), HashMap<ChunkIdx, u32>>,
+}
+
+impl HeldUsages {
+    /// Add a client's hold on the chunks, returns the ones no client held before
+    fn hold(&mut self, key: (LayerId, UsageStrategy), chunks: &[ChunkIdx]) -> Vec<ChunkIdx> {
+        let counts = self.counts.entry(key).or_default();
+        let mut held = Vec::new();
+        for chunk_idx in chunks {
+            let count = counts.entry(*chunk_idx).or_default();
+            *count += 1;
+            if *count == 1 {
+                held.push(*chunk_idx);
+            }
+        }
+        held
+    }
+
+    /// Drop a client's hold on the chunks, returns the ones no client holds anymore
+    fn release(&mut self, key: (LayerId, UsageStrategy), chunks: &[ChunkIdx]) -> Vec<ChunkIdx> {
+        let Some(counts) = self.counts.get_mut(&key) else {
+            return Vec::new();
+        };
+        let mut released = Vec::new();
+        for chunk_idx in chunks {
+            let Some(count) = counts.get_mut(chunk_idx) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(chunk_idx);
+                released.push(*chunk_idx);
+            }
+        }
+        released
+    }
+
+    /// Replace what a client holds, `None` for a removed or inactive client. Only the chunks
+    /// that entered or left the client's requests are touched
+    pub(crate) fn replace(
+        &mut self,
+        previous: Option<&ClientHold>,
+        current: Option<&ClientHold>,
+    ) -> HeldChanges {
+        let previous = Self::by_key(previous);
+        let current = Self::by_key(current);
+        let mut changes = HeldChanges::default();
+        for (key, chunks) in previous.iter() {
+            let kept = current.get(key);
+            let left: Vec<ChunkIdx> = chunks
+                .iter()
+                .filter(|chunk_idx| kept.is_none_or(|kept| !kept.contains(chunk_idx)))
+                .copied()
+                .collect();
+            let released = self.release(*key, &left);
+            if !released.is_empty() {
+                changes.released.push((*key, released));
+            }
+        }
+        for (key, chunks) in current.iter() {
+            let had = previous.get(key);
+            let entered: Vec<ChunkIdx> = chunks
+                .iter()
+                .filter(|chunk_idx| had.is_none_or(|had| !had.contains(chunk_idx)))
+                .copied()
+                .collect();
+            let held = self.hold(*key, &entered);
+            if !held.is_empty() {
+                changes.held.push((*key, held));
+            }
+        }
+        changes
+    }
+
+    /// The requests of a client as sets, a layer the client depends on twice is held once
+    fn by_key(hold: Option<&ClientHold>) -> HashMap<(LayerId, UsageStrategy), HashSet<ChunkIdx>> {
+        let mut by_key: HashMap<(LayerId, UsageStrategy), HashSet<ChunkIdx>> = HashMap::new();
+        if let Some((strategy, layers)) = hold {
+            for (layer_id, chunks) in layers {
+                by_key.entry((*layer_id, *strategy)).or_default().extend(chunks);
+            }
+        }
+        by_key
+    }
+
+    /// Chunks of the layer held by any client, by strategy
+    pub(crate) fn of_layer(
+        &self,
+        layer_id: LayerId,
+    ) -> impl Iterator<Item = (UsageStrategy, Vec<ChunkIdx>)> + '_ {
+        self.counts
+            .iter()
+            .filter(move |((id, _), _)| *id == layer_id)
+            .map(|((_, strategy), counts)| (*strategy, counts.keys().copied().collect()))
+    }
+
+    /// Every held chunk with its layer and strategy
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (LayerId, UsageStrategy, ChunkIdx)> + '_ {
+        self.counts.iter().flat_map(|((layer_id, strategy), counts)| {
+            counts.keys().map(move |chunk_idx| (*layer_id, *strategy, *chunk_idx))
+        })
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+/// Chunks that entered and left a client's interest area on the last regenerate
+#[derive(Debug, Default, Clone)]
+pub struct ClientAreaDelta {
+    pub entered: Vec<ChunkIdx>,
+    pub left: Vec<ChunkIdx>,
+}
+
+impl ClientAreaDelta {
+    fn between(previous: &[ChunkIdx], current: &[ChunkIdx]) -> Self {
+        let previous_set: HashSet<&ChunkIdx> = previous.iter().collect();
+        let current_set: HashSet<&ChunkIdx> = current.iter().collect();
+        ClientAreaDelta {
+            entered: current
+                .iter()
+                .filter(|idx| !previous_set.contains(idx))
+                .copied()
+                .collect(),
+            left: previous
+                .iter()
+                .filter(|idx| !current_set.contains(idx))
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entered.is_empty() && self.left.is_empty()
+    }
+}
+
+/// Chunks a client needed on the last regenerate, reused while the client doesn't move
+#[derive(Debug, Default)]
+pub(crate) struct ClientInterest {
+    valid: bool,
+    center: Point,
+    /// Layer and padding of each dependency, used to detect changes in the client
+    key: Vec<(LayerId, Padding)>,
+    chunks: Vec<(LayerId, Vec<ChunkIdx>)>,
+    /// Difference from the chunks of the previous regenerate
+    deltas: HashMap<LayerId, ClientAreaDelta>,
+    /// Chunks actually requested when the client has a request limit, a subset of `chunks`
+    /// catching up with it. None when every chunk is requested
+    requested: Option<Vec<(LayerId, Vec<ChunkIdx>)>>,
+    /// Layers whose chunks were all generated, see [`RegionReady`](crate::events::RegionReady)
+    ready: HashSet<LayerId>,
+    /// Requests the client holds in the [`HeldUsages`]
+    held: Option<ClientHold>,
+}
+
+impl ClientInterest {
+    pub(crate) fn compute(
+        client: &LayerClient,
+        chunk_sizes: &HashMap<LayerId, Point>,
+        coordinates: CoordinateMath,
+        previous: &ClientInterest,
+    ) -> Self {
+        debug!(
+            target: log_targets::CLIENTS,
+            "Recomputing interest of client {} around {:?}",
+            client.label(),
+            client.get_center()
+        );
+        let key: Vec<(LayerId, Padding)> = client
+            .get_dependencies()
+            .iter()
+            .map(|dep| (dep.get_layer_id(), dep.get_padding()))
+            .collect();
+        let chunks: Vec<(LayerId, Vec<ChunkIdx>)> = client
+            .get_dependencies()
+            .iter()
+            .map(|dep| {
+                let layer_id = dep.get_layer_id();
+                let bounds =
+                    Bounds::from_point(client.get_center()).add_padding(dep.get_padding());
+                let chunks = coordinates
+                    .chunks(&bounds, chunk_sizes[&layer_id])
+                    .filter(|idx| client.wants(layer_id, idx))
+                    .collect();
+                (layer_id, chunks)
+            })
+            .collect();
+        let mut deltas = HashMap::new();
+        for (layer_id, current) in chunks.iter() {
+            let previous = previous.get_layer_chunks(*layer_id);
+            deltas.insert(*layer_id, ClientAreaDelta::between(previous, current));
+        }
+        // Layers the client stopped depending on are left entirely
+        for (layer_id, previous) in previous.chunks.iter() {
+            deltas
+                .entry(*layer_id)
+                .or_insert_with(|| ClientAreaDelta::between(previous, &[]));
+        }
+        // Filtered clients are recomputed on each regenerate, their area stays the same while
+        // they don't move
+        let ready = if previous.center == client.get_center() && previous.key == key {
+            previous.ready.clone()
+        } else {
+            HashSet::new()
+        };
+        ClientInterest {
+            valid: true,
+            center: client.get_center(),
+            key,
+            chunks,
+            deltas,
+            requested: previous.requested.clone(),
+            ready,
+            held: None,
+        }
+    }
+
+    /// Requests the chunks admitted before that are still needed, plus at most `limit` new
+    /// ones, the closest to the client first. Without a limit every chunk is requested
+    pub(crate) fn admit(&mut self, client: &LayerClient, chunk_sizes: &HashMap<LayerId, Point>) {
+        let Some(limit) = client.get_request_limit() else {
+            self.requested = None;
+            return;
+        };
+        let previous = self.requested.take().unwrap_or_default();
+        let mut requested = Vec::with_capacity(self.chunks.len());
+        let mut new_chunks = Vec::new();
+        for (layer_id, chunks) in self.chunks.iter() {
+            let admitted: HashSet<ChunkIdx> = previous
+                .iter()
+                .find(|(id, _)| id == layer_id)
+                .map(|(_, chunks)| chunks.iter().copied().collect())
+                .unwrap_or_default();
+            let chunk_size = chunk_sizes[layer_id];
+            let mut kept = Vec::with_capacity(chunks.len());
+            for idx in chunks {
+                if admitted.contains(idx) {
+                    kept.push(*idx);
+                } else {
+                    let distance = idx.center(chunk_size).distance_squared(self.center);
+                    new_chunks.push((distance, requested.len(), *idx));
+                }
+            }
+            requested.push((*layer_id, kept));
+        }
+        if new_chunks.len() > limit {
+            new_chunks.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+            debug!(
+                target: log_targets::CLIENTS,
+                "Client {} deferred {} chunk requests",
+                client.label(),
+                new_chunks.len() - limit
+            );
+        }
+        for (_, layer, idx) in new_chunks.into_iter().take(limit) {
+            requested[layer].1.push(idx);
+        }
+        self.requested = Some(requested);
+    }
+
+    /// Chunks requested on this regenerate, may lag behind the area when the client
+    /// has a request limit
+    pub(crate) fn get_requested(&self) -> &Vec<(LayerId, Vec<ChunkIdx>)> {
+        self.requested.as_ref().unwrap_or(&self.chunks)
+    }
+
+    /// Take the requests the client held until now
+    pub(crate) fn take_held(&mut self) -> Option<ClientHold> {
+        self.held.take()
+    }
+
+    /// Record the requests the client holds from now on
+    pub(crate) fn set_held(&mut self, held: Option<ClientHold>) {
+        self.held = held;
+    }
+
+    /// Check if a request limit has nothing left to admit
+    pub(crate) fn is_caught_up(&self) -> bool {
+        let count = |chunks: &Vec<(LayerId, Vec<ChunkIdx>)>| -> usize {
+            chunks.iter().map(|(_, chunks)| chunks.len()).sum()
+        };
+        self.requested
+            .as_ref()
+            .is_none_or(|requested| count(requested) == count(&self.chunks))
+    }
+
+    /// The client is inactive, so it left all the chunks it had
+    pub(crate) fn deactivate(&mut self) {
+        let previous = std::mem::take(self);
+        self.deltas = previous
+            .chunks
+            .iter()
+            .map(|(layer_id, chunks)| (*layer_id, ClientAreaDelta::between(chunks, &[])))
+            .collect();
+    }
+
+    /// The client didn't change, so nothing entered or left
+    pub(crate) fn clear_deltas(&mut self) {
+        self.deltas.clear();
+    }
+
+    /// Every chunk the client needs, with the ones a request limit defers
+    pub(crate) fn get_chunks(&self) -> &Vec<(LayerId, Vec<ChunkIdx>)> {
+        &self.chunks
+    }
+
+    /// Record whether all the chunks of the layer are generated, returns the previous state
+    pub(crate) fn set_ready(&mut self, layer_id: LayerId, ready: bool) -> bool {
+        if ready {
+            !self.ready.insert(layer_id)
+        } else {
+            self.ready.remove(&layer_id)
+        }
+    }
+
+    fn get_layer_chunks(&self, layer_id: LayerId) -> &[ChunkIdx] {
+        self.chunks
+            .iter()
+            .find(|(id, _)| *id == layer_id)
+            .map(|(_, chunks)| chunks.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub(crate) fn get_delta(&self, layer_id: LayerId) -> Option<&ClientAreaDelta> {
+        self.deltas.get(&layer_id)
+    }
+
+    /// Check if the cached chunks are still the ones the client needs
+    pub(crate) fn matches(&self, client: &LayerClient) -> bool {
+        // Filters may change their answer at any time, so they are never cached
+        self.valid
+            && !client.has_filter()
+            && self.center == client.get_center()
+            && self.key.len() == client.get_dependencies().len()
+            && self
+                .key
+                .iter()
+                .zip(client.get_dependencies())
+                .all(|(key, dep)| key.0 == dep.get_layer_id() && key.1 == dep.get_padding())
+    }
+}
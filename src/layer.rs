@@ -0,0 +1,1760 @@
+use rayon::iter::ParallelIterator;
+use crate::bounds::{Bounds, ChunkIdx, ChunkLocalPos, CoordinateMath, Padding, Point, WorldPos};
+use crate::context::PrepareContext;
+use crate::diagnostics::ReadHistogram;
+use crate::error::ChunksError;
+use crate::layer_id::LayerId;
+use crate::layer_manager::{LayerLookupChunk, ReadLog};
+use crate::log_targets;
+use crate::snapshot::LayerSnapshot;
+use crate::usage::UsageStrategy::Fast;
+use crate::usage::{
+    GracePeriod, SlowSchedule, UsageCounter, UsageDecay, UsageLeak, UsageStrategy,
+};
+use bevy::log::{debug, info_span};
+use bevy::math::Vec2;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task, TaskPool};
+use downcast_rs::{impl_downcast, Downcast};
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::any::Any;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rayon::iter::IntoParallelRefIterator;
+
+/// Storage of the chunks of a layer, ordered by Morton key with the `deterministic` feature
+#[cfg(not(feature = "deterministic"))]
+pub type ChunkStorage = std::collections::HashMap<ChunkIdx, ChunkWrapper>;
+#[cfg(feature = "deterministic")]
+pub type ChunkStorage = std::collections::BTreeMap<ChunkIdx, ChunkWrapper>;
+
+type ChunkGenerator =
+    Arc<dyn Fn(&LayerLookupChunk, &ChunkIdx) -> Option<Arc<dyn Chunk>> + Send + Sync>;
+/// A chunk generating on the async pool, with the time it took
+type AsyncGeneration = Task<(Option<Arc<dyn Chunk>>, Duration)>;
+type PriorityFn = Box<dyn Fn(&ChunkIdx, f32) -> f32 + Send + Sync>;
+type PlaceholderFn = Box<dyn Fn(&ChunkIdx) -> Option<Arc<dyn Chunk>> + Send + Sync>;
+type ExpiryFn = Box<dyn Fn(&dyn Chunk, &ChunkIdx) -> Option<Duration> + Send + Sync>;
+type DependencyBoundsFn =
+    Box<dyn Fn(&LayerLookupChunk, &ChunkIdx) -> Vec<(LayerId, Bounds)> + Send + Sync>;
+type DependencyChangedFn = Box<
+    dyn Fn(&LayerLookupChunk, &dyn Chunk, &ChunkIdx, LayerId, &ChunkIdx) -> Option<Arc<dyn Chunk>>
+        + Send
+        + Sync,
+>;
+
+/// A chunk waiting in the schedule, with its deadline and priority
+type Scheduled = (Option<Duration>, f32, ChunkIdx);
+
+/// Earliest deadline first, chunks without a deadline last, then by priority. Ties go in
+/// Morton order, so the order doesn't depend on the storage
+fn by_deadline(a: &Scheduled, b: &Scheduled) -> std::cmp::Ordering {
+    let due_at = |scheduled: &Scheduled| scheduled.0.unwrap_or(Duration::MAX);
+    due_at(a)
+        .cmp(&due_at(b))
+        .then(b.1.total_cmp(&a.1))
+        .then(a.2.cmp(&b.2))
+}
+
+/// Split `limit` chunks between the Fast and Slow queues, one Slow chunk after every
+/// `fast_ratio` Fast ones. `fast_credit` is the number of Fast chunks since the last Slow one,
+/// carried across regenerates so a limit smaller than the ratio still reaches the Slow queue.
+/// Returns how many of each and the credit after them
+fn split_limit(
+    limit: usize,
+    fast: usize,
+    slow: usize,
+    fast_ratio: usize,
+    fast_credit: usize,
+) -> (usize, usize, usize) {
+    let (mut fast_count, mut slow_count, mut credit) = (0, 0, fast_credit);
+    for _ in 0..limit.min(fast + slow) {
+        // The slots the Fast queue leaves go to the Slow one
+        let slow_turn = credit >= fast_ratio || fast_count == fast;
+        if slow_turn && slow_count < slow {
+            slow_count += 1;
+            credit = 0;
+        } else {
+            fast_count += 1;
+            credit = (credit + 1).min(fast_ratio);
+        }
+    }
+    (fast_count, slow_count, credit)
+}
+
+/// Chunks picked by [`LayerConfig::schedule`], the due chunks pre-empt the limits
+struct ScheduledChunks {
+    due: Vec<ChunkIdx>,
+    fast: Vec<ChunkIdx>,
+    slow: Vec<ChunkIdx>,
+    fast_ratio: usize,
+    /// Fast chunks generated since the last Slow one, before these
+    fast_credit: usize,
+}
+
+impl ScheduledChunks {
+    /// Keep at most `limit` chunks, the due chunks always stay and the rest is split between
+    /// the queues, see [`split_limit`]
+    fn truncate(&mut self, limit: usize) {
+        let limit = limit.saturating_sub(self.due.len());
+        let (fast, slow, _) = self.split(limit);
+        self.fast.truncate(fast);
+        self.slow.truncate(slow);
+    }
+
+    /// Fast chunks since the last Slow one once these are generated
+    fn fast_credit_after(&self) -> usize {
+        self.split(usize::MAX).2
+    }
+
+    fn split(&self, limit: usize) -> (usize, usize, usize) {
+        let (fast, slow) = (self.fast.len(), self.slow.len());
+        split_limit(limit, fast, slow, self.fast_ratio, self.fast_credit)
+    }
+
+    fn into_chunks(self) -> Vec<ChunkIdx> {
+        self.due.into_iter().chain(self.fast).chain(self.slow).collect()
+    }
+}
+
+// #[derive(Debug)]
+pub struct LayerConfig {
+    /// This layer id
+    layer_id: LayerId,
+    /// The layer depends on these layers to be generated
+    depends_on: Vec<Dependency>,
+    /// Chunk size of the layer
+    chunk_size: Point,
+    /// Chunk storage
+    storage: ChunkStorage,
+    /// Generate chunk function
+    generate: ChunkGenerator,
+    /// Regions of the dependencies each chunk needs
+    dependency_bounds: DependencyBoundsFn,
+    /// Scheduling priority of a chunk, given its distance to the nearest client
+    priority: PriorityFn,
+    /// Maximum number of chunks generated per regenerate, the rest stays queued
+    max_in_flight: Option<usize>,
+    /// Current frame, usages are stamped with it
+    frame: u64,
+    /// How long the usages last after their last request
+    usage_decay: UsageDecay,
+    /// How many Slow chunks are generated per regenerate
+    slow_schedule: SlowSchedule,
+    /// Fast chunks generated since the last Slow one, see [`split_limit`]
+    fast_since_slow: usize,
+    /// Chunks scheduled this frame, the in flight limit holds across the requirement passes
+    scheduled_in_frame: usize,
+    /// How long the unused generated chunks are kept
+    grace_period: Option<GracePeriod>,
+    /// Snapshot of the generated chunks, dropped whenever they change. Behind a mutex so
+    /// readers holding the layer read lock can fill it
+    snapshot: Mutex<Option<Arc<LayerSnapshot>>>,
+    /// Thread pool the chunks are generated on
+    lane: GenerationLane,
+    /// Expected generation time of a single chunk
+    chunk_budget: Option<Duration>,
+    /// Group whose budget the layer shares
+    group: Option<&'static str>,
+    /// Keys of the region facts the layer reads
+    facts: Vec<&'static str>,
+    /// Generate on the calling thread in a deterministic order
+    lockstep: bool,
+    /// Panic when a chunk reads outside the regions it declared
+    strict_dependencies: bool,
+    /// Record the regions of the dependencies the chunks read
+    record_reads: bool,
+    /// Smallest padding of each dependency covering the recorded reads
+    recorded_padding: Mutex<HashMap<LayerId, Padding>>,
+    /// Recorded reads of each dependency by how far they reach past the chunks
+    read_histograms: Mutex<HashMap<LayerId, ReadHistogram>>,
+    /// How the chunks containing a point are found
+    coordinates: CoordinateMath,
+    /// Version of the generator, saved chunks of another version are stale
+    version: u32,
+    /// Time [`Layer::prepare`] took
+    prepare_time: Duration,
+    /// The layer itself, shared with the layers built from it
+    handle: Arc<dyn Any + Send + Sync>,
+    /// Builds the stand-in of a pending chunk
+    placeholder: PlaceholderFn,
+    /// How long a generated chunk stays valid
+    expiry: ExpiryFn,
+    /// Updates a chunk after a chunk of a dependency it read changed
+    on_dependency_changed: DependencyChangedFn,
+    /// Changed dependency chunks each chunk waits on, see [`Layer::on_dependency_changed`]
+    dependency_changes: Vec<(ChunkIdx, LayerId, ChunkIdx)>,
+    /// Game time of the current regenerate
+    clock: Duration,
+    /// Regenerates the data a regenerated chunk replaced is kept for, 0 to drop it at once
+    cross_fade: u64,
+    /// Chunks generating on the async pool, dropping a task cancels it
+    in_flight: HashMap<ChunkIdx, AsyncGeneration>,
+}
+/// Number of threads of the pool running the IO bound layers
+const IO_THREADS: usize = 8;
+
+lazy_static! {
+    /// Dedicated pool for IO bound layers, so they don't block the compute threads
+    static ref IO_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .num_threads(IO_THREADS)
+        .thread_name(|i| format!("generative-chunks-io-{}", i))
+        .build()
+        .expect("Failed to build the IO thread pool");
+}
+
+/// Where the chunks of a layer are generated
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum GenerationLane {
+    /// CPU bound generation, on the global compute pool
+    #[default]
+    Compute,
+    /// Disk or network backed generation, on a dedicated IO pool
+    Io,
+    /// Heavy generation on Bevy's `AsyncComputeTaskPool`, the regenerate doesn't wait for the
+    /// chunks and collects them on a later regenerate
+    Async,
+}
+
+/// Where a requested chunk is in its generation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkStatus {
+    /// Waiting to be scheduled
+    Queued,
+    /// Generating on the async pool, see [`GenerationLane::Async`]
+    Pending,
+    Generated,
+}
+
+/// Name of a chunk generation task in profiler captures, `gen:<layer>:<x>,<y>`
+/// Only formatted when the span is recorded
+struct GenTaskName {
+    layer_id: LayerId,
+    chunk_idx: ChunkIdx,
+}
+
+impl std::fmt::Display for GenTaskName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gen:{:?}:{},{}",
+            self.layer_id, self.chunk_idx.x, self.chunk_idx.y
+        )
+    }
+}
+
+/// A queried chunk, or its placeholder while it is pending
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkState<C> {
+    Generated(C),
+    /// Stand-in from [`Layer::placeholder`]
+    Placeholder(C),
+}
+
+impl<C> ChunkState<C> {
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self, ChunkState::Placeholder(_))
+    }
+
+    pub fn get(&self) -> &C {
+        match self {
+            ChunkState::Generated(chunk) | ChunkState::Placeholder(chunk) => chunk,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        match self {
+            ChunkState::Generated(chunk) | ChunkState::Placeholder(chunk) => chunk,
+        }
+    }
+}
+
+/// A regenerated chunk with the data it replaces, so renderers can blend the two instead of
+/// popping, see [`LayersManagerBuilder::cross_fade`]
+///
+/// [`LayersManagerBuilder::cross_fade`]: crate::layer_manager::LayersManagerBuilder::cross_fade
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossFade<C> {
+    /// Data before the chunk was regenerated
+    pub previous: C,
+    /// New data, None while the chunk is regenerating
+    pub current: Option<C>,
+    /// Progress of the fade, from 0 showing only `previous` to 1 showing only `current`
+    pub weight: f32,
+}
+
+/// Result of [`Layer::try_generate`]
+#[derive(Debug, Clone)]
+pub enum GenerateOutcome<C> {
+    Ready(C),
+    /// Retry the chunk on the next regenerate
+    Defer,
+}
+
+pub(crate) struct LayerGenerationResult {
+    /// Chunks that took longer than the budget of the layer
+    pub(crate) over_budget: Vec<(ChunkIdx, Duration)>,
+    /// Chunks generated, in Morton order
+    pub(crate) generated: Vec<ChunkIdx>,
+    /// Chunks whose generation was deferred to a later regenerate
+    pub(crate) deferred: usize,
+    /// Chunks still pending past their deadline, with the game time they were due at
+    pub(crate) missed_deadlines: Vec<(ChunkIdx, Duration)>,
+}
+
+impl LayerConfig {
+    /// Regions of the dependencies needed by the used chunks, with the best strategy
+    /// of the chunk requiring them. The regions of neighbouring chunks are joined
+    pub fn requires(&self, lookup: &LayerLookupChunk) -> Vec<(LayerId, Bounds, UsageStrategy)> {
+        // One group per dependency and strategy, in the order they are first required
+        let mut groups: Vec<((LayerId, UsageStrategy), Vec<Bounds>)> = Vec::new();
+        for (idx, chunk) in self.storage.iter() {
+            let Some(strategy) = chunk.usage_counter.best_usage_at(self.frame, &self.usage_decay)
+            else {
+                continue;
+            };
+            for (layer_id, bounds) in (self.dependency_bounds)(lookup, idx) {
+                match groups.iter_mut().find(|(key, _)| *key == (layer_id, strategy)) {
+                    Some((_, group)) => group.push(bounds),
+                    None => groups.push(((layer_id, strategy), vec![bounds])),
+                }
+            }
+        }
+        // The bounds of neighbouring chunks overlap, a region of chunks is required once
+        groups
+            .into_iter()
+            .flat_map(|((layer_id, strategy), bounds)| {
+                Bounds::coalesce(bounds)
+                    .into_iter()
+                    .map(move |bounds| (layer_id, bounds, strategy))
+            })
+            .collect()
+    }
+
+    /// Regions of the dependencies needed by the pending chunks with a deadline, so the
+    /// dependencies inherit it
+    pub(crate) fn requires_by_deadline(
+        &self,
+        lookup: &LayerLookupChunk,
+    ) -> Vec<(LayerId, Bounds, Duration)> {
+        self.storage
+            .iter()
+            .filter(|(_, chunk)| chunk.chunk.is_none())
+            .filter_map(|(idx, chunk)| Some((idx, chunk.due_at?)))
+            .flat_map(|(idx, due_at)| {
+                (self.dependency_bounds)(lookup, idx)
+                    .into_iter()
+                    .map(move |(layer_id, bounds)| (layer_id, bounds, due_at))
+            })
+            .collect()
+    }
+
+    /// Give the pending chunks a deadline, a chunk keeps the earliest one until it is generated
+    pub(crate) fn set_deadlines(
+        &mut self,
+        chunks: impl IntoIterator<Item = ChunkIdx>,
+        due_at: Duration,
+    ) {
+        for chunk_idx in chunks {
+            let Some(chunk) = self.storage.get_mut(&chunk_idx) else {
+                continue;
+            };
+            if chunk.chunk.is_none() {
+                chunk.due_at = Some(chunk.due_at.map_or(due_at, |current| current.min(due_at)));
+            }
+        }
+    }
+    pub fn ensure_generated(&mut self, bounds: &Bounds, strategy: UsageStrategy) {
+        self.ensure_generated_filtered(bounds, strategy, |_| true);
+    }
+
+    /// Same as [`LayerConfig::ensure_generated`], but skips the chunks rejected by `filter`
+    pub fn ensure_generated_filtered(
+        &mut self,
+        bounds: &Bounds,
+        strategy: UsageStrategy,
+        filter: impl Fn(&ChunkIdx) -> bool,
+    ) {
+        let chunks = self.chunks_in(bounds);
+        self.ensure_chunks(chunks.filter(|idx| filter(idx)), strategy);
+    }
+
+    /// Mark the chunks of the bounds not requested with the strategy this frame as used,
+    /// creating the missing ones. Returns how many were newly requested
+    pub(crate) fn ensure_requested(&mut self, bounds: &Bounds, strategy: UsageStrategy) -> usize {
+        let frame = self.frame;
+        let mut requested = 0;
+        for chunk_idx in self.chunks_in(bounds) {
+            let chunk_wrapper = self.storage.entry(chunk_idx).or_insert_with(ChunkWrapper::new);
+            if chunk_wrapper.usage_counter.get_count_at(frame, strategy) == 0 {
+                chunk_wrapper.usage_counter.stamp(frame);
+                chunk_wrapper.usage_counter.increment(strategy);
+                requested += 1;
+            }
+        }
+        requested
+    }
+
+    /// Mark the chunks as used, creating the missing ones so they get generated
+    pub(crate) fn ensure_chunks(
+        &mut self,
+        chunks: impl IntoIterator<Item = ChunkIdx>,
+        strategy: UsageStrategy,
+    ) {
+        let frame = self.frame;
+        // Check if the chunks are already generated
+        for chunk_idx in chunks {
+            let chunk_wrapper = self.storage.entry(chunk_idx).or_insert_with(|| {
+                // Generate the chunk
+                ChunkWrapper::new()
+            });
+            chunk_wrapper.usage_counter.stamp(frame);
+            chunk_wrapper.usage_counter.increment(strategy);
+        }
+    }
+
+    /// Request the chunks on every frame until they are released, creating the missing ones
+    pub(crate) fn hold_chunks(
+        &mut self,
+        chunks: impl IntoIterator<Item = ChunkIdx>,
+        strategy: UsageStrategy,
+    ) {
+        for chunk_idx in chunks {
+            let chunk_wrapper = self.storage.entry(chunk_idx).or_insert_with(ChunkWrapper::new);
+            chunk_wrapper.usage_counter.hold(strategy);
+        }
+    }
+
+    /// Release held chunks last requested at `last_frame`, their usage decays from there
+    pub(crate) fn release_chunks(
+        &mut self,
+        chunks: impl IntoIterator<Item = ChunkIdx>,
+        strategy: UsageStrategy,
+        last_frame: u64,
+    ) {
+        for chunk_idx in chunks {
+            if let Some(chunk_wrapper) = self.storage.get_mut(&chunk_idx) {
+                chunk_wrapper.usage_counter.release(strategy, last_frame);
+            }
+        }
+    }
+
+    /// Compare the usage counts of this frame with the expected requests, by chunk and strategy
+    pub(crate) fn audit_usages(
+        &self,
+        expected: &HashMap<(ChunkIdx, UsageStrategy), u32>,
+    ) -> Vec<UsageLeak> {
+        let strategies = [UsageStrategy::KeepAlive, UsageStrategy::Slow, Fast];
+        let mut leaks = Vec::new();
+        for (chunk_idx, chunk) in self.storage.iter() {
+            for strategy in strategies {
+                let counted = chunk.usage_counter.get_count_at(self.frame, strategy);
+                let expected = expected.get(&(*chunk_idx, strategy)).copied().unwrap_or(0);
+                if counted != expected {
+                    leaks.push(UsageLeak {
+                        layer: self.layer_id,
+                        chunk_idx: *chunk_idx,
+                        strategy,
+                        counted,
+                        expected,
+                    });
+                }
+            }
+        }
+        // Requested chunks missing from the storage
+        for (chunk_idx, strategy) in expected.keys() {
+            if !self.storage.contains_key(chunk_idx) {
+                leaks.push(UsageLeak {
+                    layer: self.layer_id,
+                    chunk_idx: *chunk_idx,
+                    strategy: *strategy,
+                    counted: 0,
+                    expected: expected[&(*chunk_idx, *strategy)],
+                });
+            }
+        }
+        leaks.sort_by_key(|leak| leak.chunk_idx);
+        leaks
+    }
+
+    /// Check if every chunk the dependencies of this chunk must provide is generated
+    fn dependencies_ready(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> bool {
+        (self.dependency_bounds)(lookup, chunk_idx)
+            .iter()
+            .all(|(layer_id, bounds)| lookup.is_generated(*layer_id, bounds))
+    }
+
+    /// Pick the pending chunks to generate this frame: the chunks past their deadline, then
+    /// the Fast chunks and then the Slow ones, each queue ordered by deadline and priority.
+    /// Chunks whose dependencies are not generated yet stay queued. With a `warm_radius` every
+    /// pending chunk within it is scheduled regardless of the limits, and the others wait
+    fn schedule(
+        &self,
+        lookup: &LayerLookupChunk,
+        distance: impl Fn(Point) -> f32,
+        warm_radius: Option<f32>,
+    ) -> ScheduledChunks {
+        let mut due: Vec<Scheduled> = Vec::new();
+        let mut fast: Vec<Scheduled> = Vec::new();
+        let mut slow: Vec<Scheduled> = Vec::new();
+        let mut warm: Vec<Scheduled> = Vec::new();
+        for (idx, chunk) in self.storage.iter() {
+            if chunk.chunk.is_some() || self.in_flight.contains_key(idx) {
+                continue;
+            }
+            let queue = match chunk.usage_counter.best_usage_at(self.frame, &self.usage_decay) {
+                Some(Fast) => &mut fast,
+                Some(UsageStrategy::Slow) => &mut slow,
+                _ => continue,
+            };
+            if !self.dependencies_ready(lookup, idx) {
+                continue;
+            }
+            let client_distance = distance(idx.center(self.chunk_size));
+            // Lockstep peers can't rely on float math, their chunks go in index order
+            let priority = if self.lockstep {
+                0.0
+            } else {
+                (self.priority)(idx, client_distance)
+            };
+            let scheduled = (chunk.due_at, priority, *idx);
+            if chunk.due_at.is_some_and(|due_at| due_at <= self.clock) {
+                due.push(scheduled);
+            } else if warm_radius.is_some_and(|radius| client_distance <= radius) {
+                warm.push(scheduled);
+            } else {
+                queue.push(scheduled);
+            }
+        }
+        let ratio = self.slow_schedule.fast_ratio.max(1);
+        let sorted = |mut queue: Vec<Scheduled>| -> Vec<ChunkIdx> {
+            queue.sort_by(by_deadline);
+            queue.into_iter().map(|(_, _, idx)| idx).collect()
+        };
+        if warm_radius.is_some() {
+            return ScheduledChunks {
+                due: sorted(due),
+                fast: sorted(warm),
+                slow: Vec::new(),
+                fast_ratio: ratio,
+                fast_credit: self.fast_since_slow,
+            };
+        }
+        let mut scheduled = ScheduledChunks {
+            due: sorted(due),
+            fast: sorted(fast),
+            slow: sorted(slow),
+            fast_ratio: ratio,
+            fast_credit: self.fast_since_slow,
+        };
+
+        // At least one slow chunk for every `fast_ratio` fast ones, so the background work
+        // keeps going while the clients move quickly
+        let slow_count = self
+            .slow_schedule
+            .per_frame
+            .max(scheduled.fast.len().div_ceil(ratio));
+        scheduled.slow.truncate(slow_count);
+        if let Some(max) = self.max_in_flight {
+            // Chunks over the in flight limit stay queued until the next regenerate
+            let (fast, slow, _) = scheduled.split(max.saturating_sub(self.scheduled_in_frame));
+            scheduled.fast.truncate(fast);
+            scheduled.slow.truncate(slow);
+        }
+        scheduled
+    }
+
+    /// Priorities of the pending chunks [`LayerConfig::schedule`] could pick this frame, to
+    /// share the limit of a group between its layers
+    pub(crate) fn pending_priorities(
+        &self,
+        lookup: &LayerLookupChunk,
+        distance: impl Fn(Point) -> f32,
+    ) -> Vec<f32> {
+        self.storage
+            .iter()
+            .filter(|(idx, chunk)| chunk.chunk.is_none() && !self.in_flight.contains_key(idx))
+            .filter(|(_, chunk)| {
+                let usage = chunk.usage_counter.best_usage_at(self.frame, &self.usage_decay);
+                matches!(usage, Some(Fast | UsageStrategy::Slow))
+            })
+            .filter(|(idx, _)| self.dependencies_ready(lookup, idx))
+            .map(|(idx, _)| match self.lockstep {
+                true => 0.0,
+                false => (self.priority)(idx, distance(idx.center(self.chunk_size))),
+            })
+            .collect()
+    }
+
+    /// Drop the chunks no usage keeps this frame once their grace period is over, returns
+    /// them in Morton order. Runs once per regenerate, after every requirement pass stamped
+    /// the chunks it needs
+    pub(crate) fn evict_unused(&mut self) -> Vec<ChunkIdx> {
+        // Check if the chunk usage is zero, the generated chunks wait out the grace period
+        let now = (self.frame, self.clock);
+        let mut to_delete: Vec<ChunkIdx> = Vec::new();
+        for (idx, chunk) in self.storage.iter_mut() {
+            if chunk.usage_counter.best_usage_at(now.0, &self.usage_decay).is_some() {
+                chunk.unused_since = None;
+                continue;
+            }
+            let since = *chunk.unused_since.get_or_insert(now);
+            let expired = match self.grace_period.filter(|_| chunk.chunk.is_some()) {
+                Some(grace_period) => grace_period.has_elapsed(since, now),
+                None => true,
+            };
+            if expired {
+                to_delete.push(*idx);
+            }
+        }
+        for chunk_idx in to_delete.iter() {
+            self.storage.remove(chunk_idx);
+            self.in_flight.remove(chunk_idx);
+        }
+        self.dependency_changes
+            .retain(|(chunk_idx, _, _)| self.storage.contains_key(chunk_idx));
+        // Keep the deleted list in Morton order
+        to_delete.sort();
+        if !to_delete.is_empty() {
+            debug!(
+                target: log_targets::EVICTION,
+                "Evicted {} chunks of {:?}",
+                to_delete.len(),
+                self.layer_id
+            );
+            *self.snapshot.get_mut().unwrap() = None;
+        }
+        to_delete
+    }
+
+    /// Generate the scheduled chunks, `distance` gives the distance from a point to the
+    /// closest client and `limit` caps the number of chunks generated, the rest stays queued.
+    /// With a `warm_radius` all the chunks within it are generated, ignoring the limits
+    pub(crate) fn generate(
+        &mut self,
+        lookup: &LayerLookupChunk,
+        distance: impl Fn(Point) -> f32,
+        limit: Option<usize>,
+        warm_radius: Option<f32>,
+    ) -> LayerGenerationResult {
+        let finished = self.collect_async(lookup);
+        let mut scheduled = self.schedule(lookup, distance, warm_radius);
+        if let Some(limit) = limit.filter(|_| warm_radius.is_none()) {
+            scheduled.truncate(limit);
+        }
+        let asynchronous = self.lane == GenerationLane::Async && !self.lockstep;
+        if let Some(max) = self.max_in_flight.filter(|_| asynchronous) {
+            // The chunks still generating count towards the limit
+            scheduled.truncate(max.saturating_sub(self.in_flight.len()));
+        }
+        self.fast_since_slow = scheduled.fast_credit_after();
+        let scheduled = scheduled.into_chunks();
+        self.scheduled_in_frame += scheduled.len();
+        let layer_id = self.layer_id;
+        let generator = &self.generate;
+        let dependency_bounds = &self.dependency_bounds;
+        let strict = self.strict_dependencies;
+        let record = self.record_reads;
+        let recorded_padding = &self.recorded_padding;
+        let read_histograms = &self.read_histograms;
+        let chunk_size = self.chunk_size;
+        let generate_chunk = |chunk_idx: &ChunkIdx| {
+            let _span = info_span!(
+                "gen_chunk",
+                task = %GenTaskName {
+                    layer_id,
+                    chunk_idx: *chunk_idx
+                }
+            )
+            .entered();
+            // Strict chunks read through a lookup checking the regions they declared
+            let declared = strict.then(|| dependency_bounds(lookup, chunk_idx));
+            let recorded = record.then(ReadLog::default);
+            // The lookup of the chunk also gives its context
+            let tracking =
+                lookup.tracking(layer_id, *chunk_idx, declared.as_deref(), recorded.as_ref());
+            let start = Instant::now();
+            let chunk = generator(&tracking, chunk_idx);
+            let elapsed = start.elapsed();
+            if let Some(recorded) = recorded {
+                let bounds = chunk_idx.bounds(chunk_size);
+                let mut padding = recorded_padding.lock().unwrap();
+                let mut histograms = read_histograms.lock().unwrap();
+                for (dependency, read) in recorded.into_inner().unwrap() {
+                    let required = read.map_or(Padding::default(), |read| bounds.padding_to(&read));
+                    let entry = padding.entry(dependency).or_default();
+                    *entry = entry.union(required);
+                    let reach = read.map(|_| required.reach(lookup.chunk_size_of(dependency)));
+                    histograms.entry(dependency).or_default().record(reach);
+                }
+            }
+            let reads = declared.unwrap_or_else(|| dependency_bounds(lookup, chunk_idx));
+            (*chunk_idx, chunk, reads, elapsed)
+        };
+        let run = || -> Vec<(ChunkIdx, Option<Arc<dyn Chunk>>, Vec<(LayerId, Bounds)>, Duration)> {
+            scheduled.par_iter().map(&generate_chunk).collect()
+        };
+        let (generated, deferred): (Vec<_>, Vec<_>) = match self.lane {
+            // Lockstep runs on the calling thread only
+            _ if self.lockstep => scheduled.iter().map(&generate_chunk).collect(),
+            GenerationLane::Compute => run(),
+            GenerationLane::Io => IO_POOL.install(run),
+            GenerationLane::Async => {
+                if !scheduled.is_empty() {
+                    let pool = AsyncComputeTaskPool::get_or_init(TaskPool::new);
+                    let detached = Arc::new(lookup.detach());
+                    for chunk_idx in scheduled.iter().copied() {
+                        let generator = generator.clone();
+                        let detached = detached.clone();
+                        let task = pool.spawn(async move {
+                            let _span = info_span!(
+                                "gen_chunk",
+                                task = %GenTaskName {
+                                    layer_id,
+                                    chunk_idx
+                                }
+                            )
+                            .entered();
+                            let start = Instant::now();
+                            let lookup = detached.lookup();
+                            let tracking = lookup.tracking(layer_id, chunk_idx, None, None);
+                            let chunk = generator(&tracking, &chunk_idx);
+                            (chunk, start.elapsed())
+                        });
+                        self.in_flight.insert(chunk_idx, task);
+                    }
+                }
+                finished
+            }
+        }
+        .into_iter()
+        .partition(|(_, chunk, _, _)| chunk.is_some());
+        // Deferred chunks stay pending, they are scheduled again on the next regenerate
+        if !deferred.is_empty() {
+            debug!(
+                target: log_targets::GENERATION,
+                "Deferred {} chunks of {:?}",
+                deferred.len(),
+                self.layer_id
+            );
+        }
+        if !generated.is_empty() {
+            *self.snapshot.get_mut().unwrap() = None;
+        }
+        if !generated.is_empty() {
+            debug!(
+                target: log_targets::GENERATION,
+                "Generated {} chunks of {:?}",
+                generated.len(),
+                self.layer_id
+            );
+        }
+        let mut generated_list: Vec<ChunkIdx> =
+            generated.iter().map(|(chunk_idx, _, _, _)| *chunk_idx).collect();
+        generated_list.sort();
+        let mut over_budget = Vec::new();
+        for (chunk_idx, gen_chunk, reads, elapsed) in generated {
+            if self.chunk_budget.is_some_and(|budget| elapsed > budget) {
+                over_budget.push((chunk_idx, elapsed));
+            }
+            if let Some(chunk) = self.storage.get_mut(&chunk_idx) {
+                chunk.expires_at = gen_chunk
+                    .as_deref()
+                    .and_then(|gen_chunk| (self.expiry)(gen_chunk, &chunk_idx))
+                    .map(|ttl| self.clock + ttl);
+                if gen_chunk.is_some() {
+                    chunk.due_at = None;
+                    chunk.deadline_missed = false;
+                    chunk.loaded = false;
+                    // The fade to the new data starts now
+                    if let Some(previous) = chunk.previous.as_mut() {
+                        previous.replaced_at = Some(self.frame);
+                    }
+                }
+                chunk.chunk = gen_chunk;
+                chunk.reads = reads;
+                chunk.cost = elapsed;
+            }
+        }
+        // Each pending chunk past its deadline is reported once
+        let mut missed_deadlines = Vec::new();
+        for (chunk_idx, chunk) in self.storage.iter_mut() {
+            let Some(due_at) = chunk.due_at.filter(|due_at| *due_at <= self.clock) else {
+                continue;
+            };
+            if chunk.chunk.is_none() && !chunk.deadline_missed {
+                chunk.deadline_missed = true;
+                missed_deadlines.push((*chunk_idx, due_at));
+            }
+        }
+        missed_deadlines.sort();
+
+        LayerGenerationResult {
+            over_budget,
+            generated: generated_list,
+            deferred: deferred.len(),
+            missed_deadlines,
+        }
+    }
+
+    pub fn get_chunk_size(&self) -> Point {
+        self.chunk_size
+    }
+
+    pub fn get_layer_id(&self) -> LayerId {
+        self.layer_id
+    }
+
+    pub fn get_storage(&self) -> &ChunkStorage {
+        &self.storage
+    }
+
+    /// The generated chunk at the index, the error says why there is none
+    pub(crate) fn try_get_chunk<T: Chunk>(&self, chunk_idx: ChunkIdx) -> Result<&T, ChunksError> {
+        let chunk = self
+            .storage
+            .get(&chunk_idx)
+            .and_then(|chunk| chunk.get_dyn_chunk())
+            .ok_or(ChunksError::ChunkNotGenerated {
+                layer: self.layer_id,
+                chunk: chunk_idx,
+            })?;
+        chunk.downcast_ref::<T>().ok_or(ChunksError::TypeMismatch {
+            layer: self.layer_id,
+            chunk: chunk_idx,
+        })
+    }
+
+    /// Store an already built chunk, e.g. one loaded from a save
+    pub(crate) fn insert_chunk(&mut self, chunk_idx: ChunkIdx, chunk: Arc<dyn Chunk>) {
+        *self.snapshot.get_mut().unwrap() = None;
+        self.in_flight.remove(&chunk_idx);
+        let wrapper = self.storage.entry(chunk_idx).or_insert_with(ChunkWrapper::new);
+        wrapper.chunk = Some(chunk);
+        wrapper.loaded = true;
+    }
+
+    /// Drop everything this frame doesn't use: the unused chunks waiting out their grace
+    /// period or usage decay, the pending chunks no longer requested, the data kept to
+    /// cross-fade and the snapshot. Loaded chunks are kept, they can't be generated again.
+    /// Returns the dropped chunks in Morton order
+    pub(crate) fn relieve_memory_pressure(&mut self) -> Vec<ChunkIdx> {
+        let strategies = [UsageStrategy::KeepAlive, UsageStrategy::Slow, Fast];
+        let frame = self.frame;
+        let mut dropped: Vec<ChunkIdx> = self
+            .storage
+            .iter()
+            .filter(|(_, chunk)| {
+                let unused = strategies
+                    .iter()
+                    .all(|strategy| chunk.usage_counter.get_count_at(frame, *strategy) == 0);
+                unused && !chunk.loaded
+            })
+            .map(|(chunk_idx, _)| *chunk_idx)
+            .collect();
+        for chunk_idx in dropped.iter() {
+            self.storage.remove(chunk_idx);
+            self.in_flight.remove(chunk_idx);
+        }
+        self.dependency_changes
+            .retain(|(chunk_idx, _, _)| self.storage.contains_key(chunk_idx));
+        for (_, chunk) in self.storage.iter_mut() {
+            chunk.previous = None;
+        }
+        *self.snapshot.get_mut().unwrap() = None;
+        dropped.sort();
+        dropped
+    }
+
+    /// Take the chunks the async pool finished since the last regenerate, the ones no longer
+    /// used or invalidated meanwhile were cancelled
+    fn collect_async(
+        &mut self,
+        lookup: &LayerLookupChunk,
+    ) -> Vec<(ChunkIdx, Option<Arc<dyn Chunk>>, Vec<(LayerId, Bounds)>, Duration)> {
+        let done: Vec<ChunkIdx> = self
+            .in_flight
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(chunk_idx, _)| *chunk_idx)
+            .collect();
+        let mut finished = Vec::with_capacity(done.len());
+        for chunk_idx in done {
+            let task = self.in_flight.remove(&chunk_idx).unwrap();
+            let (chunk, elapsed) = block_on(task);
+            if self.storage.get(&chunk_idx).is_some_and(|chunk| !chunk.is_generated()) {
+                let reads = (self.dependency_bounds)(lookup, &chunk_idx);
+                finished.push((chunk_idx, chunk, reads, elapsed));
+            }
+        }
+        finished
+    }
+
+    /// Where the chunk is in its generation, None when it is not requested
+    pub fn get_chunk_status(&self, chunk_idx: &ChunkIdx) -> Option<ChunkStatus> {
+        let chunk = self.storage.get(chunk_idx)?;
+        Some(if chunk.is_generated() {
+            ChunkStatus::Generated
+        } else if self.in_flight.contains_key(chunk_idx) {
+            ChunkStatus::Pending
+        } else {
+            ChunkStatus::Queued
+        })
+    }
+
+    /// Drop the data of the generated chunks among these, they are regenerated if still used.
+    /// Returns the chunks that were generated
+    pub(crate) fn invalidate_chunks(
+        &mut self,
+        chunks: impl IntoIterator<Item = ChunkIdx>,
+    ) -> Vec<ChunkIdx> {
+        let mut invalidated = Vec::new();
+        let cross_fade = self.cross_fade;
+        for chunk_idx in chunks {
+            // A generation started before the invalidation would bring back the old data
+            self.in_flight.remove(&chunk_idx);
+            if let Some(chunk) = self.storage.get_mut(&chunk_idx) {
+                if let Some(old) = chunk.chunk.take() {
+                    if cross_fade > 0 {
+                        chunk.previous = Some(PreviousChunk {
+                            chunk: old,
+                            replaced_at: None,
+                        });
+                    }
+                    chunk.reads.clear();
+                    chunk.expires_at = None;
+                    invalidated.push(chunk_idx);
+                }
+            }
+        }
+        if !invalidated.is_empty() {
+            *self.snapshot.get_mut().unwrap() = None;
+            // Regenerated chunks read the new data anyway
+            self.dependency_changes
+                .retain(|(chunk_idx, _, _)| !invalidated.contains(chunk_idx));
+        }
+        invalidated
+    }
+
+    /// Wait for the damaged chunks of the dependency to be generated again and notify the
+    /// chunks that read them, instead of invalidating those chunks
+    pub(crate) fn watch_dependency_changes(
+        &mut self,
+        layer_id: LayerId,
+        dependency_chunk_size: Point,
+        damaged: &HashSet<ChunkIdx>,
+    ) {
+        for (chunk_idx, chunk) in self.storage.iter() {
+            if !chunk.is_generated() {
+                continue;
+            }
+            for (_, bounds) in chunk.reads.iter().filter(|(read, _)| *read == layer_id) {
+                for read in self.coordinates.chunks(bounds, dependency_chunk_size) {
+                    let change = (*chunk_idx, layer_id, read);
+                    if damaged.contains(&read) && !self.dependency_changes.contains(&change) {
+                        self.dependency_changes.push(change);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Notify the chunks whose changed dependency chunks are generated again, returns the
+    /// chunks that changed. Chunks the layer doesn't update are invalidated
+    pub(crate) fn apply_dependency_changes(&mut self, lookup: &LayerLookupChunk) -> Vec<ChunkIdx> {
+        let (ready, waiting): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.dependency_changes)
+                .into_iter()
+                .partition(|(_, dependency, dependency_idx)| {
+                    lookup.is_chunk_generated(*dependency, dependency_idx)
+                });
+        self.dependency_changes = waiting;
+        let mut changed = Vec::new();
+        for (chunk_idx, dependency, dependency_idx) in ready {
+            // Chunks deleted or invalidated since are generated from the new data anyway
+            let Some(chunk) = self.storage.get(&chunk_idx).and_then(|chunk| chunk.chunk.clone())
+            else {
+                continue;
+            };
+            let updated = (self.on_dependency_changed)(
+                &lookup.tracking(self.layer_id, chunk_idx, None, None),
+                chunk.as_ref(),
+                &chunk_idx,
+                dependency,
+                &dependency_idx,
+            );
+            match updated {
+                Some(updated) => {
+                    let frame = self.frame;
+                    let wrapper = self.storage.get_mut(&chunk_idx).unwrap();
+                    if self.cross_fade > 0 {
+                        wrapper.previous = Some(PreviousChunk {
+                            chunk,
+                            replaced_at: Some(frame),
+                        });
+                    }
+                    wrapper.chunk = Some(updated);
+                }
+                None => {
+                    self.invalidate_chunks([chunk_idx]);
+                }
+            }
+            if !changed.contains(&chunk_idx) {
+                changed.push(chunk_idx);
+            }
+        }
+        if !changed.is_empty() {
+            *self.snapshot.get_mut().unwrap() = None;
+        }
+        changed
+    }
+
+    /// The generated chunks built from any of the damaged chunks of the dependency
+    pub(crate) fn chunks_reading(
+        &self,
+        layer_id: LayerId,
+        dependency_chunk_size: Point,
+        damaged: &HashSet<ChunkIdx>,
+    ) -> Vec<ChunkIdx> {
+        self.storage
+            .iter()
+            .filter(|(_, chunk)| {
+                chunk.reads.iter().any(|(read_layer, bounds)| {
+                    *read_layer == layer_id
+                        && self
+                            .coordinates
+                            .chunks(bounds, dependency_chunk_size)
+                            .any(|read| damaged.contains(&read))
+                })
+            })
+            .map(|(chunk_idx, _)| *chunk_idx)
+            .collect()
+    }
+
+    pub fn get_storage_mut(&mut self) -> &mut ChunkStorage {
+        *self.snapshot.get_mut().unwrap() = None;
+        &mut self.storage
+    }
+
+    /// Snapshot of the generated chunks, only rebuilt after they change
+    pub fn snapshot(&self) -> Arc<LayerSnapshot> {
+        self.snapshot
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let chunks = self
+                    .storage
+                    .iter()
+                    .filter_map(|(idx, chunk)| Some((*idx, chunk.chunk.clone()?)))
+                    .collect();
+                Arc::new(LayerSnapshot::new(self.chunk_size, self.coordinates, chunks))
+            })
+            .clone()
+    }
+
+    pub fn get_dependencies(&self) -> &Vec<Dependency> {
+        &self.depends_on
+    }
+
+    pub fn get_max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    pub fn get_grace_period(&self) -> Option<GracePeriod> {
+        self.grace_period
+    }
+
+    pub fn get_lane(&self) -> GenerationLane {
+        self.lane
+    }
+
+    pub fn get_chunk_budget(&self) -> Option<Duration> {
+        self.chunk_budget
+    }
+
+    pub fn get_group(&self) -> Option<&'static str> {
+        self.group
+    }
+
+    /// Check if the layer reads the region facts of the key, see [`Layer::region_facts`]
+    pub fn reads_fact(&self, key: &str) -> bool {
+        self.facts.contains(&key)
+    }
+
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Time [`Layer::prepare`] took
+    pub fn get_prepare_time(&self) -> Duration {
+        self.prepare_time
+    }
+
+    /// The chunk if generated, else its placeholder while it is pending
+    pub(crate) fn get_chunk_state<T: Chunk + Clone>(
+        &self,
+        chunk_idx: &ChunkIdx,
+    ) -> Option<ChunkState<T>> {
+        let chunk = self.storage.get(chunk_idx)?;
+        match chunk.get_chunk::<T>() {
+            Some(chunk) => Some(ChunkState::Generated(chunk.clone())),
+            None if chunk.is_generated() => None,
+            None => {
+                let placeholder = (self.placeholder)(chunk_idx)?;
+                placeholder.downcast_ref::<T>().cloned().map(ChunkState::Placeholder)
+            }
+        }
+    }
+
+    /// The chunk and the data it replaced while it fades in, None when nothing is fading
+    pub(crate) fn get_cross_fade<T: Chunk + Clone>(
+        &self,
+        chunk_idx: &ChunkIdx,
+    ) -> Option<CrossFade<T>> {
+        let chunk = self.storage.get(chunk_idx)?;
+        let previous = chunk.previous.as_ref()?;
+        let weight = match previous.replaced_at {
+            Some(replaced_at) => {
+                ((self.frame + 1 - replaced_at) as f32 / self.cross_fade as f32).min(1.0)
+            }
+            None => 0.0,
+        };
+        Some(CrossFade {
+            previous: previous.chunk.downcast_ref::<T>()?.clone(),
+            current: chunk.get_chunk::<T>().cloned(),
+            weight,
+        })
+    }
+
+    /// The layer this config was built from, if it is a `T`
+    pub(crate) fn get_layer<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.handle.clone().downcast::<T>().ok()
+    }
+
+    /// Mean generation time of the generated chunks, None before the first one
+    pub(crate) fn mean_chunk_cost(&self) -> Option<Duration> {
+        let costs: Vec<Duration> = self
+            .storage
+            .values()
+            .filter(|chunk| chunk.chunk.is_some() && !chunk.cost.is_zero())
+            .map(|chunk| chunk.cost)
+            .collect();
+        if costs.is_empty() {
+            return None;
+        }
+        Some(costs.iter().sum::<Duration>() / costs.len() as u32)
+    }
+
+    /// Number of chunks generating on the async pool
+    pub(crate) fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Some chunk generating on the async pool is done, the next regenerate collects it
+    pub(crate) fn has_finished_async(&self) -> bool {
+        self.in_flight.values().any(|task| task.is_finished())
+    }
+
+    /// Number of used chunks that are not generated yet
+    pub fn pending_count(&self) -> usize {
+        self.storage
+            .values()
+            .filter(|chunk| {
+                chunk.chunk.is_none()
+                    && chunk
+                        .usage_counter
+                        .best_usage_at(self.frame, &self.usage_decay)
+                        .is_some()
+            })
+            .count()
+    }
+
+    /// Shallow estimate of the memory used by the stored chunks
+    pub fn storage_bytes(&self) -> usize {
+        self.storage
+            .values()
+            .map(|chunk| {
+                std::mem::size_of::<(ChunkIdx, ChunkWrapper)>()
+                    + chunk
+                        .chunk
+                        .as_ref()
+                        .map_or(0, |data| std::mem::size_of_val(data.as_ref()))
+            })
+            .sum()
+    }
+
+    /// Start a new frame, the usages of previous frames decay instead of being cleared
+    pub(crate) fn begin_frame(&mut self, frame: u64, clock: Duration) {
+        self.frame = frame;
+        self.clock = clock;
+        self.scheduled_in_frame = 0;
+        if self.cross_fade > 0 {
+            let cross_fade = self.cross_fade;
+            for chunk in self.storage.values_mut() {
+                let faded = chunk.previous.as_ref().is_some_and(|previous| {
+                    previous
+                        .replaced_at
+                        .is_some_and(|replaced_at| frame + 1 - replaced_at >= cross_fade)
+                });
+                if faded {
+                    chunk.previous = None;
+                }
+            }
+        }
+    }
+
+    /// Invalidate the chunks whose expiry passed, returns them in Morton order
+    pub(crate) fn expire(&mut self) -> Vec<ChunkIdx> {
+        let clock = self.clock;
+        let expired: Vec<ChunkIdx> = self
+            .storage
+            .iter()
+            .filter(|(_, chunk)| chunk.expires_at.is_some_and(|expires_at| expires_at <= clock))
+            .map(|(chunk_idx, _)| *chunk_idx)
+            .collect();
+        let mut expired = self.invalidate_chunks(expired);
+        expired.sort();
+        expired
+    }
+
+    pub(crate) fn set_usage_decay(&mut self, usage_decay: UsageDecay) {
+        self.usage_decay = usage_decay;
+    }
+
+    pub(crate) fn set_slow_schedule(&mut self, slow_schedule: SlowSchedule) {
+        self.slow_schedule = slow_schedule;
+    }
+
+    pub(crate) fn set_lockstep(&mut self, lockstep: bool) {
+        self.lockstep = lockstep;
+    }
+
+    pub(crate) fn set_strict_dependencies(&mut self, strict_dependencies: bool) {
+        self.strict_dependencies = strict_dependencies;
+    }
+
+    pub(crate) fn set_record_reads(&mut self, record_reads: bool) {
+        self.record_reads = record_reads;
+    }
+
+    pub fn is_recording_reads(&self) -> bool {
+        self.record_reads
+    }
+
+    /// Smallest padding of each dependency covering the reads of the chunks generated so far,
+    /// empty unless the reads are recorded
+    pub fn get_recorded_padding(&self) -> HashMap<LayerId, Padding> {
+        self.recorded_padding.lock().unwrap().clone()
+    }
+
+    /// Reads of each dependency by how many of its chunks they reach past the border, empty
+    /// unless the reads are recorded
+    pub fn get_read_histograms(&self) -> HashMap<LayerId, ReadHistogram> {
+        self.read_histograms.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_coordinate_math(&mut self, coordinates: CoordinateMath) {
+        self.coordinates = coordinates;
+    }
+
+    pub fn get_coordinate_math(&self) -> CoordinateMath {
+        self.coordinates
+    }
+
+    /// The chunk of this layer containing the point
+    pub fn chunk_at(&self, pos: impl Into<WorldPos>) -> ChunkIdx {
+        let pos: WorldPos = pos.into();
+        self.coordinates.chunk_idx(pos.0, self.chunk_size)
+    }
+
+    /// The chunk of this layer containing the point and the point inside it
+    pub fn to_chunk_local(&self, pos: impl Into<WorldPos>) -> (ChunkIdx, ChunkLocalPos) {
+        let pos: WorldPos = pos.into();
+        let chunk_idx = self.chunk_at(pos);
+        (chunk_idx, pos.to_local(chunk_idx, self.chunk_size))
+    }
+
+    /// The world position of a point inside a chunk of this layer
+    pub fn to_world(&self, chunk_idx: ChunkIdx, pos: ChunkLocalPos) -> WorldPos {
+        pos.to_world(chunk_idx, self.chunk_size)
+    }
+
+    /// The chunks of this layer overlapping the bounds
+    pub fn chunks_in(&self, bounds: &Bounds) -> impl Iterator<Item = ChunkIdx> {
+        self.coordinates.chunks(bounds, self.chunk_size)
+    }
+
+    pub(crate) fn set_cross_fade(&mut self, cross_fade: u64) {
+        self.cross_fade = cross_fade;
+    }
+}
+
+pub trait IntoLayerConfig {
+    fn into_layer_config(self) -> LayerConfig;
+}
+
+pub trait Chunk: Send + Sync + Downcast + Debug + 'static {
+    fn get_size() -> Vec2
+    where
+        Self: Sized;
+
+    /// A named part of the chunk data (e.g. "height" of a terrain chunk), so dependents can
+    /// read and depend on only the parts they need
+    fn get_product(&self, _name: &str) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Checksum of the chunk data, compared between lockstep peers to detect desyncs. Hashes
+    /// the `Debug` output by default, override it for large chunks
+    fn checksum(&self) -> u64 {
+        crate::checksum::debug_checksum(self)
+    }
+}
+impl_downcast!(Chunk);
+
+#[derive(Debug)]
+pub struct ChunkWrapper {
+    chunk: Option<Arc<dyn Chunk>>,
+    usage_counter: UsageCounter,
+    /// Regions of the dependencies the chunk was generated from
+    reads: Vec<(LayerId, Bounds)>,
+    /// Game time the chunk must be regenerated at
+    expires_at: Option<Duration>,
+    /// Time the last generation of the chunk took
+    cost: Duration,
+    /// Game time the pending chunk must be generated by
+    due_at: Option<Duration>,
+    /// The deadline passed and was reported
+    deadline_missed: bool,
+    /// Data the chunk had before it was regenerated, kept to cross-fade
+    previous: Option<PreviousChunk>,
+    /// Frame and game time the chunk was first found unused, see [`Layer::grace_period`]
+    unused_since: Option<(u64, Duration)>,
+    /// Stored from outside the generator, e.g. a save, it can't be generated again
+    loaded: bool,
+}
+
+/// Data a regenerated chunk replaced
+#[derive(Debug)]
+struct PreviousChunk {
+    chunk: Arc<dyn Chunk>,
+    /// Frame the new data was generated at, None while the chunk is regenerating
+    replaced_at: Option<u64>,
+}
+
+impl ChunkWrapper {
+    fn new() -> Self {
+        ChunkWrapper {
+            chunk: None,
+            usage_counter: UsageCounter::new(),
+            reads: Vec::new(),
+            expires_at: None,
+            cost: Duration::ZERO,
+            due_at: None,
+            deadline_missed: false,
+            previous: None,
+            unused_since: None,
+            loaded: false,
+        }
+    }
+
+    pub fn is_generated(&self) -> bool {
+        self.chunk.is_some()
+    }
+
+    pub fn get_chunk<T: Chunk>(&self) -> Option<&T> {
+        self.chunk.as_ref().and_then(|c| c.downcast_ref::<T>())
+    }
+
+    pub(crate) fn get_dyn_chunk(&self) -> Option<&dyn Chunk> {
+        self.chunk.as_deref()
+    }
+
+    /// Regions of the dependencies the chunk was generated from, empty while pending
+    pub fn get_reads(&self) -> &[(LayerId, Bounds)] {
+        &self.reads
+    }
+
+    /// Time the last generation of the chunk took
+    pub fn get_cost(&self) -> Duration {
+        self.cost
+    }
+}
+
+pub trait Layer {
+    // Required
+    type Chunk: Chunk;
+
+    /// Generate the chunk at the index. The seeded random numbers of the chunk come from
+    /// [`LayerLookupChunk::context`](crate::layer_manager::LayerLookupChunk::context), the
+    /// lookup is bound to the chunk being generated. They are not a parameter so the layers
+    /// that don't need them keep their signature
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk;
+
+    // Optional
+    /// Generate the chunk or defer it when something it needs is not available yet (e.g. an
+    /// asset still loading), deferred chunks stay pending and are retried on the next
+    /// regenerate. Calls [`Layer::generate`] by default
+    fn try_generate(
+        &self,
+        lookup: &LayerLookupChunk,
+        chunk_idx: &ChunkIdx,
+    ) -> GenerateOutcome<Self::Chunk> {
+        GenerateOutcome::Ready(self.generate(lookup, chunk_idx))
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![]
+    }
+
+    /// Maximum number of chunks generated per regenerate, useful for slow (IO or GPU backed)
+    /// layers. The chunks with the highest priority are generated first, the rest stays queued
+    fn max_in_flight(&self) -> Option<usize> {
+        None
+    }
+
+    /// How long the generated chunks are kept once unused, by default they are evicted at
+    /// once. Chunks coming back into use within it are not regenerated
+    fn grace_period(&self) -> Option<GracePeriod> {
+        None
+    }
+
+    /// Thread pool the chunks are generated on, mark disk or network backed layers as
+    /// [`GenerationLane::Io`] so they don't block the CPU bound layers, and layers too heavy
+    /// to finish within a frame as [`GenerationLane::Async`]
+    fn lane(&self) -> GenerationLane {
+        GenerationLane::Compute
+    }
+
+    /// Expected generation time of a single chunk, slower chunks are reported with a
+    /// [`LayerBudgetExceeded`](crate::events::LayerBudgetExceeded)
+    fn chunk_budget(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Name of the [`LayerGroup`](crate::group::LayerGroup) whose budget the layer shares,
+    /// the group must be added with
+    /// [`LayersManagerBuilder::add_group`](crate::layer_manager::LayersManagerBuilder::add_group)
+    fn group(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Keys of the region facts the layer reads with
+    /// [`LayerLookupChunk::get_fact`](crate::layer_manager::LayerLookupChunk::get_fact), its
+    /// chunks overlapping a fact of these keys are regenerated when the fact changes
+    fn region_facts(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// Cheap stand-in of a chunk while it is pending, e.g. fog or low detail filler so
+    /// renderers don't show holes. Query it with
+    /// [`LayersManager::get_chunk_or_placeholder`](crate::layer_manager::LayersManager::get_chunk_or_placeholder)
+    fn placeholder(&self, _chunk_idx: &ChunkIdx) -> Option<Self::Chunk> {
+        None
+    }
+
+    /// How long the generated chunk stays valid, in game time (e.g. market prices valid for
+    /// 10 minutes). Expired chunks are regenerated if still used, see
+    /// [`LayersManager::advance_clock`](crate::layer_manager::LayersManager::advance_clock)
+    fn expiry(&self, _chunk: &Self::Chunk, _chunk_idx: &ChunkIdx) -> Option<Duration> {
+        None
+    }
+
+    /// Version of the generator, bump it when the output changes so chunks saved by older
+    /// versions are regenerated instead of mixed with the new output
+    fn version(&self) -> u32 {
+        0
+    }
+
+    /// Precompute lookup tables or load data once, when the layer is added and before any of
+    /// its chunks is generated, so the first chunks don't pay for it. The time it takes is
+    /// reported by [`LayersManager::get_prepare_time`]
+    ///
+    /// [`LayersManager::get_prepare_time`]: crate::layer_manager::LayersManager::get_prepare_time
+    fn prepare(&mut self, _ctx: &PrepareContext) {}
+
+    /// Called when a chunk of a dependency declared with
+    /// [`Dependency::with_change_notifications`] was generated again and the chunk read it,
+    /// to update cached border data (e.g. stitched normals) without a full regeneration.
+    /// Return the updated chunk, or `None` to regenerate it
+    fn on_dependency_changed(
+        &self,
+        _lookup: &LayerLookupChunk,
+        _chunk: &Self::Chunk,
+        _chunk_idx: &ChunkIdx,
+        _dependency: LayerId,
+        _dependency_idx: &ChunkIdx,
+    ) -> Option<Self::Chunk> {
+        None
+    }
+
+    /// Scheduling priority of a chunk, higher is generated first, also across the layers
+    /// sharing the [`LayerGroup::with_max_chunks`](crate::group::LayerGroup::with_max_chunks)
+    /// limit of a group. By default the chunks closest to the clients come first, override it
+    /// to boost or demote chunks of this layer
+    fn priority(&self, _chunk_idx: &ChunkIdx, distance_to_nearest_client: f32) -> f32 {
+        -distance_to_nearest_client
+    }
+
+    /// The regions of the dependencies needed to generate the chunk, by default the chunk
+    /// bounds with the padding of each dependency. Override it for data-dependent regions,
+    /// the returned layers must still be declared in [`Layer::get_dependencies`].
+    /// The lookup only sees the chunks generated so far, enable more requirement passes in
+    /// [`LayersManagerBuilder::requirement_passes`] when the regions depend on that data
+    ///
+    /// [`LayersManagerBuilder::requirement_passes`]: crate::layer_manager::LayersManagerBuilder::requirement_passes
+    fn dependency_bounds(
+        &self,
+        _lookup: &LayerLookupChunk,
+        chunk_idx: &ChunkIdx,
+    ) -> Vec<(LayerId, Bounds)> {
+        let Vec2 {
+            x: width,
+            y: height,
+        } = Self::Chunk::get_size();
+        let bounds = chunk_idx.to_bounds(width, height);
+        self.get_dependencies()
+            .iter()
+            .map(|dep| (dep.get_layer_id(), bounds.add_padding(dep.get_padding())))
+            .collect()
+    }
+
+    // Given
+
+    fn get_layer_id(&self) -> LayerId
+    where
+        Self: Sized + 'static,
+    {
+        LayerId::from_type::<Self>()
+    }
+}
+
+/// The dependency of a layer
+/// The padding is in real coordinates, either a symmetric `Vec2` or a per side [`Padding`]
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    layer_id: LayerId,
+    padding: Padding,
+    /// The products of the dependency chunks that are read, all of them when `None`
+    products: Option<Vec<&'static str>>,
+    /// Notify the layer of changed chunks instead of regenerating its chunks
+    notify_changes: bool,
+}
+
+impl Dependency {
+    pub fn new<T: Layer + Sized + 'static>(padding: impl Into<Padding>) -> Self {
+        Dependency {
+            layer_id: LayerId::from_type::<T>(),
+            padding: padding.into(),
+            products: None,
+            notify_changes: false,
+        }
+    }
+
+    /// When chunks of the dependency are regenerated, keep the chunks that read them and
+    /// update them with [`Layer::on_dependency_changed`] instead
+    pub fn with_change_notifications(mut self) -> Self {
+        self.notify_changes = true;
+        self
+    }
+
+    pub(crate) fn notifies_changes(&self) -> bool {
+        self.notify_changes
+    }
+
+    /// Only read these products of the dependency, changes to the other products don't
+    /// regenerate this layer. See [`Chunk::get_product`]
+    pub fn with_products(mut self, products: &[&'static str]) -> Self {
+        self.products = Some(products.to_vec());
+        self
+    }
+
+    /// The same dependency with another padding
+    pub fn with_padding(mut self, padding: impl Into<Padding>) -> Self {
+        self.padding = padding.into();
+        self
+    }
+
+    /// Check if a change of these products of the dependency affects this layer
+    pub(crate) fn reads_any(&self, products: &[&str]) -> bool {
+        self.products
+            .as_ref()
+            .is_none_or(|read| read.iter().any(|product| products.contains(product)))
+    }
+
+    pub(crate) fn get_layer_id(&self) -> LayerId {
+        self.layer_id
+    }
+
+    pub(crate) fn get_padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// Widen this dependency to also cover `other`, a dependency on the same layer
+    pub(crate) fn merge(&mut self, other: &Dependency) {
+        debug_assert_eq!(self.layer_id, other.layer_id);
+        self.padding = self.padding.union(other.padding);
+        self.products = match (self.products.take(), &other.products) {
+            (Some(mut products), Some(other)) => {
+                for product in other {
+                    if !products.contains(product) {
+                        products.push(product);
+                    }
+                }
+                Some(products)
+            }
+            // One of them reads every product
+            _ => None,
+        };
+        self.notify_changes &= other.notify_changes;
+    }
+}
+
+/// What the builder does with a layer whose chunk size is neither a multiple nor a divisor of
+/// the chunk size of a dependency. Their chunk edges don't line up, so each chunk reads parts
+/// of dependency chunks and the coverage at the edges is off by one chunk in places
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkAlignment {
+    /// Allow misaligned chunk sizes
+    Ignore,
+    /// Log a warning for each misaligned dependency
+    #[default]
+    Warn,
+    /// Panic on the first misaligned dependency
+    Enforce,
+}
+
+/// A dependency whose chunk size doesn't line up with the chunk size of the layer, see
+/// [`ChunkAlignment`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MisalignedChunks {
+    pub layer: LayerId,
+    pub layer_size: Point,
+    pub dependency: LayerId,
+    pub dependency_size: Point,
+}
+
+impl MisalignedChunks {
+    /// Check if the sizes are multiples of each other on both axes, global sizes are always
+    /// aligned
+    pub fn is_aligned(layer_size: Point, dependency_size: Point) -> bool {
+        let aligned = |a: f32, b: f32| {
+            if !a.is_finite() || !b.is_finite() {
+                return true;
+            }
+            let ratio = a.max(b) / a.min(b);
+            (ratio - ratio.round()).abs() <= ratio * 1e-4
+        };
+        aligned(layer_size.x, dependency_size.x) && aligned(layer_size.y, dependency_size.y)
+    }
+
+    /// The closest multiple of the dependency chunk size
+    pub fn suggested_size(&self) -> Point {
+        (self.layer_size / self.dependency_size).round().max(Vec2::ONE) * self.dependency_size
+    }
+}
+
+impl std::fmt::Display for MisalignedChunks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Chunks of {:?} ({}) are neither a multiple nor a divisor of the chunks of its \
+             dependency {:?} ({}), their edges don't line up. Use e.g. a chunk size of {}",
+            self.layer,
+            self.layer_size,
+            self.dependency,
+            self.dependency_size,
+            self.suggested_size()
+        )
+    }
+}
+
+impl<T> IntoLayerConfig for T
+where
+    T: Layer + 'static + Send + Sync,
+    T::Chunk: Chunk,
+{
+    fn into_layer_config(mut self) -> LayerConfig {
+        let start = Instant::now();
+        self.prepare(&PrepareContext::new(LayerId::from_type::<T>(), T::Chunk::get_size()));
+        let prepare_time = start.elapsed();
+        // The layer is shared by the generator and the dependency bounds callbacks
+        let layer = Arc::new(self);
+        let generator = layer.clone();
+        let prioritizer = layer.clone();
+        let placeholder = layer.clone();
+        let expiry = layer.clone();
+        let notified = layer.clone();
+        LayerConfig {
+            layer_id: LayerId::from_type::<T>(),
+            depends_on: layer.get_dependencies(),
+            chunk_size: T::Chunk::get_size(),
+            storage: ChunkStorage::new(),
+            max_in_flight: layer.max_in_flight(),
+            frame: 0,
+            usage_decay: UsageDecay::default(),
+            slow_schedule: SlowSchedule::default(),
+            fast_since_slow: 0,
+            scheduled_in_frame: 0,
+            grace_period: layer.grace_period(),
+            snapshot: Mutex::new(None),
+            lane: layer.lane(),
+            chunk_budget: layer.chunk_budget(),
+            group: layer.group(),
+            facts: layer.region_facts(),
+            lockstep: false,
+            strict_dependencies: false,
+            record_reads: false,
+            recorded_padding: Mutex::new(HashMap::new()),
+            read_histograms: Mutex::new(HashMap::new()),
+            coordinates: CoordinateMath::Float,
+            version: layer.version(),
+            prepare_time,
+            handle: layer.clone(),
+            generate: Arc::new(
+                move |lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx| -> Option<Arc<dyn Chunk>> {
+                    match generator.try_generate(lookup, chunk_idx) {
+                        GenerateOutcome::Ready(chunk) => Some(Arc::new(chunk)),
+                        GenerateOutcome::Defer => None,
+                    }
+                },
+            ),
+            placeholder: Box::new(move |chunk_idx: &ChunkIdx| -> Option<Arc<dyn Chunk>> {
+                Some(Arc::new(placeholder.placeholder(chunk_idx)?))
+            }),
+            expiry: Box::new(move |chunk: &dyn Chunk, chunk_idx: &ChunkIdx| {
+                expiry.expiry(chunk.downcast_ref::<T::Chunk>()?, chunk_idx)
+            }),
+            on_dependency_changed: Box::new(
+                move |lookup: &LayerLookupChunk,
+                      chunk: &dyn Chunk,
+                      chunk_idx: &ChunkIdx,
+                      dependency: LayerId,
+                      dependency_idx: &ChunkIdx|
+                      -> Option<Arc<dyn Chunk>> {
+                    let chunk = chunk.downcast_ref::<T::Chunk>()?;
+                    let updated = notified.on_dependency_changed(
+                        lookup,
+                        chunk,
+                        chunk_idx,
+                        dependency,
+                        dependency_idx,
+                    )?;
+                    Some(Arc::new(updated))
+                },
+            ),
+            dependency_changes: Vec::new(),
+            clock: Duration::ZERO,
+            cross_fade: 0,
+            in_flight: HashMap::new(),
+            priority: Box::new(move |chunk_idx: &ChunkIdx, distance: f32| {
+                prioritizer.priority(chunk_idx, distance)
+            }),
+            dependency_bounds: Box::new(move |lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx| {
+                layer.dependency_bounds(lookup, chunk_idx)
+            }),
+        }
+    }
+}
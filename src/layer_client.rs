@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use bevy::ecs::entity::Entity;
+use bevy::math::Vec2;
+use crate::bounds::{ChunkIdx, Padding, Point, WorldPos};
+use crate::layer::{Dependency, Layer};
+use crate::layer_id::LayerId;
+use crate::usage::{Deadline, UsageStrategy};
+
+/// Handle of a client added to a [`LayersManager`](crate::layer_manager::LayersManager), stays
+/// valid while other clients are added and removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientId(pub(crate) u64);
+
+/// Decides whether a client actually needs a chunk of a layer inside its bounds
+pub type ClientFilter = Box<dyn Fn(LayerId, &ChunkIdx) -> bool + Send + Sync>;
+
+pub struct LayerClient {
+    /// Set when the client is added to a manager
+    id: Option<ClientId>,
+    active: bool,
+    center: Point,
+    dependencies: Vec<Dependency>,
+    strategy: UsageStrategy,
+    /// Debug name of the client, used to attribute chunk requests in logs
+    name: Option<String>,
+    /// The entity that owns this client, if any
+    owner: Option<Entity>,
+    /// Optional filter, chunks rejected by it are not requested
+    filter: Option<ClientFilter>,
+    /// Maximum number of new chunks the client requests per regenerate
+    request_limit: Option<usize>,
+    /// How soon the requested chunks must be generated
+    deadline: Deadline,
+}
+
+impl Debug for LayerClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayerClient")
+            .field("id", &self.id)
+            .field("active", &self.active)
+            .field("center", &self.center)
+            .field("dependencies", &self.dependencies)
+            .field("strategy", &self.strategy)
+            .field("name", &self.name)
+            .field("owner", &self.owner)
+            .field("filter", &self.filter.is_some())
+            .field("request_limit", &self.request_limit)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl IntoLayerClient for LayerClient {
+    fn into_layer_client(self) -> LayerClient {
+        self
+    }
+}
+
+impl LayerClient {
+    pub fn new(
+        center: impl Into<WorldPos>,
+        dependencies: Vec<Dependency>,
+        strength: UsageStrategy,
+    ) -> Self {
+        let center: WorldPos = center.into();
+        LayerClient {
+            id: None,
+            active: true,
+            center: center.0,
+            dependencies,
+            strategy: strength,
+            name: None,
+            owner: None,
+            filter: None,
+            request_limit: None,
+            deadline: Deadline::Background,
+        }
+    }
+
+    /// Request at most `limit` chunks the client didn't request before per regenerate, the
+    /// closest first. The rest are deferred to the next regenerates, so a fast moving or
+    /// teleporting client can't take the whole generation budget
+    pub fn with_request_limit(mut self, limit: usize) -> Self {
+        self.request_limit = Some(limit);
+        self
+    }
+
+    pub fn get_request_limit(&self) -> Option<usize> {
+        self.request_limit
+    }
+
+    /// How soon the chunks the client requests must be generated, missed deadlines are
+    /// reported by [`LayersManager::drain_missed_deadlines`]
+    ///
+    /// [`LayersManager::drain_missed_deadlines`]: crate::layer_manager::LayersManager::drain_missed_deadlines
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    pub fn get_deadline(&self) -> Deadline {
+        self.deadline
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Only request the chunks for which `filter` returns true, instead of the full bounds
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(LayerId, &ChunkIdx) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    pub fn activate(&mut self) {
+        self.active = true;
+    }
+    pub fn deactivate(&mut self) {
+        self.active = false;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Handle given by the manager the client was added to
+    pub fn get_id(&self) -> Option<ClientId> {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: ClientId) {
+        self.id = Some(id);
+    }
+
+    pub fn get_center(&self) -> Point {
+        self.center
+    }
+
+    pub fn set_center(&mut self, center: impl Into<WorldPos>) {
+        let center: WorldPos = center.into();
+        self.center = center.0;
+    }
+
+    pub fn set_strategy(&mut self, strategy: UsageStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn get_dependencies(&self) -> &Vec<Dependency> {
+        &self.dependencies
+    }
+    
+    pub fn get_strategy(&self) -> UsageStrategy {
+        self.strategy
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_owner(&self) -> Option<Entity> {
+        self.owner
+    }
+
+    pub fn has_filter(&self) -> bool {
+        self.filter.is_some()
+    }
+
+    /// Check if the client needs the given chunk, according to its filter
+    pub fn wants(&self, layer_id: LayerId, chunk_idx: &ChunkIdx) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter(layer_id, chunk_idx))
+    }
+
+    /// Human readable label for logs and debug tools, e.g. `"Camera (12v1)"`
+    pub fn label(&self) -> String {
+        match (&self.name, self.owner) {
+            (Some(name), Some(owner)) => format!("{} ({})", name, owner),
+            (Some(name), None) => name.clone(),
+            (None, Some(owner)) => format!("{}", owner),
+            (None, None) => "<unnamed>".to_string(),
+        }
+    }
+}
+
+pub trait IntoLayerClient {
+    fn into_layer_client(self) -> LayerClient;
+}
+
+/// New position, and optionally strategy, of the clients owned by an entity, applied in
+/// batch by [`LayersManager::update_clients`](crate::layer_manager::LayersManager::update_clients)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientUpdate {
+    pub owner: Entity,
+    pub center: Point,
+    pub strategy: Option<UsageStrategy>,
+}
+
+impl ClientUpdate {
+    pub fn new(owner: Entity, center: impl Into<WorldPos>) -> Self {
+        let center: WorldPos = center.into();
+        ClientUpdate {
+            owner,
+            center: center.0,
+            strategy: None,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: UsageStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+}
+
+/// Radii around a client where each strategy applies: Fast up to `fast`, Slow up to `slow`
+/// and KeepAlive up to `keep_alive`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceBands {
+    pub fast: f32,
+    pub slow: f32,
+    pub keep_alive: f32,
+}
+
+impl DistanceBands {
+    pub fn new(fast: f32, slow: f32, keep_alive: f32) -> Self {
+        DistanceBands {
+            fast,
+            slow,
+            keep_alive,
+        }
+    }
+}
+
+/// A client requesting each layer with the strategy of the distance band each chunk is in,
+/// added with [`LayersManager::add_banded_client`](crate::layer_manager::LayersManager::add_banded_client).
+/// It becomes one [`LayerClient`] per strategy, the inner bands win where they overlap
+#[derive(Debug, Clone)]
+pub struct BandedClient {
+    center: Point,
+    bands: DistanceBands,
+    dependencies: Vec<(Dependency, DistanceBands)>,
+    name: Option<String>,
+    owner: Option<Entity>,
+}
+
+impl BandedClient {
+    pub fn new(center: impl Into<WorldPos>, bands: DistanceBands) -> Self {
+        let center: WorldPos = center.into();
+        BandedClient {
+            center: center.0,
+            bands,
+            dependencies: Vec::new(),
+            name: None,
+            owner: None,
+        }
+    }
+
+    /// Request the layer with the bands of the client
+    pub fn with_layer<L: Layer + 'static>(self) -> Self {
+        let bands = self.bands;
+        self.with_layer_bands::<L>(bands)
+    }
+
+    /// Request the layer with its own bands
+    pub fn with_layer_bands<L: Layer + 'static>(mut self, bands: DistanceBands) -> Self {
+        self.dependencies
+            .push((Dependency::new::<L>(Padding::default()), bands));
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// One client per strategy, each requesting the layers up to the radius of its band
+    pub fn into_layer_clients(self) -> Vec<LayerClient> {
+        let strategies: [(UsageStrategy, &str, fn(&DistanceBands) -> f32); 3] = [
+            (UsageStrategy::Fast, "fast", |bands| bands.fast),
+            (UsageStrategy::Slow, "slow", |bands| bands.slow),
+            (UsageStrategy::KeepAlive, "keep alive", |bands| bands.keep_alive),
+        ];
+        strategies
+            .into_iter()
+            .map(|(strategy, band, radius)| {
+                let dependencies = self
+                    .dependencies
+                    .iter()
+                    .map(|(dependency, bands)| {
+                        dependency
+                            .clone()
+                            .with_padding(Vec2::splat(radius(bands)))
+                    })
+                    .collect();
+                let mut client = LayerClient::new(self.center, dependencies, strategy);
+                if let Some(name) = &self.name {
+                    client = client.with_name(format!("{} ({})", name, band));
+                }
+                if let Some(owner) = self.owner {
+                    client = client.with_owner(owner);
+                }
+                client
+            })
+            .collect()
+    }
+}
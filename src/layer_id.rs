@@ -2,7 +2,7 @@ use std::any::TypeId;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Mutex;
-use crate::generative_chunks::layer::Layer;
+use crate::layer::Layer;
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -21,14 +21,15 @@ impl LayerId {
         }
         LayerId(id)
     }
+
+    /// Type name of the layer
+    pub fn get_name(&self) -> &'static str {
+        LAYER_ID_MAP.lock().unwrap()[&self.0]
+    }
 }
 
 impl Debug for LayerId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = {
-            let map = LAYER_ID_MAP.lock().unwrap();
-            <&str>::clone(map.get(&self.0).unwrap())
-        };
-        write!(f, "{}", name)
+        write!(f, "{}", self.get_name())
     }
 }
\ No newline at end of file
@@ -0,0 +1,2877 @@
+use crate::bounds::{
+    Bounds, ChunkAnchor, ChunkIdx, ChunkLocalPos, CoordinateMath, Padding, Point, RayChunks,
+    WorldPos,
+};
+use crate::chunk_set::ChunkSet;
+use crate::context::GenerationContext;
+use crate::layer::{
+    Chunk, ChunkAlignment, ChunkState, ChunkStatus, CrossFade, IntoLayerConfig, Layer,
+    LayerConfig, MisalignedChunks,
+};
+#[cfg(feature = "ndarray")]
+use crate::grid::GridChunk;
+use crate::checksum::LayerChecksum;
+use crate::error::ChunksError;
+use crate::diagnostics::{
+    BoundsGeneration, DependencyReads, ProbedChunk, ReadHistogram, RegenerateStats,
+    RequiredPadding,
+};
+use crate::events::{DeadlineMissed, LayerBudgetExceeded, RegionDegraded, RegionReady};
+use crate::group::{GroupUsage, LayerGroup};
+use crate::interest::{
+    ClientAreaDelta, ClientInterest, ClientSpatialIndex, HeldChanges, HeldUsages,
+};
+use crate::layer_client::{BandedClient, ClientId, ClientUpdate, IntoLayerClient, LayerClient};
+use crate::layer_id::LayerId;
+use crate::log_targets;
+use crate::output::{LayerOutput, Output};
+use crate::persistence::{ChunkLoadError, ChunkVersioned, SavedChunk};
+use crate::facts::RegionFacts;
+use crate::resources::{capture, GenerationResources, ResourceCapture};
+use crate::snapshot::{ChunksReader, ChunksSnapshot, LayerSnapshot};
+use crate::teleport::{Teleport, TeleportId};
+use crate::usage::{SlowSchedule, UsageDecay, UsageLeak, UsageStrategy};
+use bevy::ecs::entity::Entity;
+use bevy::ecs::resource::Resource;
+use bevy::ecs::world::World;
+use bevy::log::{debug, info_span, warn};
+use bevy::math::{UVec2, Vec2};
+use bevy::transform::components::Transform;
+use daggy::petgraph::dot::{Config, Dot};
+use daggy::petgraph::visit::Topo;
+use daggy::petgraph::Direction;
+use daggy::{Dag, NodeIndex};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant};
+
+/// Default cell size of the spatial index over the layer clients
+const DEFAULT_CLIENT_CELL_SIZE: Point = Vec2::new(64.0, 64.0);
+
+/// How often [`LayersManager::generate_bounds`] checks the async chunks while it waits on them
+const ASYNC_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Layers added together with [`LayersManagerBuilder::add_bundle`], e.g. the ready made
+/// `presets` of the `presets` feature
+pub trait LayerBundle {
+    fn add_to(self, builder: LayersManagerBuilder) -> LayersManagerBuilder;
+}
+
+pub struct LayersManagerBuilder {
+    layers: Vec<LayerConfig>,
+    client_cell_size: Point,
+    max_requirement_passes: usize,
+    usage_decay: UsageDecay,
+    slow_schedule: SlowSchedule,
+    resource_captures: Vec<ResourceCapture>,
+    seed: u64,
+    audit_usages: bool,
+    groups: Vec<LayerGroup>,
+    warm_start: Option<f32>,
+    layer_tags: HashMap<LayerId, &'static str>,
+    disabled_tags: HashSet<&'static str>,
+    lockstep: bool,
+    strict_dependencies: bool,
+    record_reads: bool,
+    checksums: bool,
+    cross_fade: u64,
+    coordinates: CoordinateMath,
+    region_events: bool,
+    chunk_alignment: ChunkAlignment,
+}
+
+/// Owns the layers and their chunks, it is `Send + Sync` so it can be inserted directly as a
+/// Bevy resource
+#[derive(Resource)]
+pub struct LayersManager {
+    layers: HashMap<LayerId, Arc<RwLock<LayerConfig>>>,
+    dag: Dag<LayerId, ()>,
+    /// Layers depending directly on each layer, the reverse edges of the DAG
+    dependents: HashMap<LayerId, Vec<LayerId>>,
+    /// The clients by handle, in the order they were added
+    layer_client: BTreeMap<ClientId, LayerClient>,
+    /// List of chunks to delete
+    delete_list: HashMap<LayerId, Vec<ChunkIdx>>,
+    /// List of chunks generated on the last regenerate
+    generated_list: HashMap<LayerId, Vec<ChunkIdx>>,
+    /// List of chunks that expired on the last regenerate
+    expired_list: HashMap<LayerId, Vec<ChunkIdx>>,
+    /// Chunk size of each layer
+    chunk_sizes: HashMap<LayerId, Point>,
+    /// Spatial index over the active layer clients, the clients that moved are updated on each
+    /// regenerate
+    client_index: ClientSpatialIndex,
+    /// Chunks each client needed on the last regenerate
+    interest: HashMap<ClientId, ClientInterest>,
+    /// Chunks the clients hold, updated with the changes of the clients that moved
+    held_usages: HeldUsages,
+    /// Clients added, moved or changed since the last regenerate
+    changed_clients: HashSet<ClientId>,
+    /// Clients refreshed on every regenerate: the filtered ones, the ones with a deadline and
+    /// the ones whose request limit is catching up with their area
+    volatile_clients: HashSet<ClientId>,
+    /// Clients refreshed on the last regenerate, their area deltas are cleared on the next one
+    refreshed_clients: Vec<ClientId>,
+    /// Disabled layers whose held chunks are released until they are enabled again
+    released_layers: HashSet<LayerId>,
+    /// Maximum number of requirement passes per regenerate
+    max_requirement_passes: usize,
+    /// Number of requirement passes the last regenerate needed
+    requirement_passes: usize,
+    /// Number of the current regenerate, usages are stamped with it
+    frame: u64,
+    /// Game time, the chunk expiries are measured with it
+    clock: Duration,
+    /// Budget violations not drained yet
+    budget_violations: Vec<LayerBudgetExceeded>,
+    /// Missed deadlines not drained yet
+    missed_deadlines: Vec<DeadlineMissed>,
+    /// Numbers of the last regenerate
+    stats: RegenerateStats,
+    /// Teleports prewarming their destination
+    teleports: Vec<Teleport>,
+    next_teleport_id: u64,
+    next_client_id: u64,
+    /// Values the layers read while generating, see [`LayersManager::capture_resources`]
+    resources: GenerationResources,
+    /// Facts gameplay published over regions, see [`LayersManager::set_region_fact`]
+    facts: RegionFacts,
+    resource_captures: Vec<ResourceCapture>,
+    /// Seed of the world
+    seed: u64,
+    /// Cross-check the usage counters on each regenerate
+    audit_usages: bool,
+    /// Mismatches found by the audit, not drained yet
+    usage_leaks: Vec<UsageLeak>,
+    /// Groups sharing a generation budget, by name
+    groups: HashMap<&'static str, LayerGroup>,
+    /// What each group spent on the last regenerate
+    group_usage: HashMap<&'static str, GroupUsage>,
+    /// Dependencies before their dependents, higher priority groups first
+    generation_order: Vec<LayerId>,
+    /// Radius filled ignoring the budgets on the first regenerate with clients
+    warm_start: Option<f32>,
+    /// How the chunks containing a point are found, see [`LayersManagerBuilder::coordinate_math`]
+    coordinates: CoordinateMath,
+    /// End of the time budget of the running regenerate, see
+    /// [`LayersManager::regenerate_with_budget`]
+    budget_deadline: Option<Instant>,
+    /// Tag of each tagged layer, see [`LayersManagerBuilder::add_layer_tagged`]
+    layer_tags: HashMap<LayerId, &'static str>,
+    /// Tags whose layers are not generated
+    disabled_tags: HashSet<&'static str>,
+    /// Deterministic single threaded generation, advanced with [`LayersManager::step`]
+    lockstep: bool,
+    /// Checksums of the generated chunks of each layer, when enabled
+    checksums: Option<HashMap<LayerId, LayerChecksum>>,
+    /// Track when the areas of the clients are ready, see
+    /// [`LayersManagerBuilder::region_events`]
+    region_events: bool,
+    /// Region events not drained yet
+    ready_regions: Vec<RegionReady>,
+    degraded_regions: Vec<RegionDegraded>,
+}
+
+impl LayersManager {
+    pub fn get_chunk<L: Layer + 'static>(&self, pos: impl Into<WorldPos>) -> Option<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        let chunk_idx = layer.chunk_at(pos);
+        let wrapped_chunk = layer.get_storage().get(&chunk_idx)?;
+        let data = wrapped_chunk.get_chunk::<L::Chunk>();
+        data.cloned()
+    }
+
+    /// Like [`LayersManager::get_chunk`], with the reason there is no chunk instead of a panic
+    /// or None
+    pub fn try_get_chunk<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> Result<L::Chunk, ChunksError>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.try_read_layer(LayerId::from_type::<L>())?;
+        let chunk_idx = layer.chunk_at(pos);
+        layer.try_get_chunk::<L::Chunk>(chunk_idx).cloned()
+    }
+
+    /// The chunk of the layer at the position, or its [`Layer::placeholder`] while it is
+    /// pending. Chunks no client requested have neither
+    pub fn get_chunk_or_placeholder<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> Option<ChunkState<L::Chunk>>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        layer.get_chunk_state(&layer.chunk_at(pos))
+    }
+
+    /// The chunk of the layer at the position with the data it replaced, while it fades in
+    /// after being regenerated. None when the chunk is not fading, see
+    /// [`LayersManagerBuilder::cross_fade`]
+    pub fn get_cross_fade<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> Option<CrossFade<L::Chunk>>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        layer.get_cross_fade(&layer.chunk_at(pos))
+    }
+
+    /// The fading chunks of the layer inside the bounds, ordered by x then y
+    pub fn get_cross_fades_in<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+    ) -> Vec<(ChunkIdx, CrossFade<L::Chunk>)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers[&LayerId::from_type::<L>()].read().unwrap();
+        layer
+            .chunks_in(&bounds)
+            .filter_map(|chunk_idx| Some((chunk_idx, layer.get_cross_fade(&chunk_idx)?)))
+            .collect()
+    }
+
+    /// The chunk of a global layer, see [`GLOBAL_CHUNK_SIZE`](crate::bounds::GLOBAL_CHUNK_SIZE)
+    pub fn get_global<L: Layer + 'static>(&self) -> Option<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        layer.get_storage().get(&ChunkIdx::GLOBAL)?.get_chunk::<L::Chunk>().cloned()
+    }
+
+    /// Where the chunk of the layer at the position is in its generation, None when no client
+    /// requested it. Chunks of [`GenerationLane::Async`](crate::layer::GenerationLane::Async)
+    /// layers stay [`ChunkStatus::Pending`] while they generate
+    pub fn get_chunk_status<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> Option<ChunkStatus> {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        layer.get_chunk_status(&layer.chunk_at(pos))
+    }
+
+    /// Requested chunks of the layer inside the bounds, generated or placeholders, ordered by
+    /// x then y
+    pub fn get_chunks_or_placeholders_in<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+    ) -> Vec<(ChunkIdx, ChunkState<L::Chunk>)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers[&LayerId::from_type::<L>()].read().unwrap();
+        layer
+            .chunks_in(&bounds)
+            .filter_map(|chunk_idx| Some((chunk_idx, layer.get_chunk_state(&chunk_idx)?)))
+            .collect()
+    }
+
+    /// A product of the chunk of the layer at the position, see [`Chunk::get_product`]
+    pub fn get_product<L: Layer + 'static, T: Clone + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+        name: &str,
+    ) -> Option<T> {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        let chunk_idx = layer.chunk_at(pos);
+        let chunk = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+        chunk.get_product(name)?.downcast_ref::<T>().cloned()
+    }
+
+    /// Call `f` with the generated chunk of the layer at the index, without cloning it. The
+    /// layer is locked meanwhile, `f` must not access the manager
+    pub fn with_chunk<L: Layer + 'static, R>(
+        &self,
+        chunk_idx: ChunkIdx,
+        f: impl FnOnce(&L::Chunk) -> R,
+    ) -> Option<R> {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        let chunk = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+        Some(f(chunk))
+    }
+
+    /// Encode a generated chunk of the layer for a save
+    pub fn save_chunk<L: Layer + 'static>(&self, chunk_idx: ChunkIdx) -> Option<SavedChunk>
+    where
+        L::Chunk: ChunkVersioned,
+    {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        let chunk = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+        Some(SavedChunk::save(chunk).with_layer_version(layer.get_version()))
+    }
+
+    /// Store a chunk read from a save instead of generating it, migrating old data first.
+    /// It is evicted like a generated chunk if no client uses it. Chunks saved by another
+    /// [`Layer::version`] are refused with [`ChunkLoadError::StaleLayer`], leave them to the
+    /// generator
+    pub fn load_chunk<L: Layer + 'static>(
+        &mut self,
+        chunk_idx: ChunkIdx,
+        saved: &SavedChunk,
+    ) -> Result<(), ChunkLoadError>
+    where
+        L::Chunk: ChunkVersioned,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        let mut layer = self.layers.get(&layer_id).unwrap().write().unwrap();
+        if saved.layer_version != layer.get_version() {
+            return Err(ChunkLoadError::StaleLayer {
+                found: saved.layer_version,
+                current: layer.get_version(),
+            });
+        }
+        let chunk = saved.load::<L::Chunk>()?;
+        layer.insert_chunk(chunk_idx, Arc::new(chunk));
+        Ok(())
+    }
+
+    /// Generated chunks of the layer inside the bounds, ordered by x then y
+    pub fn get_chunks_in<L: Layer + 'static>(&self, bounds: Bounds) -> Vec<(ChunkIdx, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        Self::collect_chunks_in::<L>(&layer, &bounds)
+    }
+
+    /// Like [`LayersManager::get_chunks_in`], errors when the layer is not registered
+    pub fn try_get_chunks_in<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+    ) -> Result<Vec<(ChunkIdx, L::Chunk)>, ChunksError>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.try_read_layer(LayerId::from_type::<L>())?;
+        Ok(Self::collect_chunks_in::<L>(&layer, &bounds))
+    }
+
+    fn collect_chunks_in<L: Layer + 'static>(
+        layer: &LayerConfig,
+        bounds: &Bounds,
+    ) -> Vec<(ChunkIdx, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let mut chunks = Vec::new();
+        for chunk_idx in layer.chunks_in(bounds) {
+            let chunk = layer.get_storage().get(&chunk_idx);
+            if let Some(chunk_wrapper) = chunk {
+                let data = chunk_wrapper.get_chunk::<L::Chunk>();
+                if let Some(data) = data {
+                    chunks.push((chunk_idx, data.clone()));
+                }
+            }
+        }
+        chunks
+    }
+
+    /// Like [`LayersManager::get_chunks_in`], with the transform placing each chunk at its
+    /// anchor for a render `scale` (pixels per unit)
+    pub fn get_chunk_transforms_in<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+        anchor: ChunkAnchor,
+        scale: f32,
+    ) -> Vec<(ChunkIdx, Transform, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let chunk_size = L::Chunk::get_size();
+        self.get_chunks_in::<L>(bounds)
+            .into_iter()
+            .map(|(chunk_idx, chunk)| {
+                (chunk_idx, chunk_idx.transform(chunk_size, anchor, scale), chunk)
+            })
+            .collect()
+    }
+
+    /// Generated chunks of the layer inside the bounds matching the predicate, only the
+    /// matching chunks are cloned
+    pub fn find_chunks<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+        predicate: impl Fn(&L::Chunk) -> bool,
+    ) -> Vec<(ChunkIdx, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        layer
+            .chunks_in(&bounds)
+            .filter_map(|chunk_idx| {
+                let data = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+                predicate(data).then(|| (chunk_idx, data.clone()))
+            })
+            .collect()
+    }
+
+    /// Check if any generated chunk of the layer inside the bounds matches the predicate,
+    /// stops at the first match
+    pub fn any_chunk<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+        predicate: impl Fn(&L::Chunk) -> bool,
+    ) -> bool {
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        layer.chunks_in(&bounds).any(|chunk_idx| {
+            layer
+                .get_storage()
+                .get(&chunk_idx)
+                .and_then(|chunk| chunk.get_chunk::<L::Chunk>())
+                .is_some_and(&predicate)
+        })
+    }
+
+    /// Generated chunks of the layer crossed by the segment from `a` to `b`, in order from `a`,
+    /// e.g. the chunks a road or a line of sight goes through
+    pub fn chunks_on_segment<L: Layer + 'static>(
+        &self,
+        a: Point,
+        b: Point,
+    ) -> Vec<(ChunkIdx, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers[&LayerId::from_type::<L>()].read().unwrap();
+        RayChunks::segment(a, b, layer.get_chunk_size())
+            .filter_map(|chunk_idx| {
+                let data = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+                Some((chunk_idx, data.clone()))
+            })
+            .collect()
+    }
+
+    /// Walk the chunks of the layer along the ray up to `max_distance`, returns the first
+    /// generated chunk the predicate hits, e.g. a wall blocking a bullet. Chunks not generated
+    /// are walked through
+    pub fn raycast<L: Layer + 'static>(
+        &self,
+        origin: Point,
+        direction: Vec2,
+        max_distance: f32,
+        hit: impl Fn(&L::Chunk) -> bool,
+    ) -> Option<ChunkIdx> {
+        let layer = self.layers[&LayerId::from_type::<L>()].read().unwrap();
+        RayChunks::new(origin, direction, max_distance, layer.get_chunk_size()).find(|chunk_idx| {
+            layer
+                .get_storage()
+                .get(chunk_idx)
+                .and_then(|chunk| chunk.get_chunk::<L::Chunk>())
+                .is_some_and(&hit)
+        })
+    }
+
+    /// Sample the layer over the bounds into `buffer`, one value per pixel of `resolution`,
+    /// without allocating. Rows go from the top (max y) to the bottom so the buffer can be
+    /// used as image data. The sampler gets the chunk under the pixel center, if generated,
+    /// and the pixel center in real coordinates
+    pub fn rasterize<L: Layer + 'static, T>(
+        &self,
+        bounds: &Bounds,
+        resolution: UVec2,
+        buffer: &mut [T],
+        sampler: impl Fn(Option<&L::Chunk>, Point) -> T,
+    ) {
+        assert_eq!(
+            buffer.len(),
+            (resolution.x * resolution.y) as usize,
+            "The buffer must have one value per pixel"
+        );
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        Self::rasterize_layer::<L, T>(&layer, bounds, resolution, buffer, sampler);
+    }
+
+    /// Like [`LayersManager::rasterize`], errors when the layer is not registered or the buffer
+    /// doesn't have one value per pixel
+    pub fn try_rasterize<L: Layer + 'static, T>(
+        &self,
+        bounds: &Bounds,
+        resolution: UVec2,
+        buffer: &mut [T],
+        sampler: impl Fn(Option<&L::Chunk>, Point) -> T,
+    ) -> Result<(), ChunksError> {
+        let layer = self.try_read_layer(LayerId::from_type::<L>())?;
+        let expected = (resolution.x * resolution.y) as usize;
+        if buffer.len() != expected {
+            return Err(ChunksError::BufferSizeMismatch {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+        Self::rasterize_layer::<L, T>(&layer, bounds, resolution, buffer, sampler);
+        Ok(())
+    }
+
+    fn rasterize_layer<L: Layer + 'static, T>(
+        layer: &LayerConfig,
+        bounds: &Bounds,
+        resolution: UVec2,
+        buffer: &mut [T],
+        sampler: impl Fn(Option<&L::Chunk>, Point) -> T,
+    ) {
+        let pixel_size = (bounds.get_max() - bounds.get_min()) / resolution.as_vec2();
+        // Neighbouring pixels usually fall in the same chunk
+        let mut last: Option<(ChunkIdx, Option<&L::Chunk>)> = None;
+        for (i, value) in buffer.iter_mut().enumerate() {
+            let column = (i as u32 % resolution.x) as f32;
+            let row = (i as u32 / resolution.x) as f32;
+            let point = Vec2::new(
+                bounds.get_min().x + (column + 0.5) * pixel_size.x,
+                bounds.get_max().y - (row + 0.5) * pixel_size.y,
+            );
+            let chunk_idx = layer.chunk_at(point);
+            let chunk = match last {
+                Some((last_idx, chunk)) if last_idx == chunk_idx => chunk,
+                _ => {
+                    let chunk = layer
+                        .get_storage()
+                        .get(&chunk_idx)
+                        .and_then(|chunk| chunk.get_chunk::<L::Chunk>());
+                    last = Some((chunk_idx, chunk));
+                    chunk
+                }
+            };
+            *value = sampler(chunk, point);
+        }
+    }
+
+    /// All generated chunks of the layer, in ascending Morton order of their index
+    pub fn get_all_chunks_in<L: Layer + 'static>(&self) -> Vec<(ChunkIdx, L::Chunk)>
+    where
+        L::Chunk: Clone,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        let mut chunks = Vec::new();
+        for (chunk_idx, chunk_wrapper) in layer.get_storage().iter() {
+            let data = chunk_wrapper.get_chunk::<L::Chunk>();
+            if let Some(data) = data {
+                chunks.push((*chunk_idx, data.clone()));
+            }
+        }
+        chunks.sort_by_key(|(chunk_idx, _)| *chunk_idx);
+        chunks
+    }
+
+    /// Add the client, the returned handle moves, toggles or removes it later without
+    /// rebuilding the other clients
+    pub fn add_layer_client(&mut self, layer_client: impl IntoLayerClient) -> ClientId {
+        let id = ClientId(self.next_client_id);
+        self.insert_layer_client(id, layer_client.into_layer_client());
+        id
+    }
+
+    /// Add the client under an id handed out ahead, see [`LayersWorker::add_layer_client`]
+    ///
+    /// [`LayersWorker::add_layer_client`]: crate::worker::LayersWorker::add_layer_client
+    pub(crate) fn insert_layer_client(&mut self, id: ClientId, mut layer_client: LayerClient) {
+        self.next_client_id = self.next_client_id.max(id.0 + 1);
+        layer_client.set_id(id);
+        self.layer_client.insert(id, layer_client);
+        self.changed_clients.insert(id);
+    }
+
+    /// The id the next added client gets
+    pub(crate) fn next_client_id(&self) -> ClientId {
+        ClientId(self.next_client_id)
+    }
+
+    /// Add the clients of each distance band of the banded client
+    pub fn add_banded_client(&mut self, client: BandedClient) -> Vec<ClientId> {
+        client
+            .into_layer_clients()
+            .into_iter()
+            .map(|client| self.add_layer_client(client))
+            .collect()
+    }
+
+    pub fn get_client(&self, id: ClientId) -> Option<&LayerClient> {
+        self.layer_client.get(&id)
+    }
+
+    /// Move the client, its chunks are planned again on the next regenerate. Returns false
+    /// when the client was removed
+    pub fn update_client_center(&mut self, id: ClientId, center: impl Into<WorldPos>) -> bool {
+        let Some(client) = self.layer_client.get_mut(&id) else {
+            return false;
+        };
+        client.set_center(center);
+        self.changed_clients.insert(id);
+        true
+    }
+
+    /// Pause or resume the client, an inactive client requests no chunks but keeps its
+    /// place. Returns false when the client was removed
+    pub fn set_client_active(&mut self, id: ClientId, active: bool) -> bool {
+        let Some(client) = self.layer_client.get_mut(&id) else {
+            return false;
+        };
+        if active {
+            client.activate();
+        } else {
+            client.deactivate();
+        }
+        self.changed_clients.insert(id);
+        true
+    }
+
+    /// Remove the client, its chunks are released now and decay from the last regenerate
+    pub fn remove_client(&mut self, id: ClientId) -> Option<LayerClient> {
+        let client = self.layer_client.remove(&id)?;
+        self.forget_client(id);
+        Some(client)
+    }
+
+    /// Release what the removed client held and drop its bookkeeping
+    fn forget_client(&mut self, id: ClientId) {
+        if let Some(mut interest) = self.interest.remove(&id) {
+            let changes = self.held_usages.replace(interest.take_held().as_ref(), None);
+            // The client was last requested on the last regenerate
+            self.apply_held_changes(changes, self.frame);
+        }
+        self.client_index.remove(id);
+        self.changed_clients.remove(&id);
+        self.volatile_clients.remove(&id);
+    }
+
+    pub fn clear_layer_clients(&mut self) {
+        let ids: Vec<ClientId> = self.layer_client.keys().copied().collect();
+        self.layer_client.clear();
+        for id in ids {
+            self.forget_client(id);
+        }
+        self.interest.clear();
+        self.client_index.clear();
+        self.refreshed_clients.clear();
+    }
+
+    /// Replace the client owned by the entity, or add it if the entity has none. The client
+    /// keeps its place and handle, so its area deltas are computed against its previous area
+    pub fn set_layer_client_of(
+        &mut self,
+        owner: Entity,
+        layer_client: impl IntoLayerClient,
+    ) -> ClientId {
+        let mut layer_client = layer_client.into_layer_client().with_owner(owner);
+        match self
+            .layer_client
+            .values_mut()
+            .find(|client| client.get_owner() == Some(owner))
+        {
+            Some(client) => {
+                let id = client.get_id().expect("Added clients have an id");
+                layer_client.set_id(id);
+                *client = layer_client;
+                self.changed_clients.insert(id);
+                id
+            }
+            None => self.add_layer_client(layer_client),
+        }
+    }
+
+    /// Move many clients at once, e.g. every player of a server. The owners are looked up
+    /// once for the whole batch and the chunks are only planned again on the next regenerate.
+    /// Returns the owners without a client
+    pub fn update_clients(
+        &mut self,
+        updates: impl IntoIterator<Item = ClientUpdate>,
+    ) -> Vec<Entity> {
+        let mut by_owner: HashMap<Entity, Vec<ClientId>> = HashMap::new();
+        for (id, client) in self.layer_client.iter() {
+            if let Some(owner) = client.get_owner() {
+                by_owner.entry(owner).or_default().push(*id);
+            }
+        }
+        let mut unknown = Vec::new();
+        for update in updates {
+            let Some(clients) = by_owner.get(&update.owner) else {
+                unknown.push(update.owner);
+                continue;
+            };
+            for id in clients {
+                let client = self.layer_client.get_mut(id).unwrap();
+                client.set_center(update.center);
+                if let Some(strategy) = update.strategy {
+                    client.set_strategy(strategy);
+                }
+                self.changed_clients.insert(*id);
+            }
+        }
+        unknown
+    }
+
+    /// Remove the clients owned by the entity, e.g. when it despawns
+    pub fn remove_layer_clients_of(&mut self, owner: Entity) {
+        let owned: Vec<ClientId> = self
+            .layer_client
+            .iter()
+            .filter(|(_, client)| client.get_owner() == Some(owner))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in owned {
+            self.remove_client(id);
+        }
+    }
+    /// Chunks of the layer that expired on the last regenerate, in Morton order. The used ones
+    /// are regenerated, they are also in [`LayersManager::get_generated_chunks`]
+    pub fn get_expired_chunks<L: Layer + 'static>(&self) -> &Vec<ChunkIdx> {
+        let layer_id = LayerId::from_type::<L>();
+        self.expired_list.get(&layer_id).unwrap()
+    }
+
+    /// Chunks of the layer generated on the last regenerate
+    pub fn get_generated_chunks<L: Layer + 'static>(&self) -> &Vec<ChunkIdx> {
+        let layer_id = LayerId::from_type::<L>();
+        self.generated_list.get(&layer_id).unwrap()
+    }
+
+    /// Every layer with the chunks deleted on the last regenerate
+    pub fn get_all_deleted_chunks(&self) -> &HashMap<LayerId, Vec<ChunkIdx>> {
+        &self.delete_list
+    }
+
+    /// Every layer with the chunks generated on the last regenerate
+    pub fn get_all_generated_chunks(&self) -> &HashMap<LayerId, Vec<ChunkIdx>> {
+        &self.generated_list
+    }
+
+    /// Chunks of the layer deleted on the last regenerate, in ascending Morton order
+    pub fn get_deleted_chunks<L: Layer + 'static>(&self) -> &Vec<ChunkIdx> {
+        let layer_id = LayerId::from_type::<L>();
+        self.delete_list.get(&layer_id).unwrap()
+    }
+
+    /// Like [`LayersManager::get_deleted_chunks`], errors when the layer is not registered
+    pub fn try_get_deleted_chunks<L: Layer + 'static>(
+        &self,
+    ) -> Result<&Vec<ChunkIdx>, ChunksError> {
+        let layer_id = LayerId::from_type::<L>();
+        self.delete_list
+            .get(&layer_id)
+            .ok_or(ChunksError::LayerNotRegistered(layer_id))
+    }
+
+    /// Like [`LayersManager::get_generated_chunks`], errors when the layer is not registered
+    pub fn try_get_generated_chunks<L: Layer + 'static>(
+        &self,
+    ) -> Result<&Vec<ChunkIdx>, ChunksError> {
+        let layer_id = LayerId::from_type::<L>();
+        self.generated_list
+            .get(&layer_id)
+            .ok_or(ChunksError::LayerNotRegistered(layer_id))
+    }
+
+    /// Immutable view of the current chunks of every layer, cheap to clone and safe to send to
+    /// other threads. Layers that didn't change since the last snapshot share their data
+    pub fn read_snapshot(&self) -> ChunksSnapshot {
+        let layers = self
+            .layers
+            .iter()
+            .map(|(layer_id, layer)| (*layer_id, layer.read().unwrap().snapshot()))
+            .collect();
+        ChunksSnapshot::new(layers)
+    }
+
+    /// Run `f` with a reader handing out read views of single layers. The views are
+    /// `Send + Sync`, so systems or threads can read different layers concurrently without
+    /// cloning chunks or waiting on each other
+    pub fn scope<R>(&self, f: impl FnOnce(&ChunksReader) -> R) -> R {
+        f(&ChunksReader::new(self))
+    }
+
+    pub(crate) fn layer_snapshot(&self, layer_id: LayerId) -> Option<Arc<LayerSnapshot>> {
+        Some(self.layers.get(&layer_id)?.read().unwrap().snapshot())
+    }
+
+    /// Chunks of the layer that entered and left the area of the client on the last regenerate
+    pub fn get_client_area_delta<L: Layer + 'static>(
+        &self,
+        client: ClientId,
+    ) -> Option<&ClientAreaDelta> {
+        let layer_id = LayerId::from_type::<L>();
+        self.interest.get(&client)?.get_delta(layer_id)
+    }
+
+    /// Active clients centered inside the bounds, as of the last regenerate
+    pub fn get_clients_in(&self, bounds: &Bounds) -> Vec<&LayerClient> {
+        self.client_index
+            .query(bounds)
+            .into_iter()
+            .filter_map(|id| self.get_client(id))
+            .collect()
+    }
+
+    /// Distance from the point to the closest active client, as of the last regenerate
+    pub fn distance_to_nearest_client(&self, point: impl Into<WorldPos>) -> Option<f32> {
+        let point: WorldPos = point.into();
+        self.client_index.nearest_distance(point.0)
+    }
+
+    /// The dependency chunks the chunk of the layer was generated from, by dependency layer.
+    /// Empty while the chunk is pending
+    pub fn get_chunk_sources<L: Layer + 'static>(
+        &self,
+        chunk_idx: ChunkIdx,
+    ) -> Vec<(LayerId, Vec<ChunkIdx>)> {
+        self.get_chunk_reads(LayerId::from_type::<L>(), chunk_idx)
+            .into_iter()
+            .map(|(layer_id, bounds)| {
+                let chunk_size = self.chunk_sizes[&layer_id];
+                (layer_id, self.coordinates.chunks(&bounds, chunk_size).collect())
+            })
+            .collect()
+    }
+
+    /// Regions of the dependencies the chunk of the layer was generated from, empty while
+    /// pending
+    pub fn get_chunk_reads(
+        &self,
+        layer_id: LayerId,
+        chunk_idx: ChunkIdx,
+    ) -> Vec<(LayerId, Bounds)> {
+        self.layers
+            .get(&layer_id)
+            .and_then(|layer| {
+                let layer = layer.read().unwrap();
+                Some(layer.get_storage().get(&chunk_idx)?.get_reads().to_vec())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Generation time of each generated chunk of the layer, to find the costly regions
+    pub fn get_chunk_costs(&self, layer_id: LayerId) -> Vec<(ChunkIdx, Duration)> {
+        let Some(layer) = self.layers.get(&layer_id) else {
+            return Vec::new();
+        };
+        let layer = layer.read().unwrap();
+        layer
+            .get_storage()
+            .iter()
+            .filter(|(_, chunk)| chunk.is_generated())
+            .map(|(chunk_idx, chunk)| (*chunk_idx, chunk.get_cost()))
+            .collect()
+    }
+
+    pub fn get_layer_chunk_size(&self, layer_id: LayerId) -> Option<Point> {
+        self.chunk_sizes.get(&layer_id).copied()
+    }
+
+    /// The chunk of the layer containing the point and the point inside it
+    pub fn to_chunk_local<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> (ChunkIdx, ChunkLocalPos) {
+        self.layers[&LayerId::from_type::<L>()].read().unwrap().to_chunk_local(pos)
+    }
+
+    /// The world position of a point inside a chunk of the layer
+    pub fn to_world<L: Layer + 'static>(
+        &self,
+        chunk_idx: ChunkIdx,
+        pos: ChunkLocalPos,
+    ) -> WorldPos {
+        self.layers[&LayerId::from_type::<L>()].read().unwrap().to_world(chunk_idx, pos)
+    }
+
+    /// The generated chunks of the other layers built from the chunk of the layer, the chunks
+    /// [`LayersManager::invalidate_region`] regenerates along with it
+    pub fn get_chunk_dependents<L: Layer + 'static>(
+        &self,
+        chunk_idx: ChunkIdx,
+    ) -> Vec<(LayerId, ChunkIdx)> {
+        let layer_id = LayerId::from_type::<L>();
+        let chunk_size = self.chunk_sizes[&layer_id];
+        let damaged = HashSet::from([chunk_idx]);
+        let mut dependents = Vec::new();
+        for dependent in self.get_dependents(layer_id) {
+            let layer = self.layers[dependent].read().unwrap();
+            let mut readers = layer.chunks_reading(layer_id, chunk_size, &damaged);
+            readers.sort();
+            dependents.extend(readers.into_iter().map(|reader| (layer.get_layer_id(), reader)));
+        }
+        dependents
+    }
+
+    /// Copy the resources registered with [`LayersManagerBuilder::capture_resource`] from the
+    /// world, call it right before [`LayersManager::regenerate`] from an exclusive system
+    pub fn capture_resources(&mut self, world: &World) {
+        for capture in self.resource_captures.iter() {
+            capture(world, &mut self.resources);
+        }
+    }
+
+    /// Set a value the layers read while generating, for values that are not Bevy resources
+    /// or when the manager runs outside of the world
+    pub fn set_resource<R: Send + Sync + 'static>(&mut self, value: R) {
+        self.resources.insert(value);
+    }
+
+    /// Publish a fact over the region for the layers to read, e.g. "this area is war-torn". The
+    /// chunks overlapping the region of the layers reading the key are regenerated, with
+    /// their dependents
+    pub fn set_region_fact<T: Send + Sync + 'static>(
+        &mut self,
+        key: &'static str,
+        bounds: Bounds,
+        value: T,
+    ) {
+        self.facts.insert(key, bounds, value);
+        self.invalidate_fact_readers(key, &bounds);
+    }
+
+    /// Remove the facts of the key overlapping the region, the chunks that could read them are
+    /// regenerated
+    pub fn remove_region_facts(&mut self, key: &str, bounds: &Bounds) {
+        for removed in self.facts.remove(key, bounds) {
+            self.invalidate_fact_readers(key, &removed);
+        }
+    }
+
+    pub fn get_region_facts(&self) -> &RegionFacts {
+        &self.facts
+    }
+
+    fn invalidate_fact_readers(&mut self, key: &str, bounds: &Bounds) {
+        let readers: Vec<LayerId> = self
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.read().unwrap().reads_fact(key))
+            .map(|(layer_id, _)| *layer_id)
+            .collect();
+        for layer_id in readers {
+            self.invalidate_cascade(layer_id, bounds, None);
+        }
+    }
+
+    /// Regenerate the chunks of the layer inside the bounds, e.g. after an edit of its source
+    /// data. The invalidation cascades downstream: every dependent chunk generated from the
+    /// damaged chunks is regenerated too, the rest of the dependent layers is kept
+    pub fn invalidate_region<L: Layer + 'static>(&mut self, bounds: &Bounds) {
+        self.invalidate_cascade(LayerId::from_type::<L>(), bounds, None);
+    }
+
+    /// Drop the generated data of the chunk of the layer so it is generated again on the next
+    /// regenerate, e.g. after an explosion. The clients using it keep it requested, and the
+    /// dependent chunks generated from it are regenerated too. For every chunk overlapping
+    /// some bounds, e.g. a blast radius, see [`LayersManager::invalidate_region`]
+    pub fn invalidate_chunk<L: Layer + 'static>(&mut self, chunk_idx: ChunkIdx) {
+        let layer_id = LayerId::from_type::<L>();
+        let invalidated = self.layers[&layer_id].write().unwrap().invalidate_chunks([chunk_idx]);
+        self.invalidate_dependents(layer_id, invalidated, None);
+    }
+
+    /// The layers depending directly on the layer, in generation order
+    pub fn get_dependents(&self, layer_id: LayerId) -> &[LayerId] {
+        self.dependents.get(&layer_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Like [`LayersManager::invalidate_region`] when only some products of the chunks
+    /// changed, the dependents that declared they read other products are kept
+    pub fn invalidate_products<L: Layer + 'static>(
+        &mut self,
+        bounds: &Bounds,
+        products: &[&str],
+    ) {
+        self.invalidate_cascade(LayerId::from_type::<L>(), bounds, Some(products));
+    }
+
+    fn invalidate_cascade(
+        &mut self,
+        layer_id: LayerId,
+        bounds: &Bounds,
+        products: Option<&[&str]>,
+    ) {
+        let invalidated = {
+            let mut layer = self.layers[&layer_id].write().unwrap();
+            let chunks = layer.chunks_in(bounds);
+            layer.invalidate_chunks(chunks)
+        };
+        self.invalidate_dependents(layer_id, invalidated, products);
+    }
+
+    /// Invalidate the dependent chunks generated from the invalidated chunks of the layer
+    fn invalidate_dependents(
+        &self,
+        layer_id: LayerId,
+        invalidated: Vec<ChunkIdx>,
+        products: Option<&[&str]>,
+    ) {
+        // The dependents are regenerated in full, so only the first step filters by product
+        let mut damaged = vec![(layer_id, invalidated, products)];
+        while let Some((layer_id, chunks, products)) = damaged.pop() {
+            if chunks.is_empty() {
+                continue;
+            }
+            let chunks: HashSet<ChunkIdx> = chunks.into_iter().collect();
+            let chunk_size = self.chunk_sizes[&layer_id];
+            for dependent in self.get_dependents(layer_id) {
+                let mut layer = self.layers[dependent].write().unwrap();
+                let Some(dependency) = layer.get_dependencies().iter().find(|dependency| {
+                    dependency.get_layer_id() == layer_id
+                        && products.is_none_or(|products| dependency.reads_any(products))
+                }) else {
+                    continue;
+                };
+                if dependency.notifies_changes() {
+                    // The readers are updated once the chunks are generated again
+                    layer.watch_dependency_changes(layer_id, chunk_size, &chunks);
+                    continue;
+                }
+                let readers = layer.chunks_reading(layer_id, chunk_size, &chunks);
+                damaged.push((layer.get_layer_id(), layer.invalidate_chunks(readers), None));
+            }
+        }
+    }
+
+    /// Check if all the chunks of the layer inside the bounds are generated
+    pub fn is_region_ready<L: Layer + 'static>(&self, bounds: &Bounds) -> bool {
+        LayerLookupChunk {
+            layers: &self.layers,
+            resources: &self.resources,
+            facts: &self.facts,
+            seed: self.seed,
+            declared: None,
+            recorded: None,
+            generating: None,
+        }
+        .is_generated(LayerId::from_type::<L>(), bounds)
+    }
+
+    /// Generate the layer around the destination of a teleport ahead of the clients, with
+    /// the priority of chunks next to a client. The chunks the clients use now stay loaded
+    /// until [`LayersManager::complete_teleport`], so the teleport can still be cancelled
+    pub fn prewarm_for_teleport<L: Layer + 'static>(
+        &mut self,
+        destination: Point,
+        radius: f32,
+    ) -> TeleportId {
+        let id = TeleportId(self.next_teleport_id);
+        self.next_teleport_id += 1;
+        let pinned = self
+            .interest
+            .values()
+            .flat_map(|interest| interest.get_requested().iter().cloned())
+            .collect();
+        let extent = Vec2::splat(radius);
+        self.teleports.push(Teleport {
+            id,
+            layer_id: LayerId::from_type::<L>(),
+            bounds: Bounds::new(destination - extent, destination + extent),
+            pinned,
+        });
+        id
+    }
+
+    /// Check if the destination of the teleport is generated, unknown teleports are never ready
+    pub fn is_teleport_ready(&self, id: TeleportId) -> bool {
+        self.teleports
+            .iter()
+            .find(|teleport| teleport.id == id)
+            .is_some_and(|teleport| {
+                LayerLookupChunk {
+                    layers: &self.layers,
+                    resources: &self.resources,
+                    facts: &self.facts,
+                    seed: self.seed,
+                    declared: None,
+                    recorded: None,
+                    generating: None,
+                }
+                .is_generated(teleport.layer_id, &teleport.bounds)
+            })
+    }
+
+    /// The clients arrived, or the teleport was cancelled: release the destination and the
+    /// origin, from now on only the clients keep chunks alive
+    pub fn complete_teleport(&mut self, id: TeleportId) {
+        self.teleports.retain(|teleport| teleport.id != id);
+    }
+
+    /// Generate the chunks of the layer inside the bounds and their dependencies, regenerating
+    /// until they are all generated. Meant for offline tools, tests and world baking, it blocks
+    /// for as long as it takes. The chunks no client uses are evicted by the next regenerate,
+    /// read them before
+    pub fn generate_bounds<L: Layer + 'static>(&mut self, bounds: Bounds) -> BoundsGeneration {
+        let start = Instant::now();
+        let layer_id = LayerId::from_type::<L>();
+        let id = TeleportId(self.next_teleport_id);
+        self.next_teleport_id += 1;
+        // Requested like the destination of a teleport, without pinning the current chunks
+        self.teleports.push(Teleport {
+            id,
+            layer_id,
+            bounds,
+            pinned: Vec::new(),
+        });
+        let mut generation = BoundsGeneration::default();
+        while !self.is_region_ready::<L>(&bounds) {
+            self.regenerate();
+            generation.passes += 1;
+            for (generated_id, generated) in self.generated_list.iter() {
+                if generated.is_empty() {
+                    continue;
+                }
+                let layer = self.layers[generated_id].read().unwrap();
+                let timing = generation.layers.entry(*generated_id).or_default();
+                for chunk_idx in generated {
+                    timing.chunks += 1;
+                    let cost = layer.get_storage().get(chunk_idx).map(|chunk| chunk.get_cost());
+                    timing.time += cost.unwrap_or_default();
+                }
+            }
+            if self.stats.generated > 0 {
+                continue;
+            }
+            // Nothing left to generate here, regenerating again only spins until the async
+            // chunks are done
+            let Some(waited) = self.wait_async() else {
+                break;
+            };
+            generation.waiting += waited;
+        }
+        self.complete_teleport(id);
+        generation.complete = self.is_region_ready::<L>(&bounds);
+        generation.duration = start.elapsed();
+        generation
+    }
+
+    /// Sleep until a chunk of the async pool is done, None when none is in flight. Returns the
+    /// time waited
+    fn wait_async(&self) -> Option<Duration> {
+        let start = Instant::now();
+        loop {
+            let mut in_flight = false;
+            for layer in self.layers.values() {
+                let layer = layer.read().unwrap();
+                if layer.has_finished_async() {
+                    return Some(start.elapsed());
+                }
+                in_flight |= layer.in_flight_count() > 0;
+            }
+            if !in_flight {
+                return None;
+            }
+            std::thread::sleep(ASYNC_POLL_INTERVAL);
+        }
+    }
+}
+
+pub struct LayerLookupChunk<'a> {
+    layers: &'a HashMap<LayerId, Arc<RwLock<LayerConfig>>>,
+    resources: &'a GenerationResources,
+    facts: &'a RegionFacts,
+    seed: u64,
+    /// Reads the chunk being generated declared, checked on every read in strict mode, see
+    /// [`LayersManagerBuilder::strict_dependencies`]
+    declared: Option<DeclaredReads<'a>>,
+    /// Reads of the chunk being generated, see [`LayersManagerBuilder::record_reads`]
+    recorded: Option<&'a ReadLog>,
+    /// Layer and index of the chunk being generated, see [`LayerLookupChunk::context`]
+    generating: Option<(LayerId, ChunkIdx)>,
+}
+
+/// Layers read by a chunk, with the read region, None for a global chunk
+pub(crate) type ReadLog = Mutex<Vec<(LayerId, Option<Bounds>)>>;
+
+#[derive(Clone, Copy)]
+struct DeclaredReads<'a> {
+    layer: LayerId,
+    chunk: ChunkIdx,
+    reads: &'a [(LayerId, Bounds)],
+}
+
+/// What a [`LayerLookupChunk`] reads, owned so chunks can generate on the async pool after the
+/// regenerate returns
+pub(crate) struct DetachedLookup {
+    layers: HashMap<LayerId, Arc<RwLock<LayerConfig>>>,
+    resources: GenerationResources,
+    facts: RegionFacts,
+    seed: u64,
+}
+
+impl DetachedLookup {
+    pub(crate) fn lookup(&self) -> LayerLookupChunk<'_> {
+        LayerLookupChunk {
+            layers: &self.layers,
+            resources: &self.resources,
+            facts: &self.facts,
+            seed: self.seed,
+            declared: None,
+            recorded: None,
+            generating: None,
+        }
+    }
+}
+
+impl LayerLookupChunk<'_> {
+    pub(crate) fn detach(&self) -> DetachedLookup {
+        DetachedLookup {
+            layers: self.layers.clone(),
+            resources: self.resources.clone(),
+            facts: self.facts.clone(),
+            seed: self.seed,
+        }
+    }
+
+    /// The same lookup for the generation of `chunk` of `layer`, panicking when the chunk
+    /// reads outside the `declared` regions and logging its reads to `recorded`
+    pub(crate) fn tracking<'b>(
+        &'b self,
+        layer: LayerId,
+        chunk: ChunkIdx,
+        declared: Option<&'b [(LayerId, Bounds)]>,
+        recorded: Option<&'b ReadLog>,
+    ) -> LayerLookupChunk<'b> {
+        LayerLookupChunk {
+            layers: self.layers,
+            resources: self.resources,
+            facts: self.facts,
+            seed: self.seed,
+            declared: declared.map(|reads| DeclaredReads {
+                layer,
+                chunk,
+                reads,
+            }),
+            recorded,
+            generating: Some((layer, chunk)),
+        }
+    }
+
+    /// Record the read, and panic if the chunk being generated did not declare it. The whole
+    /// layer is read when there are no bounds
+    fn check_read(&self, layer_id: LayerId, bounds: Option<Bounds>) {
+        if let Some(recorded) = self.recorded {
+            recorded.lock().unwrap().push((layer_id, bounds));
+        }
+        let Some(declared) = self.declared else {
+            return;
+        };
+        let mut regions = declared
+            .reads
+            .iter()
+            .filter(|(dependency, _)| *dependency == layer_id)
+            .map(|(_, region)| region)
+            .peekable();
+        assert!(
+            regions.peek().is_some(),
+            "chunk ({}, {}) of layer {:?} read the undeclared layer {:?}, declare it in \
+             Layer::get_dependencies",
+            declared.chunk.x,
+            declared.chunk.y,
+            declared.layer,
+            layer_id
+        );
+        if let Some(bounds) = bounds {
+            assert!(
+                regions.any(|region| region.contains_bounds(&bounds)),
+                "chunk ({}, {}) of layer {:?} read {:?} of layer {:?}, outside its declared \
+                 padding",
+                declared.chunk.x,
+                declared.chunk.y,
+                declared.layer,
+                bounds,
+                layer_id
+            );
+        }
+    }
+
+    /// Chunk size of a layer, to measure the reads of the chunk being generated
+    pub(crate) fn chunk_size_of(&self, layer_id: LayerId) -> Point {
+        self.layers[&layer_id].read().unwrap().get_chunk_size()
+    }
+
+    /// Seed of the world, see [`LayersManagerBuilder::seed`]
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seeded random numbers for the chunk being generated, use it instead of seeds made up
+    /// from the chunk index. Bound to the chunk, so a layer can't draw from the numbers of
+    /// another layer or chunk by mistake
+    ///
+    /// # Panics
+    /// Outside of [`Layer::generate`], [`Layer::try_generate`] and
+    /// [`Layer::on_dependency_changed`], e.g. in [`Layer::dependency_bounds`]
+    pub fn context(&self) -> GenerationContext {
+        let (layer, chunk_idx) = self
+            .generating
+            .expect("The context is only known while a chunk is generated");
+        GenerationContext::new(self.seed, layer, chunk_idx)
+    }
+
+    /// The chunk of a global dependency, see
+    /// [`GLOBAL_CHUNK_SIZE`](crate::bounds::GLOBAL_CHUNK_SIZE)
+    pub fn get_global<L: Layer + 'static>(&self) -> Option<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        self.check_read(LayerId::from_type::<L>(), None);
+        self.get_chunk_from_idx::<L>(LayerId::from_type::<L>(), ChunkIdx::GLOBAL)
+    }
+
+    /// A value captured for the layers, see [`LayersManager::capture_resources`]
+    pub fn get_resource<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        self.resources.get::<R>()
+    }
+
+    /// The latest region fact of the key covering the point, the layer must list the key in
+    /// [`Layer::region_facts`] to be regenerated when it changes
+    pub fn get_fact<T: Send + Sync + 'static>(
+        &self,
+        key: &str,
+        point: impl Into<WorldPos>,
+    ) -> Option<&T> {
+        let point: WorldPos = point.into();
+        self.facts.get(key, point.0)
+    }
+
+    /// The region facts of the key overlapping the bounds with their regions, oldest first
+    pub fn get_facts_in<T: Send + Sync + 'static>(
+        &self,
+        key: &str,
+        bounds: &Bounds,
+    ) -> Vec<(Bounds, &T)> {
+        self.facts.get_in(key, bounds)
+    }
+
+    /// Check if all the chunks of the layer inside the bounds are generated
+    pub(crate) fn is_generated(&self, layer_id: LayerId, bounds: &Bounds) -> bool {
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        layer.chunks_in(bounds).all(|chunk_idx| {
+            layer
+                .get_storage()
+                .get(&chunk_idx)
+                .is_some_and(|chunk| chunk.is_generated())
+        })
+    }
+
+    pub(crate) fn is_chunk_generated(&self, layer_id: LayerId, chunk_idx: &ChunkIdx) -> bool {
+        self.layers[&layer_id]
+            .read()
+            .unwrap()
+            .get_storage()
+            .get(chunk_idx)
+            .is_some_and(|chunk| chunk.is_generated())
+    }
+
+    fn get_chunk_from_idx<L: Layer + 'static>(
+        &self,
+        layer_id: LayerId,
+        chunk_idx: ChunkIdx,
+    ) -> Option<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        let chunk = layer.get_storage().get(&chunk_idx)?;
+        let data = chunk.get_chunk::<L::Chunk>();
+        data.cloned()
+    }
+
+    pub fn get_chunk<L: Layer + 'static>(
+        &self,
+        layer_id: LayerId,
+        pos: impl Into<WorldPos>,
+    ) -> Option<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        let pos: WorldPos = pos.into();
+        self.check_read(layer_id, Some(Bounds::from_point(pos.0)));
+        // Get the chunk index
+        let chunk_idx = self.layers[&layer_id].read().unwrap().chunk_at(pos);
+        self.get_chunk_from_idx::<L>(layer_id, chunk_idx)
+    }
+
+    /// Like [`LayerLookupChunk::get_chunk`], with the reason there is no chunk, e.g. a
+    /// `layer_id` of another layer than `L`
+    pub fn try_get_chunk<L: Layer + 'static>(
+        &self,
+        layer_id: LayerId,
+        pos: impl Into<WorldPos>,
+    ) -> Result<L::Chunk, ChunksError>
+    where
+        L::Chunk: Clone,
+    {
+        let pos: WorldPos = pos.into();
+        self.check_read(layer_id, Some(Bounds::from_point(pos.0)));
+        let layer = self
+            .layers
+            .get(&layer_id)
+            .ok_or(ChunksError::LayerNotRegistered(layer_id))?
+            .read()
+            .unwrap();
+        let chunk_idx = layer.chunk_at(pos);
+        layer.try_get_chunk::<L::Chunk>(chunk_idx).cloned()
+    }
+
+    /// A product of the chunk of the dependency at the position, see [`Chunk::get_product`]
+    pub fn get_product<L: Layer + 'static, T: Clone + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+        name: &str,
+    ) -> Option<T> {
+        let pos: WorldPos = pos.into();
+        self.check_read(LayerId::from_type::<L>(), Some(Bounds::from_point(pos.0)));
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        let chunk_idx = layer.chunk_at(pos);
+        let chunk = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+        chunk.get_product(name)?.downcast_ref::<T>().cloned()
+    }
+
+    pub fn get_chunks_in<L: Layer + 'static>(&self, bounds: Bounds) -> Vec<L::Chunk>
+    where
+        L::Chunk: Clone,
+    {
+        let layer_id = LayerId::from_type::<L>();
+        self.check_read(layer_id, Some(bounds));
+        let mut chunks = Vec::new();
+        let chunks_in = self.layers[&layer_id].read().unwrap().chunks_in(&bounds);
+        for chunk_idx in chunks_in {
+            let chunk = self.get_chunk_from_idx::<L>(layer_id, chunk_idx);
+            if let Some(chunk) = chunk {
+                chunks.push(chunk);
+            }
+        }
+        chunks
+    }
+
+    /// Check if any chunk of the dependency inside the bounds matches the predicate,
+    /// without cloning the chunks
+    pub fn any_chunk<L: Layer + 'static>(
+        &self,
+        bounds: Bounds,
+        predicate: impl Fn(&L::Chunk) -> bool,
+    ) -> bool {
+        let layer_id = LayerId::from_type::<L>();
+        self.check_read(layer_id, Some(bounds));
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        layer.chunks_in(&bounds).any(|chunk_idx| {
+            layer
+                .get_storage()
+                .get(&chunk_idx)
+                .and_then(|chunk| chunk.get_chunk::<L::Chunk>())
+                .is_some_and(&predicate)
+        })
+    }
+}
+
+impl LayersManager {
+    pub fn print_dot(&self) {
+        println!(
+            "{:?}",
+            Dot::with_config(
+                &self.dag.graph(),
+                &[
+                    Config::EdgeNoLabel,
+                    // Config::NodeIndexLabel
+                ]
+            )
+        );
+    }
+
+    fn begin_frame(&mut self) {
+        self.frame += 1;
+        for layer in self.layers.values() {
+            if let Ok(mut layer) = layer.write() {
+                layer.begin_frame(self.frame, self.clock);
+            }
+        }
+    }
+
+    /// Drop the expired chunks, the used ones are regenerated by this regenerate along with
+    /// the dependent chunks generated from them
+    fn expire_chunks(&mut self) {
+        for layer_id in self.get_layer_ids() {
+            let expired = self.layers[&layer_id].write().unwrap().expire();
+            if expired.is_empty() {
+                continue;
+            }
+            self.expired_list
+                .get_mut(&layer_id)
+                .unwrap()
+                .extend_from_slice(&expired);
+            self.invalidate_dependents(layer_id, expired, None);
+        }
+    }
+
+    /// Advance the game time the chunk expiries are measured with, see [`Layer::expiry`]
+    pub fn advance_clock(&mut self, delta: Duration) {
+        self.clock += delta;
+    }
+
+    pub fn get_clock(&self) -> Duration {
+        self.clock
+    }
+
+    fn clear_deleted(&mut self) {
+        self.delete_list.iter_mut().for_each(|(_, list)| {
+            list.clear();
+        });
+        self.generated_list.iter_mut().for_each(|(_, list)| {
+            list.clear();
+        });
+        self.expired_list.iter_mut().for_each(|(_, list)| {
+            list.clear();
+        });
+    }
+
+    pub fn regenerate(&mut self) {
+        self.regenerate_until(None);
+    }
+
+    /// Regenerate, generating only the chunks that fit in `budget`. The chunks closest to the
+    /// clients are generated first, the rest stays pending for the next regenerates. At least
+    /// one chunk is generated per call, so a chunk slower than the budget can't stall the world
+    pub fn regenerate_with_budget(&mut self, budget: Duration) {
+        self.regenerate_until(Some(Instant::now() + budget));
+    }
+
+    fn regenerate_until(&mut self, budget_deadline: Option<Instant>) {
+        let _span = info_span!("regenerate").entered();
+        let start = Instant::now();
+        self.budget_deadline = budget_deadline;
+        self.stats = RegenerateStats::default();
+        self.group_usage.clear();
+        self.begin_frame();
+        self.clear_deleted();
+        self.expire_chunks();
+        // Check what the layer clients need to be regenerated
+        self.check_client_usages();
+
+        self.generate_requirements();
+        self.check_regions();
+        self.update_checksums();
+        self.update_stats();
+        self.stats.duration = start.elapsed();
+    }
+
+    /// Advance a lockstep world by one step, see [`LayersManagerBuilder::lockstep`]. Peers
+    /// must apply the same client changes before each step
+    pub fn step(&mut self) {
+        assert!(self.lockstep, "step is only for lockstep managers, call regenerate instead");
+        self.regenerate();
+    }
+
+    pub fn is_lockstep(&self) -> bool {
+        self.lockstep
+    }
+
+    pub fn get_coordinate_math(&self) -> CoordinateMath {
+        self.coordinates
+    }
+
+    fn update_checksums(&mut self) {
+        let Some(checksums) = self.checksums.as_mut() else {
+            return;
+        };
+        for (layer_id, generated) in self.generated_list.iter() {
+            let mut generated = generated.clone();
+            generated.sort();
+            generated.dedup();
+            let layer = self.layers[layer_id].read().unwrap();
+            let chunks = generated.iter().filter_map(|chunk_idx| {
+                let chunk = layer.get_storage().get(chunk_idx)?.get_dyn_chunk()?;
+                Some((*chunk_idx, chunk))
+            });
+            checksums.entry(*layer_id).or_default().update(chunks);
+        }
+    }
+
+    /// Checksums of the chunks the layer generated, `None` unless enabled with
+    /// [`LayersManagerBuilder::checksums`] or lockstep mode. Peers with different checksums
+    /// desynced, compare the chunks of the step with [`LayersManager::dump_chunk`]
+    pub fn get_checksum<L: Layer + 'static>(&self) -> Option<LayerChecksum> {
+        let checksums = self.checksums.as_ref()?;
+        Some(checksums.get(&LayerId::from_type::<L>()).copied().unwrap_or_default())
+    }
+
+    /// Pretty printed data of the chunk, to compare the chunks of desynced peers
+    pub fn dump_chunk<L: Layer + 'static>(&self, chunk_idx: ChunkIdx) -> Option<String> {
+        let layer = self.layers.get(&LayerId::from_type::<L>())?.read().unwrap();
+        let chunk = layer.get_storage().get(&chunk_idx)?.get_chunk::<L::Chunk>()?;
+        Some(format!("{:#?}", chunk))
+    }
+
+    /// The chunk of every layer under the point with its status and data, in generation
+    /// order. Formats every chunk, meant for debug tools like the
+    /// [`ChunkProbePlugin`](crate::debug_overlay::ChunkProbePlugin)
+    pub fn probe(&self, pos: impl Into<WorldPos>) -> Vec<ProbedChunk> {
+        let pos: WorldPos = pos.into();
+        self.generation_order
+            .iter()
+            .map(|layer_id| {
+                let layer = self.layers[layer_id].read().unwrap();
+                let chunk_idx = layer.chunk_at(pos);
+                let data = layer.get_storage().get(&chunk_idx).and_then(|chunk| {
+                    chunk.get_dyn_chunk().map(|chunk| format!("{:#?}", chunk))
+                });
+                ProbedChunk {
+                    layer: *layer_id,
+                    chunk: chunk_idx,
+                    status: layer.get_chunk_status(&chunk_idx),
+                    data,
+                }
+            })
+            .collect()
+    }
+
+    /// Free memory now, e.g. on a low memory warning of the OS: every chunk the clients and
+    /// dependents don't use this frame is dropped without waiting for its grace period, with
+    /// the data kept to cross-fade and the snapshots. Chunks stored with
+    /// [`LayersManager::load_chunk`] are kept, they can't be generated again. The dropped
+    /// chunks are reported as deleted. Returns how many chunks were dropped
+    pub fn relieve_memory_pressure(&mut self) -> usize {
+        let mut total = 0;
+        for (layer_id, layer) in self.layers.iter() {
+            let dropped = layer.write().unwrap().relieve_memory_pressure();
+            if dropped.is_empty() {
+                continue;
+            }
+            total += dropped.len();
+            // Reported once, as deleted
+            let generated = self.generated_list.get_mut(layer_id).unwrap();
+            generated.retain(|chunk_idx| dropped.binary_search(chunk_idx).is_err());
+            let deleted = self.delete_list.get_mut(layer_id).unwrap();
+            deleted.extend(dropped);
+            deleted.sort();
+        }
+        debug!(
+            target: log_targets::EVICTION,
+            "Dropped {} chunks to relieve the memory pressure",
+            total
+        );
+        total
+    }
+
+    /// The padding each dependency needs to cover the reads of the chunks generated so far,
+    /// next to the declared padding, in generation order. Empty unless the reads are recorded,
+    /// see [`LayersManagerBuilder::record_reads`]
+    pub fn report_required_padding(&self) -> Vec<RequiredPadding> {
+        let mut report = Vec::new();
+        for layer_id in &self.generation_order {
+            let layer = self.layers[layer_id].read().unwrap();
+            if !layer.is_recording_reads() {
+                continue;
+            }
+            let mut recorded = layer.get_recorded_padding();
+            for dependency in layer.get_dependencies() {
+                report.push(RequiredPadding {
+                    layer: *layer_id,
+                    dependency: dependency.get_layer_id(),
+                    declared: Some(dependency.get_padding()),
+                    required: recorded.remove(&dependency.get_layer_id()),
+                });
+            }
+            // Sorted, so the report doesn't depend on the map order
+            let mut undeclared: Vec<(LayerId, Padding)> = recorded.into_iter().collect();
+            undeclared.sort_by_key(|(dependency, _)| dependency.get_name());
+            for (dependency, required) in undeclared {
+                report.push(RequiredPadding {
+                    layer: *layer_id,
+                    dependency,
+                    declared: None,
+                    required: Some(required),
+                });
+            }
+        }
+        report
+    }
+
+    /// How many chunks of each dependency the reads of the chunks generated so far reach past
+    /// their border, next to the reach of the declared padding, in generation order. Shows how
+    /// far a padding can shrink, e.g. when 95% of the reads stay within 1 chunk. Empty unless
+    /// the reads are recorded, see [`LayersManagerBuilder::record_reads`]
+    pub fn report_read_histograms(&self) -> Vec<DependencyReads> {
+        let mut report = Vec::new();
+        for layer_id in &self.generation_order {
+            let layer = self.layers[layer_id].read().unwrap();
+            if !layer.is_recording_reads() {
+                continue;
+            }
+            let mut histograms = layer.get_read_histograms();
+            for dependency in layer.get_dependencies() {
+                let dependency_id = dependency.get_layer_id();
+                let chunk_size = self.layers[&dependency_id].read().unwrap().get_chunk_size();
+                report.push(DependencyReads {
+                    layer: *layer_id,
+                    dependency: dependency_id,
+                    declared_reach: Some(dependency.get_padding().reach(chunk_size)),
+                    reads: histograms.remove(&dependency_id).unwrap_or_default(),
+                });
+            }
+            // Sorted, so the report doesn't depend on the map order
+            let mut undeclared: Vec<(LayerId, ReadHistogram)> = histograms.into_iter().collect();
+            undeclared.sort_by_key(|(dependency, _)| dependency.get_name());
+            for (dependency, reads) in undeclared {
+                report.push(DependencyReads {
+                    layer: *layer_id,
+                    dependency,
+                    declared_reach: None,
+                    reads,
+                });
+            }
+        }
+        report
+    }
+
+    fn update_stats(&mut self) {
+        for layer in self.layers.values() {
+            let layer = layer.read().unwrap();
+            self.stats.pending += layer.pending_count();
+            self.stats.stored += layer.get_storage().len();
+            self.stats.storage_bytes += layer.storage_bytes();
+        }
+    }
+
+    /// The registered layers, dependents before their dependencies
+    pub fn get_layer_ids(&self) -> Vec<LayerId> {
+        let mut topo = Topo::new(&self.dag);
+        let mut ids = Vec::new();
+        while let Some(node) = topo.next(&self.dag) {
+            ids.push(self.dag[node]);
+        }
+        ids
+    }
+
+    fn try_read_layer(
+        &self,
+        layer_id: LayerId,
+    ) -> Result<RwLockReadGuard<'_, LayerConfig>, ChunksError> {
+        self.read_layer(layer_id).ok_or(ChunksError::LayerNotRegistered(layer_id))
+    }
+
+    pub(crate) fn read_layer(&self, layer_id: LayerId) -> Option<RwLockReadGuard<'_, LayerConfig>> {
+        self.layers.get(&layer_id).map(|layer| layer.read().unwrap())
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of regenerates run so far
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Time the layer spent in [`Layer::prepare`], None for unknown layers
+    pub fn get_prepare_time(&self, layer_id: LayerId) -> Option<Duration> {
+        Some(self.layers.get(&layer_id)?.read().unwrap().get_prepare_time())
+    }
+
+    /// Numbers of the last regenerate
+    pub fn get_stats(&self) -> RegenerateStats {
+        self.stats
+    }
+
+    fn generate_requirements(&mut self) {
+        // Transverse the DAG in topological order
+        let order = self.get_layer_ids();
+        let generation_order = self.generation_order.clone();
+        let warm_radii = self.take_warm_radii(&order);
+
+        // Data-dependent requirements may only be known once their dependencies are generated,
+        // so repeat until no new chunk is required
+        self.requirement_passes = 0;
+        let mut converged = false;
+        for pass in 0..self.max_requirement_passes {
+            let requested = self.propagate_requirements(&order, pass == 0);
+            if pass > 0 && requested == 0 {
+                converged = true;
+                break;
+            }
+            self.generate_layers(&generation_order, warm_radii.as_ref());
+            self.requirement_passes = pass + 1;
+        }
+        if self.max_requirement_passes > 1 && !converged {
+            // The next regenerate requires the missing chunks again and carries on
+            let missing = self.count_missing_requirements(&order);
+            if missing > 0 {
+                warn!(
+                    target: log_targets::REQUIREMENTS,
+                    "Requirements did not converge after {} passes, {} chunks still missing",
+                    self.max_requirement_passes, missing
+                );
+            }
+        }
+        // Every pass stamped the chunks it needs, the others can go
+        self.evict_unused(&generation_order);
+    }
+
+    /// Drop the chunks no usage keeps, once per regenerate
+    fn evict_unused(&mut self, order: &[LayerId]) {
+        for layer_id in order {
+            let deleted = self.layers[layer_id].write().unwrap().evict_unused();
+            if deleted.is_empty() {
+                continue;
+            }
+            // An async chunk collected this regenerate is reported once, as deleted
+            let generated = self.generated_list.get_mut(layer_id).unwrap();
+            generated.retain(|chunk_idx| deleted.binary_search(chunk_idx).is_err());
+            self.delete_list.get_mut(layer_id).unwrap().extend(deleted);
+        }
+    }
+
+    /// Pass the requirements of each layer to its dependencies, returns how many chunks were
+    /// newly requested. On the first pass every requirement counts as usage, later passes
+    /// only stamp the chunks not requested with the strategy this frame yet, existing or not
+    fn propagate_requirements(&mut self, order: &[LayerId], first_pass: bool) -> usize {
+        let _span = info_span!("propagate_requirements", first_pass).entered();
+        let layer_lookup = LayerLookupChunk {
+            layers: &self.layers,
+            resources: &self.resources,
+            facts: &self.facts,
+            seed: self.seed,
+            declared: None,
+            recorded: None,
+            generating: None,
+        };
+        let mut requested = 0;
+        for layer_id in order {
+            if !self.is_layer_enabled(*layer_id) {
+                continue;
+            }
+            // Check if the layer has any requirements to pass to its dependencies
+            let (requirements, deadlines) = {
+                let layer = self.layers.get(layer_id).unwrap().read().unwrap();
+                (layer.requires(&layer_lookup), layer.requires_by_deadline(&layer_lookup))
+            };
+            for (dependency_id, bounds, strategy) in requirements {
+                let mut dependency = self.layers.get(&dependency_id).unwrap().write().unwrap();
+                if first_pass {
+                    dependency.ensure_generated(&bounds, strategy);
+                } else {
+                    requested += dependency.ensure_requested(&bounds, strategy);
+                }
+            }
+            // Dependencies are due when their dependent is
+            for (dependency_id, bounds, due_at) in deadlines {
+                let mut dependency = self.layers[&dependency_id].write().unwrap();
+                let chunks = dependency.chunks_in(&bounds);
+                dependency.set_deadlines(chunks, due_at);
+            }
+        }
+        requested
+    }
+
+    /// Chunks the requirements of the layers need that don't exist yet, counted without
+    /// creating them
+    fn count_missing_requirements(&self, order: &[LayerId]) -> usize {
+        let layer_lookup = LayerLookupChunk {
+            layers: &self.layers,
+            resources: &self.resources,
+            facts: &self.facts,
+            seed: self.seed,
+            declared: None,
+            recorded: None,
+            generating: None,
+        };
+        let mut missing: HashSet<(LayerId, ChunkIdx)> = HashSet::new();
+        for layer_id in order {
+            if !self.is_layer_enabled(*layer_id) {
+                continue;
+            }
+            let requirements = self.layers[layer_id].read().unwrap().requires(&layer_lookup);
+            for (dependency_id, bounds, _) in requirements {
+                let dependency = self.layers[&dependency_id].read().unwrap();
+                missing.extend(
+                    dependency
+                        .chunks_in(&bounds)
+                        .filter(|chunk_idx| !dependency.get_storage().contains_key(chunk_idx))
+                        .map(|chunk_idx| (dependency_id, chunk_idx)),
+                );
+            }
+        }
+        missing.len()
+    }
+
+    /// Take the warm start if there are clients to warm around, with the radius each layer must
+    /// fill so the chunks of its dependents within the warm radius can be generated too
+    fn take_warm_radii(&mut self, order: &[LayerId]) -> Option<HashMap<LayerId, f32>> {
+        if self.layer_client.is_empty() {
+            return None;
+        }
+        let radius = self.warm_start.take()?;
+        let mut radii = HashMap::new();
+        // Dependents come first, so their radius is known before their dependencies
+        for layer_id in order {
+            let layer = self.layers[layer_id].read().unwrap();
+            let own_radius = *radii.entry(*layer_id).or_insert(radius);
+            let reach = own_radius + layer.get_chunk_size().length() / 2.0;
+            for dependency in layer.get_dependencies() {
+                let padding = dependency.get_padding();
+                let padding =
+                    Vec2::new(padding.left.max(padding.right), padding.bottom.max(padding.top));
+                let dependency_id = dependency.get_layer_id();
+                let dependency_radius =
+                    reach + padding.length() + self.chunk_sizes[&dependency_id].length() / 2.0;
+                let entry = radii.entry(dependency_id).or_insert(radius);
+                *entry = entry.max(dependency_radius);
+            }
+        }
+        Some(radii)
+    }
+
+    fn generate_layers(&mut self, order: &[LayerId], warm_radii: Option<&HashMap<LayerId, f32>>) {
+        // The warm start ignores the limits
+        let mut reserved = match warm_radii {
+            Some(_) => HashMap::new(),
+            None => self.reserve_group_chunks(order),
+        };
+        // Now we can generate the chunks, dependencies first
+        for layer_id in order {
+            let _span = info_span!("generate_layer", layer = ?layer_id).entered();
+            let layer_lookup = LayerLookupChunk {
+                layers: &self.layers,
+                resources: &self.resources,
+                facts: &self.facts,
+                seed: self.seed,
+                declared: None,
+                recorded: None,
+                generating: None,
+            };
+            // Update the chunks whose changed dependency chunks are generated again, before
+            // the dependents of this layer are generated
+            let changed = self.layers[layer_id]
+                .write()
+                .unwrap()
+                .apply_dependency_changes(&layer_lookup);
+            self.invalidate_dependents(*layer_id, changed.clone(), None);
+            let mut layer = self.layers.get(layer_id).unwrap().write().unwrap();
+            // Updated chunks are reported as generated again
+            let updated = changed.into_iter().filter(|chunk_idx| {
+                layer
+                    .get_storage()
+                    .get(chunk_idx)
+                    .is_some_and(|chunk| chunk.is_generated())
+            });
+            self.generated_list.get_mut(layer_id).unwrap().extend(updated);
+            // Generate the chunks
+            let client_index = &self.client_index;
+            let teleports = &self.teleports;
+            // Disabled layers still run to drop their unused chunks
+            let enabled = self.is_layer_enabled(*layer_id);
+            let budget = self.budget_chunks(&layer);
+            // The chunks left to the group that go to its layers generated after this one
+            reserved.remove(layer_id);
+            let held_back: usize = reserved
+                .values()
+                .filter(|(name, _)| layer.get_group() == Some(*name))
+                .map(|(_, chunks)| chunks)
+                .sum();
+            let mut group = layer
+                .get_group()
+                .map(|name| (&self.groups[name], self.group_usage.entry(name).or_default()));
+            let limit = match &group {
+                _ if !enabled => Some(0),
+                Some((group, usage)) => group
+                    .remaining_chunks(usage)
+                    .map(|remaining| remaining.saturating_sub(held_back)),
+                None => None,
+            };
+            let limit = match budget {
+                Some(budget) => Some(limit.map_or(budget, |limit| limit.min(budget))),
+                None => limit,
+            };
+            let warm_radius = warm_radii.map(|radii| radii[layer_id]).filter(|_| enabled);
+            let start = Instant::now();
+            let result = layer.generate(
+                &layer_lookup,
+                |point| nearest_client(client_index, teleports, point),
+                limit,
+                warm_radius,
+            );
+            if let Some((_, usage)) = group.as_mut() {
+                usage.chunks += result.generated.len();
+                usage.time += start.elapsed();
+            }
+            self.stats.generated += result.generated.len();
+            self.stats.deferred += result.deferred;
+            self.stats.missed_deadlines += result.missed_deadlines.len();
+            if !result.missed_deadlines.is_empty() {
+                warn!(
+                    target: log_targets::GENERATION,
+                    "{} chunks of {:?} missed their deadline",
+                    result.missed_deadlines.len(),
+                    layer_id
+                );
+            }
+            let clock = self.clock;
+            self.missed_deadlines
+                .extend(result.missed_deadlines.into_iter().map(|(chunk_idx, due_at)| {
+                    DeadlineMissed {
+                        layer: *layer_id,
+                        chunk_idx,
+                        due_at,
+                        late_by: clock.saturating_sub(due_at),
+                    }
+                }));
+            self.generated_list
+                .get_mut(layer_id)
+                .unwrap()
+                .extend(result.generated);
+            let budget = layer.get_chunk_budget();
+            if let Some(budget) = budget.filter(|_| !result.over_budget.is_empty()) {
+                warn!(
+                    target: log_targets::GENERATION,
+                    "{} chunks of {:?} exceeded the budget of {:?}",
+                    result.over_budget.len(),
+                    layer_id,
+                    budget
+                );
+                self.budget_violations.push(LayerBudgetExceeded {
+                    layer: *layer_id,
+                    budget,
+                    chunks: result.over_budget,
+                });
+            }
+        }
+    }
+
+    /// Share the chunks left to each group with a limit between its layers, the chunks with
+    /// the highest [`Layer::priority`] first whatever the order the layers generate in.
+    /// Returns the group and the number of chunks of each layer
+    fn reserve_group_chunks(&self, order: &[LayerId]) -> HashMap<LayerId, (&'static str, usize)> {
+        let lookup = LayerLookupChunk {
+            layers: &self.layers,
+            resources: &self.resources,
+            facts: &self.facts,
+            seed: self.seed,
+            declared: None,
+            recorded: None,
+            generating: None,
+        };
+        let distance = |point| nearest_client(&self.client_index, &self.teleports, point);
+        let mut pending: HashMap<&'static str, Vec<(f32, usize, LayerId)>> = HashMap::new();
+        for (position, layer_id) in order.iter().enumerate() {
+            let layer = self.layers[layer_id].read().unwrap();
+            let Some(name) = layer.get_group() else {
+                continue;
+            };
+            if self.groups[name].get_max_chunks().is_none() || !self.is_layer_enabled(*layer_id) {
+                continue;
+            }
+            let priorities = layer.pending_priorities(&lookup, distance);
+            pending
+                .entry(name)
+                .or_default()
+                .extend(priorities.into_iter().map(|priority| (priority, position, *layer_id)));
+        }
+        let mut reserved: HashMap<LayerId, (&'static str, usize)> = HashMap::new();
+        for (name, mut chunks) in pending {
+            let usage = self.group_usage.get(name).copied().unwrap_or_default();
+            let Some(remaining) = self.groups[name].remaining_chunks(&usage) else {
+                continue;
+            };
+            // Ties go to the layer generating first
+            chunks.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            for (_, _, layer_id) in chunks.into_iter().take(remaining) {
+                reserved.entry(layer_id).or_insert((name, 0)).1 += 1;
+            }
+        }
+        reserved
+    }
+
+    /// Chunks of the layer that fit in what is left of the time budget, estimated from the
+    /// cost of its generated chunks. None without a budget
+    fn budget_chunks(&self, layer: &LayerConfig) -> Option<usize> {
+        let deadline = self.budget_deadline?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        // The first chunk always goes, it also measures the cost of layers never generated
+        let minimum = usize::from(self.stats.generated == 0);
+        let fit = match layer.mean_chunk_cost() {
+            Some(cost) => (remaining.as_secs_f64() / cost.as_secs_f64()) as usize,
+            None => usize::from(!remaining.is_zero()),
+        };
+        Some(fit.max(minimum))
+    }
+
+    /// Take the budget violations accumulated since the last call
+    pub fn drain_budget_violations(&mut self) -> Vec<LayerBudgetExceeded> {
+        std::mem::take(&mut self.budget_violations)
+    }
+
+    /// Take the deadlines missed since the last call, see [`LayerClient::with_deadline`]
+    pub fn drain_missed_deadlines(&mut self) -> Vec<DeadlineMissed> {
+        std::mem::take(&mut self.missed_deadlines)
+    }
+
+    /// Take the client areas that became ready since the last call, see
+    /// [`LayersManagerBuilder::region_events`]
+    pub fn drain_ready_regions(&mut self) -> Vec<RegionReady> {
+        std::mem::take(&mut self.ready_regions)
+    }
+
+    /// Take the ready client areas that lost chunks since the last call, see
+    /// [`LayersManagerBuilder::region_events`]
+    pub fn drain_degraded_regions(&mut self) -> Vec<RegionDegraded> {
+        std::mem::take(&mut self.degraded_regions)
+    }
+
+    /// Take the usage mismatches found since the last call, see
+    /// [`LayersManagerBuilder::audit_usages`]
+    pub fn drain_usage_leaks(&mut self) -> Vec<UsageLeak> {
+        std::mem::take(&mut self.usage_leaks)
+    }
+
+    /// What the layers of the group spent on the last regenerate
+    pub fn get_group_usage(&self, name: &str) -> GroupUsage {
+        self.group_usage.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn get_group(&self, name: &str) -> Option<&LayerGroup> {
+        self.groups.get(name)
+    }
+
+    /// Enable or disable the layers with the tag. Disabled layers generate nothing and their
+    /// chunks are dropped once their usages decay
+    pub fn set_tag_enabled(&mut self, tag: &'static str, enabled: bool) {
+        if enabled {
+            self.disabled_tags.remove(tag);
+        } else {
+            self.disabled_tags.insert(tag);
+        }
+    }
+
+    pub fn is_tag_enabled(&self, tag: &str) -> bool {
+        !self.disabled_tags.contains(tag)
+    }
+
+    /// Check if the layer is generated, it is unless its tag is disabled
+    pub fn is_layer_enabled(&self, layer_id: LayerId) -> bool {
+        self.layer_tags
+            .get(&layer_id)
+            .is_none_or(|tag| !self.disabled_tags.contains(tag))
+    }
+
+    /// Number of requirement passes the last regenerate needed
+    pub fn get_requirement_passes(&self) -> usize {
+        self.requirement_passes
+    }
+
+    /// Compare the areas of the clients with the generated chunks, reporting the areas that
+    /// became ready or lost chunks since the last regenerate
+    fn check_regions(&mut self) {
+        if !self.region_events {
+            return;
+        }
+        let _span = info_span!("check_regions").entered();
+        let disabled: HashSet<LayerId> = self
+            .layers
+            .keys()
+            .filter(|layer_id| !self.is_layer_enabled(**layer_id))
+            .copied()
+            .collect();
+        for layer_client in self.layer_client.values() {
+            let Some(client) = layer_client.get_id().filter(|_| layer_client.is_active()) else {
+                continue;
+            };
+            let Some(interest) = self.interest.get_mut(&client) else {
+                continue;
+            };
+            for dep in layer_client.get_dependencies() {
+                let layer_id = dep.get_layer_id();
+                if disabled.contains(&layer_id) {
+                    continue;
+                }
+                let Some((_, chunks)) =
+                    interest.get_chunks().iter().find(|(id, _)| *id == layer_id)
+                else {
+                    continue;
+                };
+                let missing: Vec<ChunkIdx> = {
+                    let layer = self.layers[&layer_id].read().unwrap();
+                    chunks
+                        .iter()
+                        .filter(|idx| layer.get_chunk_status(idx) != Some(ChunkStatus::Generated))
+                        .copied()
+                        .collect()
+                };
+                let chunks = chunks.len();
+                let bounds =
+                    Bounds::from_point(layer_client.get_center()).add_padding(dep.get_padding());
+                let was_ready = interest.set_ready(layer_id, missing.is_empty());
+                if missing.is_empty() && !was_ready {
+                    self.ready_regions.push(RegionReady {
+                        client,
+                        layer: layer_id,
+                        bounds,
+                        chunks,
+                    });
+                } else if !missing.is_empty() && was_ready {
+                    self.degraded_regions.push(RegionDegraded {
+                        client,
+                        layer: layer_id,
+                        bounds,
+                        chunks,
+                        missing,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Apply the chunks that started or stopped being held to the layers, the disabled layers
+    /// hold nothing. The released chunks were last requested at `last_frame`
+    fn apply_held_changes(&mut self, changes: HeldChanges, last_frame: u64) {
+        for ((layer_id, strategy), chunks) in changes.released {
+            if !self.released_layers.contains(&layer_id) {
+                let mut layer = self.layers[&layer_id].write().unwrap();
+                layer.release_chunks(chunks, strategy, last_frame);
+            }
+        }
+        for ((layer_id, strategy), chunks) in changes.held {
+            if !self.released_layers.contains(&layer_id) {
+                self.layers[&layer_id].write().unwrap().hold_chunks(chunks, strategy);
+            }
+        }
+    }
+
+    /// Release the held chunks of the layers disabled since the last regenerate, and hold
+    /// them again in the layers enabled again
+    fn sync_released_layers(&mut self) {
+        let last_frame = self.frame.saturating_sub(1);
+        for (layer_id, layer) in self.layers.iter() {
+            let enabled = self.is_layer_enabled(*layer_id);
+            if enabled != self.released_layers.contains(layer_id) {
+                continue;
+            }
+            let mut layer = layer.write().unwrap();
+            for (strategy, chunks) in self.held_usages.of_layer(*layer_id) {
+                if enabled {
+                    layer.hold_chunks(chunks, strategy);
+                } else {
+                    layer.release_chunks(chunks, strategy, last_frame);
+                }
+            }
+            if enabled {
+                self.released_layers.remove(layer_id);
+            } else {
+                self.released_layers.insert(*layer_id);
+            }
+        }
+    }
+
+    fn check_client_usages(&mut self) {
+        let _span = info_span!("check_client_usages").entered();
+        // Disabled layers are not requested, their chunks decay away
+        self.sync_released_layers();
+        // The area deltas of the last regenerate are over
+        for id in std::mem::take(&mut self.refreshed_clients) {
+            if let Some(interest) = self.interest.get_mut(&id) {
+                interest.clear_deltas();
+            }
+        }
+
+        // Only the clients that changed and the ones whose requests change on their own are
+        // refreshed, the others keep holding their chunks. The held chunks are merged, so only
+        // the chunks that entered or left the requests of a client touch the layers
+        let mut refresh: Vec<ClientId> = self.changed_clients.drain().collect();
+        refresh.extend(self.volatile_clients.iter().copied());
+        refresh.sort();
+        refresh.dedup();
+        let mut changes = HeldChanges::default();
+        let mut deadlines: Vec<(LayerId, Duration, Vec<ChunkIdx>)> = Vec::new();
+        for id in refresh.iter() {
+            let Some(layer_client) = self.layer_client.get(id) else {
+                continue;
+            };
+            let center = layer_client.is_active().then(|| layer_client.get_center());
+            self.client_index.update(*id, center);
+            let interest = self.interest.entry(*id).or_default();
+            let previous = interest.take_held();
+            let due_at = layer_client.get_deadline().due_at(self.clock);
+            let current = if layer_client.is_active() {
+                if interest.matches(layer_client) {
+                    interest.clear_deltas();
+                } else {
+                    *interest = ClientInterest::compute(
+                        layer_client,
+                        &self.chunk_sizes,
+                        self.coordinates,
+                        interest,
+                    );
+                }
+                // Clients with a request limit catch up with their area over several
+                // regenerates
+                interest.admit(layer_client, &self.chunk_sizes);
+                if let Some(due_at) = due_at {
+                    for (layer_id, chunks) in interest.get_requested().iter() {
+                        deadlines.push((*layer_id, due_at, chunks.clone()));
+                    }
+                }
+                Some((layer_client.get_strategy(), interest.get_requested().clone()))
+            } else {
+                interest.deactivate();
+                None
+            };
+            let volatile = layer_client.is_active()
+                && (layer_client.has_filter() || due_at.is_some() || !interest.is_caught_up());
+            let client_changes = self.held_usages.replace(previous.as_ref(), current.as_ref());
+            interest.set_held(current);
+            changes.held.extend(client_changes.held);
+            changes.released.extend(client_changes.released);
+            if volatile {
+                self.volatile_clients.insert(*id);
+            } else {
+                self.volatile_clients.remove(id);
+            }
+        }
+        self.stats.refreshed_clients = refresh.len();
+        self.refreshed_clients = refresh;
+        // The chunks the clients left were last requested on the previous regenerate
+        self.apply_held_changes(changes, self.frame.saturating_sub(1));
+
+        // Teleports are short lived, they request their chunks on each regenerate
+        let mut usages: HashMap<(LayerId, UsageStrategy), ChunkSet> = HashMap::new();
+        for teleport in self.teleports.iter() {
+            let chunk_size = self.chunk_sizes[&teleport.layer_id];
+            usages
+                .entry((teleport.layer_id, UsageStrategy::Fast))
+                .or_default()
+                .extend(self.coordinates.chunks(&teleport.bounds, chunk_size));
+            for (layer_id, chunks) in teleport.pinned.iter() {
+                usages
+                    .entry((*layer_id, UsageStrategy::Fast))
+                    .or_default()
+                    .extend(chunks);
+            }
+        }
+
+        usages.retain(|(layer_id, _), _| self.is_layer_enabled(*layer_id));
+        // Requests of each chunk, counted before the requirements add theirs
+        let mut expected: HashMap<LayerId, HashMap<(ChunkIdx, UsageStrategy), u32>> =
+            HashMap::new();
+        if self.audit_usages {
+            for (layer_id, strategy, chunk_idx) in self.held_usages.iter() {
+                if !self.released_layers.contains(&layer_id) {
+                    let layer_expected = expected.entry(layer_id).or_default();
+                    *layer_expected.entry((chunk_idx, strategy)).or_default() += 1;
+                }
+            }
+            for ((layer_id, strategy), chunks) in usages.iter() {
+                let layer_expected = expected.entry(*layer_id).or_default();
+                for chunk_idx in chunks.iter() {
+                    *layer_expected.entry((chunk_idx, *strategy)).or_default() += 1;
+                }
+            }
+        }
+
+        // Apply the usages in batch, locking each layer only once
+        for ((layer_id, strategy), chunks) in usages {
+            let mut layer = self.layers.get(&layer_id).unwrap().write().unwrap();
+            layer.ensure_chunks(chunks, strategy);
+        }
+        for (layer_id, due_at, chunks) in deadlines {
+            if self.is_layer_enabled(layer_id) {
+                let mut layer = self.layers[&layer_id].write().unwrap();
+                layer.set_deadlines(chunks, due_at);
+            }
+        }
+
+        if self.audit_usages {
+            for (layer_id, layer) in self.layers.iter() {
+                let empty = HashMap::new();
+                let layer_expected = expected.get(layer_id).unwrap_or(&empty);
+                let leaks = layer.read().unwrap().audit_usages(layer_expected);
+                if !leaks.is_empty() {
+                    warn!(
+                        target: log_targets::CLIENTS,
+                        "{} usage counts of {:?} don't match the clients",
+                        leaks.len(),
+                        layer_id
+                    );
+                }
+                self.usage_leaks.extend(leaks);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl LayersManager {
+    /// Stitch the grid chunks covering the bounds into one array indexed `[y, x]`, row 0 is
+    /// the bottom row of the lowest chunk. Chunks that are not generated are left as the
+    /// default cell
+    pub fn extract_region<L: Layer + 'static>(
+        &self,
+        bounds: &Bounds,
+    ) -> ndarray::Array2<<L::Chunk as GridChunk>::Cell>
+    where
+        L::Chunk: GridChunk,
+        <L::Chunk as GridChunk>::Cell: Clone + Default,
+    {
+        let chunk_size = L::Chunk::get_size();
+        let (min_chunk, max_chunk) = self.coordinates.chunk_range(bounds, chunk_size);
+        let resolution = L::Chunk::resolution();
+        let (width, height) = (resolution.x as usize, resolution.y as usize);
+        let chunks_x = (max_chunk.x - min_chunk.x + 1) as usize;
+        let chunks_y = (max_chunk.y - min_chunk.y + 1) as usize;
+        let mut region = ndarray::Array2::default((chunks_y * height, chunks_x * width));
+
+        let layer_id = LayerId::from_type::<L>();
+        let layer = self.layers.get(&layer_id).unwrap().read().unwrap();
+        for chunk_idx in self.coordinates.chunks(bounds, chunk_size) {
+            let Some(chunk) = layer
+                .get_storage()
+                .get(&chunk_idx)
+                .and_then(|chunk| chunk.get_chunk::<L::Chunk>())
+            else {
+                continue;
+            };
+            let offset_x = (chunk_idx.x - min_chunk.x) as usize * width;
+            let offset_y = (chunk_idx.y - min_chunk.y) as usize * height;
+            for y in 0..height {
+                for x in 0..width {
+                    region[[offset_y + y, offset_x + x]] = chunk.cell(x, y).clone();
+                }
+            }
+        }
+        region
+    }
+}
+
+impl Default for LayersManagerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayersManagerBuilder {
+    pub fn new() -> Self {
+        LayersManagerBuilder {
+            layers: Vec::new(),
+            client_cell_size: DEFAULT_CLIENT_CELL_SIZE,
+            max_requirement_passes: 1,
+            usage_decay: UsageDecay::default(),
+            slow_schedule: SlowSchedule::default(),
+            resource_captures: Vec::new(),
+            seed: 0,
+            audit_usages: false,
+            groups: Vec::new(),
+            warm_start: None,
+            layer_tags: HashMap::new(),
+            disabled_tags: HashSet::new(),
+            lockstep: false,
+            strict_dependencies: false,
+            record_reads: false,
+            checksums: false,
+            cross_fade: 0,
+            coordinates: CoordinateMath::Float,
+            region_events: false,
+            chunk_alignment: ChunkAlignment::Warn,
+        }
+    }
+
+    /// Cross-check the usage counters against the client requests on each regenerate, the
+    /// mismatches are logged and kept for [`LayersManager::drain_usage_leaks`]. Costly, meant
+    /// for debugging
+    pub fn audit_usages(mut self, audit_usages: bool) -> Self {
+        self.audit_usages = audit_usages;
+        self
+    }
+
+    /// Report when the area a client requested from a layer is entirely generated, and when
+    /// chunks of a ready area are lost, see [`LayersManager::drain_ready_regions`] and
+    /// [`LayersManager::drain_degraded_regions`]. Checks every chunk of the clients on each
+    /// regenerate, so it is off by default
+    pub fn region_events(mut self, region_events: bool) -> Self {
+        self.region_events = region_events;
+        self
+    }
+
+    /// Seed of the world, the layers read it with [`LayerLookupChunk::get_seed`] or derive
+    /// the seeds of their chunks from it with [`LayerLookupChunk::context`]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// How many Slow chunks each layer generates per regenerate
+    pub fn slow_schedule(mut self, slow_schedule: SlowSchedule) -> Self {
+        self.slow_schedule = slow_schedule;
+        self
+    }
+
+    /// Keep the data of regenerated chunks (invalidated, expired or updated by a dependency
+    /// change) while the new data is generated and for `frames` regenerates after it, so
+    /// renderers can cross-fade with [`LayersManager::get_cross_fade`]. Defaults to 0, the old
+    /// data is dropped at once
+    pub fn cross_fade(mut self, frames: u64) -> Self {
+        self.cross_fade = frames;
+        self
+    }
+
+    /// How many regenerates each usage strategy outlives its last request
+    pub fn usage_decay(mut self, usage_decay: UsageDecay) -> Self {
+        self.usage_decay = usage_decay;
+        self
+    }
+
+    /// Maximum number of requirement passes per regenerate, for layers whose
+    /// [`Layer::dependency_bounds`] depend on generated data. Defaults to a single pass
+    pub fn requirement_passes(mut self, max_passes: usize) -> Self {
+        assert!(max_passes > 0, "At least one requirement pass is needed");
+        self.max_requirement_passes = max_passes;
+        self
+    }
+
+    /// Cell size of the spatial index over the clients, should be close to the client spacing
+    pub fn client_cell_size(mut self, cell_size: Point) -> Self {
+        self.client_cell_size = cell_size;
+        self
+    }
+
+    /// Fill everything within the radius of the clients on the first regenerate that has
+    /// clients, ignoring the budgets, so the first frame shows a complete world. Later
+    /// regenerates respect the budgets again
+    pub fn warm_start(mut self, radius: f32) -> Self {
+        self.warm_start = Some(radius);
+        self
+    }
+
+    /// Add a group of layers sharing a generation budget, see [`Layer::group`]
+    pub fn add_group(mut self, group: LayerGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    pub fn add_layer(mut self, layer: impl IntoLayerConfig) -> Self {
+        self.layers.push(layer.into_layer_config());
+        self
+    }
+
+    /// Add the layers of the bundle, see [`LayerBundle`]
+    pub fn add_bundle(self, bundle: impl LayerBundle) -> Self {
+        bundle.add_to(self)
+    }
+
+    /// Add a layer that is only generated while its tag is enabled (e.g. "debug" for
+    /// visualization layers), see [`LayersManager::set_tag_enabled`]. Layers others depend on
+    /// should stay untagged, their dependents would wait on them while disabled
+    pub fn add_layer_tagged(mut self, layer: impl IntoLayerConfig, tag: &'static str) -> Self {
+        let config = layer.into_layer_config();
+        self.layer_tags.insert(config.get_layer_id(), tag);
+        self.layers.push(config);
+        self
+    }
+
+    /// Deterministic mode for lockstep networked games: the chunks are generated on the
+    /// calling thread, scheduled in index order instead of by float distance, and the world
+    /// only advances on [`LayersManager::step`], so every peer generates the same chunks on
+    /// the same step
+    pub fn lockstep(mut self, lockstep: bool) -> Self {
+        self.lockstep = lockstep;
+        self
+    }
+
+    /// Check that every chunk only reads the layers it declared in
+    /// [`Layer::get_dependencies`], inside their padding, and panic otherwise. Meant for debug
+    /// builds, every read of a [`LayerLookupChunk`] is checked. Chunks generated on the
+    /// [`GenerationLane::Async`](crate::layer::GenerationLane::Async) lane are not checked
+    pub fn strict_dependencies(mut self, strict: bool) -> Self {
+        self.strict_dependencies = strict;
+        self
+    }
+
+    /// Record the regions each chunk reads from its dependencies, to find the padding the
+    /// layers need with [`LayersManager::report_required_padding`] and how far most reads
+    /// reach with [`LayersManager::report_read_histograms`]. A development tool, the
+    /// reads are locked and logged on every lookup. Chunks generated on the
+    /// [`GenerationLane::Async`](crate::layer::GenerationLane::Async) lane are not recorded
+    pub fn record_reads(mut self, record: bool) -> Self {
+        self.record_reads = record;
+        self
+    }
+
+    /// What to do with layers whose chunk size doesn't line up with the chunk size of their
+    /// dependencies, warns by default
+    pub fn chunk_alignment(mut self, chunk_alignment: ChunkAlignment) -> Self {
+        self.chunk_alignment = chunk_alignment;
+        self
+    }
+
+    /// The dependencies of the added layers whose chunk size doesn't line up with the chunk
+    /// size of their layer, see [`ChunkAlignment`]
+    pub fn misaligned_dependencies(&self) -> Vec<MisalignedChunks> {
+        let chunk_sizes: HashMap<LayerId, Point> = self
+            .layers
+            .iter()
+            .map(|layer| (layer.get_layer_id(), layer.get_chunk_size()))
+            .collect();
+        let mut misaligned = Vec::new();
+        for layer in self.layers.iter() {
+            for dependency in layer.get_dependencies() {
+                // Missing dependencies are reported by the build
+                let Some(dependency_size) = chunk_sizes.get(&dependency.get_layer_id()) else {
+                    continue;
+                };
+                if !MisalignedChunks::is_aligned(layer.get_chunk_size(), *dependency_size) {
+                    misaligned.push(MisalignedChunks {
+                        layer: layer.get_layer_id(),
+                        layer_size: layer.get_chunk_size(),
+                        dependency: dependency.get_layer_id(),
+                        dependency_size: *dependency_size,
+                    });
+                }
+            }
+        }
+        misaligned
+    }
+
+    /// How the chunks containing a point are found. Cross-platform lockstep games should use
+    /// [`CoordinateMath::FixedPoint`], so peers agree on the chunks at the chunk edges
+    pub fn coordinate_math(mut self, coordinates: CoordinateMath) -> Self {
+        if let CoordinateMath::FixedPoint { fraction_bits } = coordinates {
+            assert!(
+                fraction_bits <= CoordinateMath::MAX_FRACTION_BITS,
+                "At most {} fraction bits are supported, not {}",
+                CoordinateMath::MAX_FRACTION_BITS,
+                fraction_bits
+            );
+        }
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Keep checksums of the generated chunks, see [`LayersManager::get_checksum`]. Always on
+    /// in lockstep mode
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Start with the layers of the tag disabled
+    pub fn disable_tag(mut self, tag: &'static str) -> Self {
+        self.disabled_tags.insert(tag);
+        self
+    }
+
+    /// Copy the Bevy resource on each [`LayersManager::capture_resources`], so the layers can
+    /// read it while generating
+    pub fn capture_resource<R: Resource + Clone>(mut self) -> Self {
+        self.resource_captures.push(capture::<R>());
+        self
+    }
+
+    /// Expose the `C` chunks of the layer `L` as the layer [`Output<L, C>`], generated from the
+    /// chunks of `L`. The layer must be added first
+    pub fn add_output<L: LayerOutput<C>, C: Chunk>(self) -> Self {
+        let layer = self
+            .layers
+            .iter()
+            .find_map(|config| config.get_layer::<L>())
+            .expect("The layer must be added before its outputs");
+        self.add_layer(Output::<L, C>::new(layer))
+    }
+
+    pub fn build(self) -> LayersManager {
+        self.try_build().unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like [`LayersManagerBuilder::build`], errors when a layer depends on a layer that was
+    /// not added
+    pub fn try_build(self) -> Result<LayersManager, ChunksError> {
+        for layer in self.layers.iter() {
+            for dependency in layer.get_dependencies() {
+                let dependency = dependency.get_layer_id();
+                if !self.layers.iter().any(|added| added.get_layer_id() == dependency) {
+                    return Err(ChunksError::DependencyNotRegistered {
+                        layer: layer.get_layer_id(),
+                        dependency,
+                    });
+                }
+            }
+        }
+        Ok(self.build_checked())
+    }
+
+    fn build_checked(self) -> LayersManager {
+        if self.chunk_alignment != ChunkAlignment::Ignore {
+            for misaligned in self.misaligned_dependencies() {
+                if self.chunk_alignment == ChunkAlignment::Enforce {
+                    panic!("{}", misaligned);
+                }
+                warn!(target: log_targets::REQUIREMENTS, "{}", misaligned);
+            }
+        }
+        let mut layers: HashMap<LayerId, Arc<RwLock<LayerConfig>>> = HashMap::new();
+        let mut dag = Dag::new();
+        let mut dag_index = HashMap::new();
+        let mut delete_list = HashMap::new();
+        let mut generated_list = HashMap::new();
+        let mut expired_list = HashMap::new();
+        let mut chunk_sizes = HashMap::new();
+
+        for layer in self.layers.iter() {
+            dag_index.insert(layer.get_layer_id(), dag.add_node(layer.get_layer_id()));
+            delete_list.insert(layer.get_layer_id(), Vec::new());
+            generated_list.insert(layer.get_layer_id(), Vec::new());
+            expired_list.insert(layer.get_layer_id(), Vec::new());
+            chunk_sizes.insert(layer.get_layer_id(), layer.get_chunk_size());
+        }
+        for layer in self.layers.iter() {
+            let idx = dag_index.get(&layer.get_layer_id()).unwrap();
+            dag.add_edges(
+                layer
+                    .get_dependencies()
+                    .iter()
+                    .map(|id| (*idx, *dag_index.get(&id.get_layer_id()).unwrap(), ())),
+            )
+            .expect("Adding edges to DAG created a cycle");
+            // The global chunk would need its dependencies over the whole world
+            if !layer.get_chunk_size().is_finite() {
+                for dependency in layer.get_dependencies() {
+                    assert!(
+                        !chunk_sizes[&dependency.get_layer_id()].is_finite(),
+                        "Global layer {:?} can only depend on global layers, not on {:?}",
+                        layer.get_layer_id(),
+                        dependency.get_layer_id()
+                    );
+                }
+            }
+        }
+        let groups: HashMap<&'static str, LayerGroup> = self
+            .groups
+            .into_iter()
+            .map(|group| (group.get_name(), group))
+            .collect();
+        let mut priorities = HashMap::new();
+        for layer in self.layers.iter() {
+            let priority = layer.get_group().map_or(0, |name| {
+                groups
+                    .get(name)
+                    .unwrap_or_else(|| panic!("Layer group {:?} was not added", name))
+                    .get_priority()
+            });
+            priorities.insert(layer.get_layer_id(), priority);
+        }
+        let generation_order = generation_order(&dag, &priorities);
+        let mut dependents: HashMap<LayerId, Vec<LayerId>> = HashMap::new();
+        for layer_id in generation_order.iter() {
+            let layer = self.layers.iter().find(|layer| layer.get_layer_id() == *layer_id);
+            for dependency in layer.into_iter().flat_map(|layer| layer.get_dependencies()) {
+                let layer_dependents = dependents.entry(dependency.get_layer_id()).or_default();
+                if !layer_dependents.contains(layer_id) {
+                    layer_dependents.push(*layer_id);
+                }
+            }
+        }
+        for mut layer in self.layers {
+            debug!(
+                target: log_targets::GENERATION,
+                "Prepared {:?} in {:?}",
+                layer.get_layer_id(),
+                layer.get_prepare_time()
+            );
+            layer.set_usage_decay(self.usage_decay);
+            layer.set_slow_schedule(self.slow_schedule);
+            layer.set_lockstep(self.lockstep);
+            layer.set_strict_dependencies(self.strict_dependencies);
+            layer.set_record_reads(self.record_reads);
+            layer.set_coordinate_math(self.coordinates);
+            layer.set_cross_fade(self.cross_fade);
+            layers.insert(layer.get_layer_id(), Arc::new(RwLock::new(layer)));
+        }
+
+        LayersManager {
+            layers,
+            dag,
+            dependents,
+            layer_client: BTreeMap::new(),
+            delete_list,
+            generated_list,
+            expired_list,
+            chunk_sizes,
+            client_index: ClientSpatialIndex::new(self.client_cell_size),
+            interest: HashMap::new(),
+            held_usages: HeldUsages::default(),
+            changed_clients: HashSet::new(),
+            volatile_clients: HashSet::new(),
+            refreshed_clients: Vec::new(),
+            released_layers: HashSet::new(),
+            max_requirement_passes: self.max_requirement_passes,
+            requirement_passes: 0,
+            frame: 0,
+            clock: Duration::ZERO,
+            budget_violations: Vec::new(),
+            missed_deadlines: Vec::new(),
+            stats: RegenerateStats::default(),
+            teleports: Vec::new(),
+            next_teleport_id: 0,
+            next_client_id: 0,
+            resources: GenerationResources::default(),
+            facts: RegionFacts::default(),
+            resource_captures: self.resource_captures,
+            seed: self.seed,
+            audit_usages: self.audit_usages,
+            usage_leaks: Vec::new(),
+            groups,
+            group_usage: HashMap::new(),
+            generation_order,
+            warm_start: self.warm_start,
+            budget_deadline: None,
+            coordinates: self.coordinates,
+            layer_tags: self.layer_tags,
+            disabled_tags: self.disabled_tags,
+            lockstep: self.lockstep,
+            checksums: (self.checksums || self.lockstep).then(HashMap::new),
+            region_events: self.region_events,
+            ready_regions: Vec::new(),
+            degraded_regions: Vec::new(),
+        }
+    }
+}
+
+/// Distance from the point to the closest client, teleport destinations count as clients
+fn nearest_client(client_index: &ClientSpatialIndex, teleports: &[Teleport], point: Point) -> f32 {
+    teleports
+        .iter()
+        .map(|teleport| teleport.distance(point))
+        .fold(client_index.nearest_distance(point).unwrap_or(f32::MAX), f32::min)
+}
+
+/// Order the layers are generated in, each after its dependencies and the layers of higher
+/// priority groups first when the dependencies allow it
+fn generation_order(dag: &Dag<LayerId, ()>, priorities: &HashMap<LayerId, i32>) -> Vec<LayerId> {
+    let graph = dag.graph();
+    // Edges go from the dependents to their dependencies
+    let mut missing: HashMap<NodeIndex, usize> = graph
+        .node_indices()
+        .map(|node| (node, graph.neighbors_directed(node, Direction::Outgoing).count()))
+        .collect();
+    let mut ready: BinaryHeap<(i32, Reverse<NodeIndex>)> = missing
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(node, _)| (priorities[&dag[*node]], Reverse(*node)))
+        .collect();
+    let mut order = Vec::with_capacity(graph.node_count());
+    while let Some((_, Reverse(node))) = ready.pop() {
+        order.push(dag[node]);
+        for dependent in graph.neighbors_directed(node, Direction::Incoming) {
+            let count = missing.get_mut(&dependent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push((priorities[&dag[dependent]], Reverse(dependent)));
+            }
+        }
+    }
+    order
+}
@@ -1 +1,6486 @@
-pub mod generative_chunks;
\ No newline at end of file
+pub mod biome;
+pub mod bounds;
+pub mod camera_loader;
+pub mod checksum;
+pub mod chunk_entities;
+pub mod chunk_loader;
+pub mod chunk_set;
+pub mod context;
+pub mod coords;
+pub mod debug_overlay;
+pub mod diagnostics;
+pub mod error;
+pub mod events;
+pub mod facts;
+pub mod grid;
+pub mod group;
+pub mod interest;
+pub mod layer;
+pub mod layer_client;
+pub mod layer_id;
+pub mod layer_manager;
+pub mod log_targets;
+pub mod output;
+pub mod pace;
+pub mod persistence;
+pub mod plugin;
+pub mod polygon;
+#[cfg(feature = "presets")]
+pub mod presets;
+pub mod resources;
+#[cfg(feature = "remote-debug")]
+pub mod remote_debug;
+pub mod snapshot;
+pub mod teleport;
+pub mod usage;
+pub mod variations;
+pub mod worker;
+pub mod world_scale;
+
+pub use layer_manager::LayersManager;
+
+/// The types needed by most users, `use bevy_generative_chunks::prelude::*;`
+pub mod prelude {
+    pub use crate::bounds::{Bounds, ChunkIdx, ChunkLocalPos, Padding, Point, WorldPos};
+    pub use crate::chunk_entities::{ChunkSpawner, ChunkSpawnerPlugin};
+    pub use crate::chunk_loader::ChunkLoader;
+    pub use crate::chunk_set::ChunkSet;
+    pub use crate::context::{GenerationContext, PrepareContext};
+    pub use crate::error::ChunksError;
+    pub use crate::layer::{Chunk, Dependency, Layer};
+    pub use crate::layer_client::{ClientId, LayerClient};
+    pub use crate::layer_manager::{
+        LayerBundle, LayerLookupChunk, LayersManager, LayersManagerBuilder,
+    };
+    pub use crate::pace::{GenerationPace, GenerationPolicy};
+    pub use crate::plugin::{GenerativeChunksPlugin, GenerativeChunksSet};
+    pub use crate::usage::UsageStrategy;
+    pub use crate::world_scale::WorldScale;
+}
+
+/// Old module paths, kept so existing code keeps compiling
+#[deprecated(note = "use the modules at the crate root or `bevy_generative_chunks::prelude`")]
+pub mod generative_chunks {
+    pub use crate::{
+        biome, bounds, camera_loader, checksum, chunk_entities, chunk_loader, chunk_set, context,
+        coords, debug_overlay, diagnostics, error, events, facts, grid, group, interest, layer,
+        layer_client, layer_id, layer_manager, log_targets, output, pace, persistence, plugin,
+        polygon, resources, snapshot, teleport, usage, variations, worker, world_scale,
+    };
+    #[cfg(feature = "presets")]
+    pub use crate::presets;
+    #[cfg(feature = "remote-debug")]
+    pub use crate::remote_debug;
+}
+
+// The manager is shared with Bevy systems and worker threads
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<LayersManager>();
+};
+
+// Tests
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    mod test_layer {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        struct TestLayer;
+
+        #[derive(Debug)]
+        struct TestChunk;
+
+        impl Chunk for TestChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayer {
+            type Chunk = TestChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TestChunk
+            }
+        }
+
+        #[test]
+        fn test_layers_manager() {
+            let mut layers_manager = LayersManagerBuilder::new().add_layer(TestLayer).build();
+            layers_manager.print_dot();
+            layers_manager.regenerate();
+        }
+    }
+
+    mod test_layer_with_dependencies {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+
+        #[derive(Debug)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<TestLayerB>(Vec2::new(1.0, 1.0))]
+            }
+        }
+
+        struct TestLayerB;
+
+        impl Layer for TestLayerB {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_layers_manager() {
+            let mut layers_manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .add_layer(TestLayerB)
+                .build();
+            layers_manager.print_dot();
+            layers_manager.regenerate();
+        }
+    }
+
+    mod test_simple_generation {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA {
+            x: i32,
+            y: i32,
+        }
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                println!("Generating chunk {:?}", chunk_idx);
+                ChunkA {
+                    x: chunk_idx.x,
+                    y: chunk_idx.y,
+                }
+            }
+        }
+
+        #[test]
+        fn test_layers_manager() {
+            let mut layers_manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            layers_manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            layers_manager.regenerate();
+            // Check if the chunk is generated correctly
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(0.0, 0.0))
+                .is_some());
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(0.0, 0.0))
+                    .unwrap()
+                    .x,
+                0
+            );
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(0.0, 0.0))
+                    .unwrap()
+                    .y,
+                0
+            );
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(2.0, 2.0))
+                .is_some());
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(2.0, 2.0))
+                    .unwrap()
+                    .x,
+                2
+            );
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(2.0, 2.0))
+                    .unwrap()
+                    .y,
+                2
+            );
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(3.0, 3.0))
+                .is_none());
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(-1.0, -1.0))
+                .is_some());
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(-1.0, -1.0))
+                    .unwrap()
+                    .x,
+                -1
+            );
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<TestLayerA>(Vec2::new(-1.0, -1.0))
+                    .unwrap()
+                    .y,
+                -1
+            );
+        }
+    }
+
+    mod test_simple_generation_with_deps {
+        use bevy::math::Vec2;
+        use super::*;
+        use crate::layer::{Dependency, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use layer::Chunk;
+        use rand::{Rng, SeedableRng};
+        use crate::bounds::{Bounds, ChunkIdx, Point};
+        use crate::layer_client::LayerClient;
+        use crate::usage::UsageStrategy;
+
+        /// For this test we will have a layer that depends on another layer
+        /// The points layer will have a chunk size of 5x5 and will generate a single random point
+        /// and a random color for that point
+        /// The voronoi layer will have a chunk size of 1x1 and will generate the color of the closest point
+        /// in the points layer, the dependency will have a padding of 10x10 to ensure that the voronoi layer
+        /// has enough information to generate the color of the closest point
+
+        #[derive(Debug, Clone)]
+        struct PointChunk {
+            /// The point is in real coordinates
+            point: Point,
+            color: (u8, u8, u8),
+        }
+
+        struct PointsLayer;
+        impl Chunk for PointChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(5., 5.)
+            }
+        }
+
+        impl Layer for PointsLayer {
+            type Chunk = PointChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                // Get thread random with the chunk_idx as seed
+                let seed = chunk_idx.x + chunk_idx.y * 23;
+                let mut random = rand::prelude::SmallRng::seed_from_u64(seed as u64);
+
+                PointChunk {
+                    point: Vec2::new(
+                        random.gen_range(0.0..5.0) + chunk_idx.x as f32,
+                        random.gen_range(0.0..5.0) + chunk_idx.y as f32,
+                    ),
+                    color: (
+                        random.gen_range(0..255),
+                        random.gen_range(0..255),
+                        random.gen_range(0..255),
+                    ),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct VoronoiChunk {
+            /// The color of the closest point
+            color: (u8, u8, u8),
+        }
+
+        impl Chunk for VoronoiChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct VoronoiLayer;
+
+        impl Layer for VoronoiLayer {
+            type Chunk = VoronoiChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                // Get the closest point from the points layer
+                let bounds = Bounds::from_point(chunk_idx.to_point(Self::Chunk::get_size()))
+                    .expand(10.0, 10.0);
+                let points = lookup.get_chunks_in::<PointsLayer>(bounds);
+                let closest_point = points
+                    .iter()
+                    .min_by(|a, b| {
+                        let a_dist = a.point.distance(chunk_idx.center(Self::Chunk::get_size()));
+                        let b_dist = b.point.distance(chunk_idx.center(Self::Chunk::get_size()));
+                        a_dist.partial_cmp(&b_dist).unwrap()
+                    })
+                    .unwrap();
+                VoronoiChunk {
+                    color: closest_point.color,
+                }
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<PointsLayer>(Vec2::new(10.0, 10.0))]
+            }
+        }
+
+        #[test]
+        fn test_layers_manager() {
+            let mut layers_manager = LayersManagerBuilder::new()
+                .add_layer(PointsLayer)
+                .add_layer(VoronoiLayer)
+                .build();
+            layers_manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<VoronoiLayer>(Vec2::new(8.0, 9.0))],
+                UsageStrategy::Fast,
+            ));
+            layers_manager.regenerate();
+            // Check if the chunk is generated correctly
+            assert!(layers_manager
+                .get_chunk::<VoronoiLayer>(Vec2::new(0.0, 0.0))
+                .is_some());
+            assert_eq!(
+                layers_manager
+                    .get_chunk::<VoronoiLayer>(Vec2::new(0.0, 0.0))
+                    .unwrap()
+                    .color,
+                (193, 180, 73)
+            );
+        }
+    }
+
+    mod test_client_filter {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_filtered_client() {
+            let mut layers_manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            layers_manager.add_layer_client(
+                LayerClient::new(
+                    Vec2::new(0.0, 0.0),
+                    vec![Dependency::new::<TestLayerA>(Vec2::new(2.0, 2.0))],
+                    UsageStrategy::Fast,
+                )
+                .with_name("Positive quadrant")
+                .with_filter(|_, idx| idx.x >= 0 && idx.y >= 0),
+            );
+            layers_manager.regenerate();
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(1.0, 1.0))
+                .is_some());
+            assert!(layers_manager
+                .get_chunk::<TestLayerA>(Vec2::new(-1.0, -1.0))
+                .is_none());
+        }
+    }
+
+    mod test_client_area_delta {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        fn client_at(center: Vec2) -> LayerClient {
+            LayerClient::new(
+                center,
+                vec![Dependency::new::<TestLayerA>(Vec2::new(0.5, 0.5))],
+                UsageStrategy::Fast,
+            )
+        }
+
+        #[test]
+        fn test_client_area_delta() {
+            let mut layers_manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let client = layers_manager.add_layer_client(client_at(Vec2::new(0.5, 0.5)));
+            layers_manager.regenerate();
+            let delta = layers_manager.get_client_area_delta::<TestLayerA>(client).unwrap();
+            assert!(delta.entered.contains(&ChunkIdx { x: 0, y: 0 }));
+            assert!(delta.left.is_empty());
+
+            // Same position, nothing changes
+            layers_manager.update_client_center(client, Vec2::new(0.5, 0.5));
+            layers_manager.regenerate();
+            assert!(layers_manager.get_client_area_delta::<TestLayerA>(client).is_none());
+
+            layers_manager.update_client_center(client, Vec2::new(10.5, 0.5));
+            layers_manager.regenerate();
+            let delta = layers_manager.get_client_area_delta::<TestLayerA>(client).unwrap();
+            assert!(delta.entered.contains(&ChunkIdx { x: 10, y: 0 }));
+            assert!(delta.left.contains(&ChunkIdx { x: 0, y: 0 }));
+        }
+
+        #[test]
+        fn test_new_client_starts_without_area() {
+            let mut layers_manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let first = layers_manager.add_layer_client(client_at(Vec2::new(0.5, 0.5)));
+            layers_manager.regenerate();
+
+            // The deltas of a client added after a clear don't come from the removed client
+            layers_manager.clear_layer_clients();
+            let second = layers_manager.add_layer_client(client_at(Vec2::new(10.5, 0.5)));
+            layers_manager.regenerate();
+            assert!(layers_manager.get_client_area_delta::<TestLayerA>(first).is_none());
+            let delta = layers_manager.get_client_area_delta::<TestLayerA>(second).unwrap();
+            assert!(delta.entered.contains(&ChunkIdx { x: 10, y: 0 }));
+            assert!(delta.left.is_empty());
+
+            // Removing a client doesn't shift the deltas of the others
+            let third = layers_manager.add_layer_client(client_at(Vec2::new(20.5, 0.5)));
+            layers_manager.regenerate();
+            layers_manager.remove_client(second);
+            let delta = layers_manager.get_client_area_delta::<TestLayerA>(third).unwrap();
+            assert!(delta.entered.contains(&ChunkIdx { x: 20, y: 0 }));
+        }
+    }
+
+    mod test_morton_order {
+        use crate::bounds::ChunkIdx;
+
+        #[test]
+        fn test_morton_order() {
+            let mut indices = vec![
+                ChunkIdx { x: 1, y: 1 },
+                ChunkIdx { x: 0, y: 1 },
+                ChunkIdx { x: -1, y: 0 },
+                ChunkIdx { x: 1, y: 0 },
+                ChunkIdx { x: 0, y: 0 },
+            ];
+            indices.sort();
+            assert_eq!(
+                indices,
+                vec![
+                    ChunkIdx { x: -1, y: 0 },
+                    ChunkIdx { x: 0, y: 0 },
+                    ChunkIdx { x: 1, y: 0 },
+                    ChunkIdx { x: 0, y: 1 },
+                    ChunkIdx { x: 1, y: 1 },
+                ]
+            );
+        }
+    }
+
+    mod test_worker {
+        use std::time::{Duration, Instant};
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+        use crate::worker::ChunkEventKind;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_worker_generates() {
+            let worker = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .build()
+                .spawn_worker(Duration::from_millis(5));
+            worker.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            let start = Instant::now();
+            let mut generated = false;
+            while !generated && start.elapsed() < Duration::from_secs(5) {
+                generated = worker
+                    .poll_events()
+                    .iter()
+                    .any(|event| event.kind == ChunkEventKind::Generated);
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert!(generated);
+            let manager = worker.shutdown();
+            assert!(manager
+                .get_chunk::<TestLayerA>(Vec2::new(0.0, 0.0))
+                .is_some());
+        }
+
+        #[test]
+        fn test_worker_updates_clients_by_id() {
+            let client = || {
+                LayerClient::new(
+                    Vec2::new(0.0, 0.0),
+                    vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                    UsageStrategy::Fast,
+                )
+            };
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let before = manager.add_layer_client(client());
+            let worker = manager.spawn_worker(Duration::from_millis(5));
+            let moved = worker.add_layer_client(client());
+            let removed = worker.add_layer_client(client());
+            assert_ne!(before, moved);
+            assert_ne!(moved, removed);
+            worker.update_client_center(moved, Vec2::new(10.0, 10.0));
+            worker.set_client_active(moved, false);
+            worker.remove_client(removed);
+
+            let manager = worker.shutdown();
+            let client = manager.get_client(moved).unwrap();
+            assert_eq!(client.get_center(), Vec2::new(10.0, 10.0));
+            assert!(!client.is_active());
+            assert!(manager.get_client(removed).is_none());
+            assert!(manager.get_client(before).is_some());
+        }
+    }
+
+    mod test_persistence {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::persistence::{ChunkLoadError, ChunkVersioned, SavedChunk};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct HeightChunk {
+            height: u16,
+        }
+
+        struct HeightLayer;
+
+        impl Chunk for HeightChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for HeightLayer {
+            type Chunk = HeightChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                HeightChunk { height: 0 }
+            }
+        }
+
+        // Version 1 stored the height in a single byte
+        impl ChunkVersioned for HeightChunk {
+            const SCHEMA_VERSION: u32 = 2;
+
+            fn encode(&self) -> Vec<u8> {
+                self.height.to_le_bytes().to_vec()
+            }
+
+            fn decode(data: &[u8]) -> Result<Self, ChunkLoadError> {
+                let bytes: [u8; 2] = data
+                    .try_into()
+                    .map_err(|_| ChunkLoadError::Corrupt("expected 2 bytes".to_string()))?;
+                Ok(HeightChunk {
+                    height: u16::from_le_bytes(bytes),
+                })
+            }
+
+            fn migrate(from_version: u32, data: &[u8]) -> Result<Self, ChunkLoadError> {
+                match (from_version, data) {
+                    (1, [height]) => Ok(HeightChunk {
+                        height: *height as u16,
+                    }),
+                    _ => Err(ChunkLoadError::Unsupported {
+                        found: from_version,
+                    }),
+                }
+            }
+        }
+
+        #[test]
+        fn test_migrate_old_save() {
+            let old = SavedChunk {
+                schema_version: 1,
+                layer_version: 0,
+                data: vec![7],
+            };
+            assert_eq!(old.load::<HeightChunk>(), Ok(HeightChunk { height: 7 }));
+
+            let newer = SavedChunk {
+                schema_version: 3,
+                layer_version: 0,
+                data: vec![],
+            };
+            assert_eq!(
+                newer.load::<HeightChunk>(),
+                Err(ChunkLoadError::Newer {
+                    found: 3,
+                    current: 2
+                })
+            );
+        }
+
+        #[test]
+        fn test_load_chunk_into_manager() {
+            let mut manager = LayersManagerBuilder::new().add_layer(HeightLayer).build();
+            let chunk_idx = ChunkIdx { x: 2, y: 3 };
+            let saved = SavedChunk::save(&HeightChunk { height: 300 });
+            manager.load_chunk::<HeightLayer>(chunk_idx, &saved).unwrap();
+            assert_eq!(
+                manager.get_chunk::<HeightLayer>(Vec2::new(2.5, 3.5)),
+                Some(HeightChunk { height: 300 })
+            );
+            assert_eq!(manager.save_chunk::<HeightLayer>(chunk_idx), Some(saved));
+        }
+
+        #[test]
+        fn test_stale_layer_version() {
+            let mut manager = LayersManagerBuilder::new().add_layer(HeightLayer).build();
+            let chunk_idx = ChunkIdx { x: 0, y: 0 };
+            let saved = SavedChunk::save(&HeightChunk { height: 1 }).with_layer_version(4);
+            assert_eq!(
+                manager.load_chunk::<HeightLayer>(chunk_idx, &saved),
+                Err(ChunkLoadError::StaleLayer {
+                    found: 4,
+                    current: 0
+                })
+            );
+            assert!(manager
+                .get_chunk::<HeightLayer>(Vec2::new(0.5, 0.5))
+                .is_none());
+        }
+    }
+
+    mod test_teleport {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::{BandedClient, ClientId, DistanceBands, LayerClient};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        fn client_at(x: f32, y: f32) -> LayerClient {
+            LayerClient::new(
+                Vec2::new(x, y),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            )
+        }
+
+        #[test]
+        fn test_prewarm_keeps_origin_until_arrival() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(client_at(0.5, 0.5));
+            manager.regenerate();
+
+            let destination = Vec2::new(100.5, 100.5);
+            let teleport = manager.prewarm_for_teleport::<TestLayerA>(destination, 1.0);
+            assert!(!manager.is_teleport_ready(teleport));
+            manager.regenerate();
+            assert!(manager.is_teleport_ready(teleport));
+            assert!(manager.is_region_ready::<TestLayerA>(&Bounds::new(
+                destination - Vec2::ONE,
+                destination + Vec2::ONE
+            )));
+
+            // The client arrives, the origin is still pinned
+            manager.clear_layer_clients();
+            manager.add_layer_client(client_at(100.5, 100.5));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+
+            manager.complete_teleport(teleport);
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_none());
+            assert!(!manager.is_teleport_ready(teleport));
+        }
+
+        #[test]
+        fn test_usage_audit_finds_no_leak() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .audit_usages(true)
+                .build();
+            manager.add_layer_client(client_at(0.5, 0.5));
+            manager.add_layer_client(client_at(1.5, 0.5));
+            manager.prewarm_for_teleport::<TestLayerA>(Vec2::new(10.0, 10.0), 1.0);
+            manager.regenerate();
+            manager.regenerate();
+            assert_eq!(manager.drain_usage_leaks(), vec![]);
+        }
+
+        #[test]
+        fn test_banded_client() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_banded_client(
+                BandedClient::new(Vec2::new(0.5, 0.5), DistanceBands::new(1.0, 3.0, 6.0))
+                    .with_layer::<TestLayerA>()
+                    .with_name("player"),
+            );
+            assert_eq!(manager.get_clients_in(&Bounds::from_point(Vec2::ZERO)).len(), 0);
+            manager.regenerate();
+            assert_eq!(
+                manager
+                    .get_clients_in(&Bounds::from_point(Vec2::new(0.5, 0.5)))
+                    .len(),
+                3
+            );
+            // Fast band generated right away, the KeepAlive band is never generated
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(5.5, 0.5)).is_none());
+        }
+
+        #[test]
+        fn test_client_index_follows_the_clients() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let near = manager.add_layer_client(client_at(0.5, 0.5));
+            let far = manager.add_layer_client(client_at(50.5, 0.5));
+            manager.regenerate();
+            let origin = Bounds::new(Vec2::splat(-5.0), Vec2::splat(5.0));
+            assert_eq!(manager.get_clients_in(&origin).len(), 1);
+            assert_eq!(manager.distance_to_nearest_client(Vec2::new(0.5, 0.5)), Some(0.0));
+
+            // Moves and removals reach the index
+            manager.update_client_center(far, Vec2::new(2.5, 0.5));
+            manager.remove_client(near);
+            manager.regenerate();
+            let clients = manager.get_clients_in(&origin);
+            assert_eq!(clients.len(), 1);
+            assert_eq!(clients[0].get_id(), Some(far));
+            assert_eq!(manager.distance_to_nearest_client(Vec2::new(0.5, 0.5)), Some(2.0));
+
+            manager.set_client_active(far, false);
+            manager.regenerate();
+            assert!(manager.get_clients_in(&origin).is_empty());
+            assert_eq!(manager.distance_to_nearest_client(Vec2::new(0.5, 0.5)), None);
+        }
+
+        #[test]
+        fn test_only_changed_clients_are_refreshed() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .audit_usages(true)
+                .build();
+            let clients: Vec<ClientId> = (0..10)
+                .map(|i| manager.add_layer_client(client_at(10.0 * i as f32 + 0.5, 0.5)))
+                .collect();
+            manager.regenerate();
+            assert_eq!(manager.get_stats().refreshed_clients, 10);
+
+            // The clients that didn't move keep their chunks without requesting them again
+            manager.regenerate();
+            assert_eq!(manager.get_stats().refreshed_clients, 0);
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(90.5, 0.5)).is_some());
+
+            manager.update_client_center(clients[0], Vec2::new(0.5, 20.5));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().refreshed_clients, 1);
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 20.5)).is_some());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_none());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(10.5, 0.5)).is_some());
+            assert_eq!(manager.drain_usage_leaks(), vec![]);
+        }
+
+        #[test]
+        fn test_overlapping_clients_share_their_chunks() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .audit_usages(true)
+                .build();
+            let left = manager.add_layer_client(client_at(0.5, 0.5));
+            let right = manager.add_layer_client(client_at(1.5, 0.5));
+            manager.regenerate();
+
+            // The chunks the other client still holds stay
+            manager.update_client_center(left, Vec2::new(50.5, 0.5));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(-0.5, 0.5)).is_none());
+
+            manager.remove_client(right);
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(1.5, 0.5)).is_none());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(50.5, 0.5)).is_some());
+            assert_eq!(manager.drain_usage_leaks(), vec![]);
+        }
+    }
+
+    mod test_invalidate_region {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct BaseChunk;
+
+        struct BaseLayer;
+
+        impl Chunk for BaseChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(4., 4.)
+            }
+        }
+
+        impl Layer for BaseLayer {
+            type Chunk = BaseChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                BaseChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct DetailChunk;
+
+        struct DetailLayer;
+
+        impl Chunk for DetailChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for DetailLayer {
+            type Chunk = DetailChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                DetailChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::new(0.0, 0.0))]
+            }
+        }
+
+        #[test]
+        fn test_only_damaged_dependents_regenerate() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(DetailLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<DetailLayer>(Vec2::new(20.0, 20.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let all_detail = manager.get_generated_chunks::<DetailLayer>().len();
+
+            let damaged = Bounds::from_point(Vec2::new(10.0, 10.0)).expand(1.0, 1.0);
+            manager.invalidate_region::<BaseLayer>(&damaged);
+            manager.regenerate();
+
+            // Only the detail chunks reading the damaged base chunks 2 and 3
+            let regenerated = manager.get_generated_chunks::<DetailLayer>();
+            let read_damage = |v: i32| (4..=15).contains(&v);
+            assert!(!regenerated.is_empty());
+            assert!(regenerated.len() < all_detail);
+            assert!(regenerated
+                .iter()
+                .all(|chunk_idx| read_damage(chunk_idx.x) && read_damage(chunk_idx.y)));
+            assert!(!manager.get_generated_chunks::<BaseLayer>().is_empty());
+        }
+
+        #[test]
+        fn test_reverse_dependency_index() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(DetailLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<DetailLayer>(Vec2::new(8.0, 8.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            // Detail chunk (1, 1) covers [1, 2], the base chunks 0 and 1 touch it on each axis
+            let detail_idx = ChunkIdx { x: 1, y: 1 };
+            let sources = manager.get_chunk_sources::<DetailLayer>(detail_idx);
+            let base_chunks = vec![
+                ChunkIdx { x: 0, y: 0 },
+                ChunkIdx { x: 0, y: 1 },
+                ChunkIdx { x: 1, y: 0 },
+                ChunkIdx { x: 1, y: 1 },
+            ];
+            assert_eq!(sources, vec![(LayerId::from_type::<BaseLayer>(), base_chunks)]);
+
+            let dependents = manager.get_chunk_dependents::<BaseLayer>(ChunkIdx { x: 0, y: 0 });
+            assert!(dependents.contains(&(LayerId::from_type::<DetailLayer>(), detail_idx)));
+            assert!(dependents
+                .iter()
+                .all(|(layer_id, _)| *layer_id == LayerId::from_type::<DetailLayer>()));
+        }
+    }
+
+    mod test_products {
+        use std::any::Any;
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct TerrainChunk {
+            height: f32,
+            moisture: f32,
+        }
+
+        struct TerrainLayer;
+
+        impl Chunk for TerrainChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+
+            fn get_product(&self, name: &str) -> Option<&dyn Any> {
+                match name {
+                    "height" => Some(&self.height),
+                    "moisture" => Some(&self.moisture),
+                    _ => None,
+                }
+            }
+        }
+
+        impl Layer for TerrainLayer {
+            type Chunk = TerrainChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                TerrainChunk {
+                    height: chunk_idx.x as f32,
+                    moisture: 0.5,
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct SlopeChunk {
+            height: f32,
+        }
+
+        struct SlopeLayer;
+
+        impl Chunk for SlopeChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for SlopeLayer {
+            type Chunk = SlopeChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let pos = chunk_idx.center(Self::Chunk::get_size());
+                SlopeChunk {
+                    height: lookup
+                        .get_product::<TerrainLayer, f32>(pos, "height")
+                        .unwrap(),
+                }
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![
+                    Dependency::new::<TerrainLayer>(Vec2::new(0.0, 0.0))
+                        .with_products(&["height"]),
+                ]
+            }
+        }
+
+        #[test]
+        fn test_unrelated_product_change_keeps_dependents() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TerrainLayer)
+                .add_layer(SlopeLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<SlopeLayer>(Vec2::new(4.0, 4.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(
+                manager.get_product::<TerrainLayer, f32>(Vec2::new(2.5, 0.5), "height"),
+                Some(2.0)
+            );
+            assert_eq!(manager.get_chunk::<SlopeLayer>(Vec2::new(2.5, 0.5)).unwrap().height, 2.0);
+
+            let region = Bounds::from_point(Vec2::new(1.5, 1.5));
+            manager.invalidate_products::<TerrainLayer>(&region, &["moisture"]);
+            manager.regenerate();
+            assert!(!manager.get_generated_chunks::<TerrainLayer>().is_empty());
+            assert!(manager.get_generated_chunks::<SlopeLayer>().is_empty());
+
+            manager.invalidate_products::<TerrainLayer>(&region, &["height"]);
+            manager.regenerate();
+            assert!(!manager.get_generated_chunks::<SlopeLayer>().is_empty());
+        }
+    }
+
+    mod test_output {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::output::{LayerOutput, Output};
+        use crate::usage::UsageStrategy;
+
+        /// A town with a horizontal road crossing it
+        #[derive(Debug, Clone)]
+        struct TownChunk {
+            road_y: i32,
+        }
+
+        #[derive(Debug, Clone)]
+        struct RoadTile {
+            is_road: bool,
+        }
+
+        struct SettlementLayer;
+
+        impl Chunk for TownChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(8., 8.)
+            }
+        }
+
+        impl Chunk for RoadTile {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for SettlementLayer {
+            type Chunk = TownChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                TownChunk {
+                    road_y: chunk_idx.y * 8 + 3,
+                }
+            }
+        }
+
+        impl LayerOutput<RoadTile> for SettlementLayer {
+            fn output(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> RoadTile {
+                let pos = chunk_idx.center(RoadTile::get_size());
+                let town = lookup
+                    .get_chunk::<SettlementLayer>(LayerId::from_type::<SettlementLayer>(), pos);
+                RoadTile {
+                    is_road: town.is_some_and(|town| town.road_y == chunk_idx.y),
+                }
+            }
+        }
+
+        #[test]
+        fn test_output_layer() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(SettlementLayer)
+                .add_output::<SettlementLayer, RoadTile>()
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(4.0, 4.0),
+                vec![Dependency::new::<Output<SettlementLayer, RoadTile>>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let road = manager.get_chunk::<Output<SettlementLayer, RoadTile>>(Vec2::new(4.5, 3.5));
+            assert!(road.unwrap().is_road);
+            let field = manager.get_chunk::<Output<SettlementLayer, RoadTile>>(Vec2::new(4.5, 4.5));
+            assert!(!field.unwrap().is_road);
+            assert!(manager
+                .get_chunk::<SettlementLayer>(Vec2::new(4.0, 4.0))
+                .is_some());
+        }
+    }
+
+    mod test_resources {
+        use bevy::ecs::resource::Resource;
+        use bevy::ecs::world::World;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, GenerateOutcome, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Resource, Clone)]
+        struct WorldSettings {
+            sea_level: f32,
+        }
+
+        #[derive(Debug, Clone)]
+        struct WaterChunk {
+            sea_level: Option<f32>,
+        }
+
+        struct WaterLayer;
+
+        impl Chunk for WaterChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for WaterLayer {
+            type Chunk = WaterChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                WaterChunk {
+                    sea_level: lookup
+                        .get_resource::<WorldSettings>()
+                        .map(|settings| settings.sea_level),
+                }
+            }
+        }
+
+        #[test]
+        fn test_layers_read_captured_resources() {
+            let mut world = World::new();
+            world.insert_resource(WorldSettings { sea_level: 12.0 });
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(WaterLayer)
+                .capture_resource::<WorldSettings>()
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<WaterLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.capture_resources(&world);
+            manager.regenerate();
+            let chunk = manager.get_chunk::<WaterLayer>(Vec2::new(0.5, 0.5)).unwrap();
+            assert_eq!(chunk.sea_level, Some(12.0));
+        }
+
+        #[derive(Debug, Clone)]
+        struct StructureChunk;
+
+        struct StructureLayer;
+
+        /// Stands for an asset still loading
+        struct StructurePrefab;
+
+        impl Chunk for StructureChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for StructureLayer {
+            type Chunk = StructureChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                StructureChunk
+            }
+
+            fn try_generate(
+                &self,
+                lookup: &LayerLookupChunk,
+                chunk_idx: &ChunkIdx,
+            ) -> GenerateOutcome<Self::Chunk> {
+                match lookup.get_resource::<StructurePrefab>() {
+                    Some(_) => GenerateOutcome::Ready(self.generate(lookup, chunk_idx)),
+                    None => GenerateOutcome::Defer,
+                }
+            }
+        }
+
+        #[test]
+        fn test_deferred_chunks_are_retried() {
+            let mut manager = LayersManagerBuilder::new().add_layer(StructureLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<StructureLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(manager.get_stats().deferred > 0);
+            assert_eq!(manager.get_stats().generated, 0);
+            assert!(manager.get_chunk::<StructureLayer>(Vec2::new(0.5, 0.5)).is_none());
+
+            manager.set_resource(StructurePrefab);
+            manager.regenerate();
+            assert_eq!(manager.get_stats().deferred, 0);
+            assert!(manager.get_chunk::<StructureLayer>(Vec2::new(0.5, 0.5)).is_some());
+        }
+    }
+
+    mod test_placeholder {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, ChunkState, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct DetailChunk {
+            detail: bool,
+        }
+
+        struct SlowLayer;
+
+        impl Chunk for DetailChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for SlowLayer {
+            type Chunk = DetailChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                DetailChunk { detail: true }
+            }
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(1)
+            }
+
+            fn placeholder(&self, _: &ChunkIdx) -> Option<Self::Chunk> {
+                Some(DetailChunk { detail: false })
+            }
+        }
+
+        #[test]
+        fn test_pending_chunks_have_placeholders() {
+            let mut manager = LayersManagerBuilder::new().add_layer(SlowLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<SlowLayer>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let bounds = Bounds::from_point(Vec2::ZERO).expand(2.0, 2.0);
+            let chunks = manager.get_chunks_or_placeholders_in::<SlowLayer>(bounds);
+            let generated = chunks.iter().filter(|(_, state)| !state.is_placeholder());
+            assert_eq!(generated.count(), 1);
+            assert!(chunks.iter().any(|(_, state)| state.is_placeholder()));
+            assert!(chunks
+                .iter()
+                .all(|(_, state)| state.get().detail != state.is_placeholder()));
+
+            // Chunks no client asked for have no placeholder
+            assert_eq!(
+                manager.get_chunk_or_placeholder::<SlowLayer>(Vec2::new(50.0, 50.0)),
+                None::<ChunkState<DetailChunk>>
+            );
+        }
+    }
+
+    mod test_expiry {
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct PriceChunk;
+
+        struct MarketLayer;
+
+        impl Chunk for PriceChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for MarketLayer {
+            type Chunk = PriceChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                PriceChunk
+            }
+
+            fn expiry(&self, _: &Self::Chunk, _: &ChunkIdx) -> Option<Duration> {
+                Some(Duration::from_secs(600))
+            }
+        }
+
+        #[test]
+        fn test_expired_chunks_are_regenerated() {
+            let mut manager = LayersManagerBuilder::new().add_layer(MarketLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<MarketLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let all = manager.get_generated_chunks::<MarketLayer>().clone();
+
+            manager.advance_clock(Duration::from_secs(300));
+            manager.regenerate();
+            assert!(manager.get_expired_chunks::<MarketLayer>().is_empty());
+            assert!(manager.get_generated_chunks::<MarketLayer>().is_empty());
+
+            manager.advance_clock(Duration::from_secs(300));
+            manager.regenerate();
+            assert_eq!(manager.get_expired_chunks::<MarketLayer>(), &all);
+            assert_eq!(manager.get_generated_chunks::<MarketLayer>(), &all);
+        }
+    }
+
+    mod test_variations {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+        use crate::variations::{SeedSearch, SeedVariations};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct SeedChunk {
+            seed: u64,
+        }
+
+        struct SeedLayer;
+
+        impl Chunk for SeedChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for SeedLayer {
+            type Chunk = SeedChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                SeedChunk {
+                    seed: lookup.get_seed(),
+                }
+            }
+        }
+
+        #[test]
+        fn test_compare_seeds() {
+            let mut variations =
+                SeedVariations::new([1, 2, 3], || LayersManagerBuilder::new().add_layer(SeedLayer));
+            variations.add_layer_client(|| {
+                LayerClient::new(
+                    Vec2::new(0.0, 0.0),
+                    vec![Dependency::new::<SeedLayer>(Vec2::new(1.0, 1.0))],
+                    UsageStrategy::Fast,
+                )
+            });
+            variations.regenerate();
+            let chunks = variations.compare::<SeedLayer>(Vec2::new(0.5, 0.5));
+            assert_eq!(
+                chunks,
+                vec![
+                    (1, Some(SeedChunk { seed: 1 })),
+                    (2, Some(SeedChunk { seed: 2 })),
+                    (3, Some(SeedChunk { seed: 3 })),
+                ]
+            );
+            assert_eq!(variations.get(2).unwrap().get_seed(), 2);
+        }
+
+        #[test]
+        fn test_seed_search() {
+            let search = SeedSearch::new(|| LayersManagerBuilder::new().add_layer(SeedLayer))
+                .client(|| {
+                    LayerClient::new(
+                        Vec2::new(0.0, 0.0),
+                        vec![Dependency::new::<SeedLayer>(Vec2::new(1.0, 1.0))],
+                        UsageStrategy::Fast,
+                    )
+                });
+            let seeds = search.run(0..20, |manager| {
+                manager
+                    .get_chunk::<SeedLayer>(Vec2::new(0.5, 0.5))
+                    .is_some_and(|chunk| chunk.seed % 7 == 0)
+            });
+            assert_eq!(seeds, vec![0, 7, 14]);
+        }
+    }
+
+    mod test_scope {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::snapshot::ChunksSnapshot;
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA(i32);
+
+        #[derive(Debug, Clone)]
+        struct ChunkB(i32);
+
+        struct TestLayerA;
+
+        struct TestLayerB;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Chunk for ChunkB {
+            fn get_size() -> Vec2 {
+                Vec2::new(2., 2.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                ChunkA(chunk_idx.x)
+            }
+        }
+
+        impl Layer for TestLayerB {
+            type Chunk = ChunkB;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                ChunkB(chunk_idx.x * 10)
+            }
+        }
+
+        #[test]
+        fn test_layer_views_on_threads() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .add_layer(TestLayerB)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![
+                    Dependency::new::<TestLayerA>(Vec2::new(4.0, 4.0)),
+                    Dependency::new::<TestLayerB>(Vec2::new(4.0, 4.0)),
+                ],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let (a, b) = manager.scope(|reader| {
+                let view_a = reader.layer::<TestLayerA>().unwrap();
+                let view_b = reader.layer::<TestLayerB>().unwrap();
+                std::thread::scope(|threads| {
+                    let a = threads.spawn(move || view_a.get_chunk(Vec2::new(3.5, 0.5)).unwrap().0);
+                    let b = threads.spawn(move || view_b.get_chunk(Vec2::new(3.5, 0.5)).unwrap().0);
+                    (a.join().unwrap(), b.join().unwrap())
+                })
+            });
+            assert_eq!((a, b), (3, 10));
+        }
+
+        #[test]
+        fn test_snapshots_read_concurrently() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(4.0, 4.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            // A reader holding the layer doesn't keep the others from taking snapshots
+            let _layer = manager.read_layer(LayerId::from_type::<TestLayerA>()).unwrap();
+            let snapshots: Vec<ChunksSnapshot> = std::thread::scope(|threads| {
+                let handles: Vec<_> = (0..4)
+                    .map(|_| threads.spawn(|| manager.read_snapshot()))
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+            });
+            let first = snapshots[0].get_layer::<TestLayerA>().unwrap();
+            assert_eq!(first.get_chunk::<TestLayerA>(Vec2::new(3.5, 0.5)).unwrap().0, 3);
+            // Built once and shared by every reader
+            for snapshot in &snapshots {
+                assert!(std::ptr::eq(snapshot.get_layer::<TestLayerA>().unwrap(), first));
+            }
+        }
+    }
+
+    mod test_chunk_entities {
+        use bevy::app::App;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use bevy::ecs::component::Component;
+        use bevy::ecs::entity::Entity;
+        use bevy::ecs::system::Commands;
+        use crate::chunk_entities::{
+            ChunkEntitiesPlugin, ChunkEntity, ChunkEntities, ChunkSpawnerPlugin,
+        };
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_despawn_on_delete() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let mut app = App::new();
+            app.add_plugins(ChunkEntitiesPlugin).insert_resource(manager);
+            let chunk_idx = ChunkIdx { x: 0, y: 0 };
+            let entity = app
+                .world_mut()
+                .spawn(ChunkEntity::new::<TestLayerA>(chunk_idx))
+                .id();
+            app.update();
+            let entities = app.world().resource::<ChunkEntities>();
+            assert_eq!(entities.get_for::<TestLayerA>(chunk_idx), Some(entity));
+
+            let mut manager = app.world_mut().resource_mut::<LayersManager>();
+            manager.clear_layer_clients();
+            manager.regenerate();
+            app.update();
+            assert!(app.world().get_entity(entity).is_err());
+            assert!(app.world().resource::<ChunkEntities>().is_empty());
+        }
+
+        #[derive(Component)]
+        struct Shown(ChunkIdx);
+
+        fn spawn_chunk(commands: &mut Commands, idx: ChunkIdx, _: &ChunkA) -> Entity {
+            commands.spawn(Shown(idx)).id()
+        }
+
+        #[test]
+        fn test_chunk_spawner() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let mut app = App::new();
+            app.add_plugins(ChunkSpawnerPlugin::<TestLayerA, _>::new(spawn_chunk))
+                .insert_resource(manager);
+            app.update();
+            let chunk_idx = ChunkIdx { x: -1, y: 1 };
+            let entity = app
+                .world()
+                .resource::<ChunkEntities>()
+                .get_for::<TestLayerA>(chunk_idx)
+                .unwrap();
+            assert_eq!(app.world().get::<Shown>(entity).unwrap().0, chunk_idx);
+            assert_eq!(
+                app.world().get::<ChunkEntity>(entity),
+                Some(&ChunkEntity::new::<TestLayerA>(chunk_idx))
+            );
+            assert_eq!(app.world().resource::<ChunkEntities>().len(), 9);
+            // No regenerate, nothing new to spawn
+            app.update();
+            assert_eq!(app.world_mut().query::<&Shown>().iter(app.world()).count(), 9);
+
+            let mut manager = app.world_mut().resource_mut::<LayersManager>();
+            manager.clear_layer_clients();
+            manager.regenerate();
+            app.update();
+            assert_eq!(app.world_mut().query::<&Shown>().iter(app.world()).count(), 0);
+        }
+    }
+
+    mod test_groups {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::group::LayerGroup;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct Terrain;
+
+        struct Decoration;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for Terrain {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn group(&self) -> Option<&'static str> {
+                Some("gameplay")
+            }
+        }
+
+        impl Layer for Decoration {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn group(&self) -> Option<&'static str> {
+                Some("visual")
+            }
+        }
+
+        #[test]
+        fn test_group_budget() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_group(LayerGroup::new("gameplay").with_priority(1))
+                .add_group(LayerGroup::new("visual").with_max_chunks(4))
+                .add_layer(Terrain)
+                .add_layer(Decoration)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![
+                    Dependency::new::<Terrain>(Vec2::new(2.0, 2.0)),
+                    Dependency::new::<Decoration>(Vec2::new(2.0, 2.0)),
+                ],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            // The decoration budget doesn't hold back the terrain
+            assert_eq!(manager.get_generated_chunks::<Terrain>().len(), 25);
+            assert_eq!(manager.get_generated_chunks::<Decoration>().len(), 4);
+            assert_eq!(manager.get_group_usage("visual").chunks, 4);
+            assert_eq!(manager.get_group_usage("gameplay").chunks, 25);
+
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<Decoration>().len(), 4);
+        }
+
+        struct Grass;
+
+        struct Collision;
+
+        impl Layer for Grass {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn group(&self) -> Option<&'static str> {
+                Some("shared")
+            }
+        }
+
+        impl Layer for Collision {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn group(&self) -> Option<&'static str> {
+                Some("shared")
+            }
+
+            fn priority(&self, _: &ChunkIdx, distance_to_nearest_client: f32) -> f32 {
+                100.0 - distance_to_nearest_client
+            }
+        }
+
+        #[test]
+        fn test_layer_priority_in_group() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_group(LayerGroup::new("shared").with_max_chunks(2))
+                .add_layer(Grass)
+                .add_layer(Collision)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![
+                    Dependency::new::<Grass>(Vec2::ZERO),
+                    Dependency::new::<Collision>(Vec2::ZERO),
+                ],
+                UsageStrategy::Fast,
+            ));
+            // The collision chunks take the whole limit, whichever layer generates first
+            let generated = |manager: &LayersManager| {
+                (
+                    manager.get_generated_chunks::<Collision>().len(),
+                    manager.get_generated_chunks::<Grass>().len(),
+                )
+            };
+            let mut counts = Vec::new();
+            for _ in 0..3 {
+                manager.regenerate();
+                counts.push(generated(&manager));
+            }
+            assert_eq!(counts, vec![(2, 0), (2, 0), (0, 2)]);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_missing_group() {
+            LayersManagerBuilder::new().add_layer(Terrain).build();
+        }
+    }
+
+    mod test_warm_start {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct Base;
+
+        struct Dependent;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for Base {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        impl Layer for Dependent {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<Base>(Vec2::new(1.0, 1.0))]
+            }
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(2)
+            }
+        }
+
+        #[test]
+        fn test_warm_start() {
+            let mut manager = LayersManagerBuilder::new()
+                .warm_start(4.0)
+                .add_layer(Base)
+                .add_layer(Dependent)
+                .build();
+            // Nothing to warm without clients, the warm start waits for them
+            manager.regenerate();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<Dependent>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<Base>().len(), 49);
+            assert_eq!(manager.get_generated_chunks::<Dependent>().len(), 25);
+            assert_eq!(manager.get_stats().pending, 0);
+
+            // Back to the budgets
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(100.0, 100.0),
+                vec![Dependency::new::<Base>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<Base>().len(), 2);
+        }
+    }
+
+    mod test_dependency_changes {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        static GENERATED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        #[derive(Debug, Clone)]
+        struct Stitched {
+            updates: usize,
+        }
+
+        struct Base;
+
+        struct Normals;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Chunk for Stitched {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for Base {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        impl Layer for Normals {
+            type Chunk = Stitched;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                GENERATED.fetch_add(1, Ordering::SeqCst);
+                Stitched { updates: 0 }
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<Base>(Vec2::new(0.5, 0.5)).with_change_notifications()]
+            }
+
+            fn on_dependency_changed(
+                &self,
+                _: &LayerLookupChunk,
+                chunk: &Self::Chunk,
+                _: &ChunkIdx,
+                dependency: LayerId,
+                _: &ChunkIdx,
+            ) -> Option<Self::Chunk> {
+                assert_eq!(dependency, LayerId::from_type::<Base>());
+                Some(Stitched {
+                    updates: chunk.updates + 1,
+                })
+            }
+        }
+
+        #[test]
+        fn test_on_dependency_changed() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(Base)
+                .add_layer(Normals)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<Normals>(Vec2::new(4.0, 4.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let generated = GENERATED.load(Ordering::SeqCst);
+
+            manager.invalidate_region::<Base>(&Bounds::from_point(Vec2::new(0.5, 0.5)));
+            manager.regenerate();
+            // The readers are updated, not regenerated
+            assert_eq!(GENERATED.load(Ordering::SeqCst), generated);
+            let chunk = manager.get_chunk::<Normals>(Vec2::new(0.5, 0.5)).unwrap();
+            assert_eq!(chunk.updates, 1);
+            assert!(manager
+                .get_generated_chunks::<Normals>()
+                .contains(&ChunkIdx { x: 0, y: 0 }));
+            let far = manager.get_chunk::<Normals>(Vec2::new(-3.5, -3.5)).unwrap();
+            assert_eq!(far.updates, 0);
+        }
+    }
+
+    mod test_chunk_transforms {
+        use bevy::math::{Vec2, Vec3};
+        use crate::bounds::{Bounds, ChunkAnchor, ChunkIdx};
+
+        #[test]
+        fn test_chunk_transform() {
+            let chunk_idx = ChunkIdx { x: 1, y: -2 };
+            let size = Vec2::new(16.0, 8.0);
+            assert_eq!(
+                chunk_idx.bounds(size),
+                Bounds::new(Vec2::new(16.0, -16.0), Vec2::new(32.0, -8.0))
+            );
+            let center = chunk_idx.transform(size, ChunkAnchor::Center, 2.0);
+            assert_eq!(center.translation, Vec3::new(48.0, -24.0, 0.0));
+            let origin = chunk_idx.transform(size, ChunkAnchor::Origin, 2.0);
+            assert_eq!(origin.translation, Vec3::new(32.0, -32.0, 0.0));
+        }
+    }
+
+    mod test_coords {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::coords::{convert_idx, covering_indices};
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_manager::LayerLookupChunk;
+
+        #[derive(Debug)]
+        struct Small;
+
+        #[derive(Debug)]
+        struct Large;
+
+        struct SmallLayer;
+
+        struct LargeLayer;
+
+        impl Chunk for Small {
+            fn get_size() -> Vec2 {
+                Vec2::new(4., 4.)
+            }
+        }
+
+        impl Chunk for Large {
+            fn get_size() -> Vec2 {
+                Vec2::new(16., 8.)
+            }
+        }
+
+        impl Layer for SmallLayer {
+            type Chunk = Small;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                Small
+            }
+        }
+
+        impl Layer for LargeLayer {
+            type Chunk = Large;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                Large
+            }
+        }
+
+        #[test]
+        fn test_convert_idx() {
+            let small = ChunkIdx { x: -1, y: 2 };
+            assert_eq!(
+                convert_idx::<SmallLayer, LargeLayer>(small),
+                ChunkIdx { x: -1, y: 1 }
+            );
+            let large = ChunkIdx { x: 1, y: -1 };
+            assert_eq!(
+                convert_idx::<LargeLayer, SmallLayer>(large),
+                ChunkIdx { x: 6, y: -1 }
+            );
+        }
+
+        #[test]
+        fn test_covering_indices() {
+            let large = ChunkIdx { x: 1, y: -1 };
+            let covering = covering_indices::<LargeLayer, SmallLayer>(large);
+            assert_eq!(covering.len(), 8);
+            assert_eq!(covering.first(), Some(&ChunkIdx { x: 4, y: -2 }));
+            assert_eq!(covering.last(), Some(&ChunkIdx { x: 7, y: -1 }));
+
+            let small = ChunkIdx { x: 3, y: 1 };
+            assert_eq!(
+                covering_indices::<SmallLayer, LargeLayer>(small),
+                vec![ChunkIdx { x: 0, y: 0 }]
+            );
+        }
+    }
+
+    mod test_tags {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct Terrain;
+
+        struct DebugView;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for Terrain {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        impl Layer for DebugView {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<Terrain>(Vec2::new(1.0, 1.0))]
+            }
+        }
+
+        #[test]
+        fn test_tagged_layers() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(Terrain)
+                .add_layer_tagged(DebugView, "debug")
+                .disable_tag("debug")
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<DebugView>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(!manager.is_layer_enabled(LayerId::from_type::<DebugView>()));
+            assert!(manager.get_generated_chunks::<DebugView>().is_empty());
+            // Nothing requests the terrain while the debug view is disabled
+            assert!(manager.get_generated_chunks::<Terrain>().is_empty());
+
+            manager.set_tag_enabled("debug", true);
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<DebugView>().len(), 25);
+            assert_eq!(manager.get_generated_chunks::<Terrain>().len(), 49);
+
+            manager.set_tag_enabled("debug", false);
+            manager.regenerate();
+            assert!(manager.get_generated_chunks::<DebugView>().is_empty());
+        }
+    }
+
+    mod test_camera_loader {
+        use bevy::ecs::entity::Entity;
+        use bevy::math::{Rect, Vec2};
+        use crate::bounds::{Bounds, ChunkIdx};
+        use bevy::app::App;
+        use bevy::render::camera::{OrthographicProjection, Projection};
+        use bevy::render::view::{RenderLayers, Visibility};
+        use bevy::transform::components::GlobalTransform;
+        use crate::camera_loader::{
+            CameraChunkLoader, CameraChunkLoaderPlugin, ChunkViews, SyncChunkVisibility,
+        };
+        use crate::chunk_entities::ChunkEntity;
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_camera_client() {
+            let loader = CameraChunkLoader::new(10.0)
+                .with_layer::<TestLayerA>()
+                .with_margin(Vec2::new(1.0, 1.0));
+            let visible = Rect::new(-40.0, -20.0, 60.0, 20.0);
+            let client = loader.client_for(visible, 1.0);
+            assert_eq!(client.get_center(), Vec2::new(1.0, 0.0));
+            let padding = client.get_dependencies()[0].get_padding();
+            assert_eq!((padding.left, padding.top), (6.0, 3.0));
+
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let camera = Entity::from_raw(1);
+            manager.set_layer_client_of(camera, client);
+            manager.set_layer_client_of(camera, loader.client_for(visible, 1.0));
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<TestLayerA>().len(), 13 * 7);
+
+            manager.remove_layer_clients_of(camera);
+            manager.regenerate();
+            let bounds = Bounds::new(Vec2::new(-4.0, -2.0), Vec2::new(6.0, 2.0));
+            assert!(manager.get_clients_in(&bounds).is_empty());
+        }
+
+        struct TestLayerB;
+
+        impl Layer for TestLayerB {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_camera_lod() {
+            let fine = LayerId::from_type::<TestLayerA>();
+            let coarse = LayerId::from_type::<TestLayerB>();
+            let mut loader = CameraChunkLoader::new(1.0)
+                .with_lod_layer::<TestLayerA>(0.0..2.0)
+                .with_lod_layer::<TestLayerB>(2.0..f32::INFINITY)
+                .with_lod_fade(1.0);
+            let visible = Rect::new(-1.0, -1.0, 1.0, 1.0);
+            let requested = |loader: &CameraChunkLoader, scale: f32| -> Vec<LayerId> {
+                loader
+                    .client_for(visible, scale)
+                    .get_dependencies()
+                    .iter()
+                    .map(|dependency| dependency.get_layer_id())
+                    .collect()
+            };
+            assert_eq!(requested(&loader, 1.0), vec![fine]);
+            assert_eq!(requested(&loader, 2.0), vec![fine, coarse]);
+            assert_eq!(requested(&loader, 3.0), vec![coarse]);
+
+            assert_eq!(loader.update_lod_weights(1.0), vec![(fine, 1.0)]);
+            let changed = loader.update_lod_weights(2.0);
+            assert_eq!(changed, vec![(fine, 0.5), (coarse, 0.5)]);
+            assert_eq!(loader.get_lod_weight(coarse), 0.5);
+            assert!(loader.update_lod_weights(2.0).is_empty());
+        }
+
+        #[test]
+        fn test_split_screen_views() {
+            let manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let mut app = App::new();
+            app.add_plugins(CameraChunkLoaderPlugin).insert_resource(manager);
+            let projection = Projection::Orthographic(OrthographicProjection {
+                area: Rect::new(-2.0, -2.0, 2.0, 2.0),
+                ..OrthographicProjection::default_2d()
+            });
+            app.world_mut().spawn((
+                CameraChunkLoader::new(1.0).with_layer::<TestLayerA>(),
+                projection.clone(),
+                GlobalTransform::default(),
+            ));
+            app.world_mut().spawn((
+                CameraChunkLoader::new(1.0)
+                    .with_layer::<TestLayerA>()
+                    .with_view(1),
+                projection,
+                GlobalTransform::from_xyz(10.0, 0.0, 0.0),
+            ));
+            let near = app
+                .world_mut()
+                .spawn(ChunkEntity::new::<TestLayerA>(ChunkIdx { x: 0, y: 0 }))
+                .id();
+            let far = app
+                .world_mut()
+                .spawn(ChunkEntity::new::<TestLayerA>(ChunkIdx { x: 10, y: 0 }))
+                .id();
+            app.update();
+            let views = |entity| *app.world().get::<ChunkViews>(entity).unwrap();
+            assert_eq!(views(near), ChunkViews(0b01));
+            assert_eq!(views(far), ChunkViews(0b10));
+            assert!(views(far).contains(1));
+        }
+
+        #[test]
+        fn test_sync_chunk_visibility() {
+            let manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let mut app = App::new();
+            app.add_plugins(CameraChunkLoaderPlugin).insert_resource(manager);
+            app.world_mut().spawn((
+                CameraChunkLoader::new(1.0)
+                    .with_layer::<TestLayerA>()
+                    .with_render_layer(2),
+                Projection::Orthographic(OrthographicProjection {
+                    area: Rect::new(-2.0, -2.0, 2.0, 2.0),
+                    ..OrthographicProjection::default_2d()
+                }),
+                GlobalTransform::default(),
+            ));
+            let near = app
+                .world_mut()
+                .spawn((
+                    ChunkEntity::new::<TestLayerA>(ChunkIdx { x: 0, y: 0 }),
+                    SyncChunkVisibility,
+                ))
+                .id();
+            let far = app
+                .world_mut()
+                .spawn((
+                    ChunkEntity::new::<TestLayerA>(ChunkIdx { x: 10, y: 0 }),
+                    SyncChunkVisibility,
+                ))
+                .id();
+            app.update();
+            let world = app.world();
+            assert_eq!(world.get::<Visibility>(near), Some(&Visibility::Inherited));
+            assert_eq!(world.get::<RenderLayers>(near), Some(&RenderLayers::layer(2)));
+            assert_eq!(world.get::<Visibility>(far), Some(&Visibility::Hidden));
+        }
+    }
+
+    mod test_fixed_chunks {
+        use bevy::app::{App, FixedFirst};
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::snapshot::{FixedChunks, FixedChunksPlugin};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        fn client_at(x: f32) -> LayerClient {
+            LayerClient::new(
+                Vec2::new(x, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            )
+        }
+
+        #[test]
+        fn test_fixed_snapshot() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(client_at(0.0));
+            manager.regenerate();
+            let mut app = App::new();
+            app.add_plugins(FixedChunksPlugin).insert_resource(manager);
+            app.world_mut().run_schedule(FixedFirst);
+            let fixed = app.world().resource::<FixedChunks>();
+            assert_eq!(fixed.get_version(), 1);
+            assert!(fixed.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+
+            // Generation goes on, the fixed steps keep the frozen chunks until the next step
+            let mut manager = app.world_mut().resource_mut::<LayersManager>();
+            manager.add_layer_client(client_at(10.0));
+            manager.regenerate();
+            let fixed = app.world().resource::<FixedChunks>();
+            assert!(fixed.get_chunk::<TestLayerA>(Vec2::new(10.5, 0.5)).is_none());
+
+            app.world_mut().run_schedule(FixedFirst);
+            let fixed = app.world().resource::<FixedChunks>();
+            assert_eq!(fixed.get_version(), 2);
+            assert!(fixed.get_chunk::<TestLayerA>(Vec2::new(10.5, 0.5)).is_some());
+        }
+    }
+
+    mod test_lockstep {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(3)
+            }
+        }
+
+        fn peer() -> LayersManager {
+            let mut manager = LayersManagerBuilder::new()
+                .lockstep(true)
+                .add_layer(TestLayerA)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager
+        }
+
+        #[test]
+        fn test_lockstep_peers_match() {
+            let mut peer_a = peer();
+            let mut peer_b = peer();
+            let mut expected: Vec<ChunkIdx> =
+                Bounds::new(Vec2::new(-2.0, -2.0), Vec2::new(2.0, 2.0))
+                    .chunks(Vec2::new(1.0, 1.0))
+                    .collect();
+            expected.sort();
+            for step in expected.chunks(3) {
+                peer_a.step();
+                peer_b.step();
+                assert_eq!(peer_a.get_generated_chunks::<TestLayerA>(), &step.to_vec());
+                assert_eq!(
+                    peer_a.get_generated_chunks::<TestLayerA>(),
+                    peer_b.get_generated_chunks::<TestLayerA>()
+                );
+            }
+        }
+
+        #[test]
+        fn test_checksums_match() {
+            let mut peer_a = peer();
+            let mut peer_b = peer();
+            peer_a.step();
+            peer_b.step();
+            let checksum = peer_a.get_checksum::<TestLayerA>().unwrap();
+            assert_eq!(Some(checksum), peer_b.get_checksum::<TestLayerA>());
+            peer_a.step();
+            let next = peer_a.get_checksum::<TestLayerA>().unwrap();
+            assert_ne!(next.rolling, checksum.rolling);
+            let chunk_idx = peer_a.get_generated_chunks::<TestLayerA>()[0];
+            assert_eq!(
+                peer_a.dump_chunk::<TestLayerA>(chunk_idx).as_deref(),
+                Some("ChunkA")
+            );
+
+            let manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            assert_eq!(manager.get_checksum::<TestLayerA>(), None);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_step_needs_lockstep() {
+            LayersManagerBuilder::new().add_layer(TestLayerA).build().step();
+        }
+    }
+
+    mod test_update_clients {
+        use bevy::ecs::entity::Entity;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::{ClientUpdate, LayerClient};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_update_clients() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let players: Vec<Entity> = (0..100).map(Entity::from_raw).collect();
+            for player in players.iter() {
+                manager.add_layer_client(
+                    LayerClient::new(
+                        Vec2::ZERO,
+                        vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                        UsageStrategy::Fast,
+                    )
+                    .with_owner(*player),
+                );
+            }
+            let unknown = manager.update_clients(
+                players
+                    .iter()
+                    .enumerate()
+                    .map(|(i, player)| ClientUpdate::new(*player, Vec2::new(i as f32 * 10.0, 0.5)))
+                    .chain([ClientUpdate::new(Entity::from_raw(500), Vec2::ZERO)]),
+            );
+            assert_eq!(unknown, vec![Entity::from_raw(500)]);
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(990.5, 0.5)).is_some());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(5.5, 0.5)).is_none());
+        }
+    }
+
+    mod test_request_limit {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_request_limit() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(
+                LayerClient::new(
+                    Vec2::ZERO,
+                    vec![Dependency::new::<TestLayerA>(Vec2::new(2., 2.))],
+                    UsageStrategy::Fast,
+                )
+                .with_request_limit(5),
+            );
+            let area = Bounds::from_point(Vec2::ZERO).add_padding(Vec2::new(2., 2.));
+            manager.regenerate();
+            assert_eq!(manager.get_chunks_in::<TestLayerA>(area).len(), 5);
+            // The closest chunk is admitted first
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).is_some());
+            manager.regenerate();
+            assert_eq!(manager.get_chunks_in::<TestLayerA>(area).len(), 10);
+            for _ in 0..3 {
+                manager.regenerate();
+            }
+            assert_eq!(manager.get_chunks_in::<TestLayerA>(area).len(), 25);
+        }
+    }
+
+    mod test_deadlines {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::group::LayerGroup;
+        use crate::layer::{Chunk, Dependency, GenerateOutcome, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::{Deadline, UsageStrategy};
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn group(&self) -> Option<&'static str> {
+                Some("terrain")
+            }
+        }
+
+        /// Never ready, e.g. waiting on an asset
+        struct TestLayerB;
+
+        impl Layer for TestLayerB {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn try_generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> GenerateOutcome<ChunkA> {
+                GenerateOutcome::Defer
+            }
+        }
+
+        #[test]
+        fn test_this_frame_preempts() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_group(LayerGroup::new("terrain").with_max_chunks(1))
+                .add_layer(TestLayerA)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::ZERO,
+                vec![Dependency::new::<TestLayerA>(Vec2::new(2., 2.))],
+                UsageStrategy::Fast,
+            ));
+            manager.add_layer_client(
+                LayerClient::new(
+                    Vec2::new(100., 0.),
+                    vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                    UsageStrategy::Fast,
+                )
+                .with_deadline(Deadline::ThisFrame),
+            );
+            manager.regenerate();
+            // The due chunk takes the whole budget of the group
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::new(100.5, 0.5)).is_some());
+            let area = Bounds::from_point(Vec2::ZERO).add_padding(Vec2::new(2., 2.));
+            assert!(manager.get_chunks_in::<TestLayerA>(area).is_empty());
+            assert!(manager.drain_missed_deadlines().is_empty());
+            manager.regenerate();
+            assert_eq!(manager.get_chunks_in::<TestLayerA>(area).len(), 1);
+        }
+
+        #[test]
+        fn test_missed_deadline() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerB).build();
+            manager.add_layer_client(
+                LayerClient::new(
+                    Vec2::ZERO,
+                    vec![Dependency::new::<TestLayerB>(Vec2::ZERO)],
+                    UsageStrategy::Fast,
+                )
+                .with_deadline(Deadline::ThisFrame),
+            );
+            manager.regenerate();
+            let missed = manager.drain_missed_deadlines();
+            assert_eq!(missed.len(), 1);
+            assert_eq!(missed[0].chunk_idx, ChunkIdx { x: 0, y: 0 });
+            assert_eq!(manager.get_stats().missed_deadlines, 1);
+            // Reported once
+            manager.regenerate();
+            assert!(manager.drain_missed_deadlines().is_empty());
+        }
+    }
+
+    mod test_cross_fade {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct ChunkA(u32);
+
+        /// Each generation gives new data
+        #[derive(Default)]
+        struct TestLayerA {
+            generations: AtomicU32,
+        }
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA(self.generations.fetch_add(1, Ordering::Relaxed))
+            }
+        }
+
+        #[test]
+        fn test_cross_fade() {
+            let mut manager = LayersManagerBuilder::new()
+                .cross_fade(3)
+                .add_layer(TestLayerA::default())
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::ZERO,
+                vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(manager.get_cross_fade::<TestLayerA>(Vec2::ZERO).is_none());
+
+            manager.invalidate_region::<TestLayerA>(&Bounds::from_point(Vec2::ZERO));
+            let fade = manager.get_cross_fade::<TestLayerA>(Vec2::ZERO).unwrap();
+            assert_eq!(fade.previous, ChunkA(0));
+            assert_eq!(fade.current, None);
+            assert_eq!(fade.weight, 0.0);
+
+            manager.regenerate();
+            let fade = manager.get_cross_fade::<TestLayerA>(Vec2::ZERO).unwrap();
+            assert_eq!(fade.previous, ChunkA(0));
+            assert_eq!(fade.current, Some(ChunkA(1)));
+            assert!((fade.weight - 1.0 / 3.0).abs() < 1e-6);
+
+            manager.regenerate();
+            let fade = manager.get_cross_fade::<TestLayerA>(Vec2::ZERO).unwrap();
+            assert!((fade.weight - 2.0 / 3.0).abs() < 1e-6);
+
+            manager.regenerate();
+            assert!(manager.get_cross_fade::<TestLayerA>(Vec2::ZERO).is_none());
+            assert_eq!(manager.get_chunk::<TestLayerA>(Vec2::ZERO), Some(ChunkA(1)));
+        }
+    }
+
+    mod test_region_facts {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct ChunkA {
+            war_torn: bool,
+        }
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let center = chunk_idx.center(ChunkA::get_size());
+                ChunkA {
+                    war_torn: lookup.get_fact::<bool>("war", center).copied().unwrap_or(false),
+                }
+            }
+
+            fn region_facts(&self) -> Vec<&'static str> {
+                vec!["war"]
+            }
+        }
+
+        #[test]
+        fn test_region_facts() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(4., 4.))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let at = |x: f32| Vec2::new(x, 0.5);
+            assert!(!manager.get_chunk::<TestLayerA>(at(0.5)).unwrap().war_torn);
+
+            let front = Bounds::new(Vec2::new(0., 0.), Vec2::new(1., 1.));
+            manager.set_region_fact("war", front, true);
+            // Only the chunks around the region are regenerated
+            assert!(manager.get_chunk::<TestLayerA>(at(0.5)).is_none());
+            assert!(manager.get_chunk::<TestLayerA>(at(3.5)).is_some());
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(at(0.5)).unwrap().war_torn);
+            assert!(!manager.get_chunk::<TestLayerA>(at(3.5)).unwrap().war_torn);
+
+            manager.remove_region_facts("war", &front);
+            manager.regenerate();
+            assert!(!manager.get_chunk::<TestLayerA>(at(0.5)).unwrap().war_torn);
+        }
+    }
+
+    mod test_plugin {
+        use bevy::app::App;
+        use bevy::diagnostic::{DiagnosticPath, DiagnosticsStore};
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::diagnostics::ChunksDiagnosticsPlugin;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager};
+        use crate::plugin::GenerativeChunksPlugin;
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        #[test]
+        fn test_plugin_regenerates() {
+            let mut app = App::new();
+            app.add_plugins(
+                GenerativeChunksPlugin::new()
+                    .with_layer(TestLayerA)
+                    .with_manager(|builder| builder.seed(42)),
+            );
+            app.world_mut()
+                .resource_mut::<LayersManager>()
+                .add_layer_client(LayerClient::new(
+                    Vec2::ZERO,
+                    vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                    UsageStrategy::Fast,
+                ));
+            app.update();
+            let manager = app.world().resource::<LayersManager>();
+            assert_eq!(manager.get_seed(), 42);
+            assert_eq!(manager.get_frame(), 1);
+            assert!(manager.get_chunk::<TestLayerA>(Vec2::ZERO).is_some());
+        }
+
+        #[test]
+        fn test_diagnostics_after_regenerate() {
+            let mut app = App::new();
+            app.add_plugins((
+                GenerativeChunksPlugin::new().with_layer(TestLayerA),
+                ChunksDiagnosticsPlugin,
+            ));
+            app.world_mut()
+                .resource_mut::<LayersManager>()
+                .add_layer_client(LayerClient::new(
+                    Vec2::new(0.5, 0.5),
+                    vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                    UsageStrategy::Fast,
+                ));
+            let value = |app: &App, path: &DiagnosticPath| {
+                app.world()
+                    .resource::<DiagnosticsStore>()
+                    .get(path)
+                    .and_then(|diagnostic| diagnostic.value())
+                    .unwrap()
+            };
+
+            app.update();
+            let stats = app.world().resource::<LayersManager>().get_stats();
+            assert_eq!(value(&app, &ChunksDiagnosticsPlugin::GENERATED), 4.0);
+            assert_eq!(value(&app, &ChunksDiagnosticsPlugin::PENDING), 0.0);
+            assert_eq!(
+                value(&app, &ChunksDiagnosticsPlugin::STORAGE_BYTES),
+                stats.storage_bytes as f64
+            );
+            assert_eq!(
+                value(&app, &ChunksDiagnosticsPlugin::REGENERATE_DURATION),
+                stats.duration.as_secs_f64() * 1000.0
+            );
+
+            // Nothing left to generate
+            app.update();
+            assert_eq!(value(&app, &ChunksDiagnosticsPlugin::GENERATED), 0.0);
+            assert_eq!(
+                value(&app, &ChunksDiagnosticsPlugin::STORAGE_BYTES),
+                stats.storage_bytes as f64
+            );
+        }
+    }
+
+    mod test_async_generation {
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, ChunkStatus, Dependency, GenerationLane, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        struct TestLayerB;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+
+            fn lane(&self) -> GenerationLane {
+                GenerationLane::Async
+            }
+        }
+
+        impl Layer for TestLayerB {
+            type Chunk = ChunkA;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let pos = chunk_idx.center(ChunkA::get_size());
+                lookup.get_chunk::<TestLayerA>(LayerId::from_type::<TestLayerA>(), pos).unwrap()
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<TestLayerA>(Vec2::ZERO)]
+            }
+        }
+
+        #[test]
+        fn test_async_generation() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TestLayerA)
+                .add_layer(TestLayerB)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<TestLayerB>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            // The regenerate doesn't wait for the async chunks, nor do their dependents
+            let at = Vec2::new(0.5, 0.5);
+            assert_eq!(manager.get_chunk_status::<TestLayerA>(at), Some(ChunkStatus::Pending));
+            assert_eq!(manager.get_chunk_status::<TestLayerB>(at), Some(ChunkStatus::Queued));
+            assert!(manager.get_chunk::<TestLayerA>(at).is_none());
+
+            for _ in 0..1000 {
+                manager.regenerate();
+                if manager.get_chunk::<TestLayerB>(at).is_some() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert_eq!(manager.get_chunk_status::<TestLayerA>(at), Some(ChunkStatus::Generated));
+            assert_eq!(manager.get_chunk_status::<TestLayerB>(at), Some(ChunkStatus::Generated));
+        }
+    }
+
+    mod test_global_layers {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx, GLOBAL_CHUNK_SIZE};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Factions(Vec<&'static str>);
+
+        impl Chunk for Factions {
+            fn get_size() -> Vec2 {
+                GLOBAL_CHUNK_SIZE
+            }
+        }
+
+        struct FactionsLayer;
+
+        impl Layer for FactionsLayer {
+            type Chunk = Factions;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                assert_eq!(*chunk_idx, ChunkIdx::GLOBAL);
+                Factions(vec!["north", "south"])
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct Territory(&'static str);
+
+        impl Chunk for Territory {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct TerritoryLayer;
+
+        impl Layer for TerritoryLayer {
+            type Chunk = Territory;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let factions = lookup.get_global::<FactionsLayer>().unwrap();
+                Territory(factions.0[(chunk_idx.y > 0) as usize])
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<FactionsLayer>(Vec2::ZERO)]
+            }
+        }
+
+        #[test]
+        fn test_global_layer() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(FactionsLayer)
+                .add_layer(TerritoryLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(-50., 50.),
+                vec![Dependency::new::<TerritoryLayer>(Vec2::new(2., 2.))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(
+                manager.get_global::<FactionsLayer>(),
+                Some(Factions(vec!["north", "south"]))
+            );
+            // Every point maps to the single global chunk
+            assert!(manager.get_chunk::<FactionsLayer>(Vec2::new(1e6, -1e6)).is_some());
+            let territory = manager.get_chunk::<TerritoryLayer>(Vec2::new(-50., 50.));
+            assert_eq!(territory.unwrap().0, "south");
+
+            // Dependents of the global chunk are regenerated with it
+            manager.invalidate_region::<FactionsLayer>(&Bounds::from_point(Vec2::ZERO));
+            assert!(manager.get_chunk::<TerritoryLayer>(Vec2::new(-50., 50.)).is_none());
+        }
+
+        #[derive(Debug, Clone)]
+        struct Summary;
+
+        impl Chunk for Summary {
+            fn get_size() -> Vec2 {
+                GLOBAL_CHUNK_SIZE
+            }
+        }
+
+        struct SummaryLayer;
+
+        impl Layer for SummaryLayer {
+            type Chunk = Summary;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                Summary
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<TerritoryLayer>(Vec2::ZERO)]
+            }
+        }
+
+        #[test]
+        #[should_panic(expected = "can only depend on global layers")]
+        fn test_global_layer_depending_on_local() {
+            LayersManagerBuilder::new()
+                .add_layer(FactionsLayer)
+                .add_layer(TerritoryLayer)
+                .add_layer(SummaryLayer)
+                .build();
+        }
+    }
+
+    mod test_biome_dispatch {
+        use bevy::math::Vec2;
+        use crate::biome::{biome_seed, BiomeDispatch, BiomeGenerator};
+        use crate::bounds::{ChunkIdx, Padding};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct BiomeChunk {
+            name: &'static str,
+        }
+
+        #[derive(Debug, Clone)]
+        struct HeightChunk;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct GroundChunk {
+            biome: &'static str,
+            seed: u64,
+        }
+
+        impl Chunk for BiomeChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Chunk for HeightChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Chunk for GroundChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct BiomeLayer;
+        struct HeightLayer;
+
+        impl Layer for BiomeLayer {
+            type Chunk = BiomeChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let name = if chunk_idx.x < 0 { "desert" } else { "forest" };
+                BiomeChunk { name }
+            }
+        }
+
+        impl Layer for HeightLayer {
+            type Chunk = HeightChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                HeightChunk
+            }
+        }
+
+        struct Desert;
+
+        impl BiomeGenerator<GroundChunk> for Desert {
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx, seed: u64) -> GroundChunk {
+                GroundChunk { biome: "desert", seed }
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<HeightLayer>(Padding::new(2., 0., 1., 1.))]
+            }
+        }
+
+        struct Dunes;
+
+        impl BiomeGenerator<GroundChunk> for Dunes {
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx, seed: u64) -> GroundChunk {
+                GroundChunk { biome: "dunes", seed }
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<HeightLayer>(Padding::new(0., 3., 1., 0.))]
+            }
+        }
+
+        fn forest(_: &LayerLookupChunk, _: &ChunkIdx, seed: u64) -> GroundChunk {
+            GroundChunk { biome: "forest", seed }
+        }
+
+        fn dispatch() -> BiomeDispatch<BiomeLayer, GroundChunk> {
+            BiomeDispatch::new(|biome: &BiomeChunk| biome.name)
+                .with_biome("desert", Desert)
+                .with_biome("dunes", Dunes)
+                .with_biome("forest", forest)
+        }
+
+        #[test]
+        fn test_paddings_are_merged() {
+            let dependencies = dispatch().get_dependencies();
+            assert_eq!(dependencies.len(), 2);
+            assert_eq!(dependencies[0].get_layer_id(), LayerId::from_type::<BiomeLayer>());
+            assert_eq!(dependencies[1].get_layer_id(), LayerId::from_type::<HeightLayer>());
+            assert_eq!(dependencies[1].get_padding(), Padding::new(2., 3., 1., 1.));
+        }
+
+        #[test]
+        fn test_dispatch_by_biome() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BiomeLayer)
+                .add_layer(HeightLayer)
+                .add_layer(dispatch())
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0., 0.5),
+                vec![Dependency::new::<BiomeDispatch<BiomeLayer, GroundChunk>>(Vec2::new(2., 0.))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let seed = manager.get_seed();
+            let desert = manager
+                .get_chunk::<BiomeDispatch<BiomeLayer, GroundChunk>>(Vec2::new(-1.5, 0.5))
+                .unwrap();
+            let expected = biome_seed(seed, "desert", &ChunkIdx { x: -2, y: 0 });
+            assert_eq!(desert, GroundChunk { biome: "desert", seed: expected });
+            let forest = manager
+                .get_chunk::<BiomeDispatch<BiomeLayer, GroundChunk>>(Vec2::new(1.5, 0.5))
+                .unwrap();
+            assert_eq!(forest.biome, "forest");
+            assert_ne!(forest.seed, biome_seed(seed, "forest", &ChunkIdx { x: 2, y: 0 }));
+            assert_eq!(forest.seed, biome_seed(seed, "forest", &ChunkIdx { x: 1, y: 0 }));
+        }
+    }
+
+    mod test_time_budget {
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct SlowChunk;
+
+        struct SlowLayer;
+
+        impl Chunk for SlowChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for SlowLayer {
+            type Chunk = SlowChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                std::thread::sleep(Duration::from_millis(2));
+                SlowChunk
+            }
+        }
+
+        #[test]
+        fn test_regenerate_with_budget() {
+            let mut manager = LayersManagerBuilder::new().add_layer(SlowLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<SlowLayer>(Vec2::new(2., 2.))],
+                UsageStrategy::Fast,
+            ));
+            // No chunk fits, only the closest one is generated
+            manager.regenerate_with_budget(Duration::from_micros(100));
+            assert_eq!(manager.get_stats().generated, 1);
+            assert_eq!(manager.get_stats().pending, 35);
+            assert!(manager.get_chunk::<SlowLayer>(Vec2::new(0.5, 0.5)).is_some());
+            assert!(manager.get_chunk::<SlowLayer>(Vec2::new(2.5, 2.5)).is_none());
+
+            // The rest is carried over to the next calls
+            manager.regenerate_with_budget(Duration::from_micros(100));
+            assert_eq!(manager.get_stats().generated, 1);
+            assert_eq!(manager.get_stats().pending, 34);
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 34);
+            assert_eq!(manager.get_stats().pending, 0);
+        }
+
+        /// Only the chunk at the origin is slower than the budget
+        struct BudgetLayer;
+
+        impl Layer for BudgetLayer {
+            type Chunk = SlowChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                if *chunk_idx == (ChunkIdx { x: 0, y: 0 }) {
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                SlowChunk
+            }
+
+            fn chunk_budget(&self) -> Option<Duration> {
+                Some(Duration::from_millis(10))
+            }
+        }
+
+        #[test]
+        fn test_chunk_budget_exceeded() {
+            let mut manager = LayersManagerBuilder::new().add_layer(BudgetLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<BudgetLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 4);
+            let violations = manager.drain_budget_violations();
+            assert_eq!(violations.len(), 1);
+            assert_eq!(violations[0].layer, LayerId::from_type::<BudgetLayer>());
+            assert_eq!(violations[0].budget, Duration::from_millis(10));
+            assert_eq!(violations[0].chunks.len(), 1);
+            let (chunk_idx, elapsed) = violations[0].chunks[0];
+            assert_eq!(chunk_idx, ChunkIdx { x: 0, y: 0 });
+            assert!(elapsed >= Duration::from_millis(20));
+
+            // Drained, and the generated chunks are not reported again
+            assert!(manager.drain_budget_violations().is_empty());
+            manager.regenerate();
+            assert!(manager.drain_budget_violations().is_empty());
+        }
+    }
+
+    mod test_coordinate_math {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx, CoordinateMath};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        const FIXED: CoordinateMath = CoordinateMath::FixedPoint { fraction_bits: 4 };
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct IdxChunk(ChunkIdx);
+
+        impl Chunk for IdxChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct IdxLayer;
+
+        impl Layer for IdxLayer {
+            type Chunk = IdxChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                IdxChunk(*chunk_idx)
+            }
+        }
+
+        #[test]
+        fn test_fixed_point_matches_float() {
+            let size = Vec2::new(1., 2.);
+            for pos in [Vec2::new(-0.5, 1.5), Vec2::new(3.25, -4.0), Vec2::new(0., 0.)] {
+                assert_eq!(
+                    FIXED.chunk_idx(pos, size),
+                    CoordinateMath::Float.chunk_idx(pos, size)
+                );
+            }
+            let bounds = Bounds::new(Vec2::new(-0.5, -0.5), Vec2::new(2.0, 2.5));
+            assert_eq!(
+                FIXED.chunk_range(&bounds, size),
+                (ChunkIdx { x: -1, y: -1 }, ChunkIdx { x: 2, y: 2 })
+            );
+            assert_eq!(FIXED.chunk_range(&bounds, size), bounds.chunk_range(size));
+        }
+
+        #[test]
+        fn test_fixed_point_snaps() {
+            // Closer to the edge than half a step, the point snaps onto it
+            let pos = Vec2::new(0.99, -0.01);
+            let size = Vec2::new(1., 1.);
+            assert_eq!(CoordinateMath::Float.chunk_idx(pos, size), ChunkIdx { x: 0, y: -1 });
+            assert_eq!(FIXED.chunk_idx(pos, size), ChunkIdx { x: 1, y: 0 });
+        }
+
+        #[test]
+        fn test_manager_uses_fixed_point() {
+            let mut manager = LayersManagerBuilder::new()
+                .coordinate_math(FIXED)
+                .add_layer(IdxLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<IdxLayer>(Vec2::new(1., 1.))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let chunk = manager.get_chunk::<IdxLayer>(Vec2::new(0.99, 0.5)).unwrap();
+            assert_eq!(chunk, IdxChunk(ChunkIdx { x: 1, y: 0 }));
+        }
+
+        #[test]
+        #[should_panic(expected = "At most 32 fraction bits")]
+        fn test_too_many_fraction_bits() {
+            LayersManagerBuilder::new()
+                .coordinate_math(CoordinateMath::FixedPoint { fraction_bits: 40 });
+        }
+    }
+
+    mod test_client_handles {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        fn client_at(x: f32) -> LayerClient {
+            LayerClient::new(
+                Vec2::new(x, 0.5),
+                vec![Dependency::new::<TestLayerA>(Vec2::new(0.25, 0.25))],
+                UsageStrategy::Fast,
+            )
+        }
+
+        #[test]
+        fn test_client_handles() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TestLayerA).build();
+            let first = manager.add_layer_client(client_at(0.5));
+            let second = manager.add_layer_client(client_at(10.5));
+            assert_ne!(first, second);
+            assert_eq!(manager.get_client(second).unwrap().get_id(), Some(second));
+            manager.regenerate();
+            let at = |x: f32| Vec2::new(x, 0.5);
+            assert!(manager.get_chunk::<TestLayerA>(at(0.5)).is_some());
+
+            assert!(manager.update_client_center(first, at(5.5)));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(at(0.5)).is_none());
+            assert!(manager.get_chunk::<TestLayerA>(at(5.5)).is_some());
+
+            assert!(manager.set_client_active(second, false));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(at(10.5)).is_none());
+            assert!(manager.set_client_active(second, true));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(at(10.5)).is_some());
+
+            // The other handles stay valid after a removal
+            assert!(manager.remove_client(first).is_some());
+            assert!(manager.remove_client(first).is_none());
+            assert!(!manager.update_client_center(first, at(0.5)));
+            assert!(manager.update_client_center(second, at(20.5)));
+            manager.regenerate();
+            assert!(manager.get_chunk::<TestLayerA>(at(5.5)).is_none());
+            assert!(manager.get_chunk::<TestLayerA>(at(20.5)).is_some());
+        }
+    }
+
+    mod test_tolerant_chunk_idx {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+
+        const SIZE: Vec2 = Vec2::new(1., 1.);
+
+        #[test]
+        fn test_from_point_tolerant() {
+            let idx = |x: f32, y: f32| ChunkIdx::from_point_tolerant(Vec2::new(x, y), SIZE, 0.01);
+            // Both sides of an edge go to the chunk above it
+            assert_eq!(idx(0.999, 0.5), ChunkIdx { x: 1, y: 0 });
+            assert_eq!(idx(1.0, 0.5), ChunkIdx { x: 1, y: 0 });
+            assert_eq!(idx(1.001, 0.5), ChunkIdx { x: 1, y: 0 });
+            assert_eq!(idx(-0.001, -1.999), ChunkIdx { x: 0, y: -2 });
+            // Away from the edges it is the plain conversion
+            assert_eq!(idx(0.95, -0.5), ChunkIdx { x: 0, y: -1 });
+        }
+
+        #[test]
+        fn test_near_point() {
+            let near = |x: f32, y: f32| ChunkIdx::near_point(Vec2::new(x, y), SIZE, 0.01);
+            assert_eq!(near(0.5, 0.5), vec![ChunkIdx { x: 0, y: 0 }]);
+            assert_eq!(near(0.995, 0.5), vec![ChunkIdx { x: 0, y: 0 }, ChunkIdx { x: 1, y: 0 }]);
+            assert_eq!(
+                near(0.0, 0.0),
+                vec![
+                    ChunkIdx { x: -1, y: -1 },
+                    ChunkIdx { x: -1, y: 0 },
+                    ChunkIdx { x: 0, y: -1 },
+                    ChunkIdx { x: 0, y: 0 },
+                ]
+            );
+        }
+    }
+
+    mod test_chunk_loader {
+        use bevy::app::App;
+        use bevy::math::{Vec2, Vec3};
+        use bevy::transform::components::GlobalTransform;
+        use crate::bounds::ChunkIdx;
+        use crate::chunk_loader::ChunkLoader;
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManager};
+        use crate::plugin::GenerativeChunksPlugin;
+        use crate::world_scale::WorldScale;
+
+        #[derive(Debug, Clone)]
+        struct ChunkA;
+
+        struct TestLayerA;
+
+        impl Chunk for ChunkA {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = ChunkA;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                ChunkA
+            }
+        }
+
+        fn has_chunk(app: &App, x: f32) -> bool {
+            let manager = app.world().resource::<LayersManager>();
+            manager.get_chunk::<TestLayerA>(Vec2::new(x, 0.5)).is_some()
+        }
+
+        #[test]
+        fn test_chunk_loader_follows_transform() {
+            let mut app = App::new();
+            app.add_plugins(GenerativeChunksPlugin::new().with_layer(TestLayerA));
+            let player = app
+                .world_mut()
+                .spawn((
+                    ChunkLoader::new(0.25).with_layer::<TestLayerA>().with_render_scale(16.0),
+                    GlobalTransform::from_translation(Vec3::new(8.0, 8.0, 0.0)),
+                ))
+                .id();
+            app.update();
+            assert!(has_chunk(&app, 0.5));
+
+            *app.world_mut().get_mut::<GlobalTransform>(player).unwrap() =
+                GlobalTransform::from_translation(Vec3::new(88.0, 8.0, 0.0));
+            app.update();
+            assert!(!has_chunk(&app, 0.5));
+            assert!(has_chunk(&app, 5.5));
+
+            app.world_mut().entity_mut(player).remove::<ChunkLoader>();
+            app.update();
+            assert!(!has_chunk(&app, 5.5));
+        }
+
+        #[test]
+        fn test_chunk_loader_uses_world_scale() {
+            let mut app = App::new();
+            app.add_plugins(
+                GenerativeChunksPlugin::new()
+                    .with_layer(TestLayerA)
+                    .with_world_scale(WorldScale::new(16.0)),
+            );
+            app.world_mut().spawn((
+                ChunkLoader::new(0.25).with_layer::<TestLayerA>(),
+                GlobalTransform::from_translation(Vec3::new(88.0, 8.0, 0.0)),
+            ));
+            // Its own render scale wins over the world scale
+            app.world_mut().spawn((
+                ChunkLoader::new(0.25).with_layer::<TestLayerA>().with_render_scale(8.0),
+                GlobalTransform::from_translation(Vec3::new(-12.0, 4.0, 0.0)),
+            ));
+            app.update();
+            assert!(has_chunk(&app, 5.5));
+            assert!(has_chunk(&app, -1.5));
+            assert!(!has_chunk(&app, 0.5));
+        }
+    }
+
+    mod test_generation_context {
+        use bevy::math::Vec2;
+        use rand::Rng;
+        use crate::bounds::ChunkIdx;
+        use crate::context::GenerationContext;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct SeedChunk(u64);
+
+        struct TestLayerA;
+        struct TestLayerB;
+
+        impl Chunk for SeedChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TestLayerA {
+            type Chunk = SeedChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                SeedChunk(lookup.context().rng().random())
+            }
+        }
+
+        impl Layer for TestLayerB {
+            type Chunk = SeedChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                SeedChunk(0)
+            }
+        }
+
+        #[test]
+        fn test_seeds_differ() {
+            let a = LayerId::from_type::<TestLayerA>();
+            let b = LayerId::from_type::<TestLayerB>();
+            let origin = ChunkIdx { x: 0, y: 0 };
+            let seed = |world: u64, layer: LayerId, chunk_idx: ChunkIdx| {
+                GenerationContext::new(world, layer, chunk_idx).get_seed()
+            };
+            assert_eq!(seed(1, a, origin), seed(1, a, origin));
+            assert_ne!(seed(1, a, origin), seed(2, a, origin));
+            assert_ne!(seed(1, a, origin), seed(1, b, origin));
+            // Hand-rolled `x + y * 512` seeds collide here
+            assert_ne!(seed(1, a, ChunkIdx { x: 512, y: 0 }), seed(1, a, ChunkIdx { x: 0, y: 1 }));
+
+            let context = GenerationContext::new(1, a, origin);
+            let trees: u64 = context.rng_for("trees").random();
+            let rocks: u64 = context.rng_for("rocks").random();
+            assert_ne!(trees, rocks);
+        }
+
+        #[test]
+        fn test_layers_read_the_context() {
+            let build = |seed: u64| {
+                let mut manager =
+                    LayersManagerBuilder::new().seed(seed).add_layer(TestLayerA).build();
+                manager.add_layer_client(LayerClient::new(
+                    Vec2::new(0.5, 0.5),
+                    vec![Dependency::new::<TestLayerA>(Vec2::ZERO)],
+                    UsageStrategy::Fast,
+                ));
+                manager.regenerate();
+                manager.get_chunk::<TestLayerA>(Vec2::new(0.5, 0.5)).unwrap().0
+            };
+            let origin = ChunkIdx { x: 0, y: 0 };
+            let context = GenerationContext::new(7, LayerId::from_type::<TestLayerA>(), origin);
+            assert_eq!(build(7), context.rng().random::<u64>());
+            assert_ne!(build(7), build(8));
+        }
+    }
+
+    mod test_ray_chunks {
+        use bevy::math::Vec2;
+        use crate::bounds::{ChunkIdx, RayChunks};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        const SIZE: Vec2 = Vec2::new(1., 1.);
+
+        #[derive(Debug, Clone)]
+        struct WallChunk(bool);
+
+        struct WallLayer;
+
+        impl Chunk for WallChunk {
+            fn get_size() -> Vec2 {
+                SIZE
+            }
+        }
+
+        impl Layer for WallLayer {
+            type Chunk = WallChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                WallChunk(chunk_idx.x == 3)
+            }
+        }
+
+        fn idx(x: i32, y: i32) -> ChunkIdx {
+            ChunkIdx { x, y }
+        }
+
+        fn segment(a: Vec2, b: Vec2) -> Vec<ChunkIdx> {
+            RayChunks::segment(a, b, SIZE).collect()
+        }
+
+        #[test]
+        fn test_segment() {
+            let origin = Vec2::new(0.5, 0.5);
+            assert_eq!(
+                segment(origin, Vec2::new(2.5, 1.5)),
+                vec![idx(0, 0), idx(1, 0), idx(1, 1), idx(2, 1)]
+            );
+            assert_eq!(
+                segment(origin, Vec2::new(-1.2, 0.5)),
+                vec![idx(0, 0), idx(-1, 0), idx(-2, 0)]
+            );
+            assert_eq!(segment(origin, origin), vec![idx(0, 0)]);
+        }
+
+        #[test]
+        fn test_raycast() {
+            let mut manager = LayersManagerBuilder::new().add_layer(WallLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(2.5, 0.5),
+                vec![Dependency::new::<WallLayer>(Vec2::new(3., 0.))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let wall = |chunk: &WallChunk| chunk.0;
+            let origin = Vec2::new(0.5, 0.5);
+            assert_eq!(manager.raycast::<WallLayer>(origin, Vec2::X, 10.0, wall), Some(idx(3, 0)));
+            assert_eq!(manager.raycast::<WallLayer>(origin, Vec2::X, 2.0, wall), None);
+            assert_eq!(manager.raycast::<WallLayer>(origin, Vec2::NEG_X, 10.0, wall), None);
+
+            let path = manager.chunks_on_segment::<WallLayer>(origin, Vec2::new(4.5, 0.5));
+            let path: Vec<_> = path.into_iter().map(|(chunk_idx, _)| chunk_idx.x).collect();
+            assert_eq!(path, vec![0, 1, 2, 3, 4]);
+        }
+    }
+
+    mod test_polygon {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::polygon::{chunks_in_polygon, Polygon};
+
+        const SIZE: Vec2 = Vec2::new(1., 1.);
+
+        fn square(min: f32, max: f32) -> Vec<Vec2> {
+            vec![
+                Vec2::new(min, min),
+                Vec2::new(max, min),
+                Vec2::new(max, max),
+                Vec2::new(min, max),
+            ]
+        }
+
+        #[test]
+        fn test_square_matches_bounds() {
+            let bounds = Bounds::new(Vec2::new(0., 0.), Vec2::new(2., 2.));
+            let expected: Vec<ChunkIdx> = bounds.chunks(SIZE).collect();
+            assert_eq!(chunks_in_polygon(&square(0., 2.), SIZE), expected);
+        }
+
+        #[test]
+        fn test_hole() {
+            let polygon = Polygon::new(square(0., 4.)).with_hole(square(1., 3.));
+            assert!(polygon.contains(Vec2::new(0.5, 2.0)));
+            assert!(!polygon.contains(Vec2::new(2.0, 2.0)));
+            assert!(!polygon.contains(Vec2::new(5.0, 2.0)));
+            let chunks = polygon.chunks(SIZE);
+            // Only the chunk entirely inside the hole is left out
+            assert_eq!(chunks.len(), 24);
+            assert!(!chunks.contains(&ChunkIdx { x: 2, y: 2 }));
+        }
+
+        #[test]
+        fn test_triangle() {
+            let triangle = [Vec2::new(0.5, 0.5), Vec2::new(4.5, 0.5), Vec2::new(0.5, 4.5)];
+            let chunks = chunks_in_polygon(&triangle, SIZE);
+            assert!(chunks.contains(&ChunkIdx { x: 0, y: 4 }));
+            assert!(chunks.contains(&ChunkIdx { x: 2, y: 2 }));
+            assert!(!chunks.contains(&ChunkIdx { x: 3, y: 3 }));
+            assert!(!chunks.contains(&ChunkIdx { x: 4, y: 4 }));
+            assert!(chunks_in_polygon(&[], SIZE).is_empty());
+        }
+    }
+
+    mod test_chunk_set {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::chunk_set::ChunkSet;
+
+        fn idx(x: i32, y: i32) -> ChunkIdx {
+            ChunkIdx { x, y }
+        }
+
+        fn square(min: i32, max: i32) -> ChunkSet {
+            (min..max).flat_map(|x| (min..max).map(move |y| idx(x, y))).collect()
+        }
+
+        #[test]
+        fn test_insert_remove() {
+            let mut set = ChunkSet::new();
+            assert!(set.insert(idx(-1, -9)));
+            assert!(!set.insert(idx(-1, -9)));
+            assert!(set.insert(idx(7, 0)));
+            assert!(set.contains(&idx(-1, -9)));
+            assert!(!set.contains(&idx(-1, 9)));
+            assert_eq!(set.len(), 2);
+            assert!(set.remove(&idx(-1, -9)));
+            assert!(!set.remove(&idx(-1, -9)));
+            assert_eq!(set.iter().collect::<Vec<_>>(), vec![idx(7, 0)]);
+            set.clear();
+            assert!(set.is_empty());
+        }
+
+        #[test]
+        fn test_set_operations() {
+            let a = square(-4, 4);
+            let b = square(0, 10);
+            assert_eq!(a.union(&b).len(), 64 + 100 - 16);
+            assert_eq!(a.intersection(&b), square(0, 4));
+            let difference = a.difference(&b);
+            assert_eq!(difference.len(), 64 - 16);
+            assert!(!difference.contains(&idx(1, 1)));
+            assert!(difference.contains(&idx(-1, 1)));
+            assert_eq!(a.difference(&a), ChunkSet::new());
+        }
+
+        #[test]
+        fn test_iteration_is_deterministic() {
+            let chunks: Vec<ChunkIdx> = square(-10, 10).iter().collect();
+            let reversed: ChunkSet = chunks.iter().rev().copied().collect();
+            assert_eq!(reversed.into_iter().collect::<Vec<_>>(), chunks);
+            assert_eq!(chunks.len(), 400);
+
+            let bounds = Bounds::new(Vec2::new(0., 0.), Vec2::new(2., 2.));
+            assert_eq!(ChunkSet::from_bounds(&bounds, Vec2::ONE), square(0, 3));
+        }
+    }
+
+    mod test_slow_strategy {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::group::LayerGroup;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::{SlowSchedule, UsageStrategy};
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        struct UnitLayer;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for UnitLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        /// Generates a single chunk per regenerate
+        struct SingleLayer;
+
+        impl Layer for SingleLayer {
+            type Chunk = UnitChunk;
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(1)
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        /// Generates 2 chunks per regenerate
+        struct PairLayer;
+
+        impl Layer for PairLayer {
+            type Chunk = UnitChunk;
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(2)
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        /// Generates 5 chunks per regenerate, fewer than `fast_ratio + 1`
+        struct ThrottledLayer;
+
+        impl Layer for ThrottledLayer {
+            type Chunk = UnitChunk;
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(5)
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        /// Generates 9 chunks per regenerate
+        struct BatchLayer;
+
+        impl Layer for BatchLayer {
+            type Chunk = UnitChunk;
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(9)
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        struct GroupedLayer;
+
+        impl Layer for GroupedLayer {
+            type Chunk = UnitChunk;
+
+            fn group(&self) -> Option<&'static str> {
+                Some("background")
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        fn client(center: Vec2, padding: f32, strategy: UsageStrategy) -> LayerClient {
+            LayerClient::new(
+                center,
+                vec![Dependency::new::<UnitLayer>(Vec2::splat(padding))],
+                strategy,
+            )
+        }
+
+        /// 36 Slow chunks around the origin and 16 Fast ones around (20, 20)
+        fn add_clients<L: Layer + 'static>(manager: &mut LayersManager) {
+            for (center, padding, strategy) in [
+                (Vec2::new(0.5, 0.5), 2., UsageStrategy::Slow),
+                (Vec2::new(20.5, 20.5), 1., UsageStrategy::Fast),
+            ] {
+                manager.add_layer_client(LayerClient::new(
+                    center,
+                    vec![Dependency::new::<L>(Vec2::splat(padding))],
+                    strategy,
+                ));
+            }
+        }
+
+        /// Fast and Slow chunks generated by the last regenerate
+        fn generated<L: Layer + 'static>(manager: &LayersManager) -> (usize, usize) {
+            let generated = manager.get_generated_chunks::<L>();
+            let slow = generated.iter().filter(|chunk_idx| chunk_idx.x < 10).count();
+            (generated.len() - slow, slow)
+        }
+
+        /// What each of `count` regenerates generated
+        fn regenerate<L: Layer + 'static>(
+            manager: &mut LayersManager,
+            count: usize,
+        ) -> Vec<(usize, usize)> {
+            (0..count)
+                .map(|_| {
+                    manager.regenerate();
+                    generated::<L>(manager)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_max_in_flight_keeps_a_slow_slot() {
+            let mut manager = LayersManagerBuilder::new().add_layer(ThrottledLayer).build();
+            add_clients::<ThrottledLayer>(&mut manager);
+            // The Slow chunk comes after 8 Fast ones, across the regenerates
+            let generated = regenerate::<ThrottledLayer>(&mut manager, 3);
+            assert_eq!(generated, [(5, 0), (4, 1), (5, 0)]);
+        }
+
+        #[test]
+        fn test_single_slot_goes_to_fast_chunks_first() {
+            let mut manager = LayersManagerBuilder::new().add_layer(SingleLayer).build();
+            add_clients::<SingleLayer>(&mut manager);
+            let generated = regenerate::<SingleLayer>(&mut manager, 10);
+            let mut expected: Vec<(usize, usize)> = vec![(1, 0); 8];
+            expected.extend([(0, 1), (1, 0)]);
+            assert_eq!(generated, expected);
+        }
+
+        #[test]
+        fn test_two_slots_keep_the_ratio() {
+            let mut manager = LayersManagerBuilder::new().add_layer(PairLayer).build();
+            add_clients::<PairLayer>(&mut manager);
+            // One Slow chunk for every 8 Fast ones, not one in two
+            let generated = regenerate::<PairLayer>(&mut manager, 10);
+            assert_eq!(
+                generated,
+                [
+                    (2, 0),
+                    (2, 0),
+                    (2, 0),
+                    (2, 0),
+                    (1, 1),
+                    (2, 0),
+                    (2, 0),
+                    (2, 0),
+                    (1, 1),
+                    (0, 2),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_max_in_flight_splits_by_ratio() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BatchLayer)
+                .slow_schedule(SlowSchedule {
+                    per_frame: 8,
+                    fast_ratio: 2,
+                })
+                .build();
+            // 9 slots for 16 Fast and 8 Slow chunks, one Slow chunk for every 2 Fast ones
+            add_clients::<BatchLayer>(&mut manager);
+            manager.regenerate();
+            assert_eq!(generated::<BatchLayer>(&manager), (6, 3));
+        }
+
+        #[test]
+        fn test_group_budget_keeps_a_slow_slot() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_group(LayerGroup::new("background").with_max_chunks(5))
+                .add_layer(GroupedLayer)
+                .build();
+            add_clients::<GroupedLayer>(&mut manager);
+            let generated = regenerate::<GroupedLayer>(&mut manager, 2);
+            assert_eq!(generated, [(5, 0), (4, 1)]);
+        }
+
+        #[test]
+        fn test_slow_chunks_are_deferred() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(UnitLayer)
+                .slow_schedule(SlowSchedule {
+                    per_frame: 2,
+                    fast_ratio: 8,
+                })
+                .build();
+            manager.add_layer_client(client(Vec2::new(0.5, 0.5), 2., UsageStrategy::Slow));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 2);
+
+            // Fast chunks are all generated at once, the Slow ones keep their pace
+            manager.add_layer_client(client(Vec2::new(20.5, 20.5), 1., UsageStrategy::Fast));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 16 + 2);
+            assert!(manager.get_chunk::<UnitLayer>(Vec2::new(19.5, 21.5)).is_some());
+
+            // 36 Slow chunks, 4 generated so far
+            for _ in 0..16 {
+                manager.regenerate();
+            }
+            assert_eq!(manager.get_stats().generated, 2);
+            assert!(manager.get_chunk::<UnitLayer>(Vec2::new(-1.5, 2.5)).is_some());
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 0);
+        }
+
+        #[test]
+        fn test_fast_work_speeds_up_slow_chunks() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(UnitLayer)
+                .slow_schedule(SlowSchedule {
+                    per_frame: 1,
+                    fast_ratio: 4,
+                })
+                .build();
+            manager.add_layer_client(client(Vec2::new(0.5, 0.5), 2., UsageStrategy::Slow));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 1);
+
+            // 36 Fast chunks, more than `per_frame * fast_ratio`, bring 9 Slow ones along
+            manager.add_layer_client(client(Vec2::new(20.5, 20.5), 2., UsageStrategy::Fast));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 36 + 9);
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 1);
+        }
+    }
+
+    mod test_region_events {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        /// Generates 4 chunks per regenerate
+        struct ThrottledLayer;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for ThrottledLayer {
+            type Chunk = UnitChunk;
+
+            fn max_in_flight(&self) -> Option<usize> {
+                Some(4)
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        #[test]
+        fn test_ready_and_degraded() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(ThrottledLayer)
+                .region_events(true)
+                .build();
+            let client = manager.add_layer_client(LayerClient::new(
+                Vec2::new(0., 0.),
+                vec![Dependency::new::<ThrottledLayer>(Vec2::new(1., 1.))],
+                UsageStrategy::Fast,
+            ));
+            let area = Bounds::new(Vec2::new(-1., -1.), Vec2::new(1., 1.));
+            manager.regenerate();
+            manager.regenerate();
+            assert!(manager.drain_ready_regions().is_empty());
+            manager.regenerate();
+            let ready = manager.drain_ready_regions();
+            assert_eq!(ready.len(), 1);
+            assert_eq!(ready[0].client, client);
+            assert_eq!(ready[0].layer, LayerId::from_type::<ThrottledLayer>());
+            assert_eq!(ready[0].bounds, area);
+            assert_eq!(ready[0].chunks, 9);
+            // Reported once
+            manager.regenerate();
+            assert!(manager.drain_ready_regions().is_empty());
+
+            manager.invalidate_region::<ThrottledLayer>(&area);
+            manager.regenerate();
+            let degraded = manager.drain_degraded_regions();
+            assert_eq!(degraded.len(), 1);
+            assert_eq!(degraded[0].missing.len(), 5);
+            manager.regenerate();
+            manager.regenerate();
+            assert!(manager.drain_degraded_regions().is_empty());
+            assert_eq!(manager.drain_ready_regions().len(), 1);
+
+            // A new area after moving, not a degradation of the old one
+            manager.update_client_center(client, Vec2::new(10., 0.));
+            manager.regenerate();
+            assert!(manager.drain_degraded_regions().is_empty());
+            manager.regenerate();
+            manager.regenerate();
+            assert_eq!(manager.drain_ready_regions()[0].bounds.get_min(), Vec2::new(9., -1.));
+        }
+    }
+
+    mod test_grace_period {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::{GracePeriod, UsageStrategy};
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        struct GraceLayer;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for GraceLayer {
+            type Chunk = UnitChunk;
+
+            fn grace_period(&self) -> Option<GracePeriod> {
+                Some(GracePeriod::Frames(2))
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        #[test]
+        fn test_unused_chunks_wait_out_the_grace_period() {
+            let mut manager = LayersManagerBuilder::new().add_layer(GraceLayer).build();
+            let client = manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<GraceLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 4);
+
+            // Moving back and forth reuses the chunk
+            manager.update_client_center(client, Vec2::new(10.5, 0.5));
+            manager.regenerate();
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_some());
+            manager.update_client_center(client, Vec2::new(0.5, 0.5));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 0);
+
+            // Evicted once unused for the whole grace period
+            manager.update_client_center(client, Vec2::new(10.5, 0.5));
+            manager.regenerate();
+            manager.regenerate();
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_some());
+            manager.regenerate();
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_none());
+        }
+
+        #[test]
+        fn test_jiggling_client_keeps_its_chunks() {
+            let mut manager = LayersManagerBuilder::new().add_layer(GraceLayer).build();
+            let client = manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<GraceLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 4);
+
+            // One chunk to the right, the column on the left leaves the area
+            manager.update_client_center(client, Vec2::new(1.5, 0.5));
+            manager.regenerate();
+            assert_eq!(manager.get_stats().generated, 2);
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_some());
+
+            // Jiggling back and forth generates nothing
+            for x in [0.5, 1.5, 0.5, 1.5] {
+                manager.update_client_center(client, Vec2::new(x, 0.5));
+                manager.regenerate();
+                assert_eq!(manager.get_stats().generated, 0);
+            }
+            manager.regenerate();
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_some());
+            manager.regenerate();
+            assert!(manager.get_chunk::<GraceLayer>(Vec2::new(0.5, 0.5)).is_none());
+        }
+
+        #[test]
+        fn test_seconds() {
+            let grace_period = GracePeriod::Seconds(1.5);
+            let second = std::time::Duration::from_secs(1);
+            assert!(!grace_period.has_elapsed((0, second), (10, 2 * second)));
+            assert!(grace_period.has_elapsed((0, second), (1, 3 * second)));
+        }
+    }
+
+    mod test_chunk_alignment {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, ChunkAlignment, Dependency, Layer, MisalignedChunks};
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+
+        #[derive(Debug, Clone)]
+        struct BaseChunk;
+
+        struct BaseLayer;
+
+        impl Chunk for BaseChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(4., 4.)
+            }
+        }
+
+        impl Layer for BaseLayer {
+            type Chunk = BaseChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                BaseChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct FineChunk;
+
+        struct FineLayer;
+
+        impl Chunk for FineChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(2., 1.)
+            }
+        }
+
+        impl Layer for FineLayer {
+            type Chunk = FineChunk;
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::ZERO)]
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                FineChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct OddChunk;
+
+        struct OddLayer;
+
+        impl Chunk for OddChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(3., 8.)
+            }
+        }
+
+        impl Layer for OddLayer {
+            type Chunk = OddChunk;
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::ZERO)]
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                OddChunk
+            }
+        }
+
+        #[test]
+        fn test_is_aligned() {
+            assert!(MisalignedChunks::is_aligned(Vec2::new(2., 8.), Vec2::new(4., 4.)));
+            assert!(MisalignedChunks::is_aligned(Vec2::new(0.1, 0.3), Vec2::new(0.3, 0.3)));
+            assert!(MisalignedChunks::is_aligned(Vec2::INFINITY, Vec2::new(3., 3.)));
+            assert!(!MisalignedChunks::is_aligned(Vec2::new(3., 4.), Vec2::new(4., 4.)));
+            assert!(!MisalignedChunks::is_aligned(Vec2::new(4., 6.), Vec2::new(4., 4.)));
+        }
+
+        #[test]
+        fn test_misaligned_dependencies() {
+            let builder = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(FineLayer)
+                .add_layer(OddLayer)
+                .chunk_alignment(ChunkAlignment::Ignore);
+            let misaligned = builder.misaligned_dependencies();
+            assert_eq!(misaligned.len(), 1);
+            assert_eq!(misaligned[0].layer, LayerId::from_type::<OddLayer>());
+            assert_eq!(misaligned[0].dependency, LayerId::from_type::<BaseLayer>());
+            assert_eq!(misaligned[0].suggested_size(), Vec2::new(4., 8.));
+            builder.build();
+        }
+
+        #[test]
+        #[should_panic(expected = "neither a multiple nor a divisor")]
+        fn test_enforce() {
+            LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(OddLayer)
+                .chunk_alignment(ChunkAlignment::Enforce)
+                .build();
+        }
+    }
+
+    mod test_generate_bounds {
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, GenerationLane, Layer};
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+
+        #[derive(Debug, Clone)]
+        struct BaseChunk;
+
+        struct BaseLayer;
+
+        impl Chunk for BaseChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for BaseLayer {
+            type Chunk = BaseChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                BaseChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct TopChunk;
+
+        struct TopLayer;
+
+        impl Chunk for TopChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TopLayer {
+            type Chunk = TopChunk;
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::new(1., 1.))]
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                TopChunk
+            }
+        }
+
+        #[test]
+        fn test_generate_bounds() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(TopLayer)
+                .build();
+            let bounds = Bounds::new(Vec2::new(0.25, 0.25), Vec2::new(0.75, 0.75));
+            let generation = manager.generate_bounds::<TopLayer>(bounds);
+            assert!(generation.complete);
+            assert!(generation.passes >= 1);
+            assert_eq!(generation.layers[&LayerId::from_type::<TopLayer>()].chunks, 4);
+            assert!(generation.layers[&LayerId::from_type::<BaseLayer>()].chunks > 4);
+            assert!(manager.is_region_ready::<TopLayer>(&bounds));
+            assert_eq!(manager.get_chunks_in::<TopLayer>(bounds).len(), 4);
+
+            // Nothing keeps the chunks once read
+            manager.regenerate();
+            assert!(!manager.is_region_ready::<TopLayer>(&bounds));
+        }
+
+        struct SlowLayer;
+
+        impl Layer for SlowLayer {
+            type Chunk = BaseChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                std::thread::sleep(Duration::from_millis(50));
+                BaseChunk
+            }
+
+            fn lane(&self) -> GenerationLane {
+                GenerationLane::Async
+            }
+        }
+
+        #[test]
+        fn test_generate_bounds_waits_on_async_chunks() {
+            let mut manager = LayersManagerBuilder::new().add_layer(SlowLayer).build();
+            let bounds = Bounds::new(Vec2::new(0.25, 0.25), Vec2::new(0.75, 0.75));
+            let generation = manager.generate_bounds::<SlowLayer>(bounds);
+            assert!(generation.complete);
+            // One pass to start the 4 chunks, then at most one per finished chunk
+            assert!(generation.passes <= 5, "{} passes", generation.passes);
+            assert!(generation.waiting > Duration::ZERO);
+            assert_eq!(generation.layers[&LayerId::from_type::<SlowLayer>()].chunks, 4);
+        }
+    }
+
+    mod test_prepare {
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::context::PrepareContext;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct TableChunk(u32);
+
+        impl Chunk for TableChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(2., 2.)
+            }
+        }
+
+        #[derive(Default)]
+        struct TableLayer {
+            table: Vec<u32>,
+        }
+
+        impl Layer for TableLayer {
+            type Chunk = TableChunk;
+
+            fn prepare(&mut self, ctx: &PrepareContext) {
+                assert_eq!(ctx.get_layer(), LayerId::from_type::<TableLayer>());
+                assert_eq!(ctx.get_chunk_size(), Vec2::new(2., 2.));
+                std::thread::sleep(Duration::from_millis(2));
+                self.table = (0..16).map(|i| i * i).collect();
+            }
+
+            fn generate(&self, _lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                TableChunk(self.table[chunk_idx.x.rem_euclid(16) as usize])
+            }
+        }
+
+        #[test]
+        fn test_prepare_before_generating() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TableLayer::default())
+                .build();
+            let prepare_time = manager.get_prepare_time(LayerId::from_type::<TableLayer>());
+            assert!(prepare_time.unwrap() >= Duration::from_millis(2));
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(7., 1.),
+                vec![Dependency::new::<TableLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let chunk = manager.get_chunk::<TableLayer>(Vec2::new(7., 1.));
+            assert_eq!(chunk, Some(TableChunk(9)));
+        }
+    }
+
+    mod test_invalidate_cascade {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct BaseLayer;
+
+        impl Layer for BaseLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        struct MiddleLayer;
+
+        impl Layer for MiddleLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::new(0.0, 0.0))]
+            }
+        }
+
+        struct TopLayer;
+
+        impl Layer for TopLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<MiddleLayer>(Vec2::new(0.0, 0.0))]
+            }
+        }
+
+        #[test]
+        fn test_dependents() {
+            let manager = LayersManagerBuilder::new()
+                .add_layer(TopLayer)
+                .add_layer(MiddleLayer)
+                .add_layer(BaseLayer)
+                .build();
+            assert_eq!(
+                manager.get_dependents(LayerId::from_type::<BaseLayer>()),
+                &[LayerId::from_type::<MiddleLayer>()]
+            );
+            assert_eq!(
+                manager.get_dependents(LayerId::from_type::<MiddleLayer>()),
+                &[LayerId::from_type::<TopLayer>()]
+            );
+            assert!(manager.get_dependents(LayerId::from_type::<TopLayer>()).is_empty());
+        }
+
+        #[test]
+        fn test_invalidate_reaches_indirect_dependents() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(MiddleLayer)
+                .add_layer(TopLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TopLayer>(Vec2::new(4.0, 4.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let all_top = manager.get_generated_chunks::<TopLayer>().len();
+
+            let damaged = Bounds::from_point(Vec2::new(2.0, 2.0));
+            manager.invalidate_region::<BaseLayer>(&damaged);
+            manager.regenerate();
+
+            // The base chunk 2 is read by the middle chunks 1 and 2, read by the top chunks 0 to 2
+            let regenerated = manager.get_generated_chunks::<TopLayer>();
+            let read_damage = |v: i32| (0..=2).contains(&v);
+            assert!(!regenerated.is_empty());
+            assert!(regenerated.len() < all_top);
+            assert!(regenerated
+                .iter()
+                .all(|chunk_idx| read_damage(chunk_idx.x) && read_damage(chunk_idx.y)));
+        }
+    }
+
+    mod test_world_pos {
+        use bevy::math::Vec2;
+        use crate::bounds::{ChunkIdx, ChunkLocalPos, WorldPos};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct TileChunk(ChunkIdx);
+
+        struct TileLayer;
+
+        impl Chunk for TileChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(4., 2.)
+            }
+        }
+
+        impl Layer for TileLayer {
+            type Chunk = TileChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                TileChunk(*chunk_idx)
+            }
+        }
+
+        #[test]
+        fn test_local_round_trip() {
+            let size = TileChunk::get_size();
+            let pos = WorldPos::new(-3.0, 5.0);
+            let chunk_idx = ChunkIdx { x: -1, y: 2 };
+            let local = pos.to_local(chunk_idx, size);
+            assert_eq!(local, ChunkLocalPos::new(1.0, 1.0));
+            assert_eq!(local.to_world(chunk_idx, size), pos);
+        }
+
+        #[test]
+        fn test_manager_conversions() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TileLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                WorldPos::new(0.0, 0.0),
+                vec![Dependency::new::<TileLayer>(Vec2::new(8.0, 8.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let (chunk_idx, local) = manager.to_chunk_local::<TileLayer>(WorldPos::new(6.0, -1.5));
+            assert_eq!(chunk_idx, ChunkIdx { x: 1, y: -1 });
+            assert_eq!(local, ChunkLocalPos::new(2.0, 0.5));
+            assert_eq!(
+                manager.to_world::<TileLayer>(chunk_idx, local),
+                WorldPos::new(6.0, -1.5)
+            );
+
+            // Plain vectors are still accepted as world positions
+            let typed = manager.get_chunk::<TileLayer>(WorldPos::new(6.0, -1.5)).unwrap();
+            let plain = manager.get_chunk::<TileLayer>(Vec2::new(6.0, -1.5)).unwrap();
+            assert_eq!(typed.0, chunk_idx);
+            assert_eq!(plain.0, chunk_idx);
+        }
+    }
+
+    mod test_manual_invalidation {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct GroundChunk;
+
+        struct GroundLayer;
+
+        impl Chunk for GroundChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for GroundLayer {
+            type Chunk = GroundChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                GroundChunk
+            }
+        }
+
+        #[test]
+        fn test_invalidate_chunk_and_bounds() {
+            let mut manager = LayersManagerBuilder::new().add_layer(GroundLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<GroundLayer>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<GroundLayer>().len(), 25);
+
+            let crater = ChunkIdx { x: 1, y: 1 };
+            manager.invalidate_chunk::<GroundLayer>(crater);
+            assert!(manager.get_chunk::<GroundLayer>(Vec2::new(1.5, 1.5)).is_none());
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<GroundLayer>(), &vec![crater]);
+            assert!(manager.get_chunk::<GroundLayer>(Vec2::new(1.5, 1.5)).is_some());
+
+            // Bounds from -0.5 to 0.5 touch the chunks -1 to 1 on each axis
+            let blast = Bounds::from_point(Vec2::new(0.0, 0.0)).expand(0.5, 0.5);
+            manager.invalidate_region::<GroundLayer>(&blast);
+            manager.regenerate();
+            assert_eq!(manager.get_generated_chunks::<GroundLayer>().len(), 9);
+
+            // Chunks nobody requested are not generated back
+            manager.invalidate_chunk::<GroundLayer>(ChunkIdx { x: 10, y: 10 });
+            manager.regenerate();
+            assert!(manager.get_generated_chunks::<GroundLayer>().is_empty());
+        }
+    }
+
+    mod test_world_scale {
+        use bevy::math::{Rect, Vec2, Vec3};
+        use crate::bounds::{ChunkAnchor, ChunkIdx, WorldPos};
+        use crate::world_scale::WorldScale;
+
+        #[test]
+        fn test_render_conversions() {
+            let scale = WorldScale::new(10.0);
+            assert_eq!(scale.to_render(WorldPos::new(1.5, -2.0)), Vec2::new(15.0, -20.0));
+            assert_eq!(scale.to_world(Vec2::new(15.0, -20.0)), WorldPos::new(1.5, -2.0));
+
+            let chunk_size = Vec2::new(2.0, 4.0);
+            let chunk_idx = ChunkIdx { x: 1, y: -1 };
+            assert_eq!(scale.chunk_render_size(chunk_size), Vec2::new(20.0, 40.0));
+            assert_eq!(
+                scale.chunk_rect(chunk_idx, chunk_size),
+                Rect::new(20.0, -40.0, 40.0, 0.0)
+            );
+            let transform = scale.chunk_transform(chunk_idx, chunk_size, ChunkAnchor::Center);
+            assert_eq!(transform.translation, Vec3::new(30.0, -20.0, 0.0));
+        }
+
+        #[test]
+        #[should_panic(expected = "The world scale must be positive")]
+        fn test_scale_must_be_positive() {
+            WorldScale::new(0.0);
+        }
+    }
+
+    mod test_probe {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, ChunkStatus, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct HeightChunk {
+            height: i32,
+        }
+
+        struct HeightLayer;
+
+        impl Chunk for HeightChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(4., 4.)
+            }
+        }
+
+        impl Layer for HeightLayer {
+            type Chunk = HeightChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                HeightChunk {
+                    height: chunk_idx.x * 10,
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct TreeChunk;
+
+        struct TreeLayer;
+
+        impl Chunk for TreeChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for TreeLayer {
+            type Chunk = TreeChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TreeChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<HeightLayer>(Vec2::new(0.0, 0.0))]
+            }
+        }
+
+        #[test]
+        fn test_probe_every_layer() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(TreeLayer)
+                .add_layer(HeightLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<TreeLayer>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            let probed = manager.probe(Vec2::new(-1.5, 0.5));
+            assert_eq!(probed.len(), 2);
+            // Dependencies first
+            assert_eq!(probed[0].layer, LayerId::from_type::<HeightLayer>());
+            assert_eq!(probed[0].chunk, ChunkIdx { x: -1, y: 0 });
+            assert_eq!(probed[0].status, Some(ChunkStatus::Generated));
+            assert!(probed[0].data.as_ref().unwrap().contains("height: -10"));
+            assert_eq!(probed[1].layer, LayerId::from_type::<TreeLayer>());
+            assert_eq!(probed[1].chunk, ChunkIdx { x: -2, y: 0 });
+            assert_eq!(probed[1].data.as_deref(), Some("TreeChunk"));
+
+            // Outside of the client the chunks are not requested
+            let far = manager.probe(Vec2::new(50.5, 0.5));
+            assert!(far.iter().all(|probed| probed.status.is_none() && probed.data.is_none()));
+            assert!(far[1].to_string().ends_with("not requested"));
+        }
+    }
+
+    mod test_chunk_diff {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Layer};
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::variations::{ChunkDiff, ChunkDiffer};
+
+        #[derive(Debug, Clone)]
+        struct ValueChunk {
+            value: u64,
+        }
+
+        struct ValueLayer {
+            factor: u64,
+        }
+
+        impl Chunk for ValueChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for ValueLayer {
+            type Chunk = ValueChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                ValueChunk {
+                    value: chunk_idx.x as u64 * lookup.get_seed() * self.factor,
+                }
+            }
+        }
+
+        fn pipeline(factor: u64) -> LayersManagerBuilder {
+            LayersManagerBuilder::new().add_layer(ValueLayer { factor })
+        }
+
+        #[test]
+        fn test_diff_seeds() {
+            let mut differ = ChunkDiffer::seeds(1, 2, || pipeline(1));
+            // The chunks 0 to 2 on the x axis, the chunk 0 is the same under every seed
+            let diff = differ.diff::<ValueLayer>(Bounds::new(Vec2::ZERO, Vec2::new(2.0, 0.0)));
+            assert_eq!(diff.compared, 3);
+            assert_eq!(
+                diff.chunks,
+                vec![
+                    (
+                        ChunkIdx { x: 1, y: 0 },
+                        ChunkDiff::Changed {
+                            before: "ValueChunk {\n    value: 1,\n}".to_string(),
+                            after: "ValueChunk {\n    value: 2,\n}".to_string(),
+                        }
+                    ),
+                    (
+                        ChunkIdx { x: 2, y: 0 },
+                        ChunkDiff::Changed {
+                            before: "ValueChunk {\n    value: 2,\n}".to_string(),
+                            after: "ValueChunk {\n    value: 4,\n}".to_string(),
+                        }
+                    ),
+                ]
+            );
+            assert_eq!(diff.get_chunk_set().len(), 2);
+            assert!(diff.to_string().contains("2 of 3 chunks differ"));
+        }
+
+        #[test]
+        fn test_diff_versions() {
+            let bounds = Bounds::new(Vec2::ZERO, Vec2::new(2.0, 2.0));
+            let mut same = ChunkDiffer::versions(pipeline(3).seed(5), pipeline(3).seed(5));
+            assert!(same.diff::<ValueLayer>(bounds).is_empty());
+
+            let mut changed = ChunkDiffer::versions(pipeline(3).seed(5), pipeline(4).seed(5));
+            let diff = changed.diff::<ValueLayer>(bounds);
+            assert_eq!(diff.compared, 9);
+            // Only the chunks at x = 0 keep their value
+            assert_eq!(diff.chunks.len(), 6);
+            assert!(diff.chunks.iter().all(|(chunk_idx, _)| chunk_idx.x != 0));
+        }
+    }
+
+    mod test_chunks_error {
+        use bevy::math::{UVec2, Vec2};
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::error::ChunksError;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct RockChunk;
+
+        struct RockLayer;
+
+        impl Chunk for RockChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for RockLayer {
+            type Chunk = RockChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                RockChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct MossChunk;
+
+        struct MossLayer;
+
+        impl Chunk for MossChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for MossLayer {
+            type Chunk = MossChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                // Reading the rock layer as moss chunks is a type mismatch
+                let pos = chunk_idx.center(Self::Chunk::get_size());
+                let rock = LayerId::from_type::<RockLayer>();
+                assert_eq!(
+                    lookup.try_get_chunk::<MossLayer>(rock, pos).unwrap_err(),
+                    ChunksError::TypeMismatch {
+                        layer: rock,
+                        chunk: *chunk_idx
+                    }
+                );
+                assert!(lookup.try_get_chunk::<RockLayer>(rock, pos).is_ok());
+                MossChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<RockLayer>(Vec2::new(0.0, 0.0))]
+            }
+        }
+
+        #[test]
+        fn test_try_queries() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(RockLayer)
+                .add_layer(MossLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<MossLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(!manager.get_generated_chunks::<MossLayer>().is_empty());
+
+            assert_eq!(manager.try_get_chunk::<RockLayer>(Vec2::new(0.5, 0.5)), Ok(RockChunk));
+            assert_eq!(
+                manager.try_get_chunk::<RockLayer>(Vec2::new(20.5, 0.5)),
+                Err(ChunksError::ChunkNotGenerated {
+                    layer: LayerId::from_type::<RockLayer>(),
+                    chunk: ChunkIdx { x: 20, y: 0 },
+                })
+            );
+            assert!(manager.try_get_deleted_chunks::<RockLayer>().is_ok());
+            let around = Bounds::from_point(Vec2::new(0.5, 0.5));
+            assert_eq!(
+                manager.try_get_chunks_in::<RockLayer>(around),
+                Ok(manager.get_chunks_in::<RockLayer>(around))
+            );
+
+            let rocks_only = LayersManagerBuilder::new().add_layer(RockLayer).build();
+            let moss = LayerId::from_type::<MossLayer>();
+            assert_eq!(
+                rocks_only.try_get_generated_chunks::<MossLayer>(),
+                Err(ChunksError::LayerNotRegistered(moss))
+            );
+            assert_eq!(
+                rocks_only.try_get_deleted_chunks::<MossLayer>(),
+                Err(ChunksError::LayerNotRegistered(moss))
+            );
+            assert!(rocks_only.try_get_chunk::<MossLayer>(Vec2::ZERO).is_err());
+            assert_eq!(
+                rocks_only
+                    .try_get_chunks_in::<MossLayer>(Bounds::from_point(Vec2::ZERO))
+                    .unwrap_err(),
+                ChunksError::LayerNotRegistered(moss)
+            );
+        }
+
+        #[test]
+        fn test_try_rasterize() {
+            let mut manager = LayersManagerBuilder::new().add_layer(RockLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<RockLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let bounds = Bounds::new(Vec2::new(0.0, 0.0), Vec2::new(2.0, 1.0));
+            let resolution = UVec2::new(2, 1);
+            let mut buffer = [false; 2];
+            let sampler = |chunk: Option<&RockChunk>, _| chunk.is_some();
+            assert_eq!(
+                manager.try_rasterize::<RockLayer, _>(&bounds, resolution, &mut buffer, sampler),
+                Ok(())
+            );
+            assert_eq!(buffer, [true, true]);
+
+            let mut long = [false; 3];
+            assert_eq!(
+                manager.try_rasterize::<RockLayer, _>(&bounds, resolution, &mut long, sampler),
+                Err(ChunksError::BufferSizeMismatch {
+                    expected: 2,
+                    actual: 3
+                })
+            );
+            assert_eq!(
+                manager
+                    .try_rasterize::<MossLayer, _>(&bounds, resolution, &mut buffer, |_, _| false)
+                    .unwrap_err(),
+                ChunksError::LayerNotRegistered(LayerId::from_type::<MossLayer>())
+            );
+        }
+
+        #[test]
+        fn test_try_build_missing_dependency() {
+            let error = LayersManagerBuilder::new().add_layer(MossLayer).try_build().err();
+            assert_eq!(
+                error,
+                Some(ChunksError::DependencyNotRegistered {
+                    layer: LayerId::from_type::<MossLayer>(),
+                    dependency: LayerId::from_type::<RockLayer>(),
+                })
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "which is not registered")]
+        fn test_build_missing_dependency() {
+            LayersManagerBuilder::new().add_layer(MossLayer).build();
+        }
+    }
+
+    mod test_strict_dependencies {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct RockChunk;
+
+        struct RockLayer;
+
+        impl Chunk for RockChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for RockLayer {
+            type Chunk = RockChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                RockChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct MossChunk;
+
+        impl Chunk for MossChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        /// Reads the rocks around its chunks up to `reach`, declaring a padding of 1
+        struct MossLayer {
+            reach: f32,
+        }
+
+        impl Layer for MossLayer {
+            type Chunk = MossChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let bounds = chunk_idx.to_bounds(1.0, 1.0).add_padding(Vec2::splat(self.reach));
+                lookup.get_chunks_in::<RockLayer>(bounds);
+                MossChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<RockLayer>(Vec2::new(1.0, 1.0))]
+            }
+        }
+
+        /// Reads the rocks without declaring them
+        struct SandLayer;
+
+        impl Layer for SandLayer {
+            type Chunk = MossChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let pos = chunk_idx.center(Self::Chunk::get_size());
+                lookup.get_chunk::<RockLayer>(LayerId::from_type::<RockLayer>(), pos);
+                MossChunk
+            }
+        }
+
+        fn regenerate<L: Layer + 'static>(layer: L, strict: bool) {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(RockLayer)
+                .add_layer(layer)
+                .strict_dependencies(strict)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<L>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+        }
+
+        #[test]
+        fn test_declared_reads() {
+            regenerate(MossLayer { reach: 1.0 }, true);
+            regenerate(MossLayer { reach: 0.5 }, true);
+        }
+
+        #[test]
+        #[should_panic(expected = "outside its declared padding")]
+        fn test_read_outside_padding() {
+            regenerate(MossLayer { reach: 2.0 }, true);
+        }
+
+        #[test]
+        #[should_panic(expected = "declare it in Layer::get_dependencies")]
+        fn test_undeclared_layer() {
+            regenerate(SandLayer, true);
+        }
+
+        #[test]
+        fn test_not_strict() {
+            regenerate(MossLayer { reach: 2.0 }, false);
+            regenerate(SandLayer, false);
+        }
+    }
+
+    #[cfg(feature = "presets")]
+    mod test_presets {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkLocalPos;
+        use crate::layer::{Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerBundle, LayersManager, LayersManagerBuilder};
+        use crate::presets::{
+            BiomeLayer, BiomePreset, HeightmapLayer, HeightmapPreset, TilemapLayer, TilemapPreset,
+            VoronoiLayer, VoronoiPreset,
+        };
+        use crate::usage::UsageStrategy;
+
+        /// The chunks of `L` around the origin, generated with strict dependencies
+        fn generate<L: Layer + 'static>(bundle: impl LayerBundle, seed: u64) -> LayersManager {
+            let mut manager = LayersManagerBuilder::new()
+                .add_bundle(bundle)
+                .seed(seed)
+                .strict_dependencies(true)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::ZERO,
+                vec![Dependency::new::<L>(Vec2::new(8.0, 8.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(!manager.get_generated_chunks::<L>().is_empty());
+            manager
+        }
+
+        #[test]
+        fn test_presets_generate() {
+            generate::<VoronoiLayer>(VoronoiPreset::default(), 1);
+            generate::<HeightmapLayer>(HeightmapPreset::default(), 1);
+            generate::<BiomeLayer>(BiomePreset::default(), 1);
+            generate::<TilemapLayer>(TilemapPreset::default(), 1);
+        }
+
+        #[test]
+        fn test_heightmap_depends_on_seed() {
+            let pos = Vec2::new(3.5, 3.5);
+            let height = |seed| {
+                let manager = generate::<HeightmapLayer>(HeightmapPreset::default(), seed);
+                let chunk = manager.get_chunk::<HeightmapLayer>(pos).unwrap();
+                chunk.height_at(ChunkLocalPos(pos))
+            };
+            assert_eq!(height(7), height(7));
+            assert_ne!(height(7), height(8));
+        }
+    }
+
+    mod test_required_padding {
+        use bevy::math::Vec2;
+        use crate::bounds::{ChunkIdx, Padding};
+        use crate::diagnostics::RequiredPadding;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct PointChunk;
+
+        impl Chunk for PointChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(5., 5.)
+            }
+        }
+
+        struct PointsLayer;
+
+        impl Layer for PointsLayer {
+            type Chunk = PointChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                PointChunk
+            }
+        }
+
+        struct WindLayer;
+
+        impl Layer for WindLayer {
+            type Chunk = PointChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                PointChunk
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct CellChunk;
+
+        impl Chunk for CellChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        /// Declares a padding of 10 on the points but reads 3 to the left and 2 elsewhere, and
+        /// reads the wind without declaring it
+        struct CellLayer;
+
+        impl Layer for CellLayer {
+            type Chunk = CellChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let bounds = chunk_idx.bounds(Self::Chunk::get_size());
+                let reach = Padding::new(3., 2., 2., 2.);
+                lookup.get_chunks_in::<PointsLayer>(bounds.add_padding(reach));
+                let wind = LayerId::from_type::<WindLayer>();
+                lookup.get_chunk::<WindLayer>(wind, bounds.get_center());
+                CellChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<PointsLayer>(Vec2::new(10., 10.))]
+            }
+        }
+
+        fn manager(record: bool) -> crate::LayersManager {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(PointsLayer)
+                .add_layer(WindLayer)
+                .add_layer(CellLayer)
+                .record_reads(record)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<CellLayer>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            manager
+        }
+
+        #[test]
+        fn test_report_required_padding() {
+            let report = manager(true).report_required_padding();
+            let cells = LayerId::from_type::<CellLayer>();
+            assert_eq!(
+                report,
+                vec![
+                    RequiredPadding {
+                        layer: cells,
+                        dependency: LayerId::from_type::<PointsLayer>(),
+                        declared: Some(Padding::new(10., 10., 10., 10.)),
+                        required: Some(Padding::new(3., 2., 2., 2.)),
+                    },
+                    RequiredPadding {
+                        layer: cells,
+                        dependency: LayerId::from_type::<WindLayer>(),
+                        declared: None,
+                        required: Some(Padding::default()),
+                    },
+                ]
+            );
+            assert!(report[0].is_sufficient());
+            assert!(!report[1].is_sufficient());
+        }
+
+        #[test]
+        fn test_not_recorded() {
+            assert!(manager(false).report_required_padding().is_empty());
+        }
+    }
+
+    mod test_generation_policy {
+        use bevy::app::App;
+        use bevy::ecs::resource::Resource;
+        use bevy::math::Vec2;
+        use bevy::window::Window;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager};
+        use crate::pace::{GenerationPace, GenerationPolicy};
+        use crate::plugin::GenerativeChunksPlugin;
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug)]
+        struct TestChunk;
+
+        impl Chunk for TestChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct TestLayer;
+
+        impl Layer for TestLayer {
+            type Chunk = TestChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TestChunk
+            }
+        }
+
+        #[derive(Resource)]
+        struct OnBattery(bool);
+
+        fn app(policy: GenerationPolicy) -> App {
+            let mut app = App::new();
+            app.add_plugins(
+                GenerativeChunksPlugin::new()
+                    .with_layer(TestLayer)
+                    .with_policy(policy),
+            );
+            app.world_mut()
+                .resource_mut::<LayersManager>()
+                .add_layer_client(LayerClient::new(
+                    Vec2::new(0.5, 0.5),
+                    vec![Dependency::new::<TestLayer>(Vec2::new(1.0, 1.0))],
+                    UsageStrategy::Fast,
+                ));
+            app
+        }
+
+        fn generated(app: &App) -> bool {
+            let manager = app.world().resource::<LayersManager>();
+            manager.get_chunk::<TestLayer>(Vec2::new(0.5, 0.5)).is_some()
+        }
+
+        #[test]
+        fn test_paused_policy() {
+            let mut app = app(GenerationPolicy::new(|world| {
+                match world.resource::<OnBattery>().0 {
+                    true => GenerationPace::Paused,
+                    false => GenerationPace::Full,
+                }
+            }));
+            app.insert_resource(OnBattery(true));
+            app.update();
+            assert!(!generated(&app));
+
+            app.insert_resource(OnBattery(false));
+            app.update();
+            assert!(generated(&app));
+        }
+
+        #[test]
+        fn test_when_unfocused() {
+            let mut app = app(GenerationPolicy::when_unfocused(GenerationPace::Paused));
+            let window = app
+                .world_mut()
+                .spawn(Window {
+                    focused: false,
+                    ..Window::default()
+                })
+                .id();
+            app.update();
+            assert!(!generated(&app));
+
+            app.world_mut().get_mut::<Window>(window).unwrap().focused = true;
+            app.update();
+            assert!(generated(&app));
+        }
+    }
+
+    mod test_coalesce_bounds {
+        use std::collections::HashSet;
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        fn chunks(bounds: &[Bounds]) -> HashSet<ChunkIdx> {
+            bounds.iter().flat_map(|bounds| bounds.chunks(Vec2::ONE)).collect()
+        }
+
+        /// Bounds of the chunks with the padding of a dependency
+        fn required(chunks: impl IntoIterator<Item = (i32, i32)>) -> Vec<Bounds> {
+            chunks
+                .into_iter()
+                .map(|(x, y)| ChunkIdx { x, y }.bounds(Vec2::ONE).add_padding(Vec2::splat(0.5)))
+                .collect()
+        }
+
+        #[test]
+        fn test_block_becomes_one_bounds() {
+            let block = required((0..100).flat_map(|x| (0..100).map(move |y| (x, y))));
+            let merged = Bounds::coalesce(block.clone());
+            assert_eq!(
+                merged,
+                vec![Bounds::new(Vec2::new(-0.5, -0.5), Vec2::new(100.5, 100.5))]
+            );
+            assert_eq!(chunks(&merged), chunks(&block));
+        }
+
+        #[test]
+        fn test_same_chunks() {
+            // An L, a lone chunk and a duplicate
+            let mut shape = required((0..10).map(|x| (x, 0)).chain((1..10).map(|y| (0, y))));
+            shape.extend(required([(20, 20), (20, 20)]));
+            let merged = Bounds::coalesce(shape.clone());
+            assert!(merged.len() < shape.len());
+            assert_eq!(chunks(&merged), chunks(&shape));
+            // Bounds with a gap between them stay apart
+            let apart = required([(0, 0), (5, 0)]);
+            assert_eq!(Bounds::coalesce(apart.clone()), apart);
+        }
+
+        #[derive(Debug)]
+        struct TestChunk;
+
+        impl Chunk for TestChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct BaseLayer;
+
+        impl Layer for BaseLayer {
+            type Chunk = TestChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TestChunk
+            }
+        }
+
+        struct TopLayer;
+
+        impl Layer for TopLayer {
+            type Chunk = TestChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TestChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<BaseLayer>(Vec2::new(2.0, 1.0))]
+            }
+        }
+
+        #[test]
+        fn test_requirements_cover_the_padding() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(BaseLayer)
+                .add_layer(TopLayer)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<TopLayer>(Vec2::new(3.0, 3.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let top = manager.get_generated_chunks::<TopLayer>().len();
+            let base = manager.get_generated_chunks::<BaseLayer>().len();
+            // The top chunks -3..=4, the base ones with 2 more chunks on each side on x, 1 on y,
+            // and the chunks touching the edge of the padding
+            assert_eq!(top, 8 * 8);
+            assert_eq!(base, 13 * 11);
+        }
+    }
+
+    mod test_memory_pressure {
+        use bevy::app::App;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::events::MemoryPressure;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::persistence::{ChunkLoadError, ChunkVersioned, SavedChunk};
+        use crate::plugin::GenerativeChunksPlugin;
+        use crate::usage::{GracePeriod, UsageStrategy};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct TreeChunk(u8);
+
+        impl Chunk for TreeChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl ChunkVersioned for TreeChunk {
+            const SCHEMA_VERSION: u32 = 1;
+
+            fn encode(&self) -> Vec<u8> {
+                vec![self.0]
+            }
+
+            fn decode(data: &[u8]) -> Result<Self, ChunkLoadError> {
+                Ok(TreeChunk(data[0]))
+            }
+        }
+
+        struct TreeLayer;
+
+        impl Layer for TreeLayer {
+            type Chunk = TreeChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                TreeChunk(0)
+            }
+
+            fn grace_period(&self) -> Option<GracePeriod> {
+                Some(GracePeriod::Frames(100))
+            }
+        }
+
+        fn client(x: f32) -> LayerClient {
+            LayerClient::new(
+                Vec2::new(x, 0.5),
+                vec![Dependency::new::<TreeLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            )
+        }
+
+        #[test]
+        fn test_relieve_memory_pressure() {
+            let mut manager = LayersManagerBuilder::new().add_layer(TreeLayer).build();
+            let id = manager.add_layer_client(client(0.5));
+            manager.regenerate();
+            // An edited chunk nobody uses
+            let edited = ChunkIdx { x: 10, y: 10 };
+            let saved = SavedChunk::save(&TreeChunk(7));
+            manager.load_chunk::<TreeLayer>(edited, &saved).unwrap();
+            manager.update_client_center(id, Vec2::new(5.5, 0.5));
+            manager.regenerate();
+            // Waiting out its grace period
+            assert!(manager.get_chunk::<TreeLayer>(Vec2::new(0.5, 0.5)).is_some());
+
+            assert_eq!(manager.relieve_memory_pressure(), 1);
+            assert!(manager.get_chunk::<TreeLayer>(Vec2::new(0.5, 0.5)).is_none());
+            assert!(manager.get_chunk::<TreeLayer>(Vec2::new(5.5, 0.5)).is_some());
+            assert_eq!(
+                manager.get_chunk::<TreeLayer>(Vec2::new(10.5, 10.5)),
+                Some(TreeChunk(7))
+            );
+            assert_eq!(manager.get_deleted_chunks::<TreeLayer>(), &vec![ChunkIdx { x: 0, y: 0 }]);
+            assert_eq!(manager.relieve_memory_pressure(), 0);
+        }
+
+        #[test]
+        fn test_memory_pressure_event() {
+            let mut app = App::new();
+            app.add_plugins(GenerativeChunksPlugin::new().with_layer(TreeLayer));
+            let id = app
+                .world_mut()
+                .resource_mut::<LayersManager>()
+                .add_layer_client(client(0.5));
+            app.update();
+            app.world_mut()
+                .resource_mut::<LayersManager>()
+                .update_client_center(id, Vec2::new(5.5, 0.5));
+            app.update();
+            let stored = |app: &App| {
+                let manager = app.world().resource::<LayersManager>();
+                manager.get_chunk::<TreeLayer>(Vec2::new(0.5, 0.5)).is_some()
+            };
+            assert!(stored(&app));
+
+            app.world_mut().send_event(MemoryPressure);
+            app.update();
+            assert!(!stored(&app));
+        }
+    }
+
+    mod test_read_histograms {
+        use bevy::math::Vec2;
+        use crate::bounds::{ChunkIdx, Padding};
+        use crate::diagnostics::ReadHistogram;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct CellChunk;
+
+        impl Chunk for CellChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        struct GroundLayer;
+
+        impl Layer for GroundLayer {
+            type Chunk = CellChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                CellChunk
+            }
+        }
+
+        /// Declares a padding of 3 but reads its own chunk 18 times out of 20, and 1 and 2
+        /// chunks to the right once each
+        struct GrassLayer;
+
+        impl Layer for GrassLayer {
+            type Chunk = CellChunk;
+
+            fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let ground = LayerId::from_type::<GroundLayer>();
+                let center = chunk_idx.bounds(Self::Chunk::get_size()).get_center();
+                for _ in 0..18 {
+                    lookup.get_chunk::<GroundLayer>(ground, center);
+                }
+                lookup.get_chunk::<GroundLayer>(ground, center + Vec2::new(1., 0.));
+                lookup.get_chunk::<GroundLayer>(ground, center + Vec2::new(2., 0.));
+                CellChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<GroundLayer>(Vec2::new(3., 3.))]
+            }
+        }
+
+        fn manager(record: bool) -> LayersManager {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(GroundLayer)
+                .add_layer(GrassLayer)
+                .record_reads(record)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<GrassLayer>(Vec2::new(2.0, 2.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            manager
+        }
+
+        #[test]
+        fn test_padding_reach() {
+            let size = Vec2::new(2., 1.);
+            assert_eq!(Padding::default().reach(size), 0);
+            assert_eq!(Padding::new(3., 0., 0., 0.).reach(size), 2);
+            assert_eq!(Padding::new(0., 4., 0., 0.).reach(size), 2);
+            assert_eq!(Padding::new(0., 0., 0., 2.5).reach(size), 3);
+        }
+
+        #[test]
+        fn test_read_histogram() {
+            let mut histogram = ReadHistogram::default();
+            assert_eq!(histogram.reach_covering(0.95), None);
+            assert_eq!(histogram.max_reach(), None);
+            for reach in [Some(0), Some(0), Some(0), Some(3), None] {
+                histogram.record(reach);
+            }
+            assert_eq!(histogram.buckets, vec![3, 0, 0, 1]);
+            assert_eq!(histogram.unbounded, 1);
+            assert_eq!(histogram.total(), 4);
+            assert_eq!(histogram.fraction_within(2), 0.75);
+            assert_eq!(histogram.reach_covering(0.75), Some(0));
+            assert_eq!(histogram.reach_covering(0.8), Some(3));
+            assert_eq!(histogram.max_reach(), Some(3));
+        }
+
+        #[test]
+        fn test_report_read_histograms() {
+            let report = manager(true).report_read_histograms();
+            assert_eq!(report.len(), 1);
+            let grass = &report[0];
+            assert_eq!(grass.layer, LayerId::from_type::<GrassLayer>());
+            assert_eq!(grass.dependency, LayerId::from_type::<GroundLayer>());
+            assert_eq!(grass.declared_reach, Some(3));
+            let chunks = grass.reads.total() / 20;
+            assert!(chunks > 0);
+            assert_eq!(grass.reads.buckets, vec![18 * chunks, chunks, chunks]);
+            assert_eq!(grass.reads.fraction_within(0), 0.9);
+            assert_eq!(grass.reads.reach_covering(0.95), Some(1));
+            assert!(grass
+                .to_string()
+                .ends_with("within 1 chunks of the border, all within 2, declared 3"));
+        }
+
+        #[test]
+        fn test_read_histograms_not_recorded() {
+            assert!(manager(false).report_read_histograms().is_empty());
+        }
+    }
+
+    #[cfg(feature = "remote-debug")]
+    mod test_remote_debug {
+        use std::io::{ErrorKind, Read, Write};
+        use std::net::TcpStream;
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::remote_debug::{adler32, crc32, RemoteDebugServer, MAX_QUEUED, WORKERS};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        struct UnitLayer;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for UnitLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _lookup: &LayerLookupChunk, _chunk_idx: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        /// A server publishing a manager with 4 generated chunks
+        fn server() -> RemoteDebugServer {
+            let mut manager = LayersManagerBuilder::new().add_layer(UnitLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<UnitLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let server = RemoteDebugServer::start("127.0.0.1:0").unwrap();
+            server.publish(&manager);
+            server
+        }
+
+        /// Status line and body of the response
+        fn get(server: &RemoteDebugServer, path: &str) -> (String, Vec<u8>) {
+            let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\n\r\n", path).unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            let end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+            let head = String::from_utf8(response[..end].to_vec()).unwrap();
+            let status = head.lines().next().unwrap().to_string();
+            (status, response[end + 4..].to_vec())
+        }
+
+        fn be_u32(bytes: &[u8]) -> u32 {
+            u32::from_be_bytes(bytes[..4].try_into().unwrap())
+        }
+
+        /// Width, height and RGB pixels of a PNG of stored deflate blocks
+        fn decode_png(png: &[u8]) -> (u32, u32, Vec<u8>) {
+            assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+            let (mut rest, mut header, mut zlib) = (&png[8..], Vec::new(), Vec::new());
+            while !rest.is_empty() {
+                let len = be_u32(rest) as usize;
+                let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+                assert_eq!(be_u32(&rest[8 + len..]), crc32(&rest[4..8 + len]));
+                match kind {
+                    b"IHDR" => header = data.to_vec(),
+                    b"IDAT" => zlib.extend_from_slice(data),
+                    _ => {}
+                }
+                rest = &rest[12 + len..];
+            }
+            assert_eq!(&zlib[..2], &[0x78, 0x01]);
+            let (mut blocks, mut raw) = (&zlib[2..], Vec::new());
+            loop {
+                let last = blocks[0] == 1;
+                let len = u16::from_le_bytes([blocks[1], blocks[2]]);
+                assert_eq!(!len, u16::from_le_bytes([blocks[3], blocks[4]]));
+                raw.extend_from_slice(&blocks[5..5 + len as usize]);
+                blocks = &blocks[5 + len as usize..];
+                if last {
+                    break;
+                }
+            }
+            assert_eq!(be_u32(blocks), adler32(&raw));
+            let (width, height) = (be_u32(&header), be_u32(&header[4..]));
+            let rows = raw.chunks(width as usize * 3 + 1);
+            assert!(rows.clone().all(|row| row[0] == 0));
+            (width, height, rows.flat_map(|row| row[1..].to_vec()).collect())
+        }
+
+        #[test]
+        fn test_checksums() {
+            assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+            assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        }
+
+        #[test]
+        fn test_layer_png() {
+            let (status, png) = get(&server(), "/layers/0.png");
+            assert_eq!(status, "HTTP/1.1 200 OK");
+            let (width, height, pixels) = decode_png(&png);
+            assert_eq!((width, height), (2, 2));
+            assert_eq!(pixels, [60u8, 180, 90].repeat(4));
+        }
+
+        #[test]
+        fn test_routes() {
+            let server = server();
+            let (status, stats) = get(&server, "/stats");
+            assert_eq!(status, "HTTP/1.1 200 OK");
+            assert!(String::from_utf8(stats).unwrap().starts_with("{\"generated\":4,"));
+            let (_, layers) = get(&server, "/layers");
+            assert!(String::from_utf8(layers).unwrap().contains("\"generated\":4,\"pending\":0"));
+            assert_eq!(get(&server, "/layers/1.png").0, "HTTP/1.1 404 Not Found");
+            assert_eq!(get(&server, "/nothing").0, "HTTP/1.1 404 Not Found");
+        }
+
+        #[test]
+        fn test_idle_connection_blocks_nothing() {
+            let mut manager = LayersManagerBuilder::new().add_layer(UnitLayer).build();
+            let server = server();
+            let _idle = TcpStream::connect(server.local_addr()).unwrap();
+            // Publishing and serving go on while the idle connection waits
+            manager.regenerate();
+            server.publish(&manager);
+            let (_, stats) = get(&server, "/stats");
+            assert!(String::from_utf8(stats).unwrap().starts_with("{\"generated\":0,"));
+        }
+
+        #[test]
+        fn test_connections_past_the_queue_are_closed() {
+            let server = server();
+            // Every thread busy on an idle connection and the queue full
+            let idle: Vec<TcpStream> = (0..WORKERS + MAX_QUEUED)
+                .map(|_| TcpStream::connect(server.local_addr()).unwrap())
+                .collect();
+            let mut extra = TcpStream::connect(server.local_addr()).unwrap();
+            extra.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+            // Closed without waiting for a thread, a reset or the end of the stream
+            let mut buffer = [0; 16];
+            match extra.read(&mut buffer) {
+                Ok(read) => assert_eq!(read, 0),
+                Err(error) => assert_eq!(error.kind(), ErrorKind::ConnectionReset),
+            }
+            drop(idle);
+        }
+    }
+
+    mod test_requirement_passes {
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_id::LayerId;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        /// A hop of a route, pointing at the next hop to the right
+        #[derive(Debug, Clone)]
+        struct HopChunk {
+            next: Option<i32>,
+        }
+
+        impl Chunk for HopChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        /// The route goes through the hops 0 to 3
+        struct HopLayer;
+
+        impl Layer for HopLayer {
+            type Chunk = HopChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                HopChunk {
+                    next: (chunk_idx.x < 3).then_some(chunk_idx.x + 1),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        struct RouteChunk;
+
+        impl Chunk for RouteChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        /// Needs every hop of the route, each hop is only known once the previous one is
+        /// generated
+        struct RouteLayer;
+
+        impl Layer for RouteLayer {
+            type Chunk = RouteChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                RouteChunk
+            }
+
+            fn get_dependencies(&self) -> Vec<Dependency> {
+                vec![Dependency::new::<HopLayer>(Vec2::ZERO)]
+            }
+
+            fn dependency_bounds(
+                &self,
+                lookup: &LayerLookupChunk,
+                _: &ChunkIdx,
+            ) -> Vec<(LayerId, Bounds)> {
+                let hops = LayerId::from_type::<HopLayer>();
+                let mut last = 0;
+                while let Some(next) = lookup
+                    .get_chunk::<HopLayer>(hops, Vec2::new(last as f32 + 0.5, 0.5))
+                    .and_then(|hop| hop.next)
+                {
+                    last = next;
+                }
+                // The hop chunks 0 to `last` of the first row
+                vec![(hops, Bounds::new(Vec2::ZERO, Vec2::new(last as f32, 0.0)))]
+            }
+        }
+
+        fn manager(passes: usize) -> LayersManager {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(HopLayer)
+                .add_layer(RouteLayer)
+                .requirement_passes(passes)
+                .build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<RouteLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager
+        }
+
+        fn has_hop(manager: &LayersManager, x: i32) -> bool {
+            manager
+                .get_chunk::<HopLayer>(Vec2::new(x as f32 + 0.5, 0.5))
+                .is_some()
+        }
+
+        fn stores_hop(manager: &LayersManager, x: i32) -> bool {
+            let layer = manager.read_layer(LayerId::from_type::<HopLayer>()).unwrap();
+            layer.get_storage().contains_key(&ChunkIdx { x, y: 0 })
+        }
+
+        #[test]
+        fn test_requirements_converge() {
+            let mut manager = manager(8);
+            manager.regenerate();
+            // One pass per hop, the fifth finds nothing new
+            assert_eq!(manager.get_requirement_passes(), 4);
+            assert!((0..=3).all(|x| has_hop(&manager, x)));
+            assert!(!stores_hop(&manager, 4));
+        }
+
+        #[test]
+        fn test_requirement_passes_run_out() {
+            let mut manager = manager(2);
+            manager.regenerate();
+            assert_eq!(manager.get_requirement_passes(), 2);
+            assert!(has_hop(&manager, 1));
+            // Counting the missing hop doesn't create it
+            assert!(!stores_hop(&manager, 2));
+
+            // The next regenerate picks up where the last one stopped
+            manager.regenerate();
+            assert!(has_hop(&manager, 3));
+        }
+
+        #[test]
+        fn test_later_pass_keeps_an_existing_chunk() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(HopLayer)
+                .add_layer(RouteLayer)
+                .requirement_passes(8)
+                .build();
+            // The last hop is generated for another client first
+            let other = manager.add_layer_client(LayerClient::new(
+                Vec2::new(3.5, 0.5),
+                vec![Dependency::new::<HopLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert!(has_hop(&manager, 3));
+
+            // Only the fourth pass of the route requires it again
+            manager.remove_client(other);
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<RouteLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            assert_eq!(manager.get_requirement_passes(), 4);
+            assert!(has_hop(&manager, 3));
+            let hop = ChunkIdx { x: 3, y: 0 };
+            assert!(!manager.get_generated_chunks::<HopLayer>().contains(&hop));
+            assert!(!manager.get_deleted_chunks::<HopLayer>().contains(&hop));
+            assert!(manager.get_deleted_chunks::<HopLayer>().contains(&ChunkIdx { x: 4, y: 0 }));
+
+            // Still required, so kept on the next regenerates
+            manager.regenerate();
+            assert!(has_hop(&manager, 3));
+        }
+    }
+
+    mod test_usage_decay {
+        use bevy::math::Vec2;
+        use crate::bounds::ChunkIdx;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::{UsageCounter, UsageDecay, UsageStrategy};
+
+        #[derive(Debug, Clone)]
+        struct UnitChunk;
+
+        struct UnitLayer;
+
+        impl Chunk for UnitChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for UnitLayer {
+            type Chunk = UnitChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, _: &ChunkIdx) -> Self::Chunk {
+                UnitChunk
+            }
+        }
+
+        fn requested(frame: u64, strategies: &[UsageStrategy]) -> UsageCounter {
+            let mut counter = UsageCounter::new();
+            counter.stamp(frame);
+            for strategy in strategies {
+                counter.increment(*strategy);
+            }
+            counter
+        }
+
+        #[test]
+        fn test_usage_expires() {
+            let decay = UsageDecay {
+                fast: 2,
+                ..UsageDecay::default()
+            };
+            let counter = requested(1, &[UsageStrategy::Fast]);
+            assert_eq!(counter.best_usage_at(1, &decay), Some(UsageStrategy::Fast));
+            assert_eq!(counter.best_usage_at(3, &decay), Some(UsageStrategy::Fast));
+            assert_eq!(counter.best_usage_at(4, &decay), None);
+            // Without decay the usage only lasts the frame it was requested
+            assert_eq!(counter.best_usage_at(2, &UsageDecay::default()), None);
+        }
+
+        #[test]
+        fn test_request_within_window() {
+            let decay = UsageDecay {
+                fast: 2,
+                ..UsageDecay::default()
+            };
+            let mut counter = requested(1, &[UsageStrategy::Fast]);
+            counter.stamp(3);
+            assert_eq!(counter.get_count_at(3, UsageStrategy::Fast), 0);
+            counter.increment(UsageStrategy::Fast);
+            // The window restarts at the last request
+            assert_eq!(counter.best_usage_at(5, &decay), Some(UsageStrategy::Fast));
+            assert_eq!(counter.best_usage_at(6, &decay), None);
+        }
+
+        #[test]
+        fn test_strategy_precedence() {
+            let decay = UsageDecay {
+                keep_alive: 10,
+                slow: 2,
+                fast: 0,
+            };
+            let counter = requested(
+                1,
+                &[UsageStrategy::KeepAlive, UsageStrategy::Slow, UsageStrategy::Fast],
+            );
+            assert_eq!(counter.best_usage(), Some(UsageStrategy::Fast));
+            assert_eq!(counter.best_usage_at(1, &decay), Some(UsageStrategy::Fast));
+            // Each strategy decays on its own, the best one still active wins
+            assert_eq!(counter.best_usage_at(2, &decay), Some(UsageStrategy::Slow));
+            assert_eq!(counter.best_usage_at(4, &decay), Some(UsageStrategy::KeepAlive));
+            assert_eq!(counter.best_usage_at(12, &decay), None);
+        }
+
+        #[test]
+        fn test_chunks_outlive_their_requests() {
+            let mut manager = LayersManagerBuilder::new()
+                .add_layer(UnitLayer)
+                .usage_decay(UsageDecay {
+                    fast: 2,
+                    ..UsageDecay::default()
+                })
+                .build();
+            let client = manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<UnitLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+            let origin = Vec2::new(0.5, 0.5);
+
+            // Requested again within the window, nothing is generated again
+            manager.update_client_center(client, Vec2::new(10.5, 0.5));
+            manager.regenerate();
+            manager.update_client_center(client, origin);
+            manager.regenerate();
+            assert!(!manager
+                .get_generated_chunks::<UnitLayer>()
+                .contains(&ChunkIdx { x: 0, y: 0 }));
+
+            manager.update_client_center(client, Vec2::new(10.5, 0.5));
+            manager.regenerate();
+            manager.regenerate();
+            assert!(manager.get_chunk::<UnitLayer>(origin).is_some());
+            manager.regenerate();
+            assert!(manager.get_chunk::<UnitLayer>(origin).is_none());
+        }
+    }
+
+    mod test_find_chunks {
+        use std::cell::Cell;
+        use std::time::Duration;
+        use bevy::math::Vec2;
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::layer::{Chunk, Dependency, GenerationLane, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManager, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct ValueChunk(i32);
+
+        struct ValueLayer;
+
+        impl Chunk for ValueChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl Layer for ValueLayer {
+            type Chunk = ValueChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                ValueChunk(chunk_idx.x)
+            }
+
+            fn lane(&self) -> GenerationLane {
+                GenerationLane::Async
+            }
+        }
+
+        fn manager() -> LayersManager {
+            let mut manager = LayersManagerBuilder::new().add_layer(ValueLayer).build();
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.5, 0.5),
+                vec![Dependency::new::<ValueLayer>(Vec2::ZERO)],
+                UsageStrategy::Fast,
+            ));
+            manager
+        }
+
+        /// The 4 chunks the client requests
+        fn bounds() -> Bounds {
+            Bounds::new(Vec2::new(0.25, 0.25), Vec2::new(0.75, 0.75))
+        }
+
+        #[test]
+        fn test_find_matching_chunks() {
+            let mut manager = manager();
+            for _ in 0..1000 {
+                manager.regenerate();
+                if manager.is_region_ready::<ValueLayer>(&bounds()) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            let mut found = manager.find_chunks::<ValueLayer>(bounds(), |chunk| chunk.0 == 1);
+            found.sort_by_key(|(chunk_idx, _)| chunk_idx.y);
+            assert_eq!(
+                found,
+                vec![
+                    (ChunkIdx { x: 1, y: 0 }, ValueChunk(1)),
+                    (ChunkIdx { x: 1, y: 1 }, ValueChunk(1)),
+                ]
+            );
+            assert!(manager.any_chunk::<ValueLayer>(bounds(), |chunk| chunk.0 == 1));
+
+            assert!(manager.find_chunks::<ValueLayer>(bounds(), |chunk| chunk.0 == 5).is_empty());
+            assert!(!manager.any_chunk::<ValueLayer>(bounds(), |chunk| chunk.0 == 5));
+        }
+
+        #[test]
+        fn test_pending_chunks_are_skipped() {
+            let mut manager = manager();
+            // The chunks are requested, but generating on the async pool
+            manager.regenerate();
+            let checked = Cell::new(0);
+            let predicate = |_: &ValueChunk| {
+                checked.set(checked.get() + 1);
+                true
+            };
+            assert!(manager.find_chunks::<ValueLayer>(bounds(), predicate).is_empty());
+            assert!(!manager.any_chunk::<ValueLayer>(bounds(), predicate));
+            assert_eq!(checked.get(), 0);
+        }
+    }
+
+    #[cfg(feature = "ndarray")]
+    mod test_extract_region {
+        use bevy::math::{UVec2, Vec2};
+        use crate::bounds::{Bounds, ChunkIdx};
+        use crate::grid::GridChunk;
+        use crate::layer::{Chunk, Dependency, Layer};
+        use crate::layer_client::LayerClient;
+        use crate::layer_manager::{LayerLookupChunk, LayersManagerBuilder};
+        use crate::usage::UsageStrategy;
+
+        /// 2x2 cells, each holding its global cell coordinates
+        #[derive(Debug, Clone)]
+        struct CellsChunk {
+            cells: Vec<Option<(i32, i32)>>,
+        }
+
+        struct CellsLayer;
+
+        impl Chunk for CellsChunk {
+            fn get_size() -> Vec2 {
+                Vec2::new(1., 1.)
+            }
+        }
+
+        impl GridChunk for CellsChunk {
+            type Cell = Option<(i32, i32)>;
+
+            fn resolution() -> UVec2 {
+                UVec2::new(2, 2)
+            }
+
+            fn cell(&self, x: usize, y: usize) -> &Self::Cell {
+                &self.cells[y * 2 + x]
+            }
+        }
+
+        impl Layer for CellsLayer {
+            type Chunk = CellsChunk;
+
+            fn generate(&self, _: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+                let cells = (0..4)
+                    .map(|i| Some((chunk_idx.x * 2 + i % 2, chunk_idx.y * 2 + i / 2)))
+                    .collect();
+                CellsChunk { cells }
+            }
+        }
+
+        #[test]
+        fn test_extract_across_chunks() {
+            let mut manager = LayersManagerBuilder::new().add_layer(CellsLayer).build();
+            // Generates the chunks -1 to 1 on both axes
+            manager.add_layer_client(LayerClient::new(
+                Vec2::new(0.0, 0.0),
+                vec![Dependency::new::<CellsLayer>(Vec2::new(1.0, 1.0))],
+                UsageStrategy::Fast,
+            ));
+            manager.regenerate();
+
+            // Chunks -1 to 2 on x, -1 to 1 on y, the column x = 2 is not generated
+            let bounds = Bounds::new(Vec2::new(-0.5, -0.5), Vec2::new(1.5, 0.5));
+            let region = manager.extract_region::<CellsLayer>(&bounds);
+            assert_eq!(region.dim(), (6, 8));
+            for ((row, column), cell) in region.indexed_iter() {
+                let expected = (column as i32 - 2, row as i32 - 2);
+                if column < 6 {
+                    assert_eq!(*cell, Some(expected), "cell {:?}", (row, column));
+                } else {
+                    assert_eq!(*cell, None, "cell {:?}", (row, column));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Log targets of each subsystem, so integrators can silence or route the crate output,
+//! e.g. `RUST_LOG=bevy_generative_chunks::eviction=off` or the filter of Bevy's `LogPlugin`
+
+/// Client interest updates
+pub const CLIENTS: &str = "bevy_generative_chunks::clients";
+/// Propagation of the requirements between layers
+pub const REQUIREMENTS: &str = "bevy_generative_chunks::requirements";
+/// Chunk generation and its budgets
+pub const GENERATION: &str = "bevy_generative_chunks::generation";
+/// Removal of unused chunks
+pub const EVICTION: &str = "bevy_generative_chunks::eviction";
+/// Chunks under the cursor, see [`ChunkProbePlugin`](crate::debug_overlay::ChunkProbePlugin)
+pub const PROBE: &str = "bevy_generative_chunks::probe";
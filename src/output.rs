@@ -0,0 +1,57 @@
+//! Layers emitting more than one chunk type, each extra type on its own grid
+//!
+//! ```ignore
+//! // Towns are generated on a coarse grid, their roads are also exposed as fine tiles
+//! impl LayerOutput<RoadTile> for SettlementLayer { ... }
+//!
+//! LayersManagerBuilder::new()
+//!     .add_layer(SettlementLayer)
+//!     .add_output::<SettlementLayer, RoadTile>()
+//!     .build();
+//! manager.get_chunk::<Output<SettlementLayer, RoadTile>>(pos);
+//! ```
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+use crate::bounds::{ChunkIdx, Padding};
+use crate::layer::{Chunk, Dependency, Layer};
+use crate::layer_manager::LayerLookupChunk;
+
+/// A second chunk type emitted by a layer, built from the chunks of the layer
+pub trait LayerOutput<C: Chunk>: Layer + Send + Sync + Sized + 'static {
+    /// Padding around an output chunk of the layer chunks it is built from
+    fn output_padding(&self) -> Padding {
+        Padding::default()
+    }
+
+    /// Build the output chunk, `lookup` sees the chunks of the layer
+    fn output(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> C;
+}
+
+/// The layer holding the `C` chunks of the layer `L`, registered with
+/// [`LayersManagerBuilder::add_output`](crate::layer_manager::LayersManagerBuilder::add_output)
+pub struct Output<L, C> {
+    layer: Arc<L>,
+    _chunk: PhantomData<fn() -> C>,
+}
+
+impl<L, C> Output<L, C> {
+    pub(crate) fn new(layer: Arc<L>) -> Self {
+        Output {
+            layer,
+            _chunk: PhantomData,
+        }
+    }
+}
+
+impl<L: LayerOutput<C>, C: Chunk> Layer for Output<L, C> {
+    type Chunk = C;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        self.layer.output(lookup, chunk_idx)
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![Dependency::new::<L>(self.layer.output_padding())]
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+use bevy::ecs::resource::Resource;
+use bevy::ecs::world::World;
+use bevy::window::Window;
+
+/// How much generating a frame does, decided by the [`GenerationPolicy`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GenerationPace {
+    /// Everything the clients need
+    #[default]
+    Full,
+    /// The chunks fitting in the duration, see
+    /// [`LayersManager::regenerate_with_budget`](crate::LayersManager::regenerate_with_budget)
+    Budget(Duration),
+    /// Nothing, the manager is not regenerated and the requested chunks wait
+    Paused,
+}
+
+/// Consulted every frame by the [`GenerativeChunksPlugin`](crate::plugin::GenerativeChunksPlugin)
+/// before it regenerates, to slow down or pause the generation, e.g. while the window is
+/// unfocused or the laptop runs on battery. Generates at full pace when missing
+///
+/// ```ignore
+/// app.insert_resource(GenerationPolicy::new(|world| match world.resource::<Battery>().low {
+///     true => GenerationPace::Budget(Duration::from_millis(2)),
+///     false => GenerationPace::Full,
+/// }));
+/// ```
+#[derive(Resource)]
+pub struct GenerationPolicy {
+    decide: Box<dyn Fn(&World) -> GenerationPace + Send + Sync>,
+}
+
+impl GenerationPolicy {
+    pub fn new(decide: impl Fn(&World) -> GenerationPace + Send + Sync + 'static) -> Self {
+        GenerationPolicy {
+            decide: Box::new(decide),
+        }
+    }
+
+    /// Generate at `pace` while no window is focused, at full pace otherwise
+    pub fn when_unfocused(pace: GenerationPace) -> Self {
+        GenerationPolicy::new(move |world| {
+            let focused = world
+                .try_query::<&Window>()
+                .is_some_and(|mut windows| windows.iter(world).any(|window| window.focused));
+            if focused {
+                GenerationPace::Full
+            } else {
+                pace
+            }
+        })
+    }
+
+    pub fn get_pace(&self, world: &World) -> GenerationPace {
+        (self.decide)(world)
+    }
+}
@@ -0,0 +1,97 @@
+//! Saving and loading chunk data across game versions
+
+use serde::{Deserialize, Serialize};
+use crate::layer::Chunk;
+
+/// Chunk data that can be written to a save, tagged with its schema version so saves from
+/// older game versions are upgraded on load
+pub trait ChunkVersioned: Chunk + Sized {
+    /// Bump it every time the encoding of the chunk changes
+    const SCHEMA_VERSION: u32;
+
+    fn encode(&self) -> Vec<u8>;
+
+    /// Read data encoded with the current [`Self::SCHEMA_VERSION`]
+    fn decode(data: &[u8]) -> Result<Self, ChunkLoadError>;
+
+    /// Upgrade data encoded with an older schema version, refused by default
+    fn migrate(from_version: u32, _data: &[u8]) -> Result<Self, ChunkLoadError> {
+        Err(ChunkLoadError::Unsupported {
+            found: from_version,
+        })
+    }
+}
+
+/// A chunk as stored in a save
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedChunk {
+    pub schema_version: u32,
+    /// [`Layer::version`](crate::layer::Layer::version) of the layer that generated the chunk
+    #[serde(default)]
+    pub layer_version: u32,
+    pub data: Vec<u8>,
+}
+
+impl SavedChunk {
+    pub fn save<T: ChunkVersioned>(chunk: &T) -> Self {
+        SavedChunk {
+            schema_version: T::SCHEMA_VERSION,
+            layer_version: 0,
+            data: chunk.encode(),
+        }
+    }
+
+    pub fn with_layer_version(mut self, layer_version: u32) -> Self {
+        self.layer_version = layer_version;
+        self
+    }
+
+    /// Decode the chunk, migrating it first if it was saved with an older schema
+    pub fn load<T: ChunkVersioned>(&self) -> Result<T, ChunkLoadError> {
+        if self.schema_version == T::SCHEMA_VERSION {
+            T::decode(&self.data)
+        } else if self.schema_version < T::SCHEMA_VERSION {
+            T::migrate(self.schema_version, &self.data)
+        } else {
+            Err(ChunkLoadError::Newer {
+                found: self.schema_version,
+                current: T::SCHEMA_VERSION,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkLoadError {
+    /// The chunk can't migrate data from this schema version
+    Unsupported { found: u32 },
+    /// The save comes from a newer game version
+    Newer { found: u32, current: u32 },
+    /// The data doesn't match its schema version
+    Corrupt(String),
+    /// The chunk was generated by another version of the layer, it must be regenerated
+    StaleLayer { found: u32, current: u32 },
+}
+
+impl std::fmt::Display for ChunkLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkLoadError::Unsupported { found } => {
+                write!(f, "no migration from chunk schema version {}", found)
+            }
+            ChunkLoadError::Newer { found, current } => write!(
+                f,
+                "chunk schema version {} is newer than the current version {}",
+                found, current
+            ),
+            ChunkLoadError::Corrupt(reason) => write!(f, "corrupt chunk data: {}", reason),
+            ChunkLoadError::StaleLayer { found, current } => write!(
+                f,
+                "chunk generated by layer version {}, the layer is at version {}",
+                found, current
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChunkLoadError {}
@@ -0,0 +1,155 @@
+use std::sync::Mutex;
+use bevy::app::{App, Plugin, Update};
+use bevy::ecs::schedule::{InternedScheduleLabel, IntoScheduleConfigs, ScheduleLabel, SystemSet};
+use bevy::ecs::event::EventReader;
+use bevy::ecs::system::ResMut;
+use bevy::ecs::world::{Mut, World};
+use bevy::time::Time;
+use crate::chunk_loader::sync_chunk_loaders;
+use crate::events::MemoryPressure;
+use crate::layer::IntoLayerConfig;
+use crate::layer_manager::{LayerBundle, LayersManager, LayersManagerBuilder};
+use crate::pace::{GenerationPace, GenerationPolicy};
+use crate::world_scale::WorldScale;
+
+/// Sets of the systems added by [`GenerativeChunksPlugin`], order the systems reading the
+/// chunks `.after(GenerativeChunksSet::Regenerate)`
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GenerativeChunksSet {
+    /// Syncs the [`ChunkLoader`](crate::chunk_loader::ChunkLoader) clients, captures the
+    /// resources the layers read, advances the clock and regenerates
+    Regenerate,
+}
+
+/// Builds the [`LayersManager`] resource and regenerates it once per frame, in `Update`
+/// unless [`GenerativeChunksPlugin::in_schedule`] says otherwise
+///
+/// ```ignore
+/// app.add_plugins(GenerativeChunksPlugin::new().with_layer(PointsLayer).with_layer(VoronoiLayer))
+///     .add_systems(Update, draw.after(GenerativeChunksSet::Regenerate));
+/// ```
+pub struct GenerativeChunksPlugin {
+    /// Taken when the plugin is built, plugins are only built through a shared reference
+    builder: Mutex<Option<LayersManagerBuilder>>,
+    schedule: InternedScheduleLabel,
+    world_scale: Option<WorldScale>,
+    /// Taken when the plugin is built, like the builder
+    policy: Mutex<Option<GenerationPolicy>>,
+}
+
+impl Default for GenerativeChunksPlugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenerativeChunksPlugin {
+    pub fn new() -> Self {
+        GenerativeChunksPlugin {
+            builder: Mutex::new(Some(LayersManagerBuilder::new())),
+            schedule: Update.intern(),
+            world_scale: None,
+            policy: Mutex::new(None),
+        }
+    }
+
+    pub fn with_layer(self, layer: impl IntoLayerConfig) -> Self {
+        self.with_manager(|builder| builder.add_layer(layer))
+    }
+
+    pub fn with_bundle(self, bundle: impl LayerBundle) -> Self {
+        self.with_manager(|builder| builder.add_bundle(bundle))
+    }
+
+    /// Configure the manager beyond its layers, e.g. `.with_manager(|b| b.seed(42))`
+    pub fn with_manager(
+        self,
+        configure: impl FnOnce(LayersManagerBuilder) -> LayersManagerBuilder,
+    ) -> Self {
+        let mut builder = self.builder.lock().unwrap();
+        *builder = builder.take().map(configure);
+        drop(builder);
+        self
+    }
+
+    /// Schedule the [`GenerativeChunksSet::Regenerate`] systems run in
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
+
+    /// Insert the [`WorldScale`] resource the chunks are drawn with
+    pub fn with_world_scale(mut self, world_scale: WorldScale) -> Self {
+        self.world_scale = Some(world_scale);
+        self
+    }
+
+    /// Insert the [`GenerationPolicy`] deciding how much each frame generates
+    pub fn with_policy(self, policy: GenerationPolicy) -> Self {
+        *self.policy.lock().unwrap() = Some(policy);
+        self
+    }
+
+    fn capture_resources(world: &mut World) {
+        world.resource_scope(|world, mut manager: Mut<LayersManager>| {
+            manager.capture_resources(world);
+        });
+    }
+
+    fn regenerate(world: &mut World) {
+        let pace = world
+            .get_resource::<GenerationPolicy>()
+            .map_or(GenerationPace::Full, |policy| policy.get_pace(world));
+        let delta = world.get_resource::<Time>().map(|time| time.delta());
+        let mut manager = world.resource_mut::<LayersManager>();
+        if let Some(delta) = delta {
+            manager.advance_clock(delta);
+        }
+        match pace {
+            GenerationPace::Full => manager.regenerate(),
+            GenerationPace::Budget(budget) => manager.regenerate_with_budget(budget),
+            GenerationPace::Paused => {}
+        }
+    }
+
+    /// After the regenerate, so the dropped chunks are reported with its deleted ones
+    fn relieve_memory_pressure(
+        mut pressure: EventReader<MemoryPressure>,
+        mut manager: ResMut<LayersManager>,
+    ) {
+        if pressure.read().count() > 0 {
+            manager.relieve_memory_pressure();
+        }
+    }
+}
+
+impl Plugin for GenerativeChunksPlugin {
+    fn build(&self, app: &mut App) {
+        let builder = self
+            .builder
+            .lock()
+            .unwrap()
+            .take()
+            .expect("GenerativeChunksPlugin can only be added once");
+        if let Some(world_scale) = self.world_scale {
+            app.insert_resource(world_scale);
+        }
+        if let Some(policy) = self.policy.lock().unwrap().take() {
+            app.insert_resource(policy);
+        }
+        app.insert_resource(builder.build())
+            .add_event::<MemoryPressure>()
+            .configure_sets(self.schedule, GenerativeChunksSet::Regenerate)
+            .add_systems(
+                self.schedule,
+                (
+                    sync_chunk_loaders,
+                    Self::capture_resources,
+                    Self::regenerate,
+                    Self::relieve_memory_pressure,
+                )
+                    .chain()
+                    .in_set(GenerativeChunksSet::Regenerate),
+            );
+    }
+}
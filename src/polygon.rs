@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use crate::bounds::{Bounds, ChunkIdx, Point, RayChunks};
+
+/// Area bounded by a ring of points, with optional holes, in real coordinates. The rings are
+/// closed implicitly, the last point connects back to the first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    outer: Vec<Point>,
+    holes: Vec<Vec<Point>>,
+}
+
+impl Polygon {
+    pub fn new(outer: Vec<Point>) -> Self {
+        Polygon {
+            outer,
+            holes: Vec::new(),
+        }
+    }
+
+    /// Cut a hole in the polygon, e.g. a lake inside a biome mask
+    pub fn with_hole(mut self, hole: Vec<Point>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+
+    pub fn get_outer(&self) -> &[Point] {
+        &self.outer
+    }
+
+    pub fn get_holes(&self) -> &[Vec<Point>] {
+        &self.holes
+    }
+
+    /// Area around the outer ring, None without points
+    pub fn get_bounds(&self) -> Option<Bounds> {
+        let (first, rest) = self.outer.split_first()?;
+        let bounds = rest
+            .iter()
+            .fold(Bounds::from_point(*first), |bounds, point| {
+                bounds.add_point((point.x, point.y))
+            });
+        Some(bounds)
+    }
+
+    /// The edges of the outer ring and of the holes
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        std::iter::once(&self.outer)
+            .chain(self.holes.iter())
+            .flat_map(|ring| {
+                ring.iter()
+                    .zip(ring.iter().cycle().skip(1))
+                    .map(|(a, b)| (*a, *b))
+            })
+    }
+
+    /// Check if the point is inside the polygon and out of its holes, with the even-odd rule
+    pub fn contains(&self, point: Point) -> bool {
+        let right = self.crossings(point.y).into_iter().filter(|x| *x > point.x).count();
+        right % 2 == 1
+    }
+
+    /// Where the horizontal line at `y` crosses the edges, unsorted. Points on the line count
+    /// as above it, so a vertex on the line is crossed once
+    fn crossings(&self, y: f32) -> Vec<f32> {
+        self.edges()
+            .filter(|(a, b)| (a.y > y) != (b.y > y))
+            .map(|(a, b)| a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y))
+            .collect()
+    }
+
+    /// The chunks overlapping the polygon, ordered by x then y. Like [`Bounds::chunks`],
+    /// chunks touching the edges are included, chunks entirely inside a hole are not
+    pub fn chunks(&self, chunk_size: Point) -> Vec<ChunkIdx> {
+        let Some(bounds) = self.get_bounds() else {
+            return Vec::new();
+        };
+        if !chunk_size.is_finite() {
+            return vec![ChunkIdx::GLOBAL];
+        }
+        // The chunks an edge crosses, the others are entirely inside or outside
+        let mut chunks: HashSet<ChunkIdx> = self
+            .edges()
+            .flat_map(|(a, b)| RayChunks::segment(a, b, chunk_size))
+            .collect();
+        // Scanline over the chunk centers of each row
+        let (min_chunk, max_chunk) = bounds.chunk_range(chunk_size);
+        for y in min_chunk.y..=max_chunk.y {
+            let mut crossings = self.crossings((y as f32 + 0.5) * chunk_size.y);
+            crossings.sort_by(f32::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                // Columns whose center is in the span
+                let first = (span[0] / chunk_size.x - 0.5).ceil() as i32;
+                let end = (span[1] / chunk_size.x - 0.5).ceil() as i32;
+                chunks.extend((first..end).map(|x| ChunkIdx { x, y }));
+            }
+        }
+        let mut chunks: Vec<ChunkIdx> = chunks.into_iter().collect();
+        chunks.sort_by_key(|chunk_idx| (chunk_idx.x, chunk_idx.y));
+        chunks
+    }
+}
+
+/// The chunks overlapping the polygon without holes made by the points, see [`Polygon::chunks`]
+pub fn chunks_in_polygon(points: &[Point], chunk_size: Point) -> Vec<ChunkIdx> {
+    Polygon::new(points.to_vec()).chunks(chunk_size)
+}
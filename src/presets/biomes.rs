@@ -0,0 +1,137 @@
+use bevy::app::Plugin;
+use bevy::color::Color;
+use bevy::ecs::system::Commands;
+use bevy::math::Vec2;
+use crate::bounds::{ChunkIdx, WorldPos};
+use crate::checksum::fnv1a;
+use crate::chunk_entities::ChunkSpawnerPlugin;
+use crate::layer::{Chunk, Dependency, Layer};
+use crate::layer_id::LayerId;
+use crate::layer_manager::{LayerBundle, LayerLookupChunk, LayersManagerBuilder};
+use crate::world_scale::WorldScale;
+use super::heightmap::{HeightmapChunk, HeightmapLayer, HeightmapPreset};
+use super::{fractal_noise, spawn_chunk_sprite};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean,
+    Beach,
+    Desert,
+    Grassland,
+    Forest,
+    Mountain,
+    Snow,
+}
+
+impl Biome {
+    /// The biome of a height and a moisture, both in `0.0..1.0`
+    pub fn classify(height: f32, moisture: f32) -> Biome {
+        match height {
+            h if h < 0.42 => Biome::Ocean,
+            h if h < 0.46 => Biome::Beach,
+            h if h < 0.6 => match moisture {
+                m if m < 0.4 => Biome::Desert,
+                m if m < 0.55 => Biome::Grassland,
+                _ => Biome::Forest,
+            },
+            h if h < 0.66 => Biome::Mountain,
+            _ => Biome::Snow,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Biome::Ocean => Color::srgb(0.1, 0.25, 0.6),
+            Biome::Beach => Color::srgb(0.9, 0.85, 0.6),
+            Biome::Desert => Color::srgb(0.85, 0.7, 0.4),
+            Biome::Grassland => Color::srgb(0.45, 0.7, 0.3),
+            Biome::Forest => Color::srgb(0.15, 0.45, 0.2),
+            Biome::Mountain => Color::srgb(0.5, 0.45, 0.4),
+            Biome::Snow => Color::srgb(0.95, 0.95, 0.97),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BiomeChunk {
+    pub biome: Biome,
+    pub moisture: f32,
+}
+
+impl Chunk for BiomeChunk {
+    fn get_size() -> Vec2 {
+        Vec2::new(2., 2.)
+    }
+}
+
+/// Biome of each chunk, from the [`HeightmapLayer`] at its center and a moisture noise
+pub struct BiomeLayer {
+    /// Size of the wet and dry regions, in world units
+    pub moisture_scale: f32,
+}
+
+impl Layer for BiomeLayer {
+    type Chunk = BiomeChunk;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let center = chunk_idx.center(Self::Chunk::get_size());
+        let heightmap = lookup
+            .get_chunk::<HeightmapLayer>(LayerId::from_type::<HeightmapLayer>(), center)
+            .expect("The heightmap chunk is generated before its dependents");
+        let heightmap_size = HeightmapChunk::get_size();
+        let corner = (center / heightmap_size).floor().as_ivec2();
+        let heightmap_idx = ChunkIdx {
+            x: corner.x,
+            y: corner.y,
+        };
+        let height = heightmap.height_at(WorldPos(center).to_local(heightmap_idx, heightmap_size));
+        let seed = fnv1a(lookup.get_seed(), b"moisture");
+        let moisture = fractal_noise(seed, center, self.moisture_scale, 3);
+        BiomeChunk {
+            biome: Biome::classify(height, moisture),
+            moisture,
+        }
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![Dependency::new::<HeightmapLayer>(Vec2::ZERO)]
+    }
+}
+
+/// Oceans, deserts, forests and snowy peaks over a [`HeightmapPreset`], load the
+/// [`BiomeLayer`] to see them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiomePreset {
+    pub heightmap: HeightmapPreset,
+    /// See [`BiomeLayer::moisture_scale`]
+    pub moisture_scale: f32,
+}
+
+impl Default for BiomePreset {
+    fn default() -> Self {
+        BiomePreset {
+            heightmap: HeightmapPreset::default(),
+            moisture_scale: 96.0,
+        }
+    }
+}
+
+impl BiomePreset {
+    /// Draws each chunk of the [`BiomeLayer`] in the color of its biome
+    pub fn draw(world_scale: WorldScale) -> impl Plugin {
+        ChunkSpawnerPlugin::<BiomeLayer, _>::new(
+            move |commands: &mut Commands, idx: ChunkIdx, chunk: &BiomeChunk| {
+                let size = BiomeChunk::get_size();
+                spawn_chunk_sprite(commands, &world_scale, idx, size, chunk.biome.color())
+            },
+        )
+    }
+}
+
+impl LayerBundle for BiomePreset {
+    fn add_to(self, builder: LayersManagerBuilder) -> LayersManagerBuilder {
+        builder.add_layer(self.heightmap.layer()).add_layer(BiomeLayer {
+            moisture_scale: self.moisture_scale,
+        })
+    }
+}
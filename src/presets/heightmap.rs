@@ -0,0 +1,119 @@
+use bevy::app::Plugin;
+use bevy::color::Color;
+use bevy::ecs::system::Commands;
+use bevy::math::{IVec2, UVec2, Vec2};
+use crate::bounds::{ChunkIdx, ChunkLocalPos};
+use crate::checksum::fnv1a;
+use crate::chunk_entities::ChunkSpawnerPlugin;
+use crate::grid::GridChunk;
+use crate::layer::{Chunk, Layer};
+use crate::layer_manager::{LayerBundle, LayerLookupChunk, LayersManagerBuilder};
+use crate::world_scale::WorldScale;
+use super::{fractal_noise, spawn_chunk_grid};
+
+/// Cells of a [`HeightmapChunk`] on each axis, one per world unit
+pub const HEIGHTMAP_RESOLUTION: UVec2 = UVec2::new(8, 8);
+
+#[derive(Debug, Clone)]
+pub struct HeightmapChunk {
+    /// Heights in `0.0..1.0`, row by row from the bottom of the chunk
+    heights: Vec<f32>,
+}
+
+impl HeightmapChunk {
+    /// Height of the cell under the position
+    pub fn height_at(&self, local: ChunkLocalPos) -> f32 {
+        let max = HEIGHTMAP_RESOLUTION.as_ivec2() - 1;
+        let cell = local.0.floor().as_ivec2().clamp(IVec2::ZERO, max);
+        *self.cell(cell.x as usize, cell.y as usize)
+    }
+}
+
+impl Chunk for HeightmapChunk {
+    fn get_size() -> Vec2 {
+        HEIGHTMAP_RESOLUTION.as_vec2()
+    }
+}
+
+impl GridChunk for HeightmapChunk {
+    type Cell = f32;
+
+    fn resolution() -> UVec2 {
+        HEIGHTMAP_RESOLUTION
+    }
+
+    fn cell(&self, x: usize, y: usize) -> &f32 {
+        &self.heights[y * HEIGHTMAP_RESOLUTION.x as usize + x]
+    }
+}
+
+/// Fractal value noise sampled at the center of each cell, seamless across chunks
+pub struct HeightmapLayer {
+    /// Size of the largest hills, in world units
+    pub scale: f32,
+    /// Layers of finer detail added over the hills
+    pub octaves: u32,
+}
+
+impl Layer for HeightmapLayer {
+    type Chunk = HeightmapChunk;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let seed = fnv1a(lookup.get_seed(), b"height");
+        let origin = chunk_idx.to_point(Self::Chunk::get_size());
+        let mut heights = Vec::with_capacity(HEIGHTMAP_RESOLUTION.element_product() as usize);
+        for y in 0..HEIGHTMAP_RESOLUTION.y {
+            for x in 0..HEIGHTMAP_RESOLUTION.x {
+                let pos = origin + Vec2::new(x as f32, y as f32) + 0.5;
+                heights.push(fractal_noise(seed, pos, self.scale, self.octaves));
+            }
+        }
+        HeightmapChunk { heights }
+    }
+}
+
+/// Rolling hills drawn in gray levels, load the [`HeightmapLayer`] to see them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightmapPreset {
+    /// See [`HeightmapLayer::scale`]
+    pub scale: f32,
+    /// See [`HeightmapLayer::octaves`]
+    pub octaves: u32,
+}
+
+impl Default for HeightmapPreset {
+    fn default() -> Self {
+        HeightmapPreset {
+            scale: 48.0,
+            octaves: 4,
+        }
+    }
+}
+
+impl HeightmapPreset {
+    pub(crate) fn layer(&self) -> HeightmapLayer {
+        HeightmapLayer {
+            scale: self.scale,
+            octaves: self.octaves,
+        }
+    }
+
+    /// Draws each cell of the [`HeightmapLayer`], darker when lower
+    pub fn draw(world_scale: WorldScale) -> impl Plugin {
+        ChunkSpawnerPlugin::<HeightmapLayer, _>::new(
+            move |commands: &mut Commands, idx: ChunkIdx, chunk: &HeightmapChunk| {
+                let size = HeightmapChunk::get_size();
+                spawn_chunk_grid(commands, &world_scale, idx, size, HEIGHTMAP_RESOLUTION, |x, y| {
+                    let height = *chunk.cell(x, y);
+                    Color::srgb(height, height, height)
+                })
+            },
+        )
+    }
+}
+
+impl LayerBundle for HeightmapPreset {
+    fn add_to(self, builder: LayersManagerBuilder) -> LayersManagerBuilder {
+        builder.add_layer(self.layer())
+    }
+}
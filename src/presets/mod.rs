@@ -0,0 +1,114 @@
+//! Ready made layers with the plugins drawing them, to see a world on screen before writing
+//! any layer. Enabled with the `presets` feature
+//!
+//! ```ignore
+//! app.add_plugins(GenerativeChunksPlugin::new().with_bundle(VoronoiPreset::default()))
+//!     .add_plugins(VoronoiPreset::draw(WorldScale::new(10.0)));
+//! // and a camera loading the drawn layer
+//! commands.spawn((Camera2d, CameraChunkLoader::new(10.0).with_layer::<VoronoiLayer>()));
+//! ```
+//!
+//! The layers are regular layers, other layers can depend on them
+
+mod biomes;
+mod heightmap;
+mod tilemap;
+mod voronoi;
+
+pub use biomes::{Biome, BiomeChunk, BiomeLayer, BiomePreset};
+pub use heightmap::{HeightmapChunk, HeightmapLayer, HeightmapPreset, HEIGHTMAP_RESOLUTION};
+pub use tilemap::{Tile, TilemapChunk, TilemapLayer, TilemapPreset};
+pub use voronoi::{
+    VoronoiCellChunk, VoronoiLayer, VoronoiMetric, VoronoiPointChunk, VoronoiPointsLayer,
+    VoronoiPreset,
+};
+
+use bevy::color::Color;
+use bevy::ecs::entity::Entity;
+use bevy::ecs::system::Commands;
+use bevy::math::{UVec2, Vec2};
+use bevy::render::view::Visibility;
+use bevy::sprite::Sprite;
+use bevy::transform::components::Transform;
+use crate::bounds::{ChunkAnchor, ChunkIdx, Point};
+use crate::checksum::fnv1a;
+use crate::world_scale::WorldScale;
+
+/// Hash of a lattice point, in `0.0..1.0`
+fn lattice(seed: u64, x: i32, y: i32) -> f32 {
+    let hash = fnv1a(fnv1a(seed, &x.to_le_bytes()), &y.to_le_bytes());
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Smooth value noise in `0.0..1.0`, the same for a seed and point on every chunk
+fn value_noise(seed: u64, pos: Vec2) -> f32 {
+    let cell = pos.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let t = pos - cell;
+    // Smoothstep, so the cells don't show
+    let t = t * t * (Vec2::splat(3.0) - 2.0 * t);
+    let bottom = lattice(seed, x, y) + (lattice(seed, x + 1, y) - lattice(seed, x, y)) * t.x;
+    let top =
+        lattice(seed, x, y + 1) + (lattice(seed, x + 1, y + 1) - lattice(seed, x, y + 1)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+/// Value noise summed over octaves of halving size, in `0.0..1.0`
+pub(crate) fn fractal_noise(seed: u64, pos: Vec2, scale: f32, octaves: u32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0 / scale;
+    let mut weights = 0.0;
+    for octave in 0..octaves {
+        total += value_noise(fnv1a(seed, &octave.to_le_bytes()), pos * frequency) * amplitude;
+        weights += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / weights
+}
+
+/// Sprite of the color covering the chunk
+fn spawn_chunk_sprite(
+    commands: &mut Commands,
+    world_scale: &WorldScale,
+    chunk_idx: ChunkIdx,
+    chunk_size: Point,
+    color: Color,
+) -> Entity {
+    commands
+        .spawn((
+            Sprite::from_color(color, world_scale.chunk_render_size(chunk_size)),
+            world_scale.chunk_transform(chunk_idx, chunk_size, ChunkAnchor::Center),
+        ))
+        .id()
+}
+
+/// Entity at the origin of the chunk with a sprite child for each cell of its grid
+fn spawn_chunk_grid(
+    commands: &mut Commands,
+    world_scale: &WorldScale,
+    chunk_idx: ChunkIdx,
+    chunk_size: Point,
+    resolution: UVec2,
+    color: impl Fn(usize, usize) -> Color,
+) -> Entity {
+    let cell_size = world_scale.chunk_render_size(chunk_size) / resolution.as_vec2();
+    commands
+        .spawn((
+            world_scale.chunk_transform(chunk_idx, chunk_size, ChunkAnchor::Origin),
+            Visibility::default(),
+        ))
+        .with_children(|parent| {
+            for y in 0..resolution.y as usize {
+                for x in 0..resolution.x as usize {
+                    let center = (Vec2::new(x as f32, y as f32) + 0.5) * cell_size;
+                    parent.spawn((
+                        Sprite::from_color(color(x, y), cell_size),
+                        Transform::from_translation(center.extend(0.0)),
+                    ));
+                }
+            }
+        })
+        .id()
+}
@@ -0,0 +1,137 @@
+use bevy::app::Plugin;
+use bevy::color::Color;
+use bevy::ecs::system::Commands;
+use bevy::math::{UVec2, Vec2};
+use rand::Rng;
+use crate::bounds::ChunkIdx;
+use crate::chunk_entities::ChunkSpawnerPlugin;
+use crate::grid::GridChunk;
+use crate::layer::{Chunk, Dependency, Layer};
+use crate::layer_id::LayerId;
+use crate::layer_manager::{LayerBundle, LayerLookupChunk, LayersManagerBuilder};
+use crate::world_scale::WorldScale;
+use super::heightmap::{HeightmapChunk, HeightmapLayer, HeightmapPreset, HEIGHTMAP_RESOLUTION};
+use super::spawn_chunk_grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Water,
+    Sand,
+    Grass,
+    Tree,
+    Rock,
+}
+
+impl Tile {
+    pub fn color(self) -> Color {
+        match self {
+            Tile::Water => Color::srgb(0.15, 0.35, 0.7),
+            Tile::Sand => Color::srgb(0.9, 0.85, 0.6),
+            Tile::Grass => Color::srgb(0.4, 0.7, 0.3),
+            Tile::Tree => Color::srgb(0.1, 0.35, 0.15),
+            Tile::Rock => Color::srgb(0.5, 0.5, 0.5),
+        }
+    }
+}
+
+/// One tile per cell of the [`HeightmapChunk`] below it
+#[derive(Debug, Clone)]
+pub struct TilemapChunk {
+    /// Row by row from the bottom of the chunk
+    tiles: Vec<Tile>,
+}
+
+impl Chunk for TilemapChunk {
+    fn get_size() -> Vec2 {
+        HeightmapChunk::get_size()
+    }
+}
+
+impl GridChunk for TilemapChunk {
+    type Cell = Tile;
+
+    fn resolution() -> UVec2 {
+        HEIGHTMAP_RESOLUTION
+    }
+
+    fn cell(&self, x: usize, y: usize) -> &Tile {
+        &self.tiles[y * HEIGHTMAP_RESOLUTION.x as usize + x]
+    }
+}
+
+/// Tiles from the heights of the [`HeightmapLayer`], with trees scattered on the grass
+pub struct TilemapLayer {
+    /// Chance of a grass tile to hold a tree
+    pub tree_density: f32,
+}
+
+impl Layer for TilemapLayer {
+    type Chunk = TilemapChunk;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let center = chunk_idx.center(Self::Chunk::get_size());
+        let heightmap = lookup
+            .get_chunk::<HeightmapLayer>(LayerId::from_type::<HeightmapLayer>(), center)
+            .expect("The heightmap chunk is generated before its dependents");
+        let mut trees = lookup.context().rng_for("trees");
+        let mut tiles = Vec::with_capacity(HEIGHTMAP_RESOLUTION.element_product() as usize);
+        for y in 0..HEIGHTMAP_RESOLUTION.y as usize {
+            for x in 0..HEIGHTMAP_RESOLUTION.x as usize {
+                let tile = match *heightmap.cell(x, y) {
+                    h if h < 0.42 => Tile::Water,
+                    h if h < 0.46 => Tile::Sand,
+                    h if h < 0.62 => Tile::Grass,
+                    _ => Tile::Rock,
+                };
+                // Drawn for every tile, so the trees don't move when the grass does
+                let tree = trees.random::<f32>() < self.tree_density;
+                tiles.push(if tile == Tile::Grass && tree { Tile::Tree } else { tile });
+            }
+        }
+        TilemapChunk { tiles }
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![Dependency::new::<HeightmapLayer>(Vec2::ZERO)]
+    }
+}
+
+/// Water, beaches, grass with trees and rocks over a [`HeightmapPreset`], load the
+/// [`TilemapLayer`] to see them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TilemapPreset {
+    pub heightmap: HeightmapPreset,
+    /// See [`TilemapLayer::tree_density`]
+    pub tree_density: f32,
+}
+
+impl Default for TilemapPreset {
+    fn default() -> Self {
+        TilemapPreset {
+            heightmap: HeightmapPreset::default(),
+            tree_density: 0.15,
+        }
+    }
+}
+
+impl TilemapPreset {
+    /// Draws each tile of the [`TilemapLayer`] as a square of its color
+    pub fn draw(world_scale: WorldScale) -> impl Plugin {
+        ChunkSpawnerPlugin::<TilemapLayer, _>::new(
+            move |commands: &mut Commands, idx: ChunkIdx, chunk: &TilemapChunk| {
+                let size = TilemapChunk::get_size();
+                spawn_chunk_grid(commands, &world_scale, idx, size, HEIGHTMAP_RESOLUTION, |x, y| {
+                    chunk.cell(x, y).color()
+                })
+            },
+        )
+    }
+}
+
+impl LayerBundle for TilemapPreset {
+    fn add_to(self, builder: LayersManagerBuilder) -> LayersManagerBuilder {
+        builder.add_layer(self.heightmap.layer()).add_layer(TilemapLayer {
+            tree_density: self.tree_density,
+        })
+    }
+}
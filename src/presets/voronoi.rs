@@ -0,0 +1,146 @@
+use bevy::app::Plugin;
+use bevy::color::Color;
+use bevy::ecs::system::Commands;
+use bevy::math::Vec2;
+use rand::Rng;
+use crate::bounds::{Bounds, ChunkIdx, Point};
+use crate::chunk_entities::ChunkSpawnerPlugin;
+use crate::layer::{Chunk, Dependency, Layer};
+use crate::layer_manager::{LayerBundle, LayerLookupChunk, LayersManagerBuilder};
+use crate::world_scale::WorldScale;
+use super::spawn_chunk_sprite;
+
+/// Size of the chunks of the [`VoronoiPointsLayer`], each holds one point
+const POINT_CHUNK_SIZE: Vec2 = Vec2::new(25., 25.);
+
+#[derive(Debug, Clone)]
+pub struct VoronoiPointChunk {
+    /// The point is in real coordinates
+    pub point: Point,
+    pub color: Color,
+    /// The distances to the point are divided by it, stronger points get bigger cells
+    pub strength: f32,
+}
+
+impl Chunk for VoronoiPointChunk {
+    fn get_size() -> Vec2 {
+        POINT_CHUNK_SIZE
+    }
+}
+
+/// One random point of a random color per chunk
+pub struct VoronoiPointsLayer;
+
+impl Layer for VoronoiPointsLayer {
+    type Chunk = VoronoiPointChunk;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let mut random = lookup.context().rng();
+        let offset = Vec2::new(
+            random.random_range(0.0..POINT_CHUNK_SIZE.x),
+            random.random_range(0.0..POINT_CHUNK_SIZE.y),
+        );
+        VoronoiPointChunk {
+            point: chunk_idx.to_point(POINT_CHUNK_SIZE) + offset,
+            color: Color::srgb_u8(random.random(), random.random(), random.random()),
+            strength: random.random_range(0.8..1.4),
+        }
+    }
+}
+
+/// Distance the cells of a [`VoronoiLayer`] are measured with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VoronoiMetric {
+    /// Diamond shaped cells
+    #[default]
+    Manhattan,
+    Euclidean,
+}
+
+impl VoronoiMetric {
+    pub fn distance(self, a: Point, b: Point) -> f32 {
+        match self {
+            VoronoiMetric::Manhattan => (a.x - b.x).abs() + (a.y - b.y).abs(),
+            VoronoiMetric::Euclidean => a.distance(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoronoiCellChunk {
+    /// The color of the closest point
+    pub color: Color,
+}
+
+impl Chunk for VoronoiCellChunk {
+    fn get_size() -> Vec2 {
+        Vec2::new(1., 1.)
+    }
+}
+
+/// Colors each cell with the closest point of the [`VoronoiPointsLayer`]
+pub struct VoronoiLayer {
+    pub metric: VoronoiMetric,
+    /// How many point chunks around the cell are searched for its closest point
+    pub reach: f32,
+}
+
+impl Layer for VoronoiLayer {
+    type Chunk = VoronoiCellChunk;
+
+    fn generate(&self, lookup: &LayerLookupChunk, chunk_idx: &ChunkIdx) -> Self::Chunk {
+        let center = chunk_idx.center(Self::Chunk::get_size());
+        let bounds = Bounds::from_point(center).add_padding(POINT_CHUNK_SIZE * self.reach);
+        let distance = |point: &VoronoiPointChunk| {
+            self.metric.distance(point.point, center) / point.strength
+        };
+        let color = lookup
+            .get_chunks_in::<VoronoiPointsLayer>(bounds)
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .map_or(Color::BLACK, |closest| closest.color);
+        VoronoiCellChunk { color }
+    }
+
+    fn get_dependencies(&self) -> Vec<Dependency> {
+        vec![Dependency::new::<VoronoiPointsLayer>(POINT_CHUNK_SIZE * self.reach)]
+    }
+}
+
+/// The Voronoi diagram of random points, load the [`VoronoiLayer`] to see it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoronoiPreset {
+    pub metric: VoronoiMetric,
+    /// See [`VoronoiLayer::reach`]
+    pub reach: f32,
+}
+
+impl Default for VoronoiPreset {
+    fn default() -> Self {
+        VoronoiPreset {
+            metric: VoronoiMetric::Manhattan,
+            reach: 5.0,
+        }
+    }
+}
+
+impl VoronoiPreset {
+    /// Draws the cells of the [`VoronoiLayer`] as squares
+    pub fn draw(world_scale: WorldScale) -> impl Plugin {
+        ChunkSpawnerPlugin::<VoronoiLayer, _>::new(
+            move |commands: &mut Commands, idx: ChunkIdx, chunk: &VoronoiCellChunk| {
+                let size = VoronoiCellChunk::get_size();
+                spawn_chunk_sprite(commands, &world_scale, idx, size, chunk.color)
+            },
+        )
+    }
+}
+
+impl LayerBundle for VoronoiPreset {
+    fn add_to(self, builder: LayersManagerBuilder) -> LayersManagerBuilder {
+        builder.add_layer(VoronoiPointsLayer).add_layer(VoronoiLayer {
+            metric: self.metric,
+            reach: self.reach,
+        })
+    }
+}
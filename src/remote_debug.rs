@@ -0,0 +1,275 @@
+//! Tiny HTTP endpoint serving the manager state, to inspect world streaming on a dedicated
+//! server from a browser. Enabled with the `remote-debug` feature
+//!
+//! - `/stats` numbers of the last regenerate, as JSON
+//! - `/layers` the layers with their chunk counts, as JSON
+//! - `/layers/<n>.png` map of the chunks of the n-th layer, green when generated and yellow
+//!   when pending
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use crate::bounds::{ChunkIdx, Point};
+use crate::diagnostics::RegenerateStats;
+use crate::layer_manager::LayersManager;
+
+/// Largest side of the served chunk maps, in pixels
+const MAX_MAP_SIZE: i64 = 1024;
+
+/// How long a connection may take to send its request or read the response
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Threads serving the requests
+pub(crate) const WORKERS: usize = 4;
+
+/// Connections waiting for a free thread, the ones past it are closed right away
+pub(crate) const MAX_QUEUED: usize = 16;
+
+#[derive(Debug, Default)]
+struct LayerDebugState {
+    name: String,
+    chunk_size: Point,
+    generated: Vec<ChunkIdx>,
+    pending: Vec<ChunkIdx>,
+}
+
+#[derive(Debug, Default)]
+struct DebugState {
+    stats: RegenerateStats,
+    layers: Vec<LayerDebugState>,
+}
+
+/// Serves the last published manager state over HTTP from a background thread
+pub struct RemoteDebugServer {
+    state: Arc<RwLock<DebugState>>,
+    local_addr: SocketAddr,
+}
+
+impl RemoteDebugServer {
+    pub fn start(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let state = Arc::new(RwLock::new(DebugState::default()));
+        let (queue, queued) = sync_channel::<TcpStream>(MAX_QUEUED);
+        let queued = Arc::new(Mutex::new(queued));
+        for _ in 0..WORKERS {
+            let served = state.clone();
+            let queued = queued.clone();
+            // A slow or broken connection only holds up its own thread
+            std::thread::Builder::new()
+                .name("generative-chunks-remote-debug-request".to_string())
+                .spawn(move || loop {
+                    // The lock is released before serving, the other threads keep taking
+                    let next = queued.lock().unwrap().recv();
+                    let Ok(stream) = next else {
+                        break;
+                    };
+                    let _ = serve(stream, &served);
+                })?;
+        }
+        std::thread::Builder::new()
+            .name("generative-chunks-remote-debug".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // Dropping the connection closes it, so a flood of them can't pile up
+                    let _ = queue.try_send(stream);
+                }
+            })?;
+        Ok(RemoteDebugServer { state, local_addr })
+    }
+
+    /// Address the server listens on, e.g. to find the port picked for port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Copy the current state of the manager, call it after each regenerate
+    pub fn publish(&self, manager: &LayersManager) {
+        let layers = manager
+            .get_layer_ids()
+            .into_iter()
+            .filter_map(|layer_id| {
+                let layer = manager.read_layer(layer_id)?;
+                let (generated, pending) = layer
+                    .get_storage()
+                    .iter()
+                    .partition::<Vec<_>, _>(|(_, chunk)| chunk.is_generated());
+                Some(LayerDebugState {
+                    name: format!("{:?}", layer_id),
+                    chunk_size: layer.get_chunk_size(),
+                    generated: generated.into_iter().map(|(idx, _)| *idx).collect(),
+                    pending: pending.into_iter().map(|(idx, _)| *idx).collect(),
+                })
+            })
+            .collect();
+        *self.state.write().unwrap() = DebugState {
+            stats: manager.get_stats(),
+            layers,
+        };
+    }
+}
+
+fn serve(mut stream: TcpStream, state: &RwLock<DebugState>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    // The state is only locked to build the response, never while waiting on the socket
+    let (status, content_type, body) = respond(path, &state.read().unwrap());
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
+
+/// Status, content type and body of the response to the path
+fn respond(path: &str, state: &DebugState) -> (&'static str, &'static str, Vec<u8>) {
+    match path {
+        "/" | "/stats" => ("200 OK", "application/json", stats_json(state).into_bytes()),
+        "/layers" => ("200 OK", "application/json", layers_json(state).into_bytes()),
+        _ => match path
+            .strip_prefix("/layers/")
+            .and_then(|rest| rest.strip_suffix(".png"))
+            .and_then(|n| n.parse::<usize>().ok())
+            .and_then(|n| state.layers.get(n))
+        {
+            Some(layer) => ("200 OK", "image/png", layer_png(layer)),
+            None => ("404 Not Found", "text/plain", b"Not found".to_vec()),
+        },
+    }
+}
+
+fn stats_json(state: &DebugState) -> String {
+    let stats = &state.stats;
+    format!(
+        "{{\"generated\":{},\"pending\":{},\"stored\":{},\"storage_bytes\":{},\"regenerate_ms\":{}}}",
+        stats.generated,
+        stats.pending,
+        stats.stored,
+        stats.storage_bytes,
+        stats.duration.as_secs_f64() * 1000.0
+    )
+}
+
+fn layers_json(state: &DebugState) -> String {
+    let layers: Vec<String> = state
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer)| {
+            format!(
+                "{{\"index\":{},\"name\":\"{}\",\"chunk_size\":[{},{}],\"generated\":{},\"pending\":{}}}",
+                i,
+                layer.name.replace('\\', "\\\\").replace('"', "\\\""),
+                layer.chunk_size.x,
+                layer.chunk_size.y,
+                layer.generated.len(),
+                layer.pending.len()
+            )
+        })
+        .collect();
+    format!("[{}]", layers.join(","))
+}
+
+/// One pixel per chunk, the top row is the highest chunk row
+fn layer_png(layer: &LayerDebugState) -> Vec<u8> {
+    let all = layer.generated.iter().chain(layer.pending.iter());
+    let (min_x, max_x, min_y, max_y) = all.fold(
+        (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
+        |(min_x, max_x, min_y, max_y), idx| {
+            (min_x.min(idx.x), max_x.max(idx.x), min_y.min(idx.y), max_y.max(idx.y))
+        },
+    );
+    if min_x > max_x {
+        return encode_png(1, 1, &[32, 32, 32]);
+    }
+    let width = (max_x as i64 - min_x as i64 + 1).min(MAX_MAP_SIZE) as usize;
+    let height = (max_y as i64 - min_y as i64 + 1).min(MAX_MAP_SIZE) as usize;
+    let mut pixels = vec![32u8; width * height * 3];
+    let mut paint = |idx: &ChunkIdx, color: [u8; 3]| {
+        let x = (idx.x as i64 - min_x as i64) as usize;
+        let y = (max_y as i64 - idx.y as i64) as usize;
+        if x < width && y < height {
+            let i = (y * width + x) * 3;
+            pixels[i..i + 3].copy_from_slice(&color);
+        }
+    };
+    layer.pending.iter().for_each(|idx| paint(idx, [200, 160, 40]));
+    layer.generated.iter().for_each(|idx| paint(idx, [60, 180, 90]));
+    encode_png(width as u32, height as u32, &pixels)
+}
+
+/// Uncompressed RGB PNG, good enough for small debug maps
+pub(crate) fn encode_png(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    // Every row starts with the "no filter" byte
+    let row = width as usize * 3;
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for line in rgb.chunks(row) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    // zlib stream made of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(u16::MAX as usize).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        zlib.push(u8::from(i + 1 == blocks.len()));
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filter and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
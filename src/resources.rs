@@ -0,0 +1,33 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use bevy::ecs::resource::Resource;
+use bevy::ecs::world::World;
+
+/// Read-only values the layers can read while generating, through
+/// [`LayerLookupChunk::get_resource`](crate::layer_manager::LayerLookupChunk::get_resource)
+#[derive(Default, Clone)]
+pub struct GenerationResources {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl GenerationResources {
+    pub fn insert<R: Send + Sync + 'static>(&mut self, value: R) {
+        self.values.insert(TypeId::of::<R>(), Arc::new(value));
+    }
+
+    pub fn get<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        self.values.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+    }
+}
+
+/// Copies a Bevy resource from the world into the [`GenerationResources`]
+pub(crate) type ResourceCapture = Box<dyn Fn(&World, &mut GenerationResources) + Send + Sync>;
+
+pub(crate) fn capture<R: Resource + Clone>() -> ResourceCapture {
+    Box::new(|world: &World, resources: &mut GenerationResources| {
+        if let Some(resource) = world.get_resource::<R>() {
+            resources.insert(resource.clone());
+        }
+    })
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use bevy::app::{App, FixedFirst, Plugin};
+use bevy::ecs::resource::Resource;
+use bevy::ecs::system::{Res, ResMut};
+use crate::bounds::{Bounds, ChunkIdx, CoordinateMath, Point, WorldPos};
+use crate::layer::{Chunk, Layer};
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+
+/// Immutable view of the generated chunks of a layer at some point in time
+#[derive(Debug)]
+pub struct LayerSnapshot {
+    chunk_size: Point,
+    coordinates: CoordinateMath,
+    chunks: HashMap<ChunkIdx, Arc<dyn Chunk>>,
+}
+
+impl LayerSnapshot {
+    pub(crate) fn new(
+        chunk_size: Point,
+        coordinates: CoordinateMath,
+        chunks: HashMap<ChunkIdx, Arc<dyn Chunk>>,
+    ) -> Self {
+        LayerSnapshot {
+            chunk_size,
+            coordinates,
+            chunks,
+        }
+    }
+
+    pub fn get_chunk_size(&self) -> Point {
+        self.chunk_size
+    }
+
+    pub fn get_chunk_at<L: Layer + 'static>(&self, chunk_idx: &ChunkIdx) -> Option<&L::Chunk> {
+        self.chunks.get(chunk_idx)?.downcast_ref::<L::Chunk>()
+    }
+
+    pub fn get_chunk<L: Layer + 'static>(&self, pos: impl Into<WorldPos>) -> Option<&L::Chunk> {
+        let pos: WorldPos = pos.into();
+        self.get_chunk_at::<L>(&self.coordinates.chunk_idx(pos.0, self.chunk_size))
+    }
+
+    pub fn get_chunks_in<L: Layer + 'static>(&self, bounds: &Bounds) -> Vec<(ChunkIdx, &L::Chunk)> {
+        self.coordinates
+            .chunks(bounds, self.chunk_size)
+            .filter_map(|chunk_idx| Some((chunk_idx, self.get_chunk_at::<L>(&chunk_idx)?)))
+            .collect()
+    }
+
+    /// Like [`LayerSnapshot::get_chunks_in`], with the area each chunk covers
+    pub fn get_placed_chunks_in<L: Layer + 'static>(
+        &self,
+        bounds: &Bounds,
+    ) -> Vec<(ChunkIdx, Bounds, &L::Chunk)> {
+        self.get_chunks_in::<L>(bounds)
+            .into_iter()
+            .map(|(chunk_idx, chunk)| (chunk_idx, chunk_idx.bounds(self.chunk_size), chunk))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+/// Cheap, immutable and `Send + Sync` view of the chunks of every layer, it can be held
+/// across frames by other threads without blocking the generation
+#[derive(Debug, Clone, Default)]
+pub struct ChunksSnapshot {
+    layers: HashMap<LayerId, Arc<LayerSnapshot>>,
+}
+
+impl ChunksSnapshot {
+    pub(crate) fn new(layers: HashMap<LayerId, Arc<LayerSnapshot>>) -> Self {
+        ChunksSnapshot { layers }
+    }
+
+    pub fn get_layer<L: Layer + 'static>(&self) -> Option<&LayerSnapshot> {
+        self.layers
+            .get(&LayerId::from_type::<L>())
+            .map(|layer| layer.as_ref())
+    }
+
+    pub fn get_chunk<L: Layer + 'static>(&self, pos: impl Into<WorldPos>) -> Option<&L::Chunk> {
+        self.get_layer::<L>()?.get_chunk::<L>(pos)
+    }
+
+    pub fn get_chunks_in<L: Layer + 'static>(&self, bounds: &Bounds) -> Vec<(ChunkIdx, &L::Chunk)> {
+        self.get_layer::<L>()
+            .map(|layer| layer.get_chunks_in::<L>(bounds))
+            .unwrap_or_default()
+    }
+}
+
+/// Hands out read views of single layers, see [`LayersManager::scope`]
+pub struct ChunksReader<'a> {
+    manager: &'a LayersManager,
+}
+
+impl<'a> ChunksReader<'a> {
+    pub(crate) fn new(manager: &'a LayersManager) -> Self {
+        ChunksReader { manager }
+    }
+
+    /// Read view of the layer, `None` if the layer is not registered
+    pub fn layer<L: Layer + 'static>(&self) -> Option<LayerView<'a, L>> {
+        Some(LayerView {
+            snapshot: self.manager.layer_snapshot(LayerId::from_type::<L>())?,
+            _scope: PhantomData,
+        })
+    }
+}
+
+/// Typed read view of the chunks of one layer, `Send + Sync` so each view can be moved to its
+/// own thread or parallel query. Reading never locks the layer
+pub struct LayerView<'a, L> {
+    snapshot: Arc<LayerSnapshot>,
+    _scope: PhantomData<(&'a (), fn() -> L)>,
+}
+
+impl<L> Clone for LayerView<'_, L> {
+    fn clone(&self) -> Self {
+        LayerView {
+            snapshot: self.snapshot.clone(),
+            _scope: PhantomData,
+        }
+    }
+}
+
+impl<L: Layer + 'static> LayerView<'_, L> {
+    pub fn get_chunk(&self, pos: impl Into<WorldPos>) -> Option<&L::Chunk> {
+        self.snapshot.get_chunk::<L>(pos)
+    }
+
+    pub fn get_chunk_at(&self, chunk_idx: &ChunkIdx) -> Option<&L::Chunk> {
+        self.snapshot.get_chunk_at::<L>(chunk_idx)
+    }
+
+    pub fn get_chunks_in(&self, bounds: &Bounds) -> Vec<(ChunkIdx, &L::Chunk)> {
+        self.snapshot.get_chunks_in::<L>(bounds)
+    }
+
+    pub fn get_placed_chunks_in(&self, bounds: &Bounds) -> Vec<(ChunkIdx, Bounds, &L::Chunk)> {
+        self.snapshot.get_placed_chunks_in::<L>(bounds)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshot.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshot.is_empty()
+    }
+}
+
+/// The chunks as they were at the start of the current fixed timestep, so the `FixedUpdate`
+/// gameplay sees the same world on every step of a frame while generation goes on in `Update`.
+/// Filled by [`FixedChunksPlugin`]
+#[derive(Resource, Debug, Default)]
+pub struct FixedChunks {
+    snapshot: ChunksSnapshot,
+    /// Regenerate the snapshot was taken after
+    version: u64,
+}
+
+impl FixedChunks {
+    pub fn get_snapshot(&self) -> &ChunksSnapshot {
+        &self.snapshot
+    }
+
+    /// Number of the regenerate the snapshot was taken after, see
+    /// [`LayersManager::get_frame`]. Steps seeing the same version see the same chunks
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get_chunk<L: Layer + 'static>(&self, pos: impl Into<WorldPos>) -> Option<&L::Chunk> {
+        self.snapshot.get_chunk::<L>(pos)
+    }
+
+    pub fn get_chunks_in<L: Layer + 'static>(&self, bounds: &Bounds) -> Vec<(ChunkIdx, &L::Chunk)> {
+        self.snapshot.get_chunks_in::<L>(bounds)
+    }
+}
+
+/// Freezes the generated chunks into [`FixedChunks`] at the start of each fixed timestep,
+/// the snapshot is only taken again after a regenerate
+pub struct FixedChunksPlugin;
+
+impl Plugin for FixedChunksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FixedChunks>()
+            .add_systems(FixedFirst, Self::freeze);
+    }
+}
+
+impl FixedChunksPlugin {
+    fn freeze(manager: Option<Res<LayersManager>>, mut fixed: ResMut<FixedChunks>) {
+        let Some(manager) = manager else {
+            return;
+        };
+        // Chunks can be loaded before the first regenerate, so frame 0 is always taken
+        if fixed.version == manager.get_frame() && manager.get_frame() != 0 {
+            return;
+        }
+        fixed.snapshot = manager.read_snapshot();
+        fixed.version = manager.get_frame();
+    }
+}
@@ -0,0 +1,26 @@
+use crate::bounds::{Bounds, ChunkIdx, Point};
+use crate::layer_id::LayerId;
+
+/// Handle of a teleport started with
+/// [`LayersManager::prewarm_for_teleport`](crate::layer_manager::LayersManager::prewarm_for_teleport)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TeleportId(pub(crate) u64);
+
+/// A destination generated ahead of a teleport, with the chunks the clients used before
+/// leaving kept alive until the arrival
+#[derive(Debug)]
+pub(crate) struct Teleport {
+    pub(crate) id: TeleportId,
+    pub(crate) layer_id: LayerId,
+    pub(crate) bounds: Bounds,
+    /// Chunks of the clients at the origin, by layer
+    pub(crate) pinned: Vec<(LayerId, Vec<ChunkIdx>)>,
+}
+
+impl Teleport {
+    /// Distance from the point to the destination region, zero inside it
+    pub(crate) fn distance(&self, point: Point) -> f32 {
+        let closest = point.clamp(self.bounds.get_min(), self.bounds.get_max());
+        closest.distance(point)
+    }
+}
@@ -0,0 +1,283 @@
+use std::time::Duration;
+use crate::bounds::ChunkIdx;
+use crate::layer_id::LayerId;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum UsageStrategy {
+    KeepAlive,
+    Slow,
+    Fast,
+}
+
+/// How soon the chunks of a request must be generated, in game time (see
+/// [`LayersManager::advance_clock`](crate::layer_manager::LayersManager::advance_clock))
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Deadline {
+    /// Needed on this regenerate, e.g. the chunk the player is falling into. Pre-empts every
+    /// other chunk, ignoring the group and in flight limits
+    ThisFrame,
+    /// Needed within the seconds, generated earliest deadline first and pre-empting once due
+    WithinSeconds(f32),
+    /// No deadline, generated by priority
+    #[default]
+    Background,
+}
+
+impl Deadline {
+    /// Game time a chunk requested at `clock` is due at, None without a deadline
+    pub fn due_at(self, clock: Duration) -> Option<Duration> {
+        match self {
+            Deadline::ThisFrame => Some(clock),
+            Deadline::WithinSeconds(seconds) => {
+                Some(clock + Duration::from_secs_f32(seconds.max(0.0)))
+            }
+            Deadline::Background => None,
+        }
+    }
+}
+
+/// How long a layer keeps the data of its chunks once no client requests them, so a client
+/// moving back and forth doesn't regenerate them. Unlike [`UsageDecay`], the unused chunks are
+/// not generated, only the generated ones are kept. Requested chunks, including the
+/// KeepAlive ones, are never evicted
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GracePeriod {
+    /// Regenerates the chunks are kept for
+    Frames(u64),
+    /// Game time the chunks are kept for, see
+    /// [`LayersManager::advance_clock`](crate::layer_manager::LayersManager::advance_clock)
+    Seconds(f32),
+}
+
+impl GracePeriod {
+    /// Check if a chunk unused since the frame and game time can be evicted
+    pub(crate) fn has_elapsed(self, since: (u64, Duration), now: (u64, Duration)) -> bool {
+        match self {
+            GracePeriod::Frames(frames) => now.0.saturating_sub(since.0) >= frames,
+            GracePeriod::Seconds(seconds) => {
+                now.1.saturating_sub(since.1).as_secs_f32() >= seconds
+            }
+        }
+    }
+}
+
+/// How many frames each strategy stays active after its last request
+/// With the default of zero frames, chunks are only kept while they are requested
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct UsageDecay {
+    pub keep_alive: u64,
+    pub slow: u64,
+    pub fast: u64,
+}
+
+/// How many Slow chunks each layer generates per regenerate
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SlowSchedule {
+    /// Slow chunks generated per regenerate, even when there is no Fast work
+    pub per_frame: usize,
+    /// One Slow chunk is generated for every `fast_ratio` Fast chunks. When the in flight
+    /// limit or a budget cuts the chunks of the frame, the count carries over the regenerates
+    pub fast_ratio: usize,
+}
+
+impl Default for SlowSchedule {
+    fn default() -> Self {
+        SlowSchedule {
+            per_frame: 4,
+            fast_ratio: 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UsageCounter {
+    keep_alive: u32,
+    slow: u32,
+    fast: u32,
+    /// Frame the counts belong to
+    frame: u64,
+    /// Frame each strategy was last requested
+    last_keep_alive: Option<u64>,
+    last_slow: Option<u64>,
+    last_fast: Option<u64>,
+    /// Requests held across the frames until released, by the clients that don't move
+    held_keep_alive: u32,
+    held_slow: u32,
+    held_fast: u32,
+}
+
+impl UsageCounter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.keep_alive == 0
+            && self.slow == 0
+            && self.fast == 0
+            && self.held_keep_alive == 0
+            && self.held_slow == 0
+            && self.held_fast == 0
+    }
+
+    /// Start counting the requests of a new frame, the previous requests are remembered
+    /// only through the last requested frames
+    pub(crate) fn stamp(&mut self, frame: u64) {
+        if self.frame != frame {
+            self.keep_alive = 0;
+            self.slow = 0;
+            self.fast = 0;
+            self.frame = frame;
+        }
+    }
+    
+    pub fn should_keep_alive(&self) -> bool {
+        self.keep_alive > 0
+    }
+    
+    pub fn should_generate_slow(&self) -> bool {
+        self.slow > 0
+    }
+    
+    pub fn should_generate_fast(&self) -> bool {
+        self.fast > 0
+    }
+    
+}
+
+impl Default for UsageCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageCounter {
+    pub fn new() -> Self {
+        UsageCounter {
+            keep_alive: 0,
+            slow: 0,
+            fast: 0,
+            frame: 0,
+            last_keep_alive: None,
+            last_slow: None,
+            last_fast: None,
+            held_keep_alive: 0,
+            held_slow: 0,
+            held_fast: 0,
+        }
+    }
+
+    fn held_mut(&mut self, usage: UsageStrategy) -> &mut u32 {
+        match usage {
+            UsageStrategy::KeepAlive => &mut self.held_keep_alive,
+            UsageStrategy::Slow => &mut self.held_slow,
+            UsageStrategy::Fast => &mut self.held_fast,
+        }
+    }
+
+    fn held(&self, usage: UsageStrategy) -> u32 {
+        match usage {
+            UsageStrategy::KeepAlive => self.held_keep_alive,
+            UsageStrategy::Slow => self.held_slow,
+            UsageStrategy::Fast => self.held_fast,
+        }
+    }
+
+    /// Request the chunk on every frame until [`UsageCounter::release`]
+    pub(crate) fn hold(&mut self, usage: UsageStrategy) {
+        *self.held_mut(usage) += 1;
+    }
+
+    /// Drop a held request, last requested at `last_frame`. The strategy decays from there
+    pub(crate) fn release(&mut self, usage: UsageStrategy, last_frame: u64) {
+        let held = self.held_mut(usage);
+        debug_assert!(*held > 0, "Released an unheld {:?} usage", usage);
+        *held = held.saturating_sub(1);
+        let last = match usage {
+            UsageStrategy::KeepAlive => &mut self.last_keep_alive,
+            UsageStrategy::Slow => &mut self.last_slow,
+            UsageStrategy::Fast => &mut self.last_fast,
+        };
+        *last = Some(last.map_or(last_frame, |previous| previous.max(last_frame)));
+    }
+
+    pub fn increment(&mut self, usage: UsageStrategy) {
+        match usage {
+            UsageStrategy::KeepAlive => {
+                self.keep_alive += 1;
+                self.last_keep_alive = Some(self.frame);
+            }
+            UsageStrategy::Slow => {
+                self.slow += 1;
+                self.last_slow = Some(self.frame);
+            }
+            UsageStrategy::Fast => {
+                self.fast += 1;
+                self.last_fast = Some(self.frame);
+            }
+        }
+    }
+
+    /// Remove a request, a count already at zero means the bookkeeping is wrong and stays at
+    /// zero instead of wrapping
+    pub fn decrement(&mut self, usage: UsageStrategy) {
+        let count = match usage {
+            UsageStrategy::KeepAlive => &mut self.keep_alive,
+            UsageStrategy::Slow => &mut self.slow,
+            UsageStrategy::Fast => &mut self.fast,
+        };
+        debug_assert!(*count > 0, "Decremented an unused {:?} usage", usage);
+        *count = count.saturating_sub(1);
+    }
+
+    /// Requests of the strategy at the frame, the held ones and the ones counted at the frame
+    pub fn get_count_at(&self, frame: u64, usage: UsageStrategy) -> u32 {
+        if self.frame != frame {
+            return self.held(usage);
+        }
+        let counted = match usage {
+            UsageStrategy::KeepAlive => self.keep_alive,
+            UsageStrategy::Slow => self.slow,
+            UsageStrategy::Fast => self.fast,
+        };
+        self.held(usage) + counted
+    }
+
+    /// Best strategy among the requests of the counted frame
+    pub fn best_usage(&self) -> Option<UsageStrategy> {
+        if self.fast > 0 {
+            Some(UsageStrategy::Fast)
+        } else if self.slow > 0 {
+            Some(UsageStrategy::Slow)
+        } else if self.keep_alive > 0 {
+            Some(UsageStrategy::KeepAlive)
+        } else {
+            None
+        }
+    }
+
+    /// Best strategy still active at the frame, given how long each strategy lasts. Held
+    /// strategies are always active
+    pub fn best_usage_at(&self, frame: u64, decay: &UsageDecay) -> Option<UsageStrategy> {
+        let active = |held: u32, last: Option<u64>, frames: u64| {
+            held > 0 || last.is_some_and(|last| frame.saturating_sub(last) <= frames)
+        };
+        if active(self.held_fast, self.last_fast, decay.fast) {
+            Some(UsageStrategy::Fast)
+        } else if active(self.held_slow, self.last_slow, decay.slow) {
+            Some(UsageStrategy::Slow)
+        } else if active(self.held_keep_alive, self.last_keep_alive, decay.keep_alive) {
+            Some(UsageStrategy::KeepAlive)
+        } else {
+            None
+        }
+    }
+}
+
+/// A usage count that doesn't match the requests of the clients, found by the usage audit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageLeak {
+    pub layer: LayerId,
+    pub chunk_idx: ChunkIdx,
+    pub strategy: UsageStrategy,
+    /// Count in the usage counter of the chunk
+    pub counted: u32,
+    /// Requests of the clients
+    pub expected: u32,
+}
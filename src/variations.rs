@@ -0,0 +1,248 @@
+//! The same pipeline generated under several seeds side by side, to preview seed variations
+//! in an editor, or diffed chunk by chunk
+
+use std::fmt;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use crate::bounds::{Bounds, ChunkIdx, WorldPos};
+use crate::chunk_set::ChunkSet;
+use crate::layer::{Chunk, Layer};
+use crate::layer_client::LayerClient;
+use crate::layer_id::LayerId;
+use crate::layer_manager::{LayersManager, LayersManagerBuilder};
+
+/// One manager per seed, all built from the same pipeline and followed by the same clients
+pub struct SeedVariations {
+    managers: Vec<(u64, LayersManager)>,
+}
+
+impl SeedVariations {
+    /// `pipeline` adds the layers and settings, the seed is set on top of it
+    pub fn new(
+        seeds: impl IntoIterator<Item = u64>,
+        pipeline: impl Fn() -> LayersManagerBuilder,
+    ) -> Self {
+        SeedVariations {
+            managers: seeds
+                .into_iter()
+                .map(|seed| (seed, pipeline().seed(seed).build()))
+                .collect(),
+        }
+    }
+
+    /// Add the client to every seed, `client` is called once per seed
+    pub fn add_layer_client(&mut self, client: impl Fn() -> LayerClient) {
+        for (_, manager) in self.managers.iter_mut() {
+            manager.add_layer_client(client());
+        }
+    }
+
+    pub fn clear_layer_clients(&mut self) {
+        for (_, manager) in self.managers.iter_mut() {
+            manager.clear_layer_clients();
+        }
+    }
+
+    pub fn regenerate(&mut self) {
+        for (_, manager) in self.managers.iter_mut() {
+            manager.regenerate();
+        }
+    }
+
+    pub fn get_seeds(&self) -> Vec<u64> {
+        self.managers.iter().map(|(seed, _)| *seed).collect()
+    }
+
+    pub fn get(&self, seed: u64) -> Option<&LayersManager> {
+        self.managers
+            .iter()
+            .find(|(manager_seed, _)| *manager_seed == seed)
+            .map(|(_, manager)| manager)
+    }
+
+    /// The chunk of the layer at the position under every seed, in the seeds order
+    pub fn compare<L: Layer + 'static>(
+        &self,
+        pos: impl Into<WorldPos>,
+    ) -> Vec<(u64, Option<L::Chunk>)>
+    where
+        L::Chunk: Clone,
+    {
+        let pos: WorldPos = pos.into();
+        self.managers
+            .iter()
+            .map(|(seed, manager)| (*seed, manager.get_chunk::<L>(pos)))
+            .collect()
+    }
+}
+
+/// Headless search of the seeds whose world matches a predicate, e.g. "the spawn area has a
+/// lake and a mountain". Each seed is generated around the clients in parallel
+pub struct SeedSearch<P> {
+    pipeline: P,
+    clients: Vec<Box<dyn Fn() -> LayerClient + Send + Sync>>,
+    max_regenerates: usize,
+}
+
+impl<P: Fn() -> LayersManagerBuilder + Sync> SeedSearch<P> {
+    /// `pipeline` adds the layers and settings, the seed is set on top of it
+    pub fn new(pipeline: P) -> Self {
+        SeedSearch {
+            pipeline,
+            clients: Vec::new(),
+            max_regenerates: 64,
+        }
+    }
+
+    /// Generate around this client, `client` is called once per seed
+    pub fn client(mut self, client: impl Fn() -> LayerClient + Send + Sync + 'static) -> Self {
+        self.clients.push(Box::new(client));
+        self
+    }
+
+    /// Regenerates per seed before evaluating, for layers limiting the chunks in flight
+    pub fn max_regenerates(mut self, max_regenerates: usize) -> Self {
+        self.max_regenerates = max_regenerates.max(1);
+        self
+    }
+
+    /// The seeds whose generated world matches, in the order they were given
+    pub fn run(
+        &self,
+        seeds: impl IntoIterator<Item = u64>,
+        predicate: impl Fn(&LayersManager) -> bool + Sync,
+    ) -> Vec<u64> {
+        let seeds: Vec<u64> = seeds.into_iter().collect();
+        seeds
+            .into_par_iter()
+            .filter(|seed| predicate(&self.generate(*seed)))
+            .collect()
+    }
+
+    fn generate(&self, seed: u64) -> LayersManager {
+        let mut manager = (self.pipeline)().seed(seed).build();
+        for client in self.clients.iter() {
+            manager.add_layer_client(client());
+        }
+        for _ in 0..self.max_regenerates {
+            manager.regenerate();
+            if manager.get_stats().pending == 0 {
+                break;
+            }
+        }
+        manager
+    }
+}
+
+/// How a chunk differs between two worlds, with the pretty printed `Debug` data of each side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkDiff {
+    Changed { before: String, after: String },
+    /// Only generated in the first world, e.g. a layer deferring the chunk
+    Removed(String),
+    /// Only generated in the second world
+    Added(String),
+}
+
+/// The chunks of a layer differing between two worlds, see [`ChunkDiffer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerDiff {
+    pub layer: LayerId,
+    /// Chunks of the bounds compared
+    pub compared: usize,
+    /// The differing chunks, ordered by x then y
+    pub chunks: Vec<(ChunkIdx, ChunkDiff)>,
+}
+
+impl LayerDiff {
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The differing chunks, to highlight them over the world
+    pub fn get_chunk_set(&self) -> ChunkSet {
+        self.chunks.iter().map(|(chunk_idx, _)| *chunk_idx).collect()
+    }
+}
+
+impl fmt::Display for LayerDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} of {} chunks differ",
+            self.layer.get_name(),
+            self.chunks.len(),
+            self.compared
+        )?;
+        for (chunk_idx, diff) in self.chunks.iter() {
+            writeln!(f, "({}, {})", chunk_idx.x, chunk_idx.y)?;
+            match diff {
+                ChunkDiff::Changed { before, after } => {
+                    writeln!(f, "- {}", before)?;
+                    writeln!(f, "+ {}", after)?;
+                }
+                ChunkDiff::Removed(before) => writeln!(f, "- {}", before)?,
+                ChunkDiff::Added(after) => writeln!(f, "+ {}", after)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generates the same bounds in two worlds, two seeds or two versions of the layers, and
+/// compares their chunks one by one. For balancing and for reviewing generator changes
+pub struct ChunkDiffer {
+    before: LayersManager,
+    after: LayersManager,
+}
+
+impl ChunkDiffer {
+    /// The pipeline under two seeds, the seed is set on top of it
+    pub fn seeds(before: u64, after: u64, pipeline: impl Fn() -> LayersManagerBuilder) -> Self {
+        ChunkDiffer::versions(pipeline().seed(before), pipeline().seed(after))
+    }
+
+    /// Two pipelines, e.g. with the old and the new version of a layer. Both must have the
+    /// layers compared
+    pub fn versions(before: LayersManagerBuilder, after: LayersManagerBuilder) -> Self {
+        ChunkDiffer {
+            before: before.build(),
+            after: after.build(),
+        }
+    }
+
+    /// Generate the chunks of the layer inside the bounds in both worlds and compare them
+    pub fn diff<L: Layer + 'static>(&mut self, bounds: Bounds) -> LayerDiff {
+        self.before.generate_bounds::<L>(bounds);
+        self.after.generate_bounds::<L>(bounds);
+        let chunk_size = L::Chunk::get_size();
+        let mut diff = LayerDiff {
+            layer: LayerId::from_type::<L>(),
+            compared: 0,
+            chunks: Vec::new(),
+        };
+        for chunk_idx in self.before.get_coordinate_math().chunks(&bounds, chunk_size) {
+            diff.compared += 1;
+            let chunk_diff = match (
+                self.before.dump_chunk::<L>(chunk_idx),
+                self.after.dump_chunk::<L>(chunk_idx),
+            ) {
+                (Some(before), Some(after)) if before != after => {
+                    ChunkDiff::Changed { before, after }
+                }
+                (Some(before), None) => ChunkDiff::Removed(before),
+                (None, Some(after)) => ChunkDiff::Added(after),
+                _ => continue,
+            };
+            diff.chunks.push((chunk_idx, chunk_diff));
+        }
+        diff
+    }
+
+    pub fn get_before(&self) -> &LayersManager {
+        &self.before
+    }
+
+    pub fn get_after(&self) -> &LayersManager {
+        &self.after
+    }
+}
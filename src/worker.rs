@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::bounds::{ChunkIdx, WorldPos};
+use crate::layer_client::{ClientId, IntoLayerClient, LayerClient};
+use crate::layer_id::LayerId;
+use crate::layer_manager::LayersManager;
+use crate::snapshot::ChunksSnapshot;
+
+/// Updates sent to a [`LayersWorker`]
+pub enum WorkerCommand {
+    AddClient(ClientId, LayerClient),
+    UpdateClientCenter(ClientId, WorldPos),
+    SetClientActive(ClientId, bool),
+    RemoveClient(ClientId),
+    ClearClients,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkEventKind {
+    Generated,
+    Deleted,
+}
+
+/// A chunk was generated or deleted by the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEvent {
+    pub layer: LayerId,
+    pub chunk_idx: ChunkIdx,
+    pub kind: ChunkEventKind,
+}
+
+/// A [`LayersManager`] running on its own thread, regenerating continuously
+pub struct LayersWorker {
+    commands: Sender<WorkerCommand>,
+    events: Receiver<ChunkEvent>,
+    snapshot: Arc<RwLock<ChunksSnapshot>>,
+    handle: Option<JoinHandle<LayersManager>>,
+    /// Ids are handed out here, so the caller gets them before the worker adds the client
+    next_client_id: AtomicU64,
+}
+
+impl LayersManager {
+    /// Move the manager to its own thread, it regenerates whenever it receives client updates
+    /// and at least once every `interval`
+    pub fn spawn_worker(mut self, interval: Duration) -> LayersWorker {
+        let (command_sender, commands) = channel();
+        let (event_sender, events) = channel();
+        let snapshot = Arc::new(RwLock::new(ChunksSnapshot::default()));
+        let published = snapshot.clone();
+        let next_client_id = AtomicU64::new(self.next_client_id().0);
+        let handle = std::thread::Builder::new()
+            .name("generative-chunks-worker".to_string())
+            .spawn(move || {
+                loop {
+                    // Wait for updates at most one interval, then apply all the queued ones
+                    let first = match commands.recv_timeout(interval) {
+                        Ok(command) => Some(command),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    let mut shutdown = false;
+                    for command in first.into_iter().chain(commands.try_iter()) {
+                        match command {
+                            WorkerCommand::AddClient(id, client) => {
+                                self.insert_layer_client(id, client);
+                            }
+                            WorkerCommand::UpdateClientCenter(id, center) => {
+                                self.update_client_center(id, center);
+                            }
+                            WorkerCommand::SetClientActive(id, active) => {
+                                self.set_client_active(id, active);
+                            }
+                            WorkerCommand::RemoveClient(id) => {
+                                self.remove_client(id);
+                            }
+                            WorkerCommand::ClearClients => self.clear_layer_clients(),
+                            WorkerCommand::Shutdown => shutdown = true,
+                        }
+                    }
+                    if shutdown {
+                        break;
+                    }
+
+                    self.regenerate();
+                    for (kind, lists) in [
+                        (ChunkEventKind::Deleted, self.get_all_deleted_chunks()),
+                        (ChunkEventKind::Generated, self.get_all_generated_chunks()),
+                    ] {
+                        for (layer, chunks) in lists {
+                            for chunk_idx in chunks {
+                                // Nobody listening is fine, the snapshot is still published
+                                let _ = event_sender.send(ChunkEvent {
+                                    layer: *layer,
+                                    chunk_idx: *chunk_idx,
+                                    kind,
+                                });
+                            }
+                        }
+                    }
+                    *published.write().unwrap() = self.read_snapshot();
+                }
+                self
+            })
+            .expect("Failed to spawn the worker thread");
+
+        LayersWorker {
+            commands: command_sender,
+            events,
+            snapshot,
+            handle: Some(handle),
+            next_client_id,
+        }
+    }
+}
+
+impl LayersWorker {
+    /// Add the client, the returned handle moves, toggles or removes it later, see
+    /// [`LayersManager::add_layer_client`]
+    pub fn add_layer_client(&self, layer_client: impl IntoLayerClient) -> ClientId {
+        let id = ClientId(self.next_client_id.fetch_add(1, Ordering::Relaxed));
+        let client = layer_client.into_layer_client();
+        let _ = self.commands.send(WorkerCommand::AddClient(id, client));
+        id
+    }
+
+    /// Move the client, applied on the next regenerate of the worker
+    pub fn update_client_center(&self, id: ClientId, center: impl Into<WorldPos>) {
+        let _ = self.commands.send(WorkerCommand::UpdateClientCenter(id, center.into()));
+    }
+
+    /// Pause or resume the client, see [`LayersManager::set_client_active`]
+    pub fn set_client_active(&self, id: ClientId, active: bool) {
+        let _ = self.commands.send(WorkerCommand::SetClientActive(id, active));
+    }
+
+    /// Remove the client, its chunks are released on the next regenerate of the worker
+    pub fn remove_client(&self, id: ClientId) {
+        let _ = self.commands.send(WorkerCommand::RemoveClient(id));
+    }
+
+    pub fn clear_layer_clients(&self) {
+        let _ = self.commands.send(WorkerCommand::ClearClients);
+    }
+
+    /// Chunk events received since the last call
+    pub fn poll_events(&self) -> Vec<ChunkEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// The chunks as of the last regenerate of the worker
+    pub fn snapshot(&self) -> ChunksSnapshot {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    /// Stop the worker and get the manager back
+    pub fn shutdown(mut self) -> LayersManager {
+        let _ = self.commands.send(WorkerCommand::Shutdown);
+        self.handle
+            .take()
+            .unwrap()
+            .join()
+            .expect("The worker thread panicked")
+    }
+}
+
+impl Drop for LayersWorker {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = self.commands.send(WorkerCommand::Shutdown);
+            let _ = handle.join();
+        }
+    }
+}
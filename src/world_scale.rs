@@ -0,0 +1,84 @@
+use bevy::ecs::resource::Resource;
+use bevy::math::{Rect, Vec2};
+use bevy::render::camera::Camera;
+use bevy::transform::components::{GlobalTransform, Transform};
+use bevy::window::Window;
+use crate::bounds::{ChunkAnchor, ChunkIdx, Point, WorldPos};
+
+/// Render units per world unit, how big the generator world is drawn. Converts the chunks to
+/// render space and the cursor back to world space, so the scale is written once. Inserted by
+/// [`GenerativeChunksPlugin::with_world_scale`](crate::plugin::GenerativeChunksPlugin::with_world_scale)
+/// and read by the [`ChunkLoader`](crate::chunk_loader::ChunkLoader)s without their own scale
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct WorldScale {
+    scale: f32,
+}
+
+impl Default for WorldScale {
+    fn default() -> Self {
+        WorldScale::new(1.0)
+    }
+}
+
+impl WorldScale {
+    pub const fn new(scale: f32) -> Self {
+        assert!(scale > 0.0, "The world scale must be positive");
+        WorldScale { scale }
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn to_render(&self, pos: impl Into<WorldPos>) -> Vec2 {
+        let pos: WorldPos = pos.into();
+        pos.0 * self.scale
+    }
+
+    pub fn to_world(&self, render_pos: Vec2) -> WorldPos {
+        WorldPos(render_pos / self.scale)
+    }
+
+    /// Size of a chunk in render units
+    pub fn chunk_render_size(&self, chunk_size: Point) -> Vec2 {
+        chunk_size * self.scale
+    }
+
+    /// Area of a chunk in render units
+    pub fn chunk_rect(&self, chunk_idx: ChunkIdx, chunk_size: Point) -> Rect {
+        let bounds = chunk_idx.bounds(chunk_size);
+        Rect::from_corners(self.to_render(bounds.get_min()), self.to_render(bounds.get_max()))
+    }
+
+    /// Transform placing a sprite or mesh at the anchor of the chunk, see
+    /// [`ChunkIdx::transform`]
+    pub fn chunk_transform(
+        &self,
+        chunk_idx: ChunkIdx,
+        chunk_size: Point,
+        anchor: ChunkAnchor,
+    ) -> Transform {
+        chunk_idx.transform(chunk_size, anchor, self.scale)
+    }
+
+    /// World position under a point of the viewport of a 2D camera
+    pub fn viewport_to_world(
+        &self,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        viewport_pos: Vec2,
+    ) -> Option<WorldPos> {
+        let render_pos = camera.viewport_to_world_2d(camera_transform, viewport_pos).ok()?;
+        Some(self.to_world(render_pos))
+    }
+
+    /// World position under the cursor, None when the cursor is outside the window
+    pub fn cursor_to_world(
+        &self,
+        window: &Window,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+    ) -> Option<WorldPos> {
+        self.viewport_to_world(camera, camera_transform, window.cursor_position()?)
+    }
+}